@@ -2,8 +2,8 @@
 
 use imgui::Ui;
 use textdistance::{self, Algorithm, Cosine};
-use win_screenshot::prelude::capture_display;
-use windows::Win32::{Foundation::POINT, UI::WindowsAndMessaging::GetCursorPos};
+
+use crate::platform::{capture_display, cursor_pos};
 
 pub struct AdvancedColorPicker {
     pub color: [f32; 4],
@@ -230,10 +230,8 @@ impl AdvancedColorPicker {
 
     fn get_cursor_color(&mut self, _ui: &Ui) -> [f32; 4] {
         let mut color = [0.0; 4];
-        if let Ok(display) = capture_display() {
-            let mut global_pos: POINT = POINT::default();
-            unsafe { GetCursorPos(&mut global_pos) };
-            let index = (display.width as i32 * global_pos.y + global_pos.x) * 4;
+        if let (Ok(display), Some((x, y))) = (capture_display(), cursor_pos()) {
+            let index = (display.width as i32 * y + x) * 4;
             if index >= 0 && index < display.pixels.len() as i32 - 2 {
                 let r = display.pixels[index as usize];
                 let g = display.pixels[index as usize + 1];
@@ -0,0 +1,355 @@
+//! what a `Graph` is run with. `CpuStorage` runs nodes with `MyNode::run_cpu` and `GlBackend` runs them with opengl,
+//! so the same graph can be run on either and the outputs compared.
+//! the cpu versions are a reference to test the shaders against, the editor doesn't use a backend: `Project` always
+//! runs its nodes with opengl through its own `Storage`, and only runs them on the cpu for debug > compare with cpu
+
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    sync::Mutex,
+};
+
+use anyhow::anyhow;
+use glium::backend::Context;
+use image::Rgba;
+use imgui_glium_renderer::Renderer;
+
+use crate::{
+    cpu::{CpuStorage, CpuTexture},
+    node::{ColorSpace, MyNode, NodeFallback, NodeMode},
+    platform::headless_context,
+    project::MUTED_FRAME_SIZE,
+    project_settings::ProjectSettings,
+    storage::{Storage, TextureDepth},
+};
+
+/// runs the nodes of a `Graph`, see `run_graph`
+pub trait Backend {
+    /// runs `node`, the nodes connected to its inputs have already been run
+    fn run_node(&mut self, node: &mut dyn MyNode, connections: &HashMap<String, String>) -> anyhow::Result<()>;
+    /// a copy of the texture with the id `k`, rows go from the bottom up
+    fn read_texture(&self, k: &String) -> Option<CpuTexture>;
+    fn read_text(&self, k: &String) -> Option<String>;
+    /// drops every texture and text, this is done before the graph is run again
+    fn reset(&mut self);
+    /// the time the next run is at, in seconds
+    fn set_time(&mut self, time: f64);
+    /// the depth the textures of the next node are made with
+    fn texture_depth(&self) -> TextureDepth;
+    fn set_texture_depth(&mut self, depth: TextureDepth);
+    fn texture_size(&self, k: &String) -> Option<(u32, u32)>;
+    /// copies the texture or text `from` to `to`
+    fn copy(&mut self, from: &String, to: String) -> anyhow::Result<()>;
+    /// sets `k` to a transparent texture of `size`
+    fn clear_texture(&mut self, k: String, size: (u32, u32));
+    /// sets `k` to a copy of the error texture
+    fn set_error_texture(&mut self, k: String);
+    /// see `Storage::decode_to_linear`
+    fn decode_to_linear(&mut self, k: &String);
+    fn clear_linear_inputs(&mut self);
+    /// see `Storage::encode_from_linear`
+    fn encode_from_linear(&mut self, k: &String);
+}
+
+impl Backend for CpuStorage {
+    fn run_node(&mut self, node: &mut dyn MyNode, connections: &HashMap<String, String>) -> anyhow::Result<()> {
+        return node.run_cpu(self, connections.clone());
+    }
+
+    fn read_texture(&self, k: &String) -> Option<CpuTexture> {
+        return self.get_texture(k).cloned();
+    }
+
+    fn read_text(&self, k: &String) -> Option<String> {
+        return self.get_text(k).cloned();
+    }
+
+    fn reset(&mut self) {
+        CpuStorage::reset(self);
+    }
+
+    fn set_time(&mut self, time: f64) {
+        self.time = time;
+    }
+
+    fn texture_depth(&self) -> TextureDepth {
+        return self.texture_depth;
+    }
+
+    fn set_texture_depth(&mut self, depth: TextureDepth) {
+        self.texture_depth = depth;
+    }
+
+    fn texture_size(&self, k: &String) -> Option<(u32, u32)> {
+        return self.get_texture(k).map(|a| a.dimensions());
+    }
+
+    fn copy(&mut self, from: &String, to: String) -> anyhow::Result<()> {
+        return CpuStorage::copy(self, from, to);
+    }
+
+    fn clear_texture(&mut self, k: String, size: (u32, u32)) {
+        CpuStorage::clear_texture(self, k, size);
+    }
+
+    fn set_error_texture(&mut self, k: String) {
+        CpuStorage::set_error_texture(self, k);
+    }
+
+    fn decode_to_linear(&mut self, k: &String) {
+        CpuStorage::decode_to_linear(self, k);
+    }
+
+    fn clear_linear_inputs(&mut self) {
+        CpuStorage::clear_linear_inputs(self);
+    }
+
+    fn encode_from_linear(&mut self, k: &String) {
+        CpuStorage::encode_from_linear(self, k);
+    }
+}
+
+/// runs nodes with opengl the same way the editor does, without the editor
+pub struct GlBackend {
+    pub storage: Storage,
+    renderer: Renderer,
+    /// the nodes `MyNode::load_assets` has been called on
+    loaded: HashSet<String>,
+}
+
+/// only one imgui context can exist at a time, and one is needed to make a `Renderer`
+static IMGUI_CONTEXT: Mutex<()> = Mutex::new(());
+
+impl GlBackend {
+    pub fn new(display: Rc<Context>) -> anyhow::Result<GlBackend> {
+        let renderer = {
+            let _lock = IMGUI_CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+            let mut imgui = imgui::Context::create();
+            Renderer::new(&mut imgui, &display).map_err(|e| anyhow!("failed to create the renderer: {e:?}"))?
+        };
+        let mut storage = Storage::new(display);
+        // there is no frame to show old results on, so jobs are always waited for
+        storage.block_on_jobs = true;
+        return Ok(GlBackend {
+            storage,
+            renderer,
+            loaded: HashSet::new(),
+        });
+    }
+
+    /// a backend with its own opengl context, see `platform::headless_context`
+    pub fn headless() -> anyhow::Result<GlBackend> {
+        return GlBackend::new(headless_context()?);
+    }
+}
+
+impl Backend for GlBackend {
+    fn run_node(&mut self, node: &mut dyn MyNode, connections: &HashMap<String, String>) -> anyhow::Result<()> {
+        if self.loaded.insert(node.id()) {
+            node.load_assets(&self.storage);
+        }
+        return node.run(&mut self.storage, connections.clone(), &mut self.renderer);
+    }
+
+    fn read_texture(&self, k: &String) -> Option<CpuTexture> {
+        let texture = self.storage.get_texture(k)?;
+        // reading as floats keeps the precision of 16 and 32 bit textures.
+        // SAFETY: glium sizes the buffer and picks the `glReadPixels` format and type (rgba, float) from the pixel
+        // type, so the read can't write past the buffer. it is unsafe because a context might not read a format as
+        // floats, but every texture in `Storage` is one of the `TextureDepth` formats (u8, f16 or f32 rgba), and
+        // desktop opengl, which `headless_context` and the editor make, reads all of them as floats: u8 is turned
+        // into 0..1 and f16 is widened
+        let rows: Vec<Vec<(f32, f32, f32, f32)>> = unsafe { texture.unchecked_read() };
+        return Some(CpuTexture::from_fn(texture.width(), texture.height(), |x, y| {
+            let (r, g, b, a) = rows[y as usize][x as usize];
+            Rgba([r, g, b, a])
+        }));
+    }
+
+    fn read_text(&self, k: &String) -> Option<String> {
+        return self.storage.get_text(k).cloned();
+    }
+
+    fn reset(&mut self) {
+        self.storage.reset();
+    }
+
+    fn set_time(&mut self, time: f64) {
+        self.storage.time = time;
+    }
+
+    fn texture_depth(&self) -> TextureDepth {
+        return self.storage.texture_depth;
+    }
+
+    fn set_texture_depth(&mut self, depth: TextureDepth) {
+        self.storage.texture_depth = depth;
+    }
+
+    fn texture_size(&self, k: &String) -> Option<(u32, u32)> {
+        return self.storage.get_texture(k).map(|a| a.dimensions());
+    }
+
+    fn copy(&mut self, from: &String, to: String) -> anyhow::Result<()> {
+        return self.storage.copy(from, to);
+    }
+
+    fn clear_texture(&mut self, k: String, size: (u32, u32)) {
+        self.storage.clear_texture(k, size);
+    }
+
+    fn set_error_texture(&mut self, k: String) {
+        self.storage.set_error_texture(k);
+    }
+
+    fn decode_to_linear(&mut self, k: &String) {
+        self.storage.decode_to_linear(k);
+    }
+
+    fn clear_linear_inputs(&mut self) {
+        self.storage.clear_linear_inputs();
+    }
+
+    fn encode_from_linear(&mut self, k: &String) {
+        self.storage.encode_from_linear(k);
+    }
+}
+
+/// the ids of the nodes connected to the inputs of `node`
+fn parents(node: &dyn MyNode, connections: &HashMap<String, String>) -> Vec<String> {
+    return node
+        .inputs()
+        .iter()
+        .filter_map(|a| connections.get(&node.input_id(a)))
+        .map(|a| a.split("-").collect::<Vec<&str>>()[1].to_owned())
+        .collect();
+}
+
+/// passes the first input of `node` straight through to its first output
+fn bypass(node: &dyn MyNode, connections: &HashMap<String, String>, backend: &mut dyn Backend) -> anyhow::Result<()> {
+    let output_id = match node.outputs().first() {
+        Some(a) => node.output_id(a),
+        None => return Ok(()),
+    };
+    let input_key = match node.inputs().first() {
+        Some(a) => connections
+            .get(&node.input_id(a))
+            .ok_or(anyhow!("missing input"))?,
+        None => return Err(anyhow!("this node has no input to pass through")),
+    };
+    return backend.copy(input_key, output_id);
+}
+
+/// sets every output of `node` to a transparent frame the size of its first input
+fn mute(node: &dyn MyNode, connections: &HashMap<String, String>, backend: &mut dyn Backend) {
+    let size = node
+        .inputs()
+        .first()
+        .and_then(|a| connections.get(&node.input_id(a)))
+        .and_then(|a| backend.texture_size(a))
+        .unwrap_or(MUTED_FRAME_SIZE);
+    for output in node.outputs() {
+        backend.clear_texture(node.output_id(&output), size);
+    }
+}
+
+/// decodes the inputs of `node` to linear light, runs it, then encodes its outputs back to srgb
+fn run_in_linear_light(
+    node: &mut dyn MyNode,
+    connections: &HashMap<String, String>,
+    backend: &mut dyn Backend,
+) -> anyhow::Result<()> {
+    for input in node.inputs() {
+        if let Some(key) = connections.get(&node.input_id(&input)) {
+            backend.decode_to_linear(key);
+        }
+    }
+
+    // the node's own outputs are linear as well, so they need the extra precision
+    let depth = backend.texture_depth();
+    backend.set_texture_depth(depth.max(TextureDepth::F16));
+    let worked = backend.run_node(node, connections);
+    backend.set_texture_depth(depth);
+    backend.clear_linear_inputs();
+
+    if worked.is_ok() {
+        for output in node.outputs() {
+            backend.encode_from_linear(&node.output_id(&output));
+        }
+    }
+    return worked;
+}
+
+/// runs every node after the nodes it depends on, returning the result of each node by id.
+/// each node is run the way the editor runs it with `settings`: with its mode, fallback and texture depth,
+/// and in linear light if that is turned on. nodes aren't fused and sources aren't shrunk to a preview size.
+/// nodes that depend on a node that failed fail as well, unless the failed node's fallback gave it an output
+pub fn run_graph(
+    nodes: &mut [Box<dyn MyNode>],
+    connections: &HashMap<String, String>,
+    settings: &ProjectSettings,
+    backend: &mut dyn Backend,
+) -> HashMap<String, anyhow::Result<()>> {
+    let ids = nodes.iter().map(|a| a.id()).collect::<HashSet<String>>();
+    let mut results: HashMap<String, anyhow::Result<()>> = HashMap::new();
+    let mut failed: HashSet<String> = HashSet::new();
+    let mut remaining = (0..nodes.len()).collect::<Vec<usize>>();
+
+    while let Some(position) = remaining.iter().position(|i| {
+        parents(&*nodes[*i], connections)
+            .iter()
+            .all(|a| results.contains_key(a) || !ids.contains(a))
+    }) {
+        let index = remaining.remove(position);
+        let node = &mut nodes[index];
+        let id = node.id();
+        let mode = settings.node_mode(&id);
+        backend.set_texture_depth(settings.node_texture_depth(&id));
+
+        // muted nodes ignore their inputs, so they can't be broken by them
+        let upstream_error = match mode {
+            NodeMode::Mute => None,
+            _ => parents(&**node, connections)
+                .into_iter()
+                .find(|a| failed.contains(a)),
+        };
+        let result = match upstream_error {
+            Some(a) => Err(anyhow!("upstream node {a} failed")),
+            None => match mode {
+                NodeMode::Normal if settings.linear_light && node.color_space() == ColorSpace::Linear => {
+                    run_in_linear_light(&mut **node, connections, backend)
+                }
+                NodeMode::Normal => backend.run_node(&mut **node, connections),
+                NodeMode::Bypass => bypass(&**node, connections, backend),
+                NodeMode::Mute => {
+                    mute(&**node, connections, backend);
+                    Ok(())
+                }
+            },
+        };
+        if result.is_err() {
+            let recovered = match settings.node_fallback(&id) {
+                NodeFallback::None => false,
+                NodeFallback::PassThrough => bypass(&**node, connections, backend).is_ok(),
+                NodeFallback::ErrorTexture => {
+                    for output in node.outputs() {
+                        backend.set_error_texture(node.output_id(&output));
+                    }
+                    true
+                }
+            };
+            if !recovered {
+                failed.insert(id.clone());
+            }
+        }
+        results.insert(id, result);
+    }
+
+    for index in remaining {
+        results.insert(
+            nodes[index].id(),
+            Err(anyhow!("the node depends on its own output")),
+        );
+    }
+    return results;
+}
@@ -0,0 +1,115 @@
+use imgui::Ui;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+/// the size generators use when they aren't given one, if the project doesn't set it
+pub const DEFAULT_CANVAS_SIZE: (u32, u32) = (1280, 720);
+
+/// how much sources are downsampled by while editing, batch files and renders always run at full resolution
+#[derive(Savefile, EnumIter, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PreviewQuality {
+    Full,
+    Half,
+    Quarter,
+}
+
+impl Default for PreviewQuality {
+    fn default() -> Self {
+        PreviewQuality::Full
+    }
+}
+
+impl PreviewQuality {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PreviewQuality::Full => "full",
+            PreviewQuality::Half => "1/2",
+            PreviewQuality::Quarter => "1/4",
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        match self {
+            PreviewQuality::Full => 1.0,
+            PreviewQuality::Half => 0.5,
+            PreviewQuality::Quarter => 0.25,
+        }
+    }
+}
+
+/// how a node with more than one input places an input that isn't the same size as its output
+#[derive(Savefile, EnumIter, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum SizePolicy {
+    /// the input is scaled to exactly cover the output, ignoring its aspect ratio
+    Stretch,
+    /// the whole input is shown, with transparent borders if the aspect ratios don't match
+    Fit,
+    /// the input covers the whole output, cropping whatever doesn't fit
+    Fill,
+    /// the input is not scaled
+    Center,
+}
+
+impl Default for SizePolicy {
+    fn default() -> Self {
+        SizePolicy::Stretch
+    }
+}
+
+impl SizePolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SizePolicy::Stretch => "stretch",
+            SizePolicy::Fit => "fit",
+            SizePolicy::Fill => "fill",
+            SizePolicy::Center => "center",
+        }
+    }
+
+    /// returns (scale, offset) so that `uv * scale + offset` is where the output at `uv` samples the input.
+    /// anything outside of 0-1 after the transform is outside of the input
+    pub fn uv_transform(&self, target: (u32, u32), source: (u32, u32)) -> ([f32; 2], [f32; 2]) {
+        let target = [target.0.max(1) as f32, target.1.max(1) as f32];
+        let source = [source.0.max(1) as f32, source.1.max(1) as f32];
+        let scale = match self {
+            SizePolicy::Stretch => return ([1.0, 1.0], [0.0, 0.0]),
+            SizePolicy::Fit => (target[0] / source[0]).min(target[1] / source[1]),
+            SizePolicy::Fill => (target[0] / source[0]).max(target[1] / source[1]),
+            SizePolicy::Center => 1.0,
+        };
+        let drawn = [source[0] * scale, source[1] * scale];
+        return (
+            [target[0] / drawn[0], target[1] / drawn[1]],
+            [
+                (drawn[0] - target[0]) / (2.0 * drawn[0]),
+                (drawn[1] - target[1]) / (2.0 * drawn[1]),
+            ],
+        );
+    }
+
+    /// returns true if the policy was changed
+    pub fn combo(&mut self, ui: &Ui, label: &str) -> bool {
+        let policies = SizePolicy::iter().collect::<Vec<SizePolicy>>();
+        let mut index = policies.iter().position(|p| p == self).unwrap_or(0);
+        let changed = ui.combo(label, &mut index, &policies, |x| x.name().into());
+        *self = policies[index];
+        if ui.is_item_hovered() {
+            ui.tooltip_text("what to do when the inputs are different sizes");
+        }
+        return changed;
+    }
+}
+
+/// the dimensions controls shared by generator nodes, which can take their size from the project canvas,
+/// their input, or a size set on the node
+pub fn generator_size_ui(ui: &Ui, use_canvas: &mut bool, use_input: &mut bool, size: &mut (u32, u32)) {
+    ui.checkbox("use project canvas size", use_canvas);
+    ui.disabled(*use_canvas, || {
+        ui.checkbox("use input texture for dimensions", use_input);
+        ui.disabled(*use_input, || {
+            let mut input_val = [size.0 as i32, size.1 as i32];
+            ui.input_int2("dimensions (w,h)", &mut input_val).build();
+            *size = (input_val[0].max(1) as u32, input_val[1].max(1) as u32);
+        });
+    });
+}
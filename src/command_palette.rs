@@ -0,0 +1,299 @@
+use std::path::PathBuf;
+
+use imgui::{Key, Ui, WindowFocusedFlags};
+use strum::IntoEnumIterator;
+use textdistance::{Algorithm, Levenshtein};
+
+use crate::{
+    generic_io::EditTab,
+    nodes::node_enum::NodeType,
+    project::{feedback_links, Project},
+    render_nodes::RenderNodesParams,
+    shortcuts::Action,
+    sidebar::SidebarParams,
+    user_info::UserSettings,
+};
+
+#[derive(Clone, Debug)]
+pub enum Command {
+    Action(Action),
+    AddNode(NodeType),
+    /// one of the actions returned by `MyNode::actions`
+    NodeAction { node_id: String, action: String },
+    OpenProject(PathBuf),
+    OpenLink(String),
+}
+
+pub struct CommandPalette {
+    pub open: bool,
+    pub search: String,
+    pub selected: usize,
+    /// true for the first frame after the palette is opened
+    focus_input: bool,
+}
+
+impl Default for CommandPalette {
+    fn default() -> Self {
+        CommandPalette {
+            open: false,
+            search: String::new(),
+            selected: 0,
+            focus_input: false,
+        }
+    }
+}
+
+impl CommandPalette {
+    pub fn show(&mut self) {
+        self.open = true;
+        self.search = String::new();
+        self.selected = 0;
+        self.focus_input = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+        self.search = String::new();
+    }
+}
+
+/// lower is a better match, anything above 0.9 should be hidden.
+/// substring and subsequence matches are checked first because the levenshtein distance
+/// between a short search and a long command name is always large
+pub fn fuzzy_score(text: &str, search: &str) -> f64 {
+    let text = text.to_lowercase();
+    let search = search.to_lowercase();
+
+    if search.is_empty() {
+        return 0.0;
+    }
+    if let Some(index) = text.find(&search) {
+        return 0.1 * index as f64 / text.len() as f64;
+    }
+
+    let mut chars = text.chars();
+    if search.chars().all(|c| chars.any(|t| t == c)) {
+        return 0.5;
+    }
+
+    let alg: Levenshtein = Levenshtein::default();
+    alg.for_str(&text, &search).ndist()
+}
+
+impl Project {
+    /// every command that can currently be run from the palette, along with its display name
+    pub fn palette_commands(&self, user_settings: &UserSettings) -> Vec<(String, Command)> {
+        let mut commands = vec![];
+
+        for action in Action::iter() {
+            if action == Action::CommandPalette {
+                continue;
+            }
+            commands.push((action.name(), Command::Action(action)));
+        }
+
+        for node in &self.nodes {
+            for action in node.actions() {
+                commands.push((
+                    format!("{} ({}): {}", node.name(), node.id(), action),
+                    Command::NodeAction {
+                        node_id: node.id(),
+                        action,
+                    },
+                ));
+            }
+        }
+
+        for node_type in NodeType::iter() {
+            #[cfg(not(debug_assertions))]
+            {
+                if node_type.disabled() {
+                    continue;
+                }
+            }
+            commands.push((
+                format!("Add Node: {}", node_type.name()),
+                Command::AddNode(node_type),
+            ));
+        }
+
+        for project in &user_settings.projects {
+            if project == &self.path {
+                continue;
+            }
+            if let Some(name) = project.file_name() {
+                commands.push((
+                    format!("Open Project: {}", name.to_string_lossy()),
+                    Command::OpenProject(project.to_path_buf()),
+                ));
+            }
+        }
+
+        for (name, link) in feedback_links() {
+            commands.push((format!("Feedback: {name}"), Command::OpenLink(link)));
+        }
+
+        return commands;
+    }
+
+    pub fn render_command_palette(
+        &mut self,
+        ui: &Ui,
+        user_settings: &mut UserSettings,
+        params: &mut RenderNodesParams,
+        sidebar_params: &mut SidebarParams,
+    ) {
+        if !self.command_palette.open {
+            return;
+        }
+
+        let size_array = ui.io().display_size;
+        let commands = self.palette_commands(user_settings);
+
+        let mut order = commands
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (fuzzy_score(name, &self.command_palette.search), i))
+            .filter(|(score, _)| *score < 0.9)
+            .collect::<Vec<(f64, usize)>>();
+        order.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut open = true;
+        let mut run = None;
+
+        ui.window("Command Palette")
+            .position([size_array[0] * 0.5, size_array[1] * 0.1], imgui::Condition::Always)
+            .position_pivot([0.5, 0.0])
+            .size([size_array[0] * 0.5, size_array[1] * 0.5], imgui::Condition::Always)
+            .collapsible(false)
+            .resizable(false)
+            .movable(false)
+            .focus_on_appearing(true)
+            .opened(&mut open)
+            .build(|| {
+                if ui.is_window_hovered() {
+                    params.moving = false;
+                    params.scale_changed = false;
+                }
+
+                let first_frame = self.command_palette.focus_input;
+                if first_frame {
+                    ui.set_keyboard_focus_here();
+                    self.command_palette.focus_input = false;
+                }
+
+                let item_width = ui.push_item_width(-1.0);
+                let enter = ui
+                    .input_text("##command palette search", &mut self.command_palette.search)
+                    .hint("type to search commands")
+                    .enter_returns_true(true)
+                    .build();
+                item_width.end();
+                if ui.is_item_edited() {
+                    self.command_palette.selected = 0;
+                }
+
+                let mut moved = false;
+                if ui.is_key_pressed(Key::DownArrow) {
+                    self.command_palette.selected += 1;
+                    moved = true;
+                }
+                if ui.is_key_pressed(Key::UpArrow) {
+                    self.command_palette.selected = self.command_palette.selected.saturating_sub(1);
+                    moved = true;
+                }
+                self.command_palette.selected = self
+                    .command_palette
+                    .selected
+                    .min(order.len().saturating_sub(1));
+
+                ui.child_window("commands").build(|| {
+                    for (row, (_, i)) in order.iter().enumerate() {
+                        let (name, command) = &commands[*i];
+                        let selected = row == self.command_palette.selected;
+                        if ui
+                            .selectable_config(format!("{name}##{i}"))
+                            .selected(selected)
+                            .build()
+                        {
+                            run = Some(*i);
+                        }
+                        if selected && moved {
+                            ui.set_scroll_here_y();
+                        }
+                        if let Command::Action(action) = command {
+                            if let Some(shortcut) = user_settings.shortcut(*action) {
+                                let text = shortcut.name();
+                                ui.same_line_with_pos(
+                                    ui.window_size()[0] - ui.calc_text_size(&text)[0] - 10.0,
+                                );
+                                ui.text_disabled(text);
+                            }
+                        }
+                    }
+                    if order.is_empty() {
+                        ui.text_disabled("no matching commands");
+                    }
+                });
+
+                if enter {
+                    run = order.get(self.command_palette.selected).map(|x| x.1);
+                }
+
+                if ui.is_key_pressed(Key::Escape)
+                    || (!first_frame
+                        && !ui.is_window_focused_with_flags(
+                            WindowFocusedFlags::ROOT_AND_CHILD_WINDOWS,
+                        ))
+                {
+                    self.command_palette.close();
+                }
+            });
+
+        if !open {
+            self.command_palette.close();
+        }
+
+        if let Some(i) = run {
+            self.command_palette.close();
+            let command = commands[i].1.clone();
+            log::info!("running command: {}", commands[i].0);
+            self.run_command(command, ui, user_settings, params, sidebar_params);
+        }
+    }
+
+    pub fn run_command(
+        &mut self,
+        command: Command,
+        ui: &Ui,
+        user_settings: &mut UserSettings,
+        params: &mut RenderNodesParams,
+        sidebar_params: &mut SidebarParams,
+    ) {
+        match command {
+            Command::Action(action) => {
+                self.run_action(action, ui, user_settings, params, sidebar_params);
+            }
+            Command::AddNode(node_type) => {
+                self.switch_tab = Some(EditTab::Nodes);
+                self.add_node(node_type, ui);
+            }
+            Command::NodeAction { node_id, action } => {
+                if let Some(index) = self.nodes.iter().position(|n| n.id() == node_id) {
+                    self.nodes[index].trigger_action(&action, ui.time());
+                    // some actions (like recording a gif) are updated by the edit window
+                    self.node_edit = Some(index);
+                    self.switch_tab = Some(EditTab::Nodes);
+                }
+            }
+            Command::OpenProject(path) => {
+                let _ = self.save();
+                user_settings.update_projects();
+                self.open_project = Some(path);
+            }
+            Command::OpenLink(link) => {
+                let _ = open::that_detached(link);
+            }
+        }
+    }
+}
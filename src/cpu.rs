@@ -0,0 +1,407 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::anyhow;
+use image::{DynamicImage, Rgba, Rgba32FImage};
+
+use crate::{backend::run_graph, node::MyNode, project::Project, project_settings::ProjectSettings, storage::TextureDepth};
+
+/// an image on the cpu. rows go from the bottom up, the same as opengl textures,
+/// so pixel (x, y) is at texture coordinates ((x + 0.5) / width, (y + 0.5) / height)
+pub type CpuTexture = Rgba32FImage;
+
+/// what `Storage` is to `MyNode::run`, for `MyNode::run_cpu`.
+/// it doesn't need an opengl context so graphs can be run anywhere, and it is the reference the shaders are checked against
+pub struct CpuStorage {
+    textures: HashMap<String, CpuTexture>,
+    text: HashMap<String, String>,
+    /// textures that have been decoded to linear light for the node that is running, keyed by the id they replace
+    linear_inputs: HashMap<String, String>,
+    /// the size generator nodes output unless they are given their own size
+    pub canvas_size: (u32, u32),
+    pub time: f64,
+    /// textures are rounded to this depth when they are set, the same as when a shader draws into a texture
+    pub texture_depth: TextureDepth,
+    pub project_root: PathBuf,
+}
+
+impl CpuStorage {
+    pub fn new(canvas_size: (u32, u32)) -> CpuStorage {
+        CpuStorage {
+            textures: HashMap::new(),
+            text: HashMap::new(),
+            linear_inputs: HashMap::new(),
+            canvas_size,
+            time: 0.0,
+            texture_depth: TextureDepth::default(),
+            project_root: PathBuf::new(),
+        }
+    }
+
+    pub fn get_texture(&self, k: &String) -> Option<&CpuTexture> {
+        if let Some(k) = self.linear_inputs.get(k) {
+            return self.textures.get(k);
+        }
+        self.textures.get(k)
+    }
+
+    /// the texture connected to the input with the id `input_id`
+    pub fn input(&self, map: &HashMap<String, String>, input_id: &str) -> anyhow::Result<&CpuTexture> {
+        let output_id = map.get(input_id).ok_or(anyhow!("missing input"))?;
+        return self
+            .get_texture(output_id)
+            .ok_or(anyhow!("cannot find input texture"));
+    }
+
+    pub fn set_texture(&mut self, k: String, mut v: CpuTexture) {
+        if self.texture_depth == TextureDepth::U8 {
+            for channel in v.iter_mut() {
+                *channel = (channel.clamp(0.0, 1.0) * 255.0).round() / 255.0;
+            }
+        }
+        self.textures.insert(k, v);
+    }
+
+    pub fn set_text(&mut self, k: String, v: String) {
+        self.text.insert(k, v);
+    }
+
+    pub fn get_text(&self, k: &String) -> Option<&String> {
+        self.text.get(k)
+    }
+
+    pub fn reset(&mut self) {
+        self.textures.clear();
+        self.text.clear();
+        self.linear_inputs.clear();
+    }
+
+    /// copies the texture or text `from` to `to`
+    pub fn copy(&mut self, from: &String, to: String) -> anyhow::Result<()> {
+        if let Some(text) = self.get_text(from) {
+            self.text.insert(to, text.to_owned());
+            return Ok(());
+        }
+        let texture = self
+            .get_texture(from)
+            .ok_or(anyhow!("cannot find input texture"))?
+            .clone();
+        self.set_texture(to, texture);
+        return Ok(());
+    }
+
+    /// sets `k` to a transparent texture of `size`
+    pub fn clear_texture(&mut self, k: String, size: (u32, u32)) {
+        self.set_texture(k, CpuTexture::new(size.0, size.1));
+    }
+
+    /// sets `k` to a copy of `Storage::error_texture`
+    pub fn set_error_texture(&mut self, k: String) {
+        let texture = image::load_from_memory(include_bytes!("img/th.jpg"))
+            .unwrap_or_else(|_x| DynamicImage::new_rgb8(20, 20))
+            .flipv()
+            .into_rgba32f();
+        self.set_texture(k, texture);
+    }
+
+    /// makes `get_texture(id)` return a linear light copy of the texture until `clear_linear_inputs` is called.
+    /// the copy isn't rounded to `texture_depth`, the same as the f16 copy `Storage` makes
+    pub fn decode_to_linear(&mut self, id: &String) {
+        if self.linear_inputs.contains_key(id) {
+            return;
+        }
+        let Some(texture) = self.get_texture(id) else {
+            return;
+        };
+        let linear_id = format!("linear-{id}");
+        self.textures.insert(linear_id.clone(), convert_color_space(texture, srgb_to_linear));
+        self.linear_inputs.insert(id.to_owned(), linear_id);
+    }
+
+    pub fn clear_linear_inputs(&mut self) {
+        for (_, linear_id) in std::mem::take(&mut self.linear_inputs) {
+            self.textures.remove(&linear_id);
+        }
+    }
+
+    /// replaces the texture `id` (which holds linear light) with an srgb copy rounded to the current `texture_depth`
+    pub fn encode_from_linear(&mut self, id: &String) {
+        let Some(texture) = self.get_texture(id) else {
+            return;
+        };
+        let texture = convert_color_space(texture, linear_to_srgb);
+        self.set_texture(id.to_owned(), texture);
+    }
+}
+
+/// `texture` with `f` run on the red, green and blue of every pixel
+fn convert_color_space(texture: &CpuTexture, f: fn(f32) -> f32) -> CpuTexture {
+    let mut texture = texture.clone();
+    for pixel in texture.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = f(*channel);
+        }
+    }
+    return texture;
+}
+
+/// the same as `srgb_to_linear` in the shader `Storage` converts colour spaces with
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c < 0.04045 {
+        return c / 12.92;
+    }
+    return ((c + 0.055) / 1.055).powf(2.4);
+}
+
+/// the same as `linear_to_srgb` in the shader `Storage` converts colour spaces with
+pub fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.max(0.0);
+    if c < 0.0031308 {
+        return c * 12.92;
+    }
+    return 1.055 * c.powf(1.0 / 2.4) - 0.055;
+}
+
+/// makes a texture by running `f` on every pixel, the same as a fragment shader.
+/// `f` is given the texture coordinates of the pixel and the colour of each of `inputs` at those coordinates
+pub fn draw(
+    size: (u32, u32),
+    inputs: &[&CpuTexture],
+    f: impl Fn([f32; 2], &[[f32; 4]]) -> [f32; 4],
+) -> CpuTexture {
+    let mut samples = vec![[0.0; 4]; inputs.len()];
+    return CpuTexture::from_fn(size.0, size.1, |x, y| {
+        let uv = [
+            (x as f32 + 0.5) / size.0 as f32,
+            (y as f32 + 0.5) / size.1 as f32,
+        ];
+        for (sample_value, input) in samples.iter_mut().zip(inputs) {
+            *sample_value = sample(input, uv);
+        }
+        Rgba(f(uv, &samples))
+    });
+}
+
+/// reads a texture at `uv` with linear filtering, outside of 0-1 the texture repeats.
+/// this is what a shader reading a texture given without a sampler does, which is how every node gives them
+pub fn sample(texture: &CpuTexture, uv: [f32; 2]) -> [f32; 4] {
+    let (width, height) = texture.dimensions();
+    if width == 0 || height == 0 {
+        return [0.0; 4];
+    }
+    let wrap = |i: i64, size: u32| i.rem_euclid(size as i64) as u32;
+    let x = uv[0] * width as f32 - 0.5;
+    let y = uv[1] * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let pixel = |dx: i64, dy: i64| {
+        texture
+            .get_pixel(wrap(x0 as i64 + dx, width), wrap(y0 as i64 + dy, height))
+            .0
+    };
+    let (a, b, c, d) = (pixel(0, 0), pixel(1, 0), pixel(0, 1), pixel(1, 1));
+    let mut out = [0.0; 4];
+    for i in 0..4 {
+        let bottom = mix(a[i], b[i], tx);
+        let top = mix(c[i], d[i], tx);
+        out[i] = mix(bottom, top, ty);
+    }
+    return out;
+}
+
+/// halves `texture` until it is 1x1, averaging each 2x2 block, like `glGenerateMipmap`.
+/// the first level is the texture itself
+pub fn mipmaps(texture: &CpuTexture) -> Vec<CpuTexture> {
+    let mut levels = vec![texture.clone()];
+    while let Some(last) = levels.last().filter(|a| a.width() > 1 || a.height() > 1) {
+        let (width, height) = last.dimensions();
+        let level = CpuTexture::from_fn((width / 2).max(1), (height / 2).max(1), |x, y| {
+            let mut out = [0.0; 4];
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let pixel = last.get_pixel((x * 2 + dx).min(width - 1), (y * 2 + dy).min(height - 1));
+                for i in 0..4 {
+                    out[i] += pixel[i] / 4.0;
+                }
+            }
+            Rgba(out)
+        });
+        levels.push(level);
+    }
+    return levels;
+}
+
+/// reads `levels` from `mipmaps` at `uv` with trilinear filtering, `lod` is the log2 of how many texels
+/// one pixel covers. this is what a shader reading a texture that has mipmaps does when it is drawn smaller
+pub fn sample_lod(levels: &[CpuTexture], uv: [f32; 2], lod: f32) -> [f32; 4] {
+    let lod = lod.clamp(0.0, (levels.len() - 1) as f32);
+    let below = sample(&levels[lod.floor() as usize], uv);
+    if lod.fract() == 0.0 {
+        return below;
+    }
+    let above = sample(&levels[lod.ceil() as usize], uv);
+    return [0, 1, 2, 3].map(|i| mix(below[i], above[i], lod.fract()));
+}
+
+/// glsl's `mix`
+pub fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a * (1.0 - t) + b * t
+}
+
+/// glsl's `fract`
+pub fn fract(a: f32) -> f32 {
+    a - a.floor()
+}
+
+/// glsl's `mod`, unlike `%` the result has the sign of `b`
+pub fn glsl_mod(a: f32, b: f32) -> f32 {
+    a - b * (a / b).floor()
+}
+
+/// glsl's `smoothstep`
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    return t * t * (3.0 - 2.0 * t);
+}
+
+/// the `rand` function most of the shaders use
+pub fn rand(co: [f32; 2]) -> f32 {
+    fract((co[0] * 12.9898 + co[1] * 78.233).sin() * 43758.5453)
+}
+
+/// the blur loop of gaussian.glsl, diff_gaussian.glsl and watercolor.glsl, with `samples * 2` samples across.
+/// like the shaders each sample is read at the offset of the one before it, `offset` is that offset and it
+/// carries over from one blur to the next. `rr` is separate because diff_gaussian.glsl doesn't update it for its second blur
+pub fn gaussian_blur(texture: &CpuTexture, uv: [f32; 2], r: f32, rr: f32, samples: f32, offset: &mut [f32; 2]) -> [f32; 4] {
+    let (xs, ys) = (texture.width() as f32, texture.height() as f32);
+    let w0 = 0.3780 / r.powf(1.975);
+    let mut weight_total = 0.0;
+    let mut col = [0.0; 4];
+    let mut p = [0.0; 2];
+    let mut x2 = -samples;
+    while x2 < samples {
+        p[0] = uv[0] + offset[0] * (1.0 / xs);
+        offset[0] = (x2 / samples) * r;
+        let xx = offset[0] * offset[0];
+        let mut y2 = -samples;
+        while y2 < samples {
+            p[1] = uv[1] + offset[1] * (1.0 / ys);
+            offset[1] = (y2 / samples) * r;
+            let yy = offset[1] * offset[1];
+            if xx + yy <= rr {
+                let w = w0 * ((-xx - yy) / (2.0 * rr)).exp();
+                weight_total += w;
+                let c = sample(texture, p);
+                for i in 0..4 {
+                    col[i] += c[i] * w;
+                }
+            }
+            y2 += 1.0;
+        }
+        x2 += 1.0;
+    }
+    return col.map(|a| a / weight_total);
+}
+
+/// the same as `rgb2hsv` in the hsv shaders
+pub fn rgb2hsv(c: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = c;
+    let k = [0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0];
+    let step = if g >= b { 1.0 } else { 0.0 };
+    let p = [
+        mix(b, g, step),
+        mix(g, b, step),
+        mix(k[3], k[0], step),
+        mix(k[2], k[1], step),
+    ];
+    let step = if r >= p[0] { 1.0 } else { 0.0 };
+    let q = [
+        mix(p[0], r, step),
+        mix(p[1], p[1], step),
+        mix(p[3], p[2], step),
+        mix(r, p[0], step),
+    ];
+    let d = q[0] - q[3].min(q[1]);
+    let e = 1.0e-10;
+    return [
+        (q[2] + (q[3] - q[1]) / (6.0 * d + e)).abs(),
+        d / (q[0] + e),
+        q[0],
+    ];
+}
+
+/// the same as `hsv2rgb` in the hsv shaders
+pub fn hsv2rgb(c: [f32; 3]) -> [f32; 3] {
+    let k = [1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0];
+    let mut out = [0.0; 3];
+    for i in 0..3 {
+        let p = (fract(c[0] + k[i]) * 6.0 - k[3]).abs();
+        out[i] = c[2] * mix(k[0], (p - k[0]).clamp(0.0, 1.0), c[1]);
+    }
+    return out;
+}
+
+/// runs every node on the cpu, see `backend::run_graph`
+pub fn run_graph_cpu(
+    nodes: &mut [Box<dyn MyNode>],
+    connections: &HashMap<String, String>,
+    settings: &ProjectSettings,
+    storage: &mut CpuStorage,
+) -> HashMap<String, anyhow::Result<()>> {
+    return run_graph(nodes, connections, settings, storage);
+}
+
+impl Project {
+    /// runs the graph on the cpu and logs how far the outputs of each node are from the textures of the last run.
+    /// nodes without a cpu version and nodes that were fused or downsampled on the last run are skipped
+    pub fn compare_with_cpu(&mut self) {
+        let mut storage = CpuStorage::new(self.project_settings.canvas_size);
+        storage.time = self.storage.time;
+        storage.texture_depth = self.project_settings.texture_depth;
+        storage.project_root = self.storage.project_root.clone();
+        let results = run_graph_cpu(&mut self.nodes, &self.connections, &self.project_settings, &mut storage);
+
+        for node in &self.nodes {
+            if let Some(Err(e)) = results.get(&node.id()) {
+                log::info!("{} ({}) was not compared: {e}", node.name(), node.id());
+                continue;
+            }
+            for output in node.outputs() {
+                let key = node.output_id(&output);
+                let (Some(cpu), Some(gl)) = (storage.get_texture(&key), self.storage.get_texture(&key)) else {
+                    continue;
+                };
+                if cpu.dimensions() != gl.dimensions() {
+                    log::info!(
+                        "{} ({}) {output} was not compared: the cpu output is {:?} and the gpu output is {:?}",
+                        node.name(),
+                        node.id(),
+                        cpu.dimensions(),
+                        gl.dimensions()
+                    );
+                    continue;
+                }
+                let pixels: Vec<(u8, u8, u8, u8)> = match gl.read_to_pixel_buffer().read() {
+                    Ok(a) => a,
+                    Err(e) => {
+                        log::error!("failed to read texture {key}: {e:?}");
+                        continue;
+                    }
+                };
+                let largest = pixels
+                    .iter()
+                    .zip(cpu.pixels())
+                    .flat_map(|((r, g, b, a), c)| {
+                        [*r, *g, *b, *a]
+                            .into_iter()
+                            .zip(c.0)
+                            .map(|(gl, cpu)| (gl as f32 / 255.0 - cpu.clamp(0.0, 1.0)).abs())
+                    })
+                    .fold(0.0, f32::max);
+                log::info!(
+                    "{} ({}) {output}: the largest difference from the cpu is {largest:.4}",
+                    node.name(),
+                    node.id()
+                );
+            }
+        }
+    }
+}
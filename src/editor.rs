@@ -0,0 +1,262 @@
+//! the ReAnimator editor, the binary just calls [`run`]
+
+use std::{
+    env::{self, current_exe}, path::PathBuf, thread::sleep, time::{Duration, Instant}
+};
+
+use imgui_winit_support::winit::{
+    dpi::{LogicalSize},
+    window::{Fullscreen},
+};
+use glium::backend::Facade;
+use log::{info};
+use platform_dirs::{AppDirs};
+use crate::{
+    debug_and_logger::{self, set_logger_mine},
+    fonts::MyFonts,
+    import_export::load_project,
+    platform::relaunch_program,
+    popups::{set_panic_hook, setup_popup, update},
+    project::Project,
+    support::{create_context, init_with_startup},
+    user_info::{UserSettings, USER_SETTINGS_SAVEFILE_VERSION},
+};
+use self_update::cargo_crate_version;
+
+use system_extensions::dialogues::messagebox::{IconType, MessageBox, WindowType};
+
+use perf_monitor::cpu::{ThreadStat, ProcessStat, processor_numbers};
+
+/// opens the editor window, returns when it is closed
+pub fn run() -> anyhow::Result<()> {
+
+    // #[cfg(debug_assertions)] {
+        set_logger_mine()?;
+    // }
+    
+    set_panic_hook();
+
+
+    let a = update();
+
+    // move this to popups.rs
+    if let Err(a2) = a {
+        if a2.to_string().contains("os error 5") {
+            if MessageBox::new("Updater", "New update available, press ok to install")
+                .set_icon_type(IconType::ICON_INFORMATION)
+                .set_window_type(WindowType::OK_CANCEL)
+                .show()
+                .is_ok()
+            {
+                relaunch_program(true, "");
+            };
+        } else {
+            
+        }
+    }
+
+    let args: Vec<String> = env::args().collect();
+
+    info!("args {args:?}");
+
+    // update();
+
+    let app_dirs = match AppDirs::new(Some("ReAnimator"), false) {
+        Some(a) => a.config_dir,
+        None => current_exe().unwrap(),
+    };
+
+    let fonts = MyFonts::new();
+
+    let mut user_settings: UserSettings = savefile::load_file(
+        app_dirs.join("settings.bat"),
+        USER_SETTINGS_SAVEFILE_VERSION,
+    )
+    .unwrap_or_default();
+
+    if !user_settings.finished_setup {
+        setup_popup(&user_settings);
+    }
+
+    let mut project: Option<Project> = None;
+
+    if !user_settings.finished_setup && false {
+        setup_popup(&user_settings);
+    }
+
+    user_settings.update_projects();
+
+    let mut return_to_home = false;
+    let mut open_project: Option<PathBuf> = None;
+
+    let mut ctx: imgui::Context = create_context();
+
+    
+
+    let mut save_timer = Instant::now();
+
+    let mut settings_window_open = false;
+
+    user_settings.load_theme(&mut ctx);
+
+    let fullscreen = user_settings.fullscreen;
+
+    let mut loaded_project = false;
+    let visible = true;
+
+    init_with_startup(
+        "ReAnimator",
+        None,
+        visible,
+        |_, _, _display| {
+            
+        },
+        move |_, ui, display, renderer, drop_file, window| {
+
+            if !loaded_project && args.len() >= 2 && args[1].contains(".repj") {
+                if let Some(p) = load_project(&args[1], &user_settings) {
+                    project = Some(Project::new(&p, display.get_context().clone()));
+                    loaded_project = true;
+                }
+            }
+            let mut stat_p: Result<ProcessStat, std::io::Error> = Err(std::io::Error::other("not debug mode"));
+            let mut stat_t: Result<ThreadStat, std::io::Error> =   Err(std::io::Error::other("not debug mode"));
+            
+            #[cfg(debug_assertions)] {
+            let _core_num = processor_numbers().unwrap();
+            stat_p = ProcessStat::cur();
+            stat_t = ThreadStat::cur();
+            }
+            
+            let frame_start = Instant::now();
+
+            let mut global_font_tokens = vec![];
+            if let Some(font_id) = user_settings.font_id {
+                if ui.fonts().get_font(font_id).is_some() {
+                    global_font_tokens.push(ui.push_font(font_id));
+                }
+            }
+
+            if return_to_home {
+                project = None;
+                return_to_home = false;
+                window.set_maximized(false);
+                let _ = window.request_inner_size(LogicalSize::new(1024, 512));
+            }
+
+            if let Some(path) = open_project.take() {
+                project = Some(Project::new(&path, display.get_context().clone()));
+            }
+
+
+            
+
+            let size_array = ui.io().display_size;
+
+            if let Some(ref mut project) = project {
+
+                
+
+
+                if let Some(path) = drop_file {
+                    project.drop_file(path, ui);
+                }
+                project.render(ui, &mut user_settings, renderer, window);
+
+                
+                return_to_home = project.return_to_home_menu;
+                open_project = project.open_project.take();
+                // ui.show_default_style_editor();
+                if save_timer.elapsed().as_secs_f32() > 2.0 {
+                    save_timer = Instant::now();
+                    if user_settings.history {
+                        let r = project.update_history_and_save();
+                        match r {
+                            Ok(_) => {}
+                            Err(e) => {
+                                info!("{e}")
+                            }
+                        }
+                    } else {
+                        if window.is_maximized() {
+                            project.project_settings.maximised = true;
+                        } else {
+                            if let Ok(pos) = window.outer_position() {
+                                project.project_settings.window_pos =
+                                    Some([pos.x as f32, pos.y as f32]);
+                            }
+                            let size = window.inner_size();
+                            project.project_settings.window_size =
+                                Some([size.width as f32, size.height as f32]);
+                        }
+
+                        let _ = project.save();
+                    }
+                }
+            } else {
+                project = Project::project_menu(ui, display, &mut user_settings, renderer);
+                ui.window("settings button")
+                    .draw_background(false)
+                    .movable(false)
+                    .no_decoration()
+                    .position_pivot([0.0, 1.0])
+                    .size_constraints(
+                        [ui.calc_text_size("settings xxxxxxxx")[0], -1.0],
+                        [9999.0, -1.0],
+                    )
+                    .position([10.0, size_array[1] + 5.0], imgui::Condition::Always)
+                    .build(|| {
+                        if ui.button("settings") {
+                            settings_window_open = true;
+                        }
+                        // ui.same_line();
+                    });
+                ui.window("v")
+                    .draw_background(false)
+                    .movable(false)
+                    .no_decoration()
+                    .position_pivot([0.5, 1.0])
+                    .size_constraints([ui.calc_text_size("")[0], -1.0], [9999.0, -1.0])
+                    .position(
+                        [size_array[0] * 0.5, size_array[1]],
+                        imgui::Condition::Always,
+                    )
+                    .build(|| {
+                        ui.text("v".to_owned() + cargo_crate_version!());
+                        // cargo_crate_version!()
+                    });
+            }
+
+            if settings_window_open {
+                user_settings.settings_window(ui, &mut settings_window_open, &fonts);
+            }
+            #[cfg(debug_assertions)] {
+            if ui.io().key_alt {
+                let usage_p = stat_p.unwrap().cpu().unwrap() * 100f64;
+                let usage_t = stat_t.unwrap().cpu().unwrap() * 100f64;
+
+
+                debug_and_logger::profile(ui, usage_p, usage_t);
+            }}
+            
+            sleep(Duration::from_secs_f32(
+                (1.0 / (user_settings.max_fps as f32)
+                    - (Instant::now() - frame_start).as_secs_f32())
+                .max(0.0),
+            ));
+
+
+
+        },
+        if fullscreen {
+            Some(Fullscreen::Borderless(None))
+        } else {
+            None
+        },
+        &mut ctx,
+    );
+
+    ctx.suspend();
+
+    return Ok(());
+}
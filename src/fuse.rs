@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use glium::{uniforms::DynamicUniforms, DrawParameters, Surface, Texture2d};
+use platform_dirs::UserDirs;
+use rfd::FileDialog;
+
+use crate::{
+    node::{NodeFallback, NodeMode},
+    nodes::basic_shader_nodes::custom_shader::UniformValue,
+    project::{calculate_hash, Project},
+    storage::TextureDepth,
+};
+
+/// glsl for a node that only reads the pixel it is drawing from each of its inputs.
+/// chains of these nodes are drawn by one generated shader instead of one draw per node
+pub struct PixelCode {
+    /// reads the inputs from `$in_0`, `$in_1`.. and writes the outputs to `$out_0`, `$out_1`..,
+    /// which are all `vec4`s in the same order as the node's `inputs` and `outputs`.
+    /// `$` is replaced with a prefix that is unique to the node, so uniforms and functions should start with it as well
+    pub code: String,
+    /// functions used by `code`
+    pub functions: String,
+    /// the uniforms used by `code`, named without the `$`
+    pub uniforms: Vec<(String, UniformValue)>,
+}
+
+impl PixelCode {
+    /// a fragment shader that draws just this node, for nodes with one input and one output.
+    /// the input is the `tex` sampler and the uniforms keep their names without a prefix, so it is drawn the same way as a hand written shader
+    pub fn standalone_glsl(&self) -> String {
+        let mut declarations = String::new();
+        for (name, value) in &self.uniforms {
+            declarations += &format!("uniform {} {name};\n", value.glsl_type());
+        }
+        let mut body = String::new();
+        for line in self.code.replace("$", "").lines() {
+            if !line.trim().is_empty() {
+                body += &format!("    {}\n", line.trim());
+            }
+        }
+        return format!(
+            "#version 140\n\nin vec2 v_tex_coords;\nout vec4 color;\n\nuniform sampler2D tex;\n{declarations}\n{}\nvoid main() {{\n    vec4 in_0 = texture(tex, v_tex_coords);\n    vec4 out_0 = vec4(0.0);\n{body}    color = out_0;\n}}\n",
+            self.functions.replace("$", "")
+        );
+    }
+}
+
+/// nodes that are drawn together by generated shaders
+pub struct FusedGroup {
+    /// node ids in the order they run
+    pub members: Vec<String>,
+    /// the textures from outside the group that it reads, these are the `input_0`, `input_1`.. samplers
+    pub inputs: Vec<String>,
+    /// the outputs of members that are used outside of the group, each one is drawn into its own texture
+    pub outputs: Vec<String>,
+    /// which input each member takes its size from, following the first input of every node back out of the group
+    pub sizes: HashMap<String, usize>,
+}
+
+impl FusedGroup {
+    pub fn contains(&self, id: &str) -> bool {
+        return self.members.iter().any(|a| a == id);
+    }
+}
+
+fn node_id(key: &str) -> &str {
+    return key.split("-").collect::<Vec<&str>>()[1];
+}
+
+/// the entries of a map of node settings in the same order every time, so they can be hashed
+fn sorted<T: Copy>(map: &HashMap<String, T>) -> Vec<(&String, T)> {
+    let mut entries = map.iter().map(|(k, v)| (k, *v)).collect::<Vec<(&String, T)>>();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    return entries;
+}
+
+impl Project {
+    /// true if the node can be drawn as part of a fused shader without changing what it outputs
+    fn can_fuse(&self, index: usize) -> bool {
+        let node = &self.nodes[index];
+        let id = node.id();
+        return node.pixel_code().is_some()
+            && self.project_settings.node_mode(&id) == NodeMode::Normal
+            && self.project_settings.node_fallback(&id) == NodeFallback::None
+            && !self.project_settings.node_texture_depths.contains_key(&id)
+            && node
+                .inputs()
+                .iter()
+                .all(|a| self.connections.contains_key(&node.input_id(a)));
+    }
+
+    /// finds the fused groups again if the connections or the settings of the nodes have changed since they were last found
+    pub(crate) fn update_fused_groups(&mut self) {
+        let settings = &self.project_settings;
+        let hash = calculate_hash(&(
+            self.node_run_order.0,
+            settings.fuse_nodes,
+            settings.texture_depth,
+            sorted(&settings.node_modes),
+            sorted(&settings.node_fallbacks),
+            sorted(&settings.node_texture_depths),
+        ));
+        if hash != self.fused_groups.0 {
+            self.fused_groups = (hash, self.find_fused_groups());
+        }
+    }
+
+    /// finds the chains of per-pixel nodes in the run order that can be drawn together.
+    /// groups are only made from nodes that don't need to run one at a time, and any group with a shader
+    /// that fails to compile is left out
+    pub fn find_fused_groups(&mut self) -> Vec<FusedGroup> {
+        if !self.project_settings.fuse_nodes {
+            return vec![];
+        }
+        let run_order = self.node_run_order.1.clone();
+        let position: HashMap<&str, usize> = run_order
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), i))
+            .collect();
+        let index_of: HashMap<String, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.id(), i))
+            .collect();
+        // the nodes that read each node's outputs
+        let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (input, output) in &self.connections {
+            children.entry(node_id(output)).or_default().push(node_id(input));
+        }
+
+        let mut members: Vec<Vec<String>> = vec![];
+        let mut group_of: HashMap<String, usize> = HashMap::new();
+        for id in &run_order {
+            let Some(&index) = index_of.get(id) else {
+                continue;
+            };
+            if !self.can_fuse(index) {
+                continue;
+            }
+            let node = &self.nodes[index];
+            let parents = node
+                .inputs()
+                .iter()
+                .filter_map(|a| self.connections.get(&node.input_id(a)))
+                .map(|a| node_id(a).to_owned())
+                .collect::<Vec<String>>();
+
+            let joins = parents.iter().filter_map(|a| group_of.get(a).copied()).find(|g| {
+                let group = &members[*g];
+                // a parent outside the group that depends on the group would have to run in the middle of it
+                let outside_parents_ok = parents
+                    .iter()
+                    .filter(|a| !group.contains(a))
+                    .filter_map(|a| index_of.get(a))
+                    .all(|a| {
+                        self.upstream_nodes(*a)
+                            .iter()
+                            .all(|b| !group.contains(&self.nodes[*b].id()))
+                    });
+                // the group runs in place of its last member, so nothing outside it can read from it before then
+                let children_ok = group.iter().chain([id]).all(|member| {
+                    children.get(member.as_str()).map_or(true, |a| {
+                        a.iter().all(|child| {
+                            *child == id.as_str()
+                                || group.iter().any(|b| b.as_str() == *child)
+                                || position.get(child).map_or(true, |p| *p > position[id.as_str()])
+                        })
+                    })
+                });
+                outside_parents_ok && children_ok
+            });
+            match joins {
+                Some(g) => {
+                    members[g].push(id.to_owned());
+                    group_of.insert(id.to_owned(), g);
+                }
+                None => {
+                    group_of.insert(id.to_owned(), members.len());
+                    members.push(vec![id.to_owned()]);
+                }
+            }
+        }
+
+        let mut groups = vec![];
+        for group_members in members {
+            if group_members.len() < 2 {
+                continue;
+            }
+            let mut group = FusedGroup {
+                members: group_members,
+                inputs: vec![],
+                outputs: vec![],
+                sizes: HashMap::new(),
+            };
+            for member in group.members.clone() {
+                let node = &self.nodes[index_of[&member]];
+                for (i, input) in node.inputs().iter().enumerate() {
+                    let output = &self.connections[&node.input_id(input)];
+                    let parent = node_id(output);
+                    let size = if group.contains(parent) {
+                        group.sizes[parent]
+                    } else {
+                        match group.inputs.iter().position(|a| a == output) {
+                            Some(a) => a,
+                            None => {
+                                group.inputs.push(output.to_owned());
+                                group.inputs.len() - 1
+                            }
+                        }
+                    };
+                    if i == 0 {
+                        group.sizes.insert(member.clone(), size);
+                    }
+                }
+                for output in node.outputs() {
+                    let output_id = node.output_id(&output);
+                    let used_outside = self.connections.iter().any(|(input, a)| {
+                        *a == output_id
+                            && !group.contains(node_id(input))
+                            && position.contains_key(node_id(input))
+                    });
+                    if used_outside {
+                        group.outputs.push(output_id);
+                    }
+                }
+            }
+
+            let compiled = group.outputs.iter().try_for_each(|output| {
+                let (source, _) = self.fused_glsl(&group, &[("color".to_owned(), output.to_owned())], false)?;
+                self.storage.compile_frag_shader(source).map_err(|e| anyhow!(e))
+            });
+            match compiled {
+                Ok(()) => groups.push(group),
+                Err(e) => log::error!("failed to compile fused shader, running the nodes one at a time: {e}"),
+            }
+        }
+        return groups;
+    }
+
+    /// writes a fragment shader that draws `outputs` of the group, given as (glsl name, output id).
+    /// the uniforms are returned with the prefixes they have in the shader.
+    /// when `values` is true the uniforms are given their current values as initialisers so the shader works on its own
+    pub fn fused_glsl(
+        &self,
+        group: &FusedGroup,
+        outputs: &[(String, String)],
+        values: bool,
+    ) -> anyhow::Result<(String, Vec<(String, UniformValue)>)> {
+        let mut declarations = String::new();
+        let mut functions = String::new();
+        let mut body = String::new();
+        let mut uniforms = vec![];
+        // the glsl variable that holds each output id
+        let mut variables: HashMap<String, String> = HashMap::new();
+        // unfused, every member is drawn into an 8 bit texture which clamps its outputs
+        let clamp = self.project_settings.texture_depth == TextureDepth::U8;
+
+        for (i, _) in group.inputs.iter().enumerate() {
+            declarations += &format!("uniform sampler2D input_{i};\n");
+        }
+
+        for (k, member) in group.members.iter().enumerate() {
+            let node = self
+                .nodes
+                .iter()
+                .find(|a| a.id() == *member)
+                .ok_or(anyhow!("cannot find node {member}"))?;
+            let code = node
+                .pixel_code()
+                .ok_or(anyhow!("{} cannot be fused", node.name()))?;
+            let prefix = format!("n{k}_");
+
+            for (name, value) in code.uniforms {
+                let name = format!("{prefix}{name}");
+                declarations += &match value.glsl_literal().filter(|_| values) {
+                    Some(literal) => format!("uniform {} {name} = {literal};\n", value.glsl_type()),
+                    None => format!("uniform {} {name};\n", value.glsl_type()),
+                };
+                uniforms.push((name, value));
+            }
+            functions += &code.functions.replace("$", &prefix);
+
+            body += &format!("    // {} ({})\n", node.name(), member);
+            for (i, input) in node.inputs().iter().enumerate() {
+                let output = &self.connections[&node.input_id(input)];
+                let value = match variables.get(output) {
+                    Some(a) => a.to_owned(),
+                    None => match group.inputs.iter().position(|a| a == output) {
+                        Some(a) => format!("texture(input_{a}, v_tex_coords)"),
+                        None => return Err(anyhow!("{input} of {member} is not part of the group")),
+                    },
+                };
+                body += &format!("    vec4 {prefix}in_{i} = {value};\n");
+            }
+            for (i, output) in node.outputs().iter().enumerate() {
+                body += &format!("    vec4 {prefix}out_{i} = vec4(0.0);\n");
+                variables.insert(node.output_id(output), format!("{prefix}out_{i}"));
+            }
+            body += "    {\n";
+            for line in code.code.replace("$", &prefix).lines() {
+                if !line.trim().is_empty() {
+                    body += &format!("        {}\n", line.trim());
+                }
+            }
+            body += "    }\n";
+            if clamp {
+                for (i, _) in node.outputs().iter().enumerate() {
+                    body += &format!("    {prefix}out_{i} = clamp({prefix}out_{i}, 0.0, 1.0);\n");
+                }
+            }
+        }
+
+        let mut out_declarations = String::new();
+        for (name, output) in outputs {
+            let variable = variables
+                .get(output)
+                .ok_or(anyhow!("{output} is not part of the group"))?;
+            out_declarations += &format!("out vec4 {name};\n");
+            body += &format!("    {name} = {variable};\n");
+        }
+
+        let source = format!(
+            "#version 140\n\nin vec2 v_tex_coords;\n{out_declarations}\n{declarations}\n{functions}\nvoid main() {{\n{body}}}\n"
+        );
+        return Ok((source, uniforms));
+    }
+
+    /// runs a group in place of its members, their errors are set the same as if they had run one at a time
+    pub fn run_fused_group(&mut self, group: &FusedGroup, failed_nodes: &mut HashMap<String, String>) {
+        let last = group.members.last().cloned().unwrap_or_default();
+        self.storage.texture_depth = self.project_settings.texture_depth;
+        let timer = self.storage.profiler.start(
+            &last,
+            format!("Fused Shader ({} nodes)", group.members.len()),
+            self.storage.texture_bytes,
+            &self.storage.display,
+        );
+
+        let upstream_error = group
+            .members
+            .iter()
+            .filter_map(|a| self.nodes.iter().position(|n| n.id() == *a))
+            .find_map(|a| self.upstream_error(a, failed_nodes));
+        let worked = match &upstream_error {
+            Some(e) => Err(anyhow!(e.to_owned())),
+            None => self.draw_fused_group(group),
+        };
+
+        let cpu_time = self.storage.profiler.stop(
+            timer,
+            self.storage.texture_bytes,
+            &self.storage.display,
+        );
+        for member in &group.members {
+            match &worked {
+                Ok(()) => {
+                    self.node_speeds
+                        .insert(member.to_owned(), cpu_time / group.members.len() as u32);
+                    self.node_error_value.insert(member.to_owned(), Ok(()));
+                }
+                Err(e) => {
+                    let message = match &upstream_error {
+                        Some(a) => a.to_owned(),
+                        None => format!("upstream fused shader ({}) failed: {}", last, e),
+                    };
+                    self.node_speeds.remove(member);
+                    failed_nodes.insert(member.to_owned(), message);
+                    self.node_error_value
+                        .insert(member.to_owned(), Err(anyhow!(e.to_string())));
+                }
+            }
+        }
+    }
+
+    /// draws the outputs of a group that are used outside of it
+    fn draw_fused_group(&mut self, group: &FusedGroup) -> anyhow::Result<()> {
+        for output in &group.outputs {
+            let (source, uniforms) =
+                self.fused_glsl(group, &[("color".to_owned(), output.to_owned())], false)?;
+            self.storage
+                .compile_frag_shader(source.clone())
+                .map_err(|e| anyhow!("failed to compile fused shader: {e}"))?;
+
+            let member = node_id(output);
+            let size = match group
+                .sizes
+                .get(member)
+                .and_then(|a| group.inputs.get(*a))
+                .and_then(|a| self.storage.get_texture(a))
+            {
+                Some(a) => a.dimensions(),
+                None => return Err(anyhow!("cannot find input texture")),
+            };
+            self.storage.create_and_set_texture(size.0, size.1, output.to_owned());
+
+            let names = (0..group.inputs.len())
+                .map(|i| format!("input_{i}"))
+                .collect::<Vec<String>>();
+            let mut textures: Vec<&Texture2d> = vec![];
+            for key in &group.inputs {
+                match self.storage.get_texture(key) {
+                    Some(a) => textures.push(a),
+                    None => return Err(anyhow!("cannot find input texture")),
+                }
+            }
+
+            let mut dynamic_uniforms = DynamicUniforms::new();
+            for (name, texture) in names.iter().zip(&textures) {
+                dynamic_uniforms.add(name, texture);
+            }
+            for (name, value) in &uniforms {
+                if let Some(value) = value.as_uniform_value() {
+                    dynamic_uniforms.add(name, value);
+                }
+            }
+
+            let shader = self.storage.get_frag_shader(source).unwrap();
+            self.storage.get_texture(output).unwrap().as_surface().draw(
+                &self.storage.vertex_buffer,
+                &self.storage.indices,
+                shader,
+                &dynamic_uniforms,
+                &DrawParameters {
+                    ..Default::default()
+                },
+            )?;
+        }
+        return Ok(());
+    }
+
+    /// saves the fused shader that the node is part of as a standalone glsl file.
+    /// every output that is used outside of the group gets its own `out vec4`
+    pub fn export_fused_shader(&self, id: &str) -> anyhow::Result<()> {
+        let group = self
+            .fused_groups
+            .1
+            .iter()
+            .find(|a| a.contains(id))
+            .ok_or(anyhow!("this node is not part of a fused shader"))?;
+
+        let outputs = if group.outputs.len() == 1 {
+            vec![("color".to_owned(), group.outputs[0].to_owned())]
+        } else {
+            group
+                .outputs
+                .iter()
+                .enumerate()
+                .map(|(i, a)| (format!("out_{i}"), a.to_owned()))
+                .collect()
+        };
+        let (source, _) = self.fused_glsl(group, &outputs, true)?;
+
+        let mut header = String::from("// generated by ReAnimator\n");
+        for (i, input) in group.inputs.iter().enumerate() {
+            header += &format!("// input_{i}: {input}\n");
+        }
+        for (name, output) in &outputs {
+            header += &format!("// {name}: {output}\n");
+        }
+        // the version directive has to stay on the first line
+        let source = source.replacen("\n", &format!("\n{header}"), 1);
+
+        if let Some(path) = FileDialog::new()
+            .set_can_create_directories(true)
+            .set_title("Export Fused Shader")
+            .set_directory(UserDirs::new().map(|a| a.download_dir).unwrap_or_default())
+            .set_file_name("fused.frag")
+            .add_filter("glsl", &["frag", "glsl"])
+            .save_file()
+        {
+            std::fs::write(path, source)?;
+        }
+        return Ok(());
+    }
+
+    /// true if the connection from `output` to `input` is inside a fused shader, so the output isn't drawn into a texture
+    pub fn is_fused_connection(&self, input: &str, output: &str) -> bool {
+        return self
+            .fused_groups
+            .1
+            .iter()
+            .any(|a| a.contains(node_id(input)) && a.contains(node_id(output)));
+    }
+}
@@ -0,0 +1,172 @@
+//! reading, writing and running node graphs without the editor.
+//! a project folder holds `connections.bin`, `project_settings.bin` and a `nodes` folder with a folder for each node type
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use savefile::SavefileError;
+use strum::IntoEnumIterator;
+
+use crate::{
+    backend::{run_graph, Backend, GlBackend},
+    cpu::CpuStorage,
+    node::MyNode,
+    nodes::node_enum::NodeType,
+    project_settings::{ProjectSettings, PROJECT_SETTINGS_VERSION},
+};
+
+/// the settings saved in the project at `path`, `None` if there aren't any
+pub fn load_settings(path: &Path) -> Option<ProjectSettings> {
+    return savefile::load_file(path.join("project_settings.bin"), PROJECT_SETTINGS_VERSION).ok();
+}
+
+/// the connections saved in the project at `path`, `None` if the project doesn't exist
+pub fn load_connections(path: &Path) -> Option<HashMap<String, String>> {
+    return savefile::load_file(path.join("connections.bin"), 0).ok();
+}
+
+/// every node saved in the project at `path`. nodes that fail to load are logged and left out
+pub fn load_nodes(path: &Path) -> Vec<Box<dyn MyNode>> {
+    let mut nodes = vec![];
+    for node_type in NodeType::iter() {
+        let Ok(node_paths) = fs::read_dir(path.join("nodes").join(node_type.name())) else {
+            continue;
+        };
+        for node in node_paths.flatten() {
+            match node_type.load_node(node.path()) {
+                Some(a) => nodes.push(a),
+                None => log::error!("failed to load {:?}", node.path()),
+            }
+        }
+    }
+    return nodes;
+}
+
+/// replaces the nodes saved in the project at `path` with `nodes`
+pub fn save_nodes(path: &Path, nodes: &[Box<dyn MyNode>]) -> Result<(), SavefileError> {
+    // remove any node files that may be left over from the last save
+    let _ = fs::remove_dir_all(path.join("nodes"));
+    fs::create_dir_all(path.join("nodes"))?;
+    for node in nodes {
+        fs::create_dir_all(path.join("nodes").join(node.name()))?;
+        node.save(path.join("nodes"))?;
+    }
+    return Ok(());
+}
+
+/// the nodes, connections and settings of a project, with nothing from the editor.
+/// use this to load projects in other tools or to build graphs in code, and `Project` to run them with opengl
+pub struct Graph {
+    pub nodes: Vec<Box<dyn MyNode>>,
+    /// input id -> output id, see `MyNode::input_id` and `MyNode::output_id`
+    pub connections: HashMap<String, String>,
+    pub settings: ProjectSettings,
+    /// the folder the graph was loaded from, files in the project are relative to `path/root`
+    pub path: PathBuf,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Graph {
+            nodes: vec![],
+            connections: HashMap::new(),
+            settings: ProjectSettings::default(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Graph {
+    /// loads the project at `path`, the same way the editor does
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Graph> {
+        let path = path.as_ref();
+        let connections =
+            load_connections(path).ok_or(anyhow!("no project found at {path:?}"))?;
+        return Ok(Graph {
+            nodes: load_nodes(path),
+            connections,
+            settings: load_settings(path).unwrap_or_default(),
+            path: path.to_path_buf(),
+        });
+    }
+
+    /// saves the graph as a project the editor can open
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SavefileError> {
+        let path = path.as_ref();
+        fs::create_dir_all(path)?;
+        savefile::save_file(path.join("connections.bin"), 0, &self.connections)?;
+        savefile::save_file(
+            path.join("project_settings.bin"),
+            PROJECT_SETTINGS_VERSION,
+            &self.settings,
+        )?;
+        return save_nodes(path, &self.nodes);
+    }
+
+    /// adds a new node of type `type_`, returning its id
+    pub fn add(&mut self, type_: NodeType) -> String {
+        return self.add_node(type_.new_node());
+    }
+
+    /// adds `node`, returning its id
+    pub fn add_node(&mut self, node: Box<dyn MyNode>) -> String {
+        let id = node.id();
+        self.nodes.push(node);
+        return id;
+    }
+
+    pub fn node(&self, id: &str) -> Option<&dyn MyNode> {
+        return self.nodes.iter().find(|a| a.id() == id).map(|a| &**a);
+    }
+
+    pub fn node_mut(&mut self, id: &str) -> Option<&mut Box<dyn MyNode>> {
+        return self.nodes.iter_mut().find(|a| a.id() == id);
+    }
+
+    /// connects `output` of the node `from` to `input` of the node `to`, replacing whatever was connected to `input`
+    pub fn connect(&mut self, from: &str, output: &str, to: &str, input: &str) -> anyhow::Result<()> {
+        let from = self.node(from).ok_or(anyhow!("there is no node {from}"))?;
+        let to = self.node(to).ok_or(anyhow!("there is no node {to}"))?;
+        if !from.outputs().iter().any(|a| a == output) {
+            return Err(anyhow!("{} has no output called {output}", from.name()));
+        }
+        if !to.inputs().iter().any(|a| a == input) {
+            return Err(anyhow!("{} has no input called {input}", to.name()));
+        }
+        let (input_id, output_id) = (to.input_id(input), from.output_id(output));
+        self.connections.insert(input_id, output_id);
+        return Ok(());
+    }
+
+    /// a `CpuStorage` with the canvas size, texture depth and files of this graph
+    pub fn cpu_storage(&self) -> CpuStorage {
+        let mut storage = CpuStorage::new(self.settings.canvas_size);
+        storage.texture_depth = self.settings.texture_depth;
+        storage.project_root = self.path.join("root");
+        return storage;
+    }
+
+    /// a `GlBackend` with its own opengl context and the canvas size, texture depth and files of this graph
+    pub fn gl_backend(&self) -> anyhow::Result<GlBackend> {
+        let mut backend = GlBackend::headless()?;
+        backend.storage.canvas_size = self.settings.canvas_size;
+        backend.storage.texture_depth = self.settings.texture_depth;
+        backend.storage.project_root = self.path.join("root");
+        return Ok(backend);
+    }
+
+    /// runs every node with `backend` and the node modes, fallbacks and texture depths in `settings`, see `run_graph`.
+    /// the outputs are in the backend, keyed by `MyNode::output_id`
+    pub fn run(&mut self, backend: &mut dyn Backend) -> HashMap<String, anyhow::Result<()>> {
+        return run_graph(&mut self.nodes, &self.connections, &self.settings, backend);
+    }
+
+    /// runs every node on the cpu, the outputs are in `storage`
+    pub fn run_cpu(&mut self, storage: &mut CpuStorage) -> HashMap<String, anyhow::Result<()>> {
+        return self.run(storage);
+    }
+}
@@ -0,0 +1,83 @@
+//! the ReAnimator engine. the editor is built on this, and other tools can use it to load, build and run node graphs.
+//!
+//! - [`MyNode`] is implemented by every node, and [`NodeType`] lists them and creates and loads them
+//! - [`Graph`] loads and saves projects and builds graphs in code, it runs them with a [`Backend`]:
+//!   on the cpu with [`CpuStorage`] or with opengl, without a window, with [`GlBackend`].
+//!   the cpu versions of the nodes are a reference implementation to test the shaders against,
+//!   the nodes that run glsl written by the user only run with opengl
+//! - [`Project`] is what the editor runs, it always runs a project with opengl through
+//!   [`Project::run_nodes_on_io_arrays`], with a [`Storage`] for the textures. it doesn't go through a [`Backend`]
+//! - [`editor::run`] opens the editor, the rest of the ui is private to the crate
+//!
+//! ```no_run
+//! use reanimator::{nodes::input::load_image::LoadImage, Graph, NodeType};
+//!
+//! let mut graph = Graph::default();
+//! let mut image = LoadImage::default();
+//! image.path = Some("photo.png".into());
+//! let image = graph.add_node(Box::new(image));
+//! let invert = graph.add(NodeType::InvertTexture);
+//! graph.connect(&image, "Selected Image", &invert, "In")?;
+//!
+//! let mut storage = graph.cpu_storage();
+//! for (id, result) in graph.run_cpu(&mut storage) {
+//!     if let Err(e) = result {
+//!         println!("{id} failed: {e}");
+//!     }
+//! }
+//! let output_id = graph.node(&invert).unwrap().output_id("Out");
+//! let inverted = storage.get_texture(&output_id);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    pub static ref LOG_TEXT: Mutex<Vec<Vec<u8>>> = Mutex::new(vec![]);
+}
+
+#[macro_use]
+extern crate savefile_derive;
+
+mod advanced_color_picker;
+pub mod backend;
+mod batch_edit;
+pub mod canvas;
+mod command_palette;
+pub mod cpu;
+mod debug_and_logger;
+pub mod editor;
+mod fonts;
+pub mod fuse;
+pub mod generic_io;
+pub mod generic_node_info;
+pub mod graph;
+mod history_tracker;
+mod import_export;
+pub mod node;
+pub mod nodes;
+mod platform;
+mod popups;
+mod profiler;
+pub mod project;
+mod project_files;
+pub mod project_settings;
+mod render_nodes;
+mod shortcuts;
+mod sidebar;
+pub mod storage;
+mod support;
+mod tiles;
+mod user_info;
+mod widgets;
+pub mod worker_pool;
+
+pub use backend::{Backend, GlBackend};
+pub use cpu::{run_graph_cpu, CpuStorage};
+pub use graph::Graph;
+pub use node::MyNode;
+pub use nodes::node_enum::NodeType;
+pub use project::Project;
+pub use storage::Storage;
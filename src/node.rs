@@ -4,16 +4,20 @@ use fastrand;
 use std::{
     any::Any,
     collections::HashMap,
+    fs,
     path::{PathBuf},
 };
 use imgui::Ui;
 use imgui_glium_renderer::Renderer;
 use savefile::prelude::*;
+use anyhow::anyhow;
 
 
-use crate::{generic_node_info::GenericNodeInfo, nodes::node_enum::NodeType, render_nodes::RenderNodesParams, storage::Storage};
+use crate::{cpu::CpuStorage, fuse::PixelCode, generic_node_info::GenericNodeInfo, nodes::node_enum::NodeType, render_nodes::RenderNodesParams, storage::Storage};
 
-pub trait MyNode {
+/// a node in the graph. nodes are created with `NodeType::new_node` and loaded with `NodeType::load_node`.
+/// `Introspect` comes from `#[derive(Savefile)]` and lists the node's fields, apart from the runtime ones
+pub trait MyNode: Introspect {
 
     fn savefile_version() -> u32
     where
@@ -25,6 +29,20 @@ pub trait MyNode {
     /// if a node has some large one time cost then that cost should take place in this function
     fn load_assets(&mut self, _storage: &Storage) {}
 
+    /// called when the node is deleted, nodes that added textures to the renderer should remove them here
+    fn unload(&mut self, _renderer: &mut Renderer) {}
+
+    /// called a couple of times a second, nodes that read files should reload them here if they have changed.
+    /// returns true if the graph needs to be run again
+    fn watch_files(&mut self, _storage: &Storage) -> bool {
+        false
+    }
+
+    /// the fragment shaders the node draws with, these are compiled on the loading screen
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![]
+    }
+
     fn as_any(&self) -> &dyn Any;
 
     fn as_any_mut(&mut self) -> &mut dyn Any;
@@ -89,6 +107,43 @@ pub trait MyNode {
 
     fn set_id(&mut self, id: String);
 
+    /// one off actions (like "render") that can be run from the command palette
+    fn actions(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// `action` is one of the strings returned by `actions`
+    fn trigger_action(&mut self, _action: &str, _time: f64) {}
+
+    /// true while the node is waiting on a job that is running on the worker pool
+    fn computing(&self) -> bool {
+        false
+    }
+
+    /// nodes that mix or average colours (blurs, blends) should work in linear light.
+    /// when the project has linear light turned on their inputs are decoded before they run
+    fn color_space(&self) -> ColorSpace {
+        ColorSpace::Perceptual
+    }
+
+    /// true if the node makes its outputs at `Storage::preview_size` itself.
+    /// the outputs of other nodes without inputs are shrunk after they run
+    fn scales_preview(&self) -> bool {
+        false
+    }
+
+    /// how many pixels around each tile the node reads when the graph is rendered in tiles.
+    /// `None` if the node can't be tiled, e.g. it looks at the whole image or depends on texture coordinates
+    fn tile_margin(&self) -> Option<u32> {
+        None
+    }
+
+    /// glsl for nodes that only read the pixel they are drawing from each input.
+    /// when the project fuses nodes, chains of these are drawn by one generated shader instead of running the nodes
+    fn pixel_code(&self) -> Option<PixelCode> {
+        None
+    }
+
     fn render_in_node(&self, _ui: &Ui,ui_scale: f32,  _renderer: &mut Renderer, _params: &mut RenderNodesParams) {
         
     }
@@ -99,8 +154,81 @@ pub trait MyNode {
         map: HashMap<String, String>,
         renderer: &mut Renderer,
     ) -> anyhow::Result<()>;
+
+    /// does the same as `run` without opengl. this is the reference the shaders are checked against,
+    /// so it should match what `run` outputs as closely as floats allow.
+    /// the nodes that run glsl written by the user keep this default, they only run with opengl
+    fn run_cpu(&mut self, _storage: &mut CpuStorage, _map: HashMap<String, String>) -> anyhow::Result<()> {
+        Err(anyhow!("{} only runs with opengl", self.name()))
+    }
+}
+
+/// how the project should treat a node when running the graph
+#[derive(Savefile, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum NodeMode {
+    Normal,
+    /// the first input is passed straight through to the first output
+    Bypass,
+    /// every output is a transparent frame
+    Mute,
+}
+
+impl Default for NodeMode {
+    fn default() -> Self {
+        NodeMode::Normal
+    }
+}
+
+/// the space a node expects the colours of its inputs to be in
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ColorSpace {
+    /// srgb encoded, the same as the image files
+    Perceptual,
+    Linear,
+}
+
+/// what a node should output when it fails to run
+#[derive(Savefile, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum NodeFallback {
+    /// the node has no output and everything after it fails as well
+    None,
+    /// the first input is passed through to the first output, the same as bypassing the node
+    PassThrough,
+    /// every output is set to `Storage::error_texture`
+    ErrorTexture,
+}
+
+impl Default for NodeFallback {
+    fn default() -> Self {
+        NodeFallback::None
+    }
+}
+
+impl NodeFallback {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeFallback::None => "none",
+            NodeFallback::PassThrough => "pass through",
+            NodeFallback::ErrorTexture => "error texture",
+        }
+    }
 }
 
 pub fn random_id() -> String {
     fastrand::i32(1000..=9999).to_string()
 }
+
+/// copies a node by saving it into `temp_path` and loading it back again.
+/// the copy keeps the id of the original so it needs to be given a new one before it is added to the project
+pub fn duplicate_node(node: &dyn MyNode, temp_path: PathBuf) -> Option<Box<dyn MyNode>> {
+    let a = fs::create_dir_all(temp_path.join(node.name()));
+    if let Err(e) = a {
+        log::info!("{e:?}")
+    }
+    let _ = node.save(temp_path.clone());
+    let node_clone = node
+        .type_()
+        .load_node(temp_path.join(node.name()).join(node.id() + ".bin"));
+    let _ = fs::remove_dir_all(temp_path);
+    return node_clone;
+}
@@ -142,13 +142,13 @@ void main()
         float xx = x*x;
         yy=y*y;
         p = pos + points[index] / u_resolution;
-        float s = (length(texture2D(tex,p) - texture2D(tex,pos))) / 1.732;
+        float s = (length(texture(tex,p) - texture(tex,pos))) / 1.732;
         if (s > u_input && index > 2) {
             break;
         }
         w=w0*exp((-xx-yy)/(2.0*rr));
         weight_total += w;
-        col+=texture2D(tex,p)*w;
+        col+=texture(tex,p)*w;
         
     
     }
@@ -1,6 +1,6 @@
 
 
-#version 140 core
+#version 140
 
 in vec2 v_tex_coords;
 out vec4 color;
@@ -56,6 +56,6 @@ void main()
     }
 
 
-    color=texture2DLod(tex,pos/u_resolution, 0.0);
+    color=textureLod(tex,pos/u_resolution, 0.0);
     
 }
\ No newline at end of file
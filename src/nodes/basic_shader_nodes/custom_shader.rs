@@ -0,0 +1,583 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    fs,
+    mem::discriminant,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use glium::{
+    uniforms::{AsUniformValue, DynamicUniforms},
+    DrawParameters, Surface, Texture2d,
+};
+use imgui_glium_renderer::Renderer;
+use lazy_static::lazy_static;
+use regex::Regex;
+use rfd::FileDialog;
+use savefile::{save_file, SavefileError};
+use crate::generic_node_info::GenericNodeInfo;
+use anyhow::anyhow;
+
+use crate::{
+    node::*,
+    nodes::{input::apply_path_root, node_enum::NodeType},
+    storage::Storage,
+};
+
+/// uniforms that are set by the node and don't get a control
+pub const BUILTIN_UNIFORMS: [&str; 2] = ["u_time", "u_resolution"];
+
+const DEFAULT_SHADER: &str = r#"#version 140
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform float u_time;
+uniform vec2 u_resolution;
+
+uniform float strength = 1.0; // 0 1
+uniform vec3 tint = vec3(1.0, 1.0, 1.0);
+
+void main() {
+    vec4 c = texture(tex, v_tex_coords);
+    color = vec4(mix(c.rgb, (1.0 - c.rgb) * tint, strength), c.a);
+}
+"#;
+
+lazy_static! {
+    static ref UNIFORM_REGEX: Regex = Regex::new(
+        r"(?m)^[ \t]*uniform[ \t]+(float|int|bool|vec2|vec3|vec4|sampler2D)[ \t]+(\w+)[ \t]*(?:=[ \t]*([^;]*))?;[ \t]*(?://[ \t]*(.*))?$"
+    )
+    .unwrap();
+    static ref NUMBER_REGEX: Regex =
+        Regex::new(r"-?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?").unwrap();
+}
+
+#[derive(Savefile, Clone, PartialEq, Debug)]
+pub enum UniformValue {
+    Float(f32),
+    Int(i32),
+    Bool(bool),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    /// `sampler2D` uniforms are inputs
+    Texture,
+}
+
+impl UniformValue {
+    pub fn as_uniform_value(&self) -> Option<&dyn AsUniformValue> {
+        return match self {
+            UniformValue::Float(v) => Some(v),
+            UniformValue::Int(v) => Some(v),
+            UniformValue::Bool(v) => Some(v),
+            UniformValue::Vec2(v) => Some(v),
+            UniformValue::Vec3(v) => Some(v),
+            UniformValue::Vec4(v) => Some(v),
+            UniformValue::Texture => None,
+        };
+    }
+
+    pub fn glsl_type(&self) -> &'static str {
+        match self {
+            UniformValue::Float(_) => "float",
+            UniformValue::Int(_) => "int",
+            UniformValue::Bool(_) => "bool",
+            UniformValue::Vec2(_) => "vec2",
+            UniformValue::Vec3(_) => "vec3",
+            UniformValue::Vec4(_) => "vec4",
+            UniformValue::Texture => "sampler2D",
+        }
+    }
+
+    /// the value written as glsl, `None` for textures
+    pub fn glsl_literal(&self) -> Option<String> {
+        let float = |v: f32| format!("{v:?}");
+        let vector = |v: &[f32]| {
+            format!(
+                "vec{}({})",
+                v.len(),
+                v.iter().map(|a| float(*a)).collect::<Vec<String>>().join(", ")
+            )
+        };
+        return match self {
+            UniformValue::Float(v) => Some(float(*v)),
+            UniformValue::Int(v) => Some(v.to_string()),
+            UniformValue::Bool(v) => Some(v.to_string()),
+            UniformValue::Vec2(v) => Some(vector(v)),
+            UniformValue::Vec3(v) => Some(vector(v)),
+            UniformValue::Vec4(v) => Some(vector(v)),
+            UniformValue::Texture => None,
+        };
+    }
+}
+
+/// a uniform declared in the shader that the user can change
+#[derive(Savefile, Clone, PartialEq, Debug)]
+pub struct ShaderParam {
+    pub name: String,
+    pub value: UniformValue,
+    /// set with a comment after the declaration, e.g. `uniform float size = 2.0; // 0 10`
+    pub range: Option<(f32, f32)>,
+}
+
+impl ShaderParam {
+    /// returns true if the value was changed
+    pub fn render(&mut self, ui: &imgui::Ui) -> bool {
+        let (min, max) = self.range.unwrap_or((0.0, 1.0));
+        match &mut self.value {
+            UniformValue::Float(v) => match self.range {
+                Some(_) => ui.slider(&self.name, min, max, v),
+                None => imgui::Drag::new(&self.name).speed(0.01).build(ui, v),
+            },
+            UniformValue::Int(v) => match self.range {
+                Some(_) => ui.slider(&self.name, min as i32, max as i32, v),
+                None => ui.input_int(&self.name, v).build(),
+            },
+            UniformValue::Bool(v) => ui.checkbox(&self.name, v),
+            UniformValue::Vec2(v) => match self.range {
+                Some(_) => imgui::Drag::new(&self.name)
+                    .range(min, max)
+                    .speed(0.01)
+                    .build_array(ui, v),
+                None => imgui::Drag::new(&self.name).speed(0.01).build_array(ui, v),
+            },
+            // vectors with a range are treated as numbers, otherwise they are colours
+            UniformValue::Vec3(v) => match self.range {
+                Some(_) => imgui::Drag::new(&self.name)
+                    .range(min, max)
+                    .speed(0.01)
+                    .build_array(ui, v),
+                None => ui.color_edit3(&self.name, v),
+            },
+            UniformValue::Vec4(v) => match self.range {
+                Some(_) => imgui::Drag::new(&self.name)
+                    .range(min, max)
+                    .speed(0.01)
+                    .build_array(ui, v),
+                None => ui.color_edit4(&self.name, v),
+            },
+            UniformValue::Texture => false,
+        }
+    }
+
+    pub fn as_uniform_value(&self) -> Option<&dyn AsUniformValue> {
+        return self.value.as_uniform_value();
+    }
+}
+
+/// finds the `uniform` declarations in a fragment shader, using their initialisers as default values
+pub fn parse_uniforms(source: &str) -> Vec<ShaderParam> {
+    let mut params = vec![];
+    for capture in UNIFORM_REGEX.captures_iter(source) {
+        let name = capture[2].to_owned();
+        if BUILTIN_UNIFORMS.contains(&name.as_str()) {
+            continue;
+        }
+        let initial = capture.get(3).map(|a| a.as_str()).unwrap_or_default();
+        let numbers = NUMBER_REGEX
+            .find_iter(initial)
+            .filter_map(|a| a.as_str().parse::<f32>().ok())
+            .collect::<Vec<f32>>();
+        // a single number fills the whole vector, the same as glsl
+        let n = |i: usize, default: f32| {
+            numbers
+                .get(i)
+                .or(if numbers.len() == 1 { numbers.first() } else { None })
+                .copied()
+                .unwrap_or(default)
+        };
+        let value = match &capture[1] {
+            "float" => UniformValue::Float(n(0, 0.0)),
+            "int" => UniformValue::Int(n(0, 0.0) as i32),
+            "bool" => UniformValue::Bool(initial.trim() == "true"),
+            "vec2" => UniformValue::Vec2([n(0, 0.0), n(1, 0.0)]),
+            "vec3" => UniformValue::Vec3([n(0, 1.0), n(1, 1.0), n(2, 1.0)]),
+            "vec4" => UniformValue::Vec4([n(0, 1.0), n(1, 1.0), n(2, 1.0), n(3, 1.0)]),
+            _ => UniformValue::Texture,
+        };
+        let range = capture.get(4).and_then(|comment| {
+            let bounds = NUMBER_REGEX
+                .find_iter(comment.as_str())
+                .filter_map(|a| a.as_str().parse::<f32>().ok())
+                .collect::<Vec<f32>>();
+            match bounds[..] {
+                [min, max, ..] => Some((min, max)),
+                _ => None,
+            }
+        });
+        params.push(ShaderParam { name, value, range });
+    }
+    return params;
+}
+
+/// keeps the values the user set for uniforms that are still in the shader with the same type
+pub fn merge_params(new: Vec<ShaderParam>, old: &[ShaderParam]) -> Vec<ShaderParam> {
+    return new
+        .into_iter()
+        .map(|mut param| {
+            if let Some(a) = old.iter().find(|a| {
+                a.name == param.name && discriminant(&a.value) == discriminant(&param.value)
+            }) {
+                param.value = a.value.clone();
+            }
+            param
+        })
+        .collect();
+}
+
+/// the state of a node's glsl that isn't saved: when its file was last read, the last version that compiled and the error.
+/// the source and the path of the file stay fields of the node so projects saved before this still load
+pub struct ShaderSource {
+    /// why the current source didn't compile
+    pub error: Option<String>,
+    /// the last source that compiled, this is drawn with while the current source has errors
+    last_good: Option<String>,
+    /// when the file was modified the last time it was read
+    file_modified: Option<SystemTime>,
+}
+
+impl Default for ShaderSource {
+    fn default() -> Self {
+        ShaderSource {
+            error: None,
+            last_good: None,
+            file_modified: None,
+        }
+    }
+}
+
+/// nodes keep this in a `#[savefile_ignore]` field, and savefile's derive still needs every field to be `Packed`
+impl savefile::Packed for ShaderSource {}
+
+impl ShaderSource {
+    /// reads the shader at `path`, which can be relative to the project root
+    pub fn read_file(&mut self, path: &Path, storage: &Storage) -> anyhow::Result<String> {
+        let path = apply_path_root::get_with_root(&path.to_path_buf(), storage);
+        self.file_modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        return Ok(fs::read_to_string(path)?);
+    }
+
+    /// the new contents of the file at `path` if it has been modified since it was last read
+    pub fn watch_file(&mut self, path: &Option<PathBuf>, storage: &Storage) -> Option<String> {
+        let path = path.as_ref()?;
+        let modified = fs::metadata(apply_path_root::get_with_root(path, storage))
+            .and_then(|m| m.modified())
+            .ok();
+        if modified.is_none() || modified == self.file_modified {
+            return None;
+        }
+        match self.read_file(path, storage) {
+            Ok(a) => return Some(a),
+            Err(e) => {
+                log::error!("failed to reload shader {path:?}: {e}");
+                return None;
+            }
+        }
+    }
+
+    /// compiles `frag`, the fragment shader made from `source`, and returns the source to draw with.
+    /// when it doesn't compile that is the last source that did, `header_lines` is passed to `Storage::compile_frag_shader_with_header`
+    pub fn compile(
+        &mut self,
+        storage: &mut Storage,
+        source: &str,
+        frag: String,
+        header_lines: usize,
+    ) -> anyhow::Result<String> {
+        // failed compiles are cached by storage so this is cheap when the source hasn't changed
+        match storage.compile_frag_shader_with_header(frag, header_lines) {
+            Ok(()) => {
+                self.error = None;
+                self.last_good = Some(source.to_owned());
+                return Ok(source.to_owned());
+            }
+            Err(e) => {
+                self.error = Some(e.clone());
+                return self
+                    .last_good
+                    .clone()
+                    .ok_or(anyhow!("failed to compile shader: {e}"));
+            }
+        }
+    }
+
+    /// the last source that compiled
+    pub fn last_good(&self) -> Option<String> {
+        return self.last_good.clone();
+    }
+
+    /// the "load file" and "embed" buttons, returns the source of a newly picked file
+    pub fn render_file_buttons(
+        &mut self,
+        ui: &imgui::Ui,
+        path: &mut Option<PathBuf>,
+        storage: &Storage,
+        filter: (&str, &[&str]),
+    ) -> Option<String> {
+        let mut picked = None;
+        if ui.button("load file") {
+            if let Some(mut new_path) = FileDialog::new().add_filter(filter.0, filter.1).pick_file() {
+                apply_path_root::set_root(&mut new_path, storage);
+                match self.read_file(&new_path, storage) {
+                    Ok(a) => picked = Some(a),
+                    Err(e) => log::error!("failed to read shader: {e}"),
+                }
+                *path = Some(new_path);
+            }
+        }
+        if let Some(file) = path {
+            ui.same_line();
+            let label = file.display().to_string();
+            if ui.button("embed") {
+                *path = None;
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("stop using the file and keep the shader in the node");
+            }
+            ui.same_line();
+            ui.text(label);
+        }
+        return picked;
+    }
+
+    /// the compile error, if there is one
+    pub fn render_error(&self, ui: &imgui::Ui) {
+        if let Some(e) = &self.error {
+            if self.last_good.is_some() {
+                ui.text_colored([0.8, 0.6, 0.1, 1.0], "using the last version that compiled");
+            }
+            ui.text_colored([0.8, 0.1, 0.1, 1.0], e);
+        }
+    }
+}
+
+#[derive(Savefile)]
+pub struct CustomShaderNode {
+    x: f32,
+    y: f32,
+    id: String,
+    source: String,
+    /// when this is set the source is read from this file instead of being edited in the node
+    path: Option<PathBuf>,
+    params: Vec<ShaderParam>,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    shader_source: ShaderSource,
+}
+
+impl Default for CustomShaderNode {
+    fn default() -> Self {
+        CustomShaderNode {
+            x: 0.0,
+            y: 0.0,
+            id: random_id(),
+            source: DEFAULT_SHADER.to_owned(),
+            path: None,
+            params: parse_uniforms(DEFAULT_SHADER),
+            shader_source: ShaderSource::default(),
+        }
+    }
+}
+
+impl CustomShaderNode {
+    fn set_source(&mut self, source: String) {
+        self.params = merge_params(parse_uniforms(&source), &self.params);
+        self.source = source;
+    }
+
+    fn textures(&self) -> Vec<String> {
+        self.params
+            .iter()
+            .filter(|p| p.value == UniformValue::Texture)
+            .map(|p| p.name.clone())
+            .collect()
+    }
+}
+
+impl MyNode for CustomShaderNode {
+    fn path(&self) -> Vec<&str> {
+        vec!["Image", "Basic Shader"]
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn generic_info(&self) -> GenericNodeInfo {
+        GenericNodeInfo {
+            x: self.x,
+            y: self.y,
+            type_: self.type_(),
+            id: self.id.to_owned(),
+        }
+    }
+
+    fn savefile_version() -> u32 {
+        0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_(&self) -> NodeType {
+        NodeType::CustomShader
+    }
+
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![self.source.clone()]
+    }
+
+    fn load_assets(&mut self, storage: &Storage) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        match self.shader_source.read_file(&path, storage) {
+            Ok(source) => self.set_source(source),
+            Err(e) => log::error!("failed to read shader {path:?}: {e}"),
+        }
+    }
+
+    fn watch_files(&mut self, storage: &Storage) -> bool {
+        let Some(source) = self.shader_source.watch_file(&self.path, storage) else {
+            return false;
+        };
+        self.set_source(source);
+        return true;
+    }
+
+    fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
+        return save_file(
+            path.join(self.name()).join(self.id() + ".bin"),
+            CustomShaderNode::savefile_version(),
+            self,
+        );
+    }
+
+    fn inputs(&self) -> Vec<String> {
+        return self.textures();
+    }
+
+    fn outputs(&self) -> Vec<String> {
+        return vec!["Out".to_string()];
+    }
+
+    fn set_xy(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    fn description(&mut self, ui: &imgui::Ui) {
+        ui.text_wrapped("runs a fragment shader that you write or load from a file");
+        ui.text_wrapped("every sampler2D uniform becomes an input, and the other uniforms get a control in the edit menu. u_time and u_resolution are set for you");
+        ui.text_wrapped("shaders loaded from a file are reloaded when the file is saved. if the new version doesn't compile the error is shown in the edit menu and the last version that did is used");
+        ui.text_wrapped("add a comment with two numbers after a uniform to give it a range, e.g. `uniform float size = 2.0; // 0 10`");
+    }
+
+    fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, storage: &Storage) {
+        if let Some(source) = self.shader_source.render_file_buttons(
+            ui,
+            &mut self.path,
+            storage,
+            ("glsl", &["glsl", "frag", "fs"]),
+        ) {
+            self.set_source(source);
+        }
+        self.shader_source.render_error(ui);
+
+        for param in &mut self.params {
+            param.render(ui);
+        }
+
+        ui.separator();
+        let mut source = self.source.clone();
+        if ui
+            .input_text_multiline(
+                "##source",
+                &mut source,
+                [ui.content_region_avail()[0], ui.content_region_avail()[1].max(200.0)],
+            )
+            .read_only(self.path.is_some())
+            .build()
+        {
+            self.set_source(source);
+        }
+    }
+
+    fn run(
+        &mut self,
+        storage: &mut Storage,
+        map: HashMap<String, String>,
+        _renderer: &mut Renderer,
+    ) -> anyhow::Result<()> {
+        let output_id = self.output_id(&self.outputs()[0]);
+
+        let mut texture_keys = vec![];
+        for name in self.textures() {
+            match map.get(&self.input_id(&name)) {
+                Some(a) => texture_keys.push((name, a.to_owned())),
+                None => return Err(anyhow!("missing input: {name}")),
+            }
+        }
+
+        let source = self
+            .shader_source
+            .compile(storage, &self.source, self.source.clone(), 0)?;
+
+        // the output is the size of the first input, shaders without inputs use the project canvas
+        let size = match texture_keys.first() {
+            Some((_, key)) => match storage.get_texture(key) {
+                Some(a) => a.dimensions(),
+                None => return Err(anyhow!("cannot find input texture")),
+            },
+            None => storage.canvas_size,
+        };
+        storage.create_and_set_texture(size.0, size.1, output_id.clone());
+
+        let mut textures: Vec<(&str, &Texture2d)> = vec![];
+        for (name, key) in &texture_keys {
+            match storage.get_texture(key) {
+                Some(a) => textures.push((name, a)),
+                None => return Err(anyhow!("cannot find input texture: {name}")),
+            }
+        }
+
+        let time = storage.time as f32;
+        let resolution = [size.0 as f32, size.1 as f32];
+        let mut uniforms = DynamicUniforms::new();
+        uniforms.add("u_time", &time);
+        uniforms.add("u_resolution", &resolution);
+        for param in &self.params {
+            if let Some(value) = param.as_uniform_value() {
+                uniforms.add(&param.name, value);
+            }
+        }
+        for (name, texture) in &textures {
+            uniforms.add(name, texture);
+        }
+
+        let shader = storage.get_frag_shader(source).unwrap();
+        storage
+            .get_texture(&output_id)
+            .unwrap()
+            .as_surface()
+            .draw(
+                &storage.vertex_buffer,
+                &storage.indices,
+                shader,
+                &uniforms,
+                &DrawParameters {
+                    ..Default::default()
+                },
+            )?;
+
+        return Ok(());
+    }
+}
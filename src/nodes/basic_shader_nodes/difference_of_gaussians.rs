@@ -7,12 +7,52 @@ use crate::generic_node_info::GenericNodeInfo;
 use anyhow::anyhow;
 
 use crate::{
+    cpu::{draw, gaussian_blur, CpuStorage, CpuTexture},
     node::{random_id, MyNode}, nodes::node_enum::NodeType, storage::Storage, widgets::link_widget
 };
 
 
 
 
+const FRAGMENT_SHADER: &str = include_str!("diff_gaussian.glsl");
+
+/// diff_gaussian.glsl at `uv`, watercolor.glsl does the same thing.
+/// `threshold` is the threshold and sigma, or `None` to skip thresholding
+pub fn difference_of_gaussians(
+    texture: &CpuTexture,
+    uv: [f32; 2],
+    [r1, r2]: [f32; 2],
+    weight: f32,
+    threshold: Option<(f32, f32)>,
+    greyscale: bool,
+) -> [f32; 4] {
+    // the second blur keeps the offset and `rr` of the first, the same as the shader
+    let mut offset = [0.0, 0.0];
+    let mut color1 = gaussian_blur(texture, uv, r1, r1 * r1, 25.0, &mut offset);
+    let mut color2 = gaussian_blur(texture, uv, r2, r1 * r1, 25.0, &mut offset);
+
+    let u1 = (color1[0] + color1[1] + color1[2]) / 3.0;
+    let u2 = (color2[0] + color2[1] + color2[2]) / 3.0;
+    if (1.0 - weight) * u1 - u2 * weight < 0.0 {
+        std::mem::swap(&mut color1, &mut color2);
+    }
+
+    let mut color3 = [0, 1, 2, 3].map(|i| (1.0 - weight) * color1[i] - color2[i] * weight);
+    color3[3] = 1.0;
+    let u = (color3[0] + color3[1] + color3[2]) / 3.0;
+    if greyscale {
+        color3 = [u, u, u, color3[3]];
+    }
+    let Some((threshold, sigma)) = threshold else {
+        return color3;
+    };
+    if u < threshold {
+        let c = |a: f32| 1.0 + (sigma * (a - threshold)).tanh();
+        return [c(color3[0]), c(color3[1]), c(color3[2]), 1.0];
+    }
+    return [1.0; 4];
+}
+
 /// https://youtu.be/5EuYKEvugLU?si=EMuCD_k6mjnqy74c
 #[derive(Savefile)]
 pub struct DifferenceofGaussiansNode {
@@ -81,6 +121,14 @@ impl MyNode for DifferenceofGaussiansNode {
         NodeType::DifferenceOfGaussians
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
+    fn tile_margin(&self) -> Option<u32> {
+        Some((self.radius + self.radius_diff).abs().ceil() as u32 + 1)
+    }
+
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
         return save_file(
@@ -103,6 +151,17 @@ impl MyNode for DifferenceofGaussiansNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let radii = [self.radius, self.radius + self.radius_diff];
+        let threshold = if self.do_threshold { Some((self.threshold, self.sigma)) } else { None };
+        let output = draw(input.dimensions(), &[], |uv, _| {
+            difference_of_gaussians(input, uv, radii, self.scale_on_2nd, threshold, self.greyscale)
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -116,7 +175,7 @@ impl MyNode for DifferenceofGaussiansNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = include_str!("diff_gaussian.glsl");
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -141,8 +200,8 @@ impl MyNode for DifferenceofGaussiansNode {
 
         let uniforms = uniform! {
             tex: texture,
-            r1: self.radius,
-            r2: self.radius + self.radius_diff,
+            r1: self.radius * storage.preview_scale,
+            r2: (self.radius + self.radius_diff) * storage.preview_scale,
             u_resolution: [texture_size.0 as f32, texture_size.1 as f32],
             weight: self.scale_on_2nd,
             sigma: self.sigma,
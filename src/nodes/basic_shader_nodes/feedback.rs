@@ -0,0 +1,352 @@
+use std::{any::Any, collections::HashMap, path::PathBuf};
+
+use glium::{
+    texture::MipmapsOption,
+    uniforms::{DynamicUniforms, MagnifySamplerFilter},
+    DrawParameters, Surface, Texture2d,
+};
+use imgui_glium_renderer::Renderer;
+use savefile::{save_file, SavefileError};
+use crate::generic_node_info::GenericNodeInfo;
+use anyhow::anyhow;
+
+use crate::{
+    node::*,
+    nodes::{
+        basic_shader_nodes::custom_shader::{
+            merge_params, parse_uniforms, ShaderParam, ShaderSource, UniformValue,
+        },
+        node_enum::NodeType,
+    },
+    storage::{Storage, TextureDepth},
+};
+
+/// uniforms set by the feedback node, on top of `u_time` and `u_resolution`
+const FEEDBACK_UNIFORMS: [&str; 3] = ["last_frame", "u_pass", "u_frame"];
+
+const MAX_PASSES: i32 = 64;
+const MAX_WARM_UP_FRAMES: i32 = 1000;
+
+const DEFAULT_SHADER: &str = r#"#version 140
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform sampler2D tex;
+uniform sampler2D last_frame;
+uniform float u_time;
+uniform vec2 u_resolution;
+uniform int u_pass;
+uniform int u_frame;
+
+uniform float amount = 0.9; // 0 1
+uniform vec2 drift = vec2(0.0, 0.002); // -0.01 0.01
+
+void main() {
+    vec4 previous = texture(last_frame, v_tex_coords - drift);
+    vec4 current = texture(tex, v_tex_coords);
+    color = mix(current, previous, amount);
+}
+"#;
+
+fn feedback_params(source: &str) -> Vec<ShaderParam> {
+    return parse_uniforms(source)
+        .into_iter()
+        .filter(|p| !FEEDBACK_UNIFORMS.contains(&p.name.as_str()))
+        .collect();
+}
+
+#[derive(Savefile)]
+pub struct FeedbackNode {
+    x: f32,
+    y: f32,
+    id: String,
+    source: String,
+    params: Vec<ShaderParam>,
+    /// how many times the shader is run each frame, each pass reads the result of the one before
+    passes: i32,
+    /// how many frames are run straight away after the state is reset
+    warm_up_frames: i32,
+    /// start from a copy of the first input instead of a transparent image
+    seed_with_input: bool,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    shader_source: ShaderSource,
+    /// (state, scratch), every pass draws into scratch from state and then they are swapped
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    buffers: Option<(Texture2d, Texture2d)>,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    buffer_depth: TextureDepth,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    reset_requested: bool,
+    /// frames since the last reset, for `u_frame`
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    frame: i32,
+}
+
+impl Default for FeedbackNode {
+    fn default() -> Self {
+        FeedbackNode {
+            x: 0.0,
+            y: 0.0,
+            id: random_id(),
+            source: DEFAULT_SHADER.to_owned(),
+            params: feedback_params(DEFAULT_SHADER),
+            passes: 1,
+            warm_up_frames: 0,
+            seed_with_input: true,
+            shader_source: ShaderSource::default(),
+            buffers: None,
+            buffer_depth: TextureDepth::default(),
+            reset_requested: false,
+            frame: 0,
+        }
+    }
+}
+
+impl FeedbackNode {
+    fn textures(&self) -> Vec<String> {
+        self.params
+            .iter()
+            .filter(|p| p.value == UniformValue::Texture)
+            .map(|p| p.name.clone())
+            .collect()
+    }
+}
+
+impl MyNode for FeedbackNode {
+    fn path(&self) -> Vec<&str> {
+        vec!["Image", "Basic Shader"]
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn generic_info(&self) -> GenericNodeInfo {
+        GenericNodeInfo {
+            x: self.x,
+            y: self.y,
+            type_: self.type_(),
+            id: self.id.to_owned(),
+        }
+    }
+
+    fn savefile_version() -> u32 {
+        0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_(&self) -> NodeType {
+        NodeType::Feedback
+    }
+
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![self.source.clone()]
+    }
+
+    fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
+        return save_file(
+            path.join(self.name()).join(self.id() + ".bin"),
+            FeedbackNode::savefile_version(),
+            self,
+        );
+    }
+
+    fn inputs(&self) -> Vec<String> {
+        return self.textures();
+    }
+
+    fn outputs(&self) -> Vec<String> {
+        return vec!["Out".to_string()];
+    }
+
+    fn set_xy(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    fn description(&mut self, ui: &imgui::Ui) {
+        ui.text_wrapped("runs a fragment shader that can read its own output from the last frame through the last_frame uniform");
+        ui.text_wrapped("the shader can be run several times per frame, u_pass is which pass is being drawn and u_frame counts up from the last reset");
+        ui.text_wrapped("useful for trails, smears, reaction diffusion and other effects that build up over time. set the node's texture depth to 16 or 32 bit float for simulations that need precision");
+    }
+
+    fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
+        if ui.button("reset state") {
+            self.reset_requested = true;
+        }
+        ui.same_line();
+        ui.text(format!("frame {}", self.frame));
+
+        if ui.input_int("passes per frame", &mut self.passes).build() {
+            self.passes = self.passes.clamp(1, MAX_PASSES);
+        }
+        if ui.input_int("warm up frames", &mut self.warm_up_frames).build() {
+            self.warm_up_frames = self.warm_up_frames.clamp(0, MAX_WARM_UP_FRAMES);
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text("frames that are run straight away after a reset, so the effect doesn't start from nothing");
+        }
+        if ui.checkbox("start from input", &mut self.seed_with_input) {
+            self.reset_requested = true;
+        }
+
+        self.shader_source.render_error(ui);
+
+        for param in &mut self.params {
+            param.render(ui);
+        }
+
+        ui.separator();
+        let mut source = self.source.clone();
+        if ui
+            .input_text_multiline(
+                "##source",
+                &mut source,
+                [ui.content_region_avail()[0], ui.content_region_avail()[1].max(200.0)],
+            )
+            .build()
+        {
+            self.params = merge_params(feedback_params(&source), &self.params);
+            self.source = source;
+        }
+    }
+
+    fn run(
+        &mut self,
+        storage: &mut Storage,
+        map: HashMap<String, String>,
+        _renderer: &mut Renderer,
+    ) -> anyhow::Result<()> {
+        let output_id = self.output_id(&self.outputs()[0]);
+
+        let mut texture_keys = vec![];
+        for name in self.textures() {
+            match map.get(&self.input_id(&name)) {
+                Some(a) => texture_keys.push((name, a.to_owned())),
+                None => return Err(anyhow!("missing input: {name}")),
+            }
+        }
+
+        let source = self
+            .shader_source
+            .compile(storage, &self.source, self.source.clone(), 0)?;
+
+        let size = match texture_keys.first() {
+            Some((_, key)) => match storage.get_texture(key) {
+                Some(a) => a.dimensions(),
+                None => return Err(anyhow!("cannot find input texture")),
+            },
+            None => storage.canvas_size,
+        };
+
+        // the state starts again when it is reset or the size or depth of the textures changes
+        let depth = storage.texture_depth;
+        let mut warm_up = 0;
+        if self.reset_requested
+            || self.buffer_depth != depth
+            || self
+                .buffers
+                .as_ref()
+                .map_or(true, |(state, _)| state.dimensions() != size)
+        {
+            let new_buffer = || {
+                Texture2d::empty_with_format(
+                    &storage.display,
+                    depth.format(),
+                    MipmapsOption::NoMipmap,
+                    size.0,
+                    size.1,
+                )
+            };
+            let state = new_buffer()?;
+            let scratch = new_buffer()?;
+            state.as_surface().clear_color(0.0, 0.0, 0.0, 0.0);
+            if self.seed_with_input {
+                if let Some(input) = texture_keys.first().and_then(|(_, key)| storage.get_texture(key)) {
+                    input
+                        .as_surface()
+                        .fill(&state.as_surface(), MagnifySamplerFilter::Nearest);
+                }
+            }
+            self.buffers = Some((state, scratch));
+            self.buffer_depth = depth;
+            self.reset_requested = false;
+            self.frame = 0;
+            warm_up = self.warm_up_frames.clamp(0, MAX_WARM_UP_FRAMES);
+        }
+
+        let passes = self.passes.clamp(1, MAX_PASSES);
+        let time = storage.time as f32;
+        let resolution = [size.0 as f32, size.1 as f32];
+        for frame in 0..=warm_up {
+            let frame_number = self.frame + frame;
+            for pass in 0..passes {
+                {
+                    let Some((state, scratch)) = &self.buffers else {
+                        return Err(anyhow!("feedback buffers are missing"));
+                    };
+                    let mut textures: Vec<(&str, &Texture2d)> = vec![];
+                    for (name, key) in &texture_keys {
+                        match storage.get_texture(key) {
+                            Some(a) => textures.push((name, a)),
+                            None => return Err(anyhow!("cannot find input texture: {name}")),
+                        }
+                    }
+
+                    let mut uniforms = DynamicUniforms::new();
+                    uniforms.add("u_time", &time);
+                    uniforms.add("u_resolution", &resolution);
+                    uniforms.add("u_pass", &pass);
+                    uniforms.add("u_frame", &frame_number);
+                    uniforms.add("last_frame", &state);
+                    for param in &self.params {
+                        if let Some(value) = param.as_uniform_value() {
+                            uniforms.add(&param.name, value);
+                        }
+                    }
+                    for (name, texture) in &textures {
+                        uniforms.add(name, texture);
+                    }
+
+                    let shader = storage.get_frag_shader(source.clone()).unwrap();
+                    scratch.as_surface().draw(
+                        &storage.vertex_buffer,
+                        &storage.indices,
+                        shader,
+                        &uniforms,
+                        &DrawParameters {
+                            ..Default::default()
+                        },
+                    )?;
+                }
+                if let Some((state, scratch)) = &mut self.buffers {
+                    std::mem::swap(state, scratch);
+                }
+            }
+        }
+        self.frame += warm_up + 1;
+
+        storage.create_and_set_texture(size.0, size.1, output_id.clone());
+        if let Some((state, _)) = &self.buffers {
+            state.as_surface().fill(
+                &storage.get_texture(&output_id).unwrap().as_surface(),
+                MagnifySamplerFilter::Nearest,
+            );
+        }
+
+        return Ok(());
+    }
+}
@@ -21,7 +21,7 @@ void main()
     vec2 pos = v_tex_coords;
     float xs = (u_resolution.x);
     float ys = (u_resolution.y);
-    float x,y,xx,yy,rr=r*r,dx,dy,w,w0;
+    float x=0.0,y=0.0,xx,yy,rr=r*r,dx,dy,w,w0;
     w0=0.3780/pow(r,1.975);
     float weight_total = 0.0;
     vec2 p;
@@ -42,7 +42,7 @@ void main()
         {
             w=w0*exp((-xx-yy)/(2.0*rr));
             weight_total += w;
-            col+=texture2D(tex,p)*w;
+            col+=texture(tex,p)*w;
         }
     }
     }
@@ -9,8 +9,11 @@ use anyhow::anyhow;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+use crate::nodes::basic_shader_nodes::custom_shader::UniformValue;
 use crate::nodes::node_enum::NodeType;
 use crate::{
+    cpu::{draw, CpuStorage},
+    fuse::PixelCode,
     node::{random_id, MyNode},
     storage::Storage,
 };
@@ -94,6 +97,35 @@ impl MyNode for GreyScaleNode {
         NodeType::Greyscale
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![self.pixel_code().unwrap().standalone_glsl()]
+    }
+
+    fn pixel_code(&self) -> Option<PixelCode> {
+        Some(PixelCode {
+            code: r#"
+                vec3 c2 = $in_0.rgb * $weights;
+                float b = 0.0;
+                if ($f_type == 0) {
+                    b = max(c2.r, max(c2.g, c2.b));
+                } else if ($f_type == 1) {
+                    b = (c2.r + c2.g + c2.b) / 3.0;
+                }
+                $out_0 = vec4(vec3(b), $in_0.a);
+            "#
+            .to_string(),
+            functions: String::new(),
+            uniforms: vec![
+                ("weights".to_string(), UniformValue::Vec3(self.weights)),
+                ("f_type".to_string(), UniformValue::Int(self.grey_type.index())),
+            ],
+        })
+    }
+
+    fn tile_margin(&self) -> Option<u32> {
+        Some(0)
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -117,6 +149,24 @@ impl MyNode for GreyScaleNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let weights = self.weights;
+        let f_type = self.grey_type.index();
+        let output = draw(input.dimensions(), &[input], |_, c| {
+            let c = c[0];
+            let c2 = [c[0] * weights[0], c[1] * weights[1], c[2] * weights[2]];
+            let b = match f_type {
+                0 => c2[0].max(c2[1].max(c2[2])),
+                1 => (c2[0] + c2[1] + c2[2]) / 3.0,
+                _ => 0.0,
+            };
+            [b, b, b, c[3]]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -130,7 +180,8 @@ impl MyNode for GreyScaleNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = include_str!("greyscale.glsl");
+        // the shader is made from the code it is fused with, so the two can't drift apart
+        let fragment_shader_src = self.pixel_code().unwrap().standalone_glsl();
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -6,11 +6,30 @@ use crate::generic_node_info::GenericNodeInfo;
 use anyhow::anyhow;
 
 use crate::{
+    cpu::{draw, CpuStorage},
+    fuse::PixelCode,
     node::{random_id, MyNode},
-    nodes::node_enum::NodeType,
+    nodes::{basic_shader_nodes::custom_shader::UniformValue, node_enum::NodeType},
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = r#"
+        #version 140
+
+        in vec2 v_tex_coords;
+        out vec4 color;
+
+        uniform sampler2D tex;
+        uniform bool alpha;
+
+        void main() {
+            color = vec4(1.0) - texture(tex, v_tex_coords);
+            if (!alpha) {
+            color.a = texture(tex, v_tex_coords).a;
+            }
+        }
+    "#;
+
 #[derive(Savefile)]
 
 pub struct InvertTextureNode {
@@ -57,6 +76,28 @@ impl MyNode for InvertTextureNode {
         NodeType::InvertTexture
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
+    fn pixel_code(&self) -> Option<PixelCode> {
+        Some(PixelCode {
+            code: r#"
+                $out_0 = vec4(1.0) - $in_0;
+                if (!$alpha) {
+                $out_0.a = $in_0.a;
+                }
+            "#
+            .to_string(),
+            functions: String::new(),
+            uniforms: vec![("alpha".to_string(), UniformValue::Bool(self.invert_alpha))],
+        })
+    }
+
+    fn tile_margin(&self) -> Option<u32> {
+        Some(0)
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -87,6 +128,22 @@ impl MyNode for InvertTextureNode {
         1
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let invert_alpha = self.invert_alpha;
+        let output = draw(input.dimensions(), &[input], |_, c| {
+            let c = c[0];
+            [
+                1.0 - c[0],
+                1.0 - c[1],
+                1.0 - c[2],
+                if invert_alpha { 1.0 - c[3] } else { c[3] },
+            ]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -100,22 +157,7 @@ impl MyNode for InvertTextureNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = r#"
-        #version 140
-
-        in vec2 v_tex_coords;
-        out vec4 color;
-
-        uniform sampler2D tex;
-        uniform bool alpha;
-
-        void main() {
-            color = vec4(1.0) - texture(tex, v_tex_coords);
-            if (!alpha) {
-            color.a = texture(tex, v_tex_coords).a;
-            }
-        }
-    "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
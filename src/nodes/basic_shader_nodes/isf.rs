@@ -0,0 +1,716 @@
+use std::{any::Any, collections::HashMap, path::PathBuf};
+
+use glium::{
+    uniforms::{DynamicUniforms, MagnifySamplerFilter},
+    DrawParameters, Surface, Texture2d,
+};
+use imgui_glium_renderer::Renderer;
+use savefile::{save_file, SavefileError};
+use serde_json::Value;
+use crate::generic_node_info::GenericNodeInfo;
+use anyhow::anyhow;
+
+use crate::{
+    node::*,
+    nodes::{
+        basic_shader_nodes::{
+            custom_shader::{merge_params, ShaderParam, ShaderSource, UniformValue},
+            shadertoy::shadertoy_date,
+        },
+        node_enum::NodeType,
+    },
+    storage::{Storage, TextureDepth},
+};
+
+/// goes before the glsl from the file, adds the functions and uniforms isf provides
+const ISF_HEADER: &str = r#"#version 140
+
+in vec2 v_tex_coords;
+
+#define isf_FragNormCoord v_tex_coords
+#define vv_FragNormCoord v_tex_coords
+#define IMG_NORM_PIXEL(img, coord) texture(img, coord)
+#define IMG_PIXEL(img, coord) texture(img, (coord) / vec2(textureSize(img, 0)))
+#define IMG_THIS_NORM_PIXEL(img) texture(img, isf_FragNormCoord)
+#define IMG_THIS_PIXEL(img) texture(img, isf_FragNormCoord)
+#define IMG_SIZE(img) vec2(textureSize(img, 0))
+
+uniform int PASSINDEX;
+uniform vec2 RENDERSIZE;
+uniform float TIME;
+uniform float TIMEDELTA;
+uniform vec4 DATE;
+uniform int FRAMEINDEX;
+"#;
+
+/// one entry of the `PASSES` array, every pass draws the same shader with a different `PASSINDEX`
+#[derive(Clone, Debug, PartialEq)]
+pub struct IsfPass {
+    pub target: Option<String>,
+    /// persistent buffers keep their contents between frames
+    pub persistent: bool,
+    pub float: bool,
+    /// expressions like `$WIDTH/2`, the pass is the render size when these are missing
+    pub width: Option<String>,
+    pub height: Option<String>,
+}
+
+/// what is read from an isf file's json header
+#[derive(Clone, Debug, PartialEq)]
+pub struct IsfShader {
+    /// the fragment shader that is compiled
+    pub frag: String,
+    pub params: Vec<ShaderParam>,
+    /// the names shown for `long` inputs that have `VALUES` and `LABELS`
+    pub labels: HashMap<String, Vec<(i32, String)>>,
+    pub passes: Vec<IsfPass>,
+    /// how many more lines `frag` has before the glsl than the file does, so compile errors point at the file's lines
+    pub header_lines: usize,
+}
+
+fn json_f32(value: Option<&Value>) -> Option<f32> {
+    return match value? {
+        Value::Number(a) => a.as_f64().map(|a| a as f32),
+        Value::Bool(a) => Some(if *a { 1.0 } else { 0.0 }),
+        _ => None,
+    };
+}
+
+fn json_array<const N: usize>(value: Option<&Value>, default: [f32; N]) -> [f32; N] {
+    let mut out = default;
+    if let Some(Value::Array(a)) = value {
+        for (i, v) in a.iter().take(N).enumerate() {
+            out[i] = json_f32(Some(v)).unwrap_or(out[i]);
+        }
+    }
+    return out;
+}
+
+/// splits an isf file into its json header and glsl, then builds the fragment shader and the parameters
+pub fn parse_isf(source: &str) -> anyhow::Result<IsfShader> {
+    let start = source
+        .find("/*")
+        .ok_or(anyhow!("the file doesn't start with a json comment"))?;
+    let end = source[start..]
+        .find("*/")
+        .map(|a| a + start)
+        .ok_or(anyhow!("the json comment is never closed"))?;
+    let json: Value = serde_json::from_str(&source[start + 2..end])?;
+    let body = &source[end + 2..];
+
+    let mut frag = ISF_HEADER.to_owned();
+    let mut params = vec![];
+    let mut labels = HashMap::new();
+
+    for input in json["INPUTS"].as_array().cloned().unwrap_or_default() {
+        let name = input["NAME"]
+            .as_str()
+            .ok_or(anyhow!("an input doesn't have a name"))?
+            .to_owned();
+        let default = input.get("DEFAULT");
+        let min = json_f32(input.get("MIN"));
+        let max = json_f32(input.get("MAX"));
+        let (glsl_type, value, range) = match input["TYPE"].as_str().unwrap_or_default() {
+            "float" => (
+                "float",
+                UniformValue::Float(json_f32(default).unwrap_or(0.0)),
+                Some((min.unwrap_or(0.0), max.unwrap_or(1.0))),
+            ),
+            "bool" | "event" => (
+                "bool",
+                UniformValue::Bool(json_f32(default).unwrap_or(0.0) != 0.0),
+                None,
+            ),
+            "long" => {
+                let values = input["VALUES"]
+                    .as_array()
+                    .map(|a| a.iter().filter_map(|v| json_f32(Some(v))).collect::<Vec<f32>>())
+                    .unwrap_or_default();
+                let names = input["LABELS"].as_array().cloned().unwrap_or_default();
+                if !values.is_empty() {
+                    labels.insert(
+                        name.clone(),
+                        values
+                            .iter()
+                            .enumerate()
+                            .map(|(i, v)| {
+                                let label = names
+                                    .get(i)
+                                    .and_then(|a| a.as_str())
+                                    .map(|a| a.to_owned())
+                                    .unwrap_or(v.to_string());
+                                (*v as i32, label)
+                            })
+                            .collect(),
+                    );
+                }
+                let low = values.iter().copied().reduce(f32::min).or(min).unwrap_or(0.0);
+                let high = values.iter().copied().reduce(f32::max).or(max).unwrap_or(10.0);
+                (
+                    "int",
+                    UniformValue::Int(json_f32(default).unwrap_or(low) as i32),
+                    Some((low, high)),
+                )
+            }
+            "color" => (
+                "vec4",
+                UniformValue::Vec4(json_array(default, [0.0, 0.0, 0.0, 1.0])),
+                None,
+            ),
+            "point2D" => {
+                let default = json_array(default, [0.0, 0.0]);
+                let min = json_array(input.get("MIN"), [0.0, 0.0]);
+                // points are often in pixels, so when there is no range use one that fits the default
+                let max = json_array(
+                    input.get("MAX"),
+                    [(default[0].max(default[1]) * 2.0).max(1.0); 2],
+                );
+                (
+                    "vec2",
+                    UniformValue::Vec2(default),
+                    Some((min[0].min(min[1]), max[0].max(max[1]))),
+                )
+            }
+            "image" => ("sampler2D", UniformValue::Texture, None),
+            other => return Err(anyhow!("{name} has the unsupported input type {other:?}")),
+        };
+        frag += &format!("uniform {glsl_type} {name};\n");
+        params.push(ShaderParam { name, value, range });
+    }
+
+    let mut passes = vec![];
+    for pass in json["PASSES"].as_array().cloned().unwrap_or_default() {
+        let expression = |key: &str| match pass.get(key) {
+            Some(Value::String(a)) => Some(a.to_owned()),
+            Some(Value::Number(a)) => Some(a.to_string()),
+            _ => None,
+        };
+        let flag = |key: &str| json_f32(pass.get(key)).is_some_and(|a| a != 0.0);
+        passes.push(IsfPass {
+            target: pass["TARGET"].as_str().map(|a| a.to_owned()),
+            persistent: flag("PERSISTENT"),
+            float: flag("FLOAT"),
+            width: expression("WIDTH"),
+            height: expression("HEIGHT"),
+        });
+    }
+    if passes.is_empty() {
+        passes.push(IsfPass {
+            target: None,
+            persistent: false,
+            float: false,
+            width: None,
+            height: None,
+        });
+    }
+    let mut targets = passes
+        .iter()
+        .filter_map(|p| p.target.clone())
+        .collect::<Vec<String>>();
+    targets.sort();
+    targets.dedup();
+    for target in targets {
+        frag += &format!("uniform sampler2D {target};\n");
+    }
+
+    // the header is padded when the json comment is longer than it, so the glsl never starts before its line in the file
+    let generated_lines = frag.matches('\n').count();
+    let file_lines = source[..end + 2].matches('\n').count();
+    frag += &"\n".repeat(file_lines.saturating_sub(generated_lines));
+    let header_lines = generated_lines.saturating_sub(file_lines);
+
+    frag += body;
+    return Ok(IsfShader {
+        frag,
+        params,
+        labels,
+        passes,
+        header_lines,
+    });
+}
+
+/// evaluates the `WIDTH` and `HEIGHT` of a pass, which can use `+ - * /`, brackets,
+/// `floor`, `ceil`, `round`, `abs`, and variables like `$WIDTH`
+pub fn eval_expression(expression: &str, vars: &HashMap<String, f32>) -> Option<f32> {
+    let chars = expression
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<char>>();
+    let mut parser = ExpressionParser {
+        chars,
+        pos: 0,
+        vars,
+    };
+    let value = parser.sum()?;
+    if parser.pos != parser.chars.len() {
+        return None;
+    }
+    return Some(value);
+}
+
+struct ExpressionParser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    vars: &'a HashMap<String, f32>,
+}
+
+impl ExpressionParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '.')
+        {
+            self.pos += 1;
+        }
+        return self.chars[start..self.pos].iter().collect();
+    }
+
+    fn sum(&mut self) -> Option<f32> {
+        let mut value = self.product()?;
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.pos += 1;
+                    value += self.product()?;
+                }
+                Some('-') => {
+                    self.pos += 1;
+                    value -= self.product()?;
+                }
+                _ => return Some(value),
+            }
+        }
+    }
+
+    fn product(&mut self) -> Option<f32> {
+        let mut value = self.atom()?;
+        loop {
+            match self.peek() {
+                Some('*') => {
+                    self.pos += 1;
+                    value *= self.atom()?;
+                }
+                Some('/') => {
+                    self.pos += 1;
+                    value /= self.atom()?;
+                }
+                _ => return Some(value),
+            }
+        }
+    }
+
+    fn atom(&mut self) -> Option<f32> {
+        match self.peek()? {
+            '-' => {
+                self.pos += 1;
+                return Some(-self.atom()?);
+            }
+            '(' => {
+                self.pos += 1;
+                let value = self.sum()?;
+                if self.peek() != Some(')') {
+                    return None;
+                }
+                self.pos += 1;
+                return Some(value);
+            }
+            '$' => {
+                self.pos += 1;
+                let name = self.word();
+                return self.vars.get(&name).copied();
+            }
+            _ => {}
+        }
+        let word = self.word();
+        if let Ok(value) = word.parse::<f32>() {
+            return Some(value);
+        }
+        let function: fn(f32) -> f32 = match word.as_str() {
+            "floor" => f32::floor,
+            "ceil" => f32::ceil,
+            "round" => f32::round,
+            "abs" => f32::abs,
+            _ => return None,
+        };
+        if self.peek() != Some('(') {
+            return None;
+        }
+        return Some(function(self.atom()?));
+    }
+}
+
+#[derive(Savefile)]
+pub struct IsfNode {
+    x: f32,
+    y: f32,
+    id: String,
+    /// the whole file, so the project still works if the file is moved
+    source: String,
+    path: Option<PathBuf>,
+    params: Vec<ShaderParam>,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    shader: Option<IsfShader>,
+    /// the json header's errors are kept here as well as compile errors
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    shader_source: ShaderSource,
+    /// the textures of passes with `PERSISTENT` set, by target name
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    persistent_buffers: HashMap<String, Texture2d>,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    frame: i32,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    last_time: f64,
+}
+
+impl Default for IsfNode {
+    fn default() -> Self {
+        IsfNode {
+            x: 0.0,
+            y: 0.0,
+            id: random_id(),
+            source: String::new(),
+            path: None,
+            params: vec![],
+            shader: None,
+            shader_source: ShaderSource::default(),
+            persistent_buffers: HashMap::new(),
+            frame: 0,
+            last_time: 0.0,
+        }
+    }
+}
+
+impl IsfNode {
+    /// uses `source` as the isf file, the path is left as it is
+    pub fn set_source(&mut self, source: String) {
+        match parse_isf(&source) {
+            Ok(shader) => {
+                // the buffers of the old passes don't fit the new ones
+                if self.shader.as_ref().map(|a| &a.passes) != Some(&shader.passes) {
+                    self.persistent_buffers.clear();
+                }
+                self.params = merge_params(shader.params.clone(), &self.params);
+                self.shader = Some(shader);
+                self.shader_source.error = None;
+            }
+            Err(e) => {
+                self.shader = None;
+                self.shader_source.error = Some(format!("failed to read the isf header: {e}"));
+            }
+        }
+        self.source = source;
+    }
+
+    /// compiles the current shader, falling back to the last one that worked
+    fn compiled_shader(&mut self, storage: &mut Storage) -> anyhow::Result<IsfShader> {
+        if self.shader.is_none() && self.shader_source.error.is_none() {
+            self.set_source(self.source.clone());
+        }
+        let source = match &self.shader {
+            Some(shader) => {
+                let frag = shader.frag.clone();
+                self.shader_source.compile(storage, &self.source, frag, shader.header_lines)?
+            }
+            None => self.shader_source.last_good().ok_or(anyhow!(
+                "{}",
+                self.shader_source.error.clone().unwrap_or("no isf file loaded".to_owned())
+            ))?,
+        };
+        if source == self.source {
+            if let Some(shader) = &self.shader {
+                return Ok(shader.clone());
+            }
+        }
+        return parse_isf(&source);
+    }
+}
+
+impl MyNode for IsfNode {
+    fn path(&self) -> Vec<&str> {
+        vec!["Image", "Basic Shader"]
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn generic_info(&self) -> GenericNodeInfo {
+        GenericNodeInfo {
+            x: self.x,
+            y: self.y,
+            type_: self.type_(),
+            id: self.id.to_owned(),
+        }
+    }
+
+    fn savefile_version() -> u32 {
+        0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_(&self) -> NodeType {
+        NodeType::Isf
+    }
+
+    fn fragment_shaders(&self) -> Vec<String> {
+        match parse_isf(&self.source) {
+            Ok(a) => vec![a.frag],
+            Err(_) => vec![],
+        }
+    }
+
+    fn load_assets(&mut self, storage: &Storage) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        match self.shader_source.read_file(&path, storage) {
+            Ok(source) => self.set_source(source),
+            Err(e) => log::error!("failed to read isf file {path:?}: {e}"),
+        }
+    }
+
+    fn watch_files(&mut self, storage: &Storage) -> bool {
+        let Some(source) = self.shader_source.watch_file(&self.path, storage) else {
+            return false;
+        };
+        self.set_source(source);
+        return true;
+    }
+
+    fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
+        return save_file(
+            path.join(self.name()).join(self.id() + ".bin"),
+            IsfNode::savefile_version(),
+            self,
+        );
+    }
+
+    fn inputs(&self) -> Vec<String> {
+        return self
+            .params
+            .iter()
+            .filter(|p| p.value == UniformValue::Texture)
+            .map(|p| p.name.clone())
+            .collect();
+    }
+
+    fn outputs(&self) -> Vec<String> {
+        return vec!["Out".to_string()];
+    }
+
+    fn set_xy(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    fn description(&mut self, ui: &imgui::Ui) {
+        ui.text_wrapped("loads an ISF (interactive shader format) file, the inputs in its json header become inputs and settings");
+        ui.text_wrapped("multiple passes and persistent buffers are supported. audio inputs, imported images and custom vertex shaders are not");
+        ui.text_wrapped("the file is reloaded when it is saved");
+    }
+
+    fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, storage: &Storage) {
+        if let Some(source) =
+            self.shader_source
+                .render_file_buttons(ui, &mut self.path, storage, ("isf", &["fs", "isf"]))
+        {
+            self.persistent_buffers.clear();
+            self.set_source(source);
+        }
+        if ui.button("clear buffers") {
+            self.persistent_buffers.clear();
+            self.frame = 0;
+        }
+        self.shader_source.render_error(ui);
+
+        let labels = self
+            .shader
+            .as_ref()
+            .map(|s| s.labels.clone())
+            .unwrap_or_default();
+        for param in &mut self.params {
+            if let (Some(options), UniformValue::Int(v)) = (labels.get(&param.name), param.value.clone()) {
+                let mut index = options.iter().position(|o| o.0 == v).unwrap_or(0);
+                if ui.combo(&param.name, &mut index, options, |o| o.1.as_str().into()) {
+                    param.value = UniformValue::Int(options[index].0);
+                }
+                continue;
+            }
+            param.render(ui);
+        }
+    }
+
+    fn run(
+        &mut self,
+        storage: &mut Storage,
+        map: HashMap<String, String>,
+        _renderer: &mut Renderer,
+    ) -> anyhow::Result<()> {
+        let output_id = self.output_id(&self.outputs()[0]);
+        let shader = self.compiled_shader(storage)?;
+
+        let mut image_keys = vec![];
+        for name in self.inputs() {
+            match map.get(&self.input_id(&name)) {
+                Some(a) => image_keys.push((name, a.to_owned())),
+                None => return Err(anyhow!("missing input: {name}")),
+            }
+        }
+
+        // the render size is the size of the first image input, shaders without inputs use the project canvas
+        let size = match image_keys.first() {
+            Some((_, key)) => match storage.get_texture(key) {
+                Some(a) => a.dimensions(),
+                None => return Err(anyhow!("cannot find input texture")),
+            },
+            None => storage.canvas_size,
+        };
+        let mut vars: HashMap<String, f32> = HashMap::new();
+        vars.insert("WIDTH".to_owned(), size.0 as f32);
+        vars.insert("HEIGHT".to_owned(), size.1 as f32);
+        for param in &self.params {
+            match param.value {
+                UniformValue::Float(v) => vars.insert(param.name.clone(), v),
+                UniformValue::Int(v) => vars.insert(param.name.clone(), v as f32),
+                _ => None,
+            };
+        }
+
+        let delta = (storage.time - self.last_time).max(0.0) as f32;
+        self.last_time = storage.time;
+        let time = storage.time as f32;
+        let frame = self.frame;
+        self.frame += 1;
+        let date = shadertoy_date();
+
+        // the storage key of the texture each target was drawn to this frame
+        let mut targets: HashMap<String, String> = HashMap::new();
+        for (index, pass) in shader.passes.iter().enumerate() {
+            let last = index + 1 == shader.passes.len();
+            let key = if last {
+                output_id.clone()
+            } else {
+                self.output_id(&format!("pass {index}"))
+            };
+            let pass_size = (
+                pass.width
+                    .as_ref()
+                    .and_then(|e| eval_expression(e, &vars))
+                    .map(|a| a.max(1.0) as u32)
+                    .unwrap_or(size.0),
+                pass.height
+                    .as_ref()
+                    .and_then(|e| eval_expression(e, &vars))
+                    .map(|a| a.max(1.0) as u32)
+                    .unwrap_or(size.1),
+            );
+
+            let depth = storage.texture_depth;
+            if pass.float {
+                storage.texture_depth = TextureDepth::F32;
+            }
+            storage.create_and_set_texture(pass_size.0, pass_size.1, key.clone());
+            storage.texture_depth = depth;
+
+            if let Some(target) = pass.target.as_ref().filter(|_| pass.persistent) {
+                let format = if pass.float {
+                    TextureDepth::F32
+                } else {
+                    TextureDepth::U8
+                };
+                let needs_new = self
+                    .persistent_buffers
+                    .get(target)
+                    .map_or(true, |t| t.dimensions() != pass_size);
+                if needs_new {
+                    let texture = Texture2d::empty_with_format(
+                        &storage.display,
+                        format.format(),
+                        glium::texture::MipmapsOption::NoMipmap,
+                        pass_size.0,
+                        pass_size.1,
+                    )?;
+                    texture.as_surface().clear_color(0.0, 0.0, 0.0, 0.0);
+                    self.persistent_buffers.insert(target.to_owned(), texture);
+                }
+            }
+
+            let mut textures: Vec<(&str, &Texture2d)> = vec![];
+            for (name, key) in &image_keys {
+                match storage.get_texture(key) {
+                    Some(a) => textures.push((name, a)),
+                    None => return Err(anyhow!("cannot find input texture: {name}")),
+                }
+            }
+            // persistent buffers still have the last frame in them while they are being drawn to
+            for (name, texture) in &self.persistent_buffers {
+                textures.push((name, texture));
+            }
+            for (name, key) in &targets {
+                if !self.persistent_buffers.contains_key(name) {
+                    if let Some(a) = storage.get_texture(key) {
+                        textures.push((name, a));
+                    }
+                }
+            }
+
+            let pass_index = index as i32;
+            let render_size = [pass_size.0 as f32, pass_size.1 as f32];
+            let mut uniforms = DynamicUniforms::new();
+            uniforms.add("PASSINDEX", &pass_index);
+            uniforms.add("RENDERSIZE", &render_size);
+            uniforms.add("TIME", &time);
+            uniforms.add("TIMEDELTA", &delta);
+            uniforms.add("DATE", &date);
+            uniforms.add("FRAMEINDEX", &frame);
+            for param in &self.params {
+                if let Some(value) = param.as_uniform_value() {
+                    uniforms.add(&param.name, value);
+                }
+            }
+            for (name, texture) in &textures {
+                uniforms.add(name, texture);
+            }
+
+            let program = storage.get_frag_shader(shader.frag.clone()).unwrap();
+            let output = storage.get_texture(&key).unwrap();
+            output.as_surface().draw(
+                &storage.vertex_buffer,
+                &storage.indices,
+                program,
+                &uniforms,
+                &DrawParameters {
+                    ..Default::default()
+                },
+            )?;
+
+            if let Some(target) = &pass.target {
+                if let Some(buffer) = self.persistent_buffers.get(target) {
+                    output
+                        .as_surface()
+                        .fill(&buffer.as_surface(), MagnifySamplerFilter::Nearest);
+                }
+                targets.insert(target.to_owned(), key);
+            }
+        }
+
+        return Ok(());
+    }
+}
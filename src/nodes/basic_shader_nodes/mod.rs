@@ -1,5 +1,9 @@
+pub mod custom_shader;
 pub mod difference_of_gaussians;
+pub mod feedback;
 pub mod greyscale;
 pub mod invert;
+pub mod isf;
 pub mod shader_generic;
+pub mod shadertoy;
 pub mod solid_color;
@@ -8,9 +8,23 @@ use crate::generic_node_info::GenericNodeInfo;
 use anyhow::anyhow;
 use strum::IntoEnumIterator;
 
-use crate::{node::*, nodes::*, storage::Storage};
+use crate::{
+    cpu::{draw, fract, gaussian_blur, glsl_mod, hsv2rgb, rand, rgb2hsv, sample, smoothstep, CpuStorage},
+    fuse::PixelCode,
+    node::*,
+    nodes::{basic_shader_nodes::custom_shader::UniformValue, *},
+    storage::Storage,
+};
 
 impl NodeType {
+    /// true if `u_input` is a distance in pixels, so it is scaled down with the preview quality
+    fn input_is_pixels(&self) -> bool {
+        matches!(
+            self,
+            NodeType::Blur | NodeType::BlurSp | NodeType::Dot | NodeType::ChromaticAberration
+        )
+    }
+
     fn generic_shader_index(&self) -> i32 {
         match self {
             NodeType::ChromaticAberration => 0,
@@ -29,6 +43,17 @@ impl NodeType {
     }
 }
 
+/// the offsets blursp.glsl reads, in order. the shader has 109 but only uses the first 44
+const BLURSP_POINTS: [[f32; 2]; 44] = [
+    [0.0, 0.0], [0.0, -1.0], [0.0, 1.0], [-1.0, -1.0], [-1.0, 1.0], [-1.0, 0.0], [1.0, -1.0],
+    [1.0, 1.0], [1.0, 0.0], [-1.0, 2.0], [-1.0, -2.0], [1.0, 2.0], [1.0, -2.0], [2.0, 2.0],
+    [2.0, 1.0], [2.0, -2.0], [2.0, -1.0], [2.0, 0.0], [-2.0, 2.0], [-2.0, 1.0], [-2.0, -2.0],
+    [-2.0, -1.0], [-2.0, 0.0], [0.0, 2.0], [0.0, -2.0], [3.0, 1.0], [3.0, 2.0], [3.0, -2.0],
+    [3.0, -1.0], [3.0, 0.0], [2.0, -3.0], [2.0, 3.0], [1.0, -3.0], [1.0, 3.0], [0.0, -3.0],
+    [0.0, 3.0], [-1.0, -3.0], [-1.0, 3.0], [-3.0, 1.0], [-3.0, 2.0], [-3.0, -2.0], [-3.0, -1.0],
+    [-3.0, 0.0], [-2.0, -3.0],
+];
+
 fn default_node_type() -> NodeType {
     NodeType::Blur
 }
@@ -37,6 +62,7 @@ fn default_node_type() -> NodeType {
 pub struct GenericShaderNode {
     #[savefile_default_fn = "default_node_type"]
     #[savefile_ignore]
+    #[savefile_introspect_ignore]
     #[savefile_versions = "..0"]
     type_: NodeType,
     #[savefile_versions = "1.."]
@@ -52,6 +78,50 @@ pub struct GenericShaderNode {
 }
 
 impl GenericShaderNode {
+    fn fragment_shader(&self) -> String {
+        // hue shift is made from the code it is fused with, so the two can't drift apart
+        if let Some(code) = self.pixel_code() {
+            return code.standalone_glsl();
+        }
+        return match self.type_ {
+            NodeType::ChromaticAberration => {
+                r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            uniform float u_time;
+            uniform float u_input;
+            uniform vec2 u_resolution;
+
+            uniform vec2 r_displacement = vec2(3.0, 0.0);
+            uniform vec2 g_displacement = vec2(0.0, 0.0);
+            uniform vec2 b_displacement = vec2(-3.0, 0.0);
+
+            void main() {
+            float r = texture(tex, v_tex_coords + (u_input * r_displacement) / u_resolution).r;
+            float g = texture(tex, v_tex_coords + (u_input * g_displacement) / u_resolution).g;
+            float b = texture(tex, v_tex_coords + (u_input * b_displacement) / u_resolution).b;
+            color = vec4(r,g,b, texture(tex, v_tex_coords).a);
+            }
+            "#
+            }
+            NodeType::VHS => include_str!("VHS.glsl"),
+            NodeType::Blur => include_str!("gaussian.glsl"),
+            NodeType::Dot => include_str!("dot.glsl"),
+            NodeType::Sharpness => include_str!("sharp.glsl"),
+            NodeType::BlurSp => include_str!("blursp.glsl"),
+            NodeType::Crystal => include_str!("crystal.glsl"),
+            a => {
+                unreachable!("node type: {a:?} is not a generic shader type or has no glsl code linked")
+            }
+        }
+        .to_owned();
+    }
+
     pub fn load_type(&mut self) {
         if self.type_index != self.type_.generic_shader_index() {
             log::info!("{}", self.type_index);
@@ -165,6 +235,64 @@ impl MyNode for GenericShaderNode {
         self.type_
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![self.fragment_shader()]
+    }
+
+    fn pixel_code(&self) -> Option<PixelCode> {
+        if self.type_ != NodeType::HueShift {
+            return None;
+        }
+        Some(PixelCode {
+            code: r#"
+                vec3 hsv = $rgb2hsv($in_0.rgb);
+                hsv.x = mod(hsv.x + $u_input / 360.0, 1.0);
+                $out_0 = vec4($hsv2rgb(hsv), $in_0.a);
+            "#
+            .to_string(),
+            functions: r#"
+vec3 $rgb2hsv(vec3 c)
+{
+    vec4 K = vec4(0.0, -1.0 / 3.0, 2.0 / 3.0, -1.0);
+    vec4 p = mix(vec4(c.bg, K.wz), vec4(c.gb, K.xy), step(c.b, c.g));
+    vec4 q = mix(vec4(p.xyw, c.r), vec4(c.r, p.yzx), step(p.x, c.r));
+
+    float d = q.x - min(q.w, q.y);
+    float e = 1.0e-10;
+    return vec3(abs(q.z + (q.w - q.y) / (6.0 * d + e)), d / (q.x + e), q.x);
+}
+
+vec3 $hsv2rgb(vec3 c)
+{
+    vec4 K = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
+    vec3 p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
+    return c.z * mix(K.xxx, clamp(p - K.xxx, 0.0, 1.0), c.y);
+}
+"#
+            .to_string(),
+            uniforms: vec![("u_input".to_string(), UniformValue::Float(self.input))],
+        })
+    }
+
+    fn tile_margin(&self) -> Option<u32> {
+        let radius = self.input.abs().ceil() as u32;
+        match self.type_ {
+            NodeType::Blur | NodeType::BlurSp => Some(radius + 1),
+            NodeType::ChromaticAberration => Some(radius * 3 + 1),
+            NodeType::Sharpness => Some(2),
+            NodeType::HueShift => Some(0),
+            // these use texture coordinates or the size of the whole image
+            _ => None,
+        }
+    }
+
+    fn color_space(&self) -> ColorSpace {
+        match self.type_ {
+            NodeType::Blur | NodeType::BlurSp | NodeType::Sharpness => ColorSpace::Linear,
+            _ => ColorSpace::Perceptual,
+        }
+    }
+
      
 
     fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
@@ -252,6 +380,135 @@ impl MyNode for GenericShaderNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let size = input.dimensions();
+        let res = [size.0 as f32, size.1 as f32];
+        let u_input = self.input;
+        let time = storage.time as f32;
+        let output = match self.type_ {
+            NodeType::ChromaticAberration => draw(size, &[input], |uv, c| {
+                let r = sample(input, [uv[0] + u_input * 3.0 / res[0], uv[1]])[0];
+                let b = sample(input, [uv[0] + u_input * -3.0 / res[0], uv[1]])[2];
+                [r, c[0][1], b, c[0][3]]
+            }),
+            NodeType::VHS => draw(size, &[], |uv, _| {
+                let health = 1.1 / ((1.0 - u_input) + 0.1);
+                let vertical_bar = |pos: f32, uv_y: f32, offset: f32| {
+                    return smoothstep(pos - 0.05, pos, uv_y) * offset - smoothstep(pos, pos + 0.05, uv_y) * offset;
+                };
+                let mut uv = uv;
+                let mut i = 0.0;
+                while i < 0.71 {
+                    let d = glsl_mod(time * i, 1.7);
+                    let o = (1.0 - (time * 0.24 * i).tan()).sin() * 0.002 * health;
+                    uv[0] += vertical_bar(d, uv[1], o);
+                    i += 0.1313;
+                }
+                let uv_y = (uv[1] * 250.0) as i32 as f32 * (1.0 / 250.0);
+                let aspect_ratio = res[0] / res[1];
+                let uv_x = (uv[0] * aspect_ratio * 250.0) as i32 as f32 * (1.0 / (250.0 * aspect_ratio));
+                uv[0] += rand([time * 0.00001, uv_y]) * 0.00088 * health;
+
+                let offset_r = 0.006 * time.sin() * 0.13 * health;
+                let offset_g = 0.0073 * (time * 0.97).cos() * 0.13 * health;
+                let r = sample(input, [uv[0] + offset_r, uv[1]])[0];
+                let g = sample(input, [uv[0] + offset_g, uv[1]])[1];
+                let b = sample(input, uv)[2];
+                let noise_uv = [uv_x + glsl_mod(time, 100.0), uv_y + glsl_mod(time, 100.0) + 3.4];
+                let white_noise = u_input * u_input * (rand(noise_uv) * 0.3 - 0.15);
+                [r + white_noise, g + white_noise, b + white_noise, 1.0]
+            }),
+            NodeType::Blur => draw(size, &[], |uv, _| {
+                return gaussian_blur(input, uv, u_input, u_input * u_input, 15.0, &mut [0.0, 0.0]);
+            }),
+            NodeType::Dot => draw(size, &[], |uv, _| {
+                let spacing = 2.0 * u_input * 2.0_f32.sqrt();
+                let pos = [res[0] * uv[0], res[1] * uv[1]];
+                let closest = pos.map(|a| a - glsl_mod(a, spacing) + u_input * 2.0_f32.sqrt());
+                let c = sample(input, [closest[0] / res[0], closest[1] / res[1]]);
+                let u = (c[0] + c[1] + c[2]) / 3.0;
+                let d = ((pos[0] - closest[0]).powi(2) + (pos[1] - closest[1]).powi(2)).sqrt();
+                [if d < u_input * u * u { 1.0 } else { 0.0 }; 4]
+            }),
+            NodeType::Sharpness => draw(size, &[], |uv, _| {
+                let taps = [(-1.0, 0.0, -1.0), (0.0, -1.0, -1.0), (0.0, 0.0, 5.0), (0.0, 1.0, -1.0), (1.0, 0.0, -1.0)];
+                let mut sum = [0.0; 4];
+                for (dx, dy, w) in taps {
+                    let c = sample(input, [uv[0] + dx * (1.0 / res[0]), uv[1] + dy * (1.0 / res[1])]);
+                    for i in 0..4 {
+                        sum[i] += u_input * w * c[i];
+                    }
+                }
+                sum
+            }),
+            NodeType::BlurSp => draw(size, &[], |uv, _| {
+                let (rr, w0) = (u_input * u_input, 0.3780 / u_input.powf(1.975));
+                let centre = sample(input, uv);
+                let mut weight_total = 0.0;
+                let mut col = [0.0; 4];
+                for (index, [x, y]) in BLURSP_POINTS.iter().enumerate() {
+                    let c = sample(input, [uv[0] + x / res[0], uv[1] + y / res[1]]);
+                    let s = (0..4).map(|i| (c[i] - centre[i]).powi(2)).sum::<f32>().sqrt() / 1.732;
+                    if s > u_input && index > 2 {
+                        break;
+                    }
+                    let w = w0 * ((-x * x - y * y) / (2.0 * rr)).exp();
+                    weight_total += w;
+                    for i in 0..4 {
+                        col[i] += c[i] * w;
+                    }
+                }
+                col.map(|a| a / weight_total)
+            }),
+            NodeType::Crystal => {
+                let size_px = ((res[0] + res[1]) / 2.0).round() / u_input.sqrt();
+                let crystal_rand = |co: [f32; 2]| {
+                    return fract(glsl_mod(co[0] * 12.9898 + co[1] * 78.233, 3.14).sin() * 43758.5453);
+                };
+                let rand_pos = |p: [i32; 2]| {
+                    let p = [p[0] as f32, p[1] as f32];
+                    if crystal_rand([p[0] * 432.32, p[1] * 432.32]) > 0.8 {
+                        return [0.0, 0.0];
+                    }
+                    return [
+                        p[0] * size_px + glsl_mod(17.0 * size_px * crystal_rand(p), size_px * 2.0),
+                        p[1] * size_px + glsl_mod(12.0 * size_px * crystal_rand([p[0] * 3.141592, p[1] * 3.141592]), size_px * 2.0),
+                    ];
+                };
+                let distance = |a: [f32; 2], b: [f32; 2]| ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt();
+                draw(size, &[], |uv, _| {
+                    let og = [res[0] * uv[0], res[1] * uv[1]];
+                    let og_pos = [(og[0] / size_px).floor() as i32, (og[1] / size_px).floor() as i32];
+                    let mut pos = rand_pos(og_pos);
+                    for x in -3..=3 {
+                        for y in -3..=3 {
+                            let new_pos = rand_pos([og_pos[0] + x, og_pos[1] + y]);
+                            let inside = (0..2).all(|i| new_pos[i] >= 0.0 && new_pos[i] <= res[i]);
+                            if inside && distance(og, pos) > distance(og, new_pos) {
+                                pos = new_pos;
+                            }
+                        }
+                    }
+                    sample(input, [pos[0] / res[0], pos[1] / res[1]])
+                })
+            }
+            NodeType::HueShift => {
+                let rot = self.input / 360.0;
+                draw(size, &[input], |_, c| {
+                    let c = c[0];
+                    let mut hsv = rgb2hsv([c[0], c[1], c[2]]);
+                    hsv[0] = fract(hsv[0] + rot);
+                    let rgb = hsv2rgb(hsv);
+                    [rgb[0], rgb[1], rgb[2], c[3]]
+                })
+            }
+            a => return Err(anyhow!("{a:?} is not a generic shader type")),
+        };
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -280,43 +537,7 @@ impl MyNode for GenericShaderNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = match self.type_ {
-            NodeType::ChromaticAberration => {
-                r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            uniform float u_time;
-            uniform float u_input;
-            uniform vec2 u_resolution;
-
-            uniform vec2 r_displacement = vec2(3.0, 0.0);
-            uniform vec2 g_displacement = vec2(0.0, 0.0);
-            uniform vec2 b_displacement = vec2(-3.0, 0.0);
-
-            void main() {
-            float r = texture(tex, v_tex_coords + (u_input * r_displacement) / u_resolution).r;
-            float g = texture(tex, v_tex_coords + (u_input * g_displacement) / u_resolution).g;
-            float b = texture(tex, v_tex_coords + (u_input * b_displacement) / u_resolution).b;
-            color = vec4(r,g,b, texture(tex, v_tex_coords).a);
-            }
-            "#
-            }
-            NodeType::VHS => include_str!("VHS.glsl"),
-            NodeType::HueShift => include_str!("hue_shift.glsl"),
-            NodeType::Blur => include_str!("gaussian.glsl"),
-            NodeType::Dot => include_str!("dot.glsl"),
-            NodeType::Sharpness => include_str!("sharp.glsl"),
-            NodeType::BlurSp => include_str!("blursp.glsl"),
-            NodeType::Crystal => include_str!("crystal.glsl"),
-            a => {
-                unreachable!("node type: {a:?} is not a generic shader type or has no glsl code linked")
-            }
-        };
+        let fragment_shader_src = self.fragment_shader();
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -340,7 +561,11 @@ impl MyNode for GenericShaderNode {
         let uniforms = uniform! {
             tex: texture,
             u_time: storage.time as f32,
-            u_input: self.input,
+            u_input: if self.type_.input_is_pixels() {
+                self.input * storage.preview_scale
+            } else {
+                self.input
+            },
             u_resolution: [texture_size.0 as f32, texture_size.1 as f32],
         };
 
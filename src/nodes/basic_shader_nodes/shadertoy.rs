@@ -0,0 +1,350 @@
+use std::{any::Any, collections::HashMap, path::PathBuf, time::SystemTime};
+
+use glium::{uniforms::DynamicUniforms, DrawParameters, Surface, Texture2d};
+use imgui_glium_renderer::Renderer;
+use lazy_static::lazy_static;
+use regex::Regex;
+use savefile::{save_file, SavefileError};
+use crate::generic_node_info::GenericNodeInfo;
+use anyhow::anyhow;
+
+use crate::{
+    node::*,
+    nodes::{basic_shader_nodes::custom_shader::ShaderSource, node_enum::NodeType},
+    storage::Storage,
+};
+
+const CHANNELS: [&str; 4] = ["iChannel0", "iChannel1", "iChannel2", "iChannel3"];
+
+/// goes before the user's code, declares everything shadertoy provides
+const SHADERTOY_HEADER: &str = r#"#version 140
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+uniform vec3 iResolution;
+uniform float iTime;
+uniform float iTimeDelta;
+uniform int iFrame;
+uniform vec4 iMouse;
+uniform vec4 iDate;
+uniform sampler2D iChannel0;
+uniform sampler2D iChannel1;
+uniform sampler2D iChannel2;
+uniform sampler2D iChannel3;
+uniform vec3 iChannelResolution[4];
+
+"#;
+
+/// goes after the user's code, calls `mainImage` with the pixel coordinate
+const SHADERTOY_FOOTER: &str = r#"
+
+void main() {
+    vec4 c = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(c, v_tex_coords * iResolution.xy);
+    color = c;
+}
+"#;
+
+const DEFAULT_SHADER: &str = r#"void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    vec2 uv = fragCoord / iResolution.xy;
+    vec3 col = 0.5 + 0.5 * cos(iTime + uv.xyx + vec3(0, 2, 4));
+    fragColor = vec4(col, 1.0);
+}
+"#;
+
+lazy_static! {
+    static ref CHANNEL_REGEX: Regex = Regex::new(r"\biChannel([0-3])\b").unwrap();
+}
+
+/// turns shadertoy code into a fragment shader that works with `Storage::gen_frag_shader`
+pub fn wrap_shadertoy(source: &str) -> String {
+    return format!("{SHADERTOY_HEADER}{source}{SHADERTOY_FOOTER}");
+}
+
+/// `iDate` is (year, month starting at 0, day, seconds since midnight), in utc
+pub fn shadertoy_date() -> [f32; 4] {
+    // rfc3339 looks like 2024-01-31T12:34:56Z
+    let now = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+    let n = |range: std::ops::Range<usize>| {
+        now.get(range)
+            .and_then(|a| a.parse::<f32>().ok())
+            .unwrap_or_default()
+    };
+    return [
+        n(0..4),
+        n(5..7) - 1.0,
+        n(8..10),
+        n(11..13) * 3600.0 + n(14..16) * 60.0 + n(17..19),
+    ];
+}
+
+#[derive(Savefile)]
+pub struct ShadertoyNode {
+    x: f32,
+    y: f32,
+    id: String,
+    /// the code from shadertoy, with a `mainImage` function
+    source: String,
+    /// when this is set the source is read from this file instead of being edited in the node
+    path: Option<PathBuf>,
+    /// where `iMouse` is, from 0-1
+    mouse: [f32; 2],
+    mouse_down: bool,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    shader_source: ShaderSource,
+    /// counts up every time the node is run, for `iFrame`
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    frame: i32,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    last_time: f64,
+}
+
+impl Default for ShadertoyNode {
+    fn default() -> Self {
+        ShadertoyNode {
+            x: 0.0,
+            y: 0.0,
+            id: random_id(),
+            source: DEFAULT_SHADER.to_owned(),
+            path: None,
+            mouse: [0.5, 0.5],
+            mouse_down: false,
+            shader_source: ShaderSource::default(),
+            frame: 0,
+            last_time: 0.0,
+        }
+    }
+}
+
+impl ShadertoyNode {
+    /// the channels the code uses, each one is an input
+    fn channels(&self) -> Vec<usize> {
+        let mut channels = CHANNEL_REGEX
+            .captures_iter(&self.source)
+            .filter_map(|c| c[1].parse::<usize>().ok())
+            .collect::<Vec<usize>>();
+        channels.sort();
+        channels.dedup();
+        return channels;
+    }
+}
+
+impl MyNode for ShadertoyNode {
+    fn path(&self) -> Vec<&str> {
+        vec!["Image", "Basic Shader"]
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn generic_info(&self) -> GenericNodeInfo {
+        GenericNodeInfo {
+            x: self.x,
+            y: self.y,
+            type_: self.type_(),
+            id: self.id.to_owned(),
+        }
+    }
+
+    fn savefile_version() -> u32 {
+        0
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_(&self) -> NodeType {
+        NodeType::Shadertoy
+    }
+
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![wrap_shadertoy(&self.source)]
+    }
+
+    fn load_assets(&mut self, storage: &Storage) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        match self.shader_source.read_file(&path, storage) {
+            Ok(source) => self.source = source,
+            Err(e) => log::error!("failed to read shader {path:?}: {e}"),
+        }
+    }
+
+    fn watch_files(&mut self, storage: &Storage) -> bool {
+        let Some(source) = self.shader_source.watch_file(&self.path, storage) else {
+            return false;
+        };
+        self.source = source;
+        return true;
+    }
+
+    fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
+        return save_file(
+            path.join(self.name()).join(self.id() + ".bin"),
+            ShadertoyNode::savefile_version(),
+            self,
+        );
+    }
+
+    fn inputs(&self) -> Vec<String> {
+        return self
+            .channels()
+            .into_iter()
+            .map(|i| CHANNELS[i].to_string())
+            .collect();
+    }
+
+    fn outputs(&self) -> Vec<String> {
+        return vec!["Out".to_string()];
+    }
+
+    fn set_xy(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    fn description(&mut self, ui: &imgui::Ui) {
+        ui.text_wrapped("runs code copied from shadertoy.com, the code needs a mainImage function");
+        ui.text_wrapped("iTime, iTimeDelta, iFrame, iResolution, iMouse, iDate and iChannelResolution are set for you. each iChannel the code uses becomes an input");
+        ui.text_wrapped("the output is the size of the first channel, or the project canvas if there are no channels. buffers and cubemaps are not supported");
+    }
+
+    fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, storage: &Storage) {
+        if let Some(source) = self.shader_source.render_file_buttons(
+            ui,
+            &mut self.path,
+            storage,
+            ("glsl", &["glsl", "frag", "fs"]),
+        ) {
+            self.source = source;
+        }
+        self.shader_source.render_error(ui);
+
+        ui.slider("mouse x", 0.0, 1.0, &mut self.mouse[0]);
+        ui.slider("mouse y", 0.0, 1.0, &mut self.mouse[1]);
+        ui.checkbox("mouse down", &mut self.mouse_down);
+
+        ui.separator();
+        ui.input_text_multiline(
+            "##source",
+            &mut self.source,
+            [ui.content_region_avail()[0], ui.content_region_avail()[1].max(200.0)],
+        )
+        .read_only(self.path.is_some())
+        .build();
+    }
+
+    fn run(
+        &mut self,
+        storage: &mut Storage,
+        map: HashMap<String, String>,
+        _renderer: &mut Renderer,
+    ) -> anyhow::Result<()> {
+        let output_id = self.output_id(&self.outputs()[0]);
+
+        let mut texture_keys = vec![];
+        for channel in self.inputs() {
+            match map.get(&self.input_id(&channel)) {
+                Some(a) => texture_keys.push((channel, a.to_owned())),
+                None => return Err(anyhow!("missing input: {channel}")),
+            }
+        }
+
+        // errors are reported with the line numbers of the code from shadertoy, not of the wrapped shader
+        let source = self.shader_source.compile(
+            storage,
+            &self.source,
+            wrap_shadertoy(&self.source),
+            SHADERTOY_HEADER.lines().count(),
+        )?;
+        let frag = wrap_shadertoy(&source);
+
+        let mut channel_resolutions = [[0.0_f32; 3]; 4];
+        let mut sizes = vec![];
+        for (channel, key) in &texture_keys {
+            let Some(texture) = storage.get_texture(key) else {
+                return Err(anyhow!("cannot find input texture: {channel}"));
+            };
+            if let Some(i) = CHANNELS.iter().position(|c| c == channel) {
+                channel_resolutions[i] = [texture.width() as f32, texture.height() as f32, 1.0];
+            }
+            sizes.push(texture.dimensions());
+        }
+
+        // the output is the size of the first channel, shaders without channels use the project canvas
+        let size = sizes.first().copied().unwrap_or(storage.canvas_size);
+        storage.create_and_set_texture(size.0, size.1, output_id.clone());
+
+        let delta = (storage.time - self.last_time).max(0.0) as f32;
+        self.last_time = storage.time;
+        self.frame += 1;
+
+        let resolution = [size.0 as f32, size.1 as f32, 1.0];
+        let time = storage.time as f32;
+        let frame = self.frame;
+        let click = [self.mouse[0] * size.0 as f32, self.mouse[1] * size.1 as f32];
+        let mouse = [
+            click[0],
+            click[1],
+            if self.mouse_down { click[0] } else { -click[0] },
+            if self.mouse_down { click[1] } else { -click[1] },
+        ];
+        let date = shadertoy_date();
+        let channel_resolution_names = [
+            "iChannelResolution[0]",
+            "iChannelResolution[1]",
+            "iChannelResolution[2]",
+            "iChannelResolution[3]",
+        ];
+
+        let mut textures: Vec<(&str, &Texture2d)> = vec![];
+        for (channel, key) in &texture_keys {
+            if let Some(a) = storage.get_texture(key) {
+                textures.push((channel, a));
+            }
+        }
+
+        let mut uniforms = DynamicUniforms::new();
+        uniforms.add("iResolution", &resolution);
+        uniforms.add("iTime", &time);
+        uniforms.add("iTimeDelta", &delta);
+        uniforms.add("iFrame", &frame);
+        uniforms.add("iMouse", &mouse);
+        uniforms.add("iDate", &date);
+        for (name, value) in channel_resolution_names.iter().zip(&channel_resolutions) {
+            uniforms.add(name, value);
+        }
+        for (name, texture) in &textures {
+            uniforms.add(name, texture);
+        }
+
+        let shader = storage.get_frag_shader(frag).unwrap();
+        storage
+            .get_texture(&output_id)
+            .unwrap()
+            .as_surface()
+            .draw(
+                &storage.vertex_buffer,
+                &storage.indices,
+                shader,
+                &uniforms,
+                &DrawParameters {
+                    ..Default::default()
+                },
+            )?;
+
+        return Ok(());
+    }
+}
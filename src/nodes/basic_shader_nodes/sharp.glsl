@@ -16,11 +16,11 @@ vec4 sharpen(sampler2D tex, vec2 coords, vec2 renderSize, float sharpnes) {
   float dx = 1.0 / renderSize.x;
   float dy = 1.0 / renderSize.y;
   vec4 sum = vec4(0.0);
-  sum += ( sharpnes * -1.) * texture2D(tex, coords + vec2( -1.0 * dx , 0.0 * dy));
-  sum += ( sharpnes * -1.) * texture2D(tex, coords + vec2( 0.0 * dx , -1.0 * dy));
-  sum += ( sharpnes * 5. )* texture2D(tex, coords + vec2( 0.0 * dx , 0.0 * dy));
-  sum += ( sharpnes * -1.) * texture2D(tex, coords + vec2( 0.0 * dx , 1.0 * dy));
-  sum += ( sharpnes * -1.) * texture2D(tex, coords + vec2( 1.0 * dx , 0.0 * dy));
+  sum += ( sharpnes * -1.) * texture(tex, coords + vec2( -1.0 * dx , 0.0 * dy));
+  sum += ( sharpnes * -1.) * texture(tex, coords + vec2( 0.0 * dx , -1.0 * dy));
+  sum += ( sharpnes * 5. )* texture(tex, coords + vec2( 0.0 * dx , 0.0 * dy));
+  sum += ( sharpnes * -1.) * texture(tex, coords + vec2( 0.0 * dx , 1.0 * dy));
+  sum += ( sharpnes * -1.) * texture(tex, coords + vec2( 1.0 * dx , 0.0 * dy));
   return sum;
 }
 
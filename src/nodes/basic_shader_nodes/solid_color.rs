@@ -7,11 +7,28 @@ use crate::generic_node_info::GenericNodeInfo;
 use anyhow::anyhow;
 
 use crate::{
+    canvas::generator_size_ui,
+    cpu::{draw, CpuStorage},
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            uniform vec4 c;
+
+            void main() {
+            color = c;
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct ColorNode {
     x: f32,
@@ -20,6 +37,9 @@ pub struct ColorNode {
     color: [f32; 4],
     input: bool,
     size: (u32, u32),
+    /// follow the project canvas size instead of `size`
+    #[savefile_versions = "1.."]
+    use_canvas: bool,
 }
 
 impl Default for ColorNode {
@@ -31,6 +51,7 @@ impl Default for ColorNode {
             color: [1.0; 4],
             input: false,
             size: (1, 1),
+            use_canvas: true,
         }
     }
 }
@@ -53,7 +74,7 @@ impl MyNode for ColorNode {
     }
 
     fn savefile_version() -> u32 {
-        0
+        1
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -66,6 +87,10 @@ impl MyNode for ColorNode {
         NodeType::SolidColor
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -96,6 +121,24 @@ impl MyNode for ColorNode {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn scales_preview(&self) -> bool {
+        true
+    }
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        if self.use_canvas {
+            self.size = storage.canvas_size;
+        } else if self.input && self.inputs().len() > 0 {
+            self.size = storage
+                .input(&map, &self.input_id(&self.inputs()[0]))?
+                .dimensions();
+        }
+        let color = self.color;
+        let output = draw(self.size, &[], |_, _| color);
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -104,22 +147,11 @@ impl MyNode for ColorNode {
     ) -> anyhow::Result<()> {
         let output_id =self.output_id(&self.outputs()[0]);;
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            uniform vec4 c;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
-            void main() {
-            color = c;
-            }
-            "#;
-
-        if self.input && self.inputs().len() > 0 {
+        if self.use_canvas {
+            self.size = storage.canvas_size;
+        } else if self.input && self.inputs().len() > 0 {
             let input_id = self.input_id(&self.inputs()[0]);
             let get_output = match map.get(&input_id) {
                 Some(a) => a,
@@ -134,7 +166,13 @@ impl MyNode for ColorNode {
                 storage
             .gen_frag_shader(fragment_shader_src.to_string())
             .ok_or(anyhow!("failed to compile shader"))?;
-        storage.create_and_set_texture(self.size.0, self.size.1, output_id.clone());
+        // sizes that don't come from the input are shrunk while editing
+        let size = if self.input && !self.use_canvas {
+            self.size
+        } else {
+            storage.preview_size(self.size)
+        };
+        storage.create_and_set_texture(size.0, size.1, output_id.clone());
 
         let texture: &glium::Texture2d = match storage.get_texture(&output_id) {
             Some(a) => a,
@@ -172,12 +210,7 @@ let shader = storage
 
     fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
         ui.columns(2, "color col", true);
-        ui.checkbox("use input texture for dimensions", &mut self.input);
-        ui.disabled(self.input, || {
-            let mut input_val = [self.size.0 as i32, self.size.1 as i32];
-            ui.input_int2("dimensions (w,h)", &mut input_val).build();
-            self.size = (input_val[0].max(1) as u32, input_val[1].max(1) as u32);
-        });
+        generator_size_ui(ui, &mut self.use_canvas, &mut self.input, &mut self.size);
         ui.next_column();
         ui.set_next_item_width(
             0.7 * ui.content_region_avail()[0].min(ui.content_region_avail()[1]),
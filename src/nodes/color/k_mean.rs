@@ -14,12 +14,15 @@ use std::hash::Hasher;
 // use okolors::*;
 // use crate::nodes::color::k_mean::palette::Srgb;
 use glium::texture::RawImage2d;
+use image::Rgba;
 use okolors::{palette::Srgb, Okolors};
 
 use crate::{
+    cpu::{CpuStorage, CpuTexture},
     node::{random_id, MyNode},
     nodes::node_enum::{NodeType},
     storage::Storage,
+    worker_pool::{poll_job, CpuJob},
 };
 
 #[derive(Savefile)]
@@ -32,6 +35,10 @@ pub struct PalletGenNode {
     sampling_factor: f32,
     map: HashMap<u64, Vec<u8>>,
     frozen_pallet: Option<Vec<u8>>,
+    /// k-means is slow on large images so it is run on the worker pool
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    job: Option<CpuJob<Option<Vec<u8>>>>,
 }
 
 fn calculate_hash<T: Hash>(t: &T) -> u64 {
@@ -39,6 +46,28 @@ fn calculate_hash<T: Hash>(t: &T) -> u64 {
     t.hash(&mut hasher);
     hasher.finish()
 }
+/// the most common colours in `data` as rgb bytes, most common first
+fn pallet(data: Vec<[u8; 3]>, lightness_weight: f32, pallet_size: u8, sampling_factor: f32) -> Option<Vec<u8>> {
+    let mut image_data = Vec::new();
+    let srgb = data
+        .into_iter()
+        .map(|x| Srgb::new(x[0], x[1], x[2]).into())
+        .collect::<Vec<Srgb<u8>>>();
+    let mut pallet = Okolors::try_from(srgb.as_slice()).ok()?;
+    let p = pallet
+        .lightness_weight(lightness_weight)
+        .parallel(true)
+        .palette_size(pallet_size)
+        .sampling_factor(sampling_factor)
+        .sort_by_frequency(true)
+        .srgb8_palette();
+
+    for i in p {
+        image_data.extend([i.red, i.green, i.blue]);
+    }
+    return Some(image_data);
+}
+
 impl Default for PalletGenNode {
     fn default() -> Self {
         PalletGenNode {
@@ -50,6 +79,7 @@ impl Default for PalletGenNode {
             sampling_factor: 0.25,
             map: HashMap::new(),
             frozen_pallet: None,
+            job: None,
         }
     }
 }
@@ -130,6 +160,47 @@ impl MyNode for PalletGenNode {
         }
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let data = input
+            .pixels()
+            .map(|x| [0, 1, 2].map(|i| (x[i].clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect::<Vec<[u8; 3]>>();
+
+        let image_data = match &self.frozen_pallet {
+            Some(frozen_pallet) if frozen_pallet.len() == self.pallet_size as usize * 3 => frozen_pallet.clone(),
+            _ => {
+                let new_hash = calculate_hash(&(
+                    &data,
+                    self.pallet_size,
+                    (self.lightness_weight * 1000000.0) as i32,
+                    (self.sampling_factor * 1000000.0) as i32,
+                ));
+                if !self.map.contains_key(&new_hash) {
+                    let image_data = pallet(data, self.lightness_weight, self.pallet_size, self.sampling_factor)
+                        .ok_or(anyhow!("failed to generate pallet"))?;
+                    self.map.insert(new_hash, image_data);
+                }
+                let image_data = self.map[&new_hash].clone();
+                if self.frozen_pallet.is_some() && image_data.len() == self.pallet_size as usize * 3 {
+                    self.frozen_pallet = Some(image_data.clone());
+                }
+                image_data
+            }
+        };
+
+        // colours that weren't found are left transparent, the same as in a new texture
+        let output = CpuTexture::from_fn(self.pallet_size as u32, 1, |x, _| match image_data.len() == self.pallet_size as usize * 3 {
+            true => Rgba([0, 1, 2, 3].map(|i| match i {
+                3 => 1.0,
+                _ => image_data[x as usize * 3 + i] as f32 / 255.0,
+            })),
+            false => Rgba([0.0; 4]),
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -183,32 +254,26 @@ impl MyNode for PalletGenNode {
             (self.sampling_factor.clone() * 1000000.0) as i32,
         ));
 
-        if !self.map.contains_key(&new_hash) {
-            let mut image_data = Vec::new();
-            // should probably do something about that .cloned
-            if let Ok(mut pallet) = Okolors::try_from(
-                data.iter()
-                    .cloned()
-                    .map(|x| Srgb::new(x[0], x[1], x[2]).into())
-                    .collect::<Vec<Srgb<u8>>>()
-                    .as_slice(),
-            ) {
-                // pallet
-                let p = pallet
-                    .lightness_weight(self.lightness_weight)
-                    .parallel(true)
-                    .palette_size(self.pallet_size)
-                    .sampling_factor(self.sampling_factor)
-                    .sort_by_frequency(true)
-                    .srgb8_palette();
-
-                for i in p {
-                    image_data.extend([i.red, i.green, i.blue]);
-                }
+        // only one job is run at a time, if the input changes while it is running the
+        // newest input is picked up the next time the node runs
+        if !self.map.contains_key(&new_hash) && self.job.is_none() {
+            let lightness_weight = self.lightness_weight;
+            let pallet_size = self.pallet_size;
+            let sampling_factor = self.sampling_factor;
+            self.job = Some(CpuJob::spawn(new_hash, move || {
+                pallet(data, lightness_weight, pallet_size, sampling_factor)
+            }));
+        }
 
-                self.map.insert(new_hash, image_data);
+        match poll_job(&mut self.job, storage.block_on_jobs) {
+            Some(Ok((hash, Some(image_data)))) => {
+                self.map.insert(hash, image_data);
             }
+            Some(Ok((_, None))) => return Err(anyhow!("failed to generate pallet")),
+            Some(Err(e)) => return Err(e),
+            None => {}
         }
+
         if (self.map.get(&new_hash)).unwrap_or(&vec![]).len() == self.pallet_size as usize * 3 {
             if self.frozen_pallet.is_some() {
                 self.frozen_pallet = self.map.get(&new_hash).cloned();
@@ -229,6 +294,10 @@ impl MyNode for PalletGenNode {
         return Ok(());
     }
 
+    fn computing(&self) -> bool {
+        self.job.is_some()
+    }
+
     fn description(&mut self, ui: &imgui::Ui) {
         ui.text_wrapped("Create a mask based on the brightness value of a pixel. Returns true if the brightness is higher than low and lower than high. If low is higher than high they are swapped and they are inverted.")
     }
@@ -12,7 +12,35 @@ use crate::generic_node_info::GenericNodeInfo;
 use anyhow::anyhow;
 
 
-use crate::{node::*, nodes::*, storage::Storage};
+use crate::{
+    cpu::{draw, CpuStorage},
+    node::*,
+    nodes::*,
+    storage::Storage,
+};
+
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            uniform float r;
+            uniform float g;
+            uniform float b;
+            uniform float a;
+
+
+            void main() {
+            float r2 = round(texture(tex, v_tex_coords).r * 255.0 * r * r)/(255.0*r*r);
+            float g2 = round(texture(tex, v_tex_coords).g * 255.0 * g * g)/(255.0*g*g);
+            float b2 = round(texture(tex, v_tex_coords).b * 255.0 * b * b)/(255.0*b*b);
+            float a2 = round(texture(tex, v_tex_coords).a * 255.0 * a * a)/(255.0*a*a);
+            color = vec4(r2,g2,b2,1.0);
+            }
+            "#;
 
 #[derive(Savefile)]
 pub struct RestrictPalletNode {
@@ -73,6 +101,10 @@ impl MyNode for RestrictPalletNode {
     fn type_(&self) -> NodeType {
         NodeType::RestrictPalletRGBA
     }
+
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
@@ -123,6 +155,17 @@ impl MyNode for RestrictPalletNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let texture = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let steps = [self.red, self.green, self.blue].map(|a| 255.0 * a * a);
+        let output = draw(texture.dimensions(), &[texture], |_, c| {
+            let [r, g, b] = [0, 1, 2].map(|i| (c[0][i] * steps[i]).round() / steps[i]);
+            [r, g, b, 1.0]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -136,28 +179,7 @@ impl MyNode for RestrictPalletNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            uniform float r;
-            uniform float g;
-            uniform float b;
-            uniform float a;
-
-
-            void main() {
-            float r2 = round(texture(tex, v_tex_coords).r * 255.0 * r * r)/(255.0*r*r);
-            float g2 = round(texture(tex, v_tex_coords).g * 255.0 * g * g)/(255.0*g*g);
-            float b2 = round(texture(tex, v_tex_coords).b * 255.0 * b * b)/(255.0*b*b);
-            float a2 = round(texture(tex, v_tex_coords).a * 255.0 * a * a)/(255.0*a*a);
-            color = vec4(r2,g2,b2,1.0);
-            }
-            "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -4,7 +4,8 @@ use std::{
     path::PathBuf,
 };
 
-use glium::{uniform, DrawParameters, Surface};
+use glium::{pixel_buffer::PixelBuffer, uniform, DrawParameters, Surface};
+use image::Rgba;
 use imgui_glium_renderer::Renderer;
 use node_enum::*;
 use savefile::{save_file, SavefileError};
@@ -12,7 +13,30 @@ use crate::generic_node_info::GenericNodeInfo;
 use anyhow::anyhow;
 
 
-use crate::{node::*, nodes::*, storage::Storage};
+use crate::{
+    cpu::{mipmaps, sample_lod, CpuStorage, CpuTexture},
+    node::*,
+    nodes::*,
+    project::calculate_hash,
+    storage::Storage,
+    worker_pool::{poll_job, request_run, CpuJob},
+};
+
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+
+
+
+            void main() {
+            color = texture(tex, v_tex_coords);
+            }
+            "#;
 
 #[derive(Savefile)]
 pub struct HistogramNode {
@@ -20,7 +44,22 @@ pub struct HistogramNode {
     y: f32,
     id: String,
     sample_size: i32,
-    histogram: [[u32;3];256]
+    histogram: [[u32;3];256],
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    job: Option<CpuJob<[[u32; 3]; 256]>>,
+    /// the hash of the samples that `histogram` was made from
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    histogram_hash: Option<u64>,
+    /// the samples from the last run, they are copied in the background and read on the next run so the ui doesn't wait for the gpu
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    readback: Option<PixelBuffer<(u8, u8, u8, u8)>>,
+    /// the hash of the last samples that were read back
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    samples_hash: Option<u64>,
 }
 
 
@@ -38,8 +77,39 @@ impl HistogramNode {
             id: random_id(),
             histogram: [[0;3];256],
             sample_size: 3000,
+            job: None,
+            histogram_hash: None,
+            readback: None,
+            samples_hash: None,
+        }
+    }
+
+    /// how many pixels of each value there are in each channel, from the last run
+    pub fn histogram(&self) -> &[[u32; 3]; 256] {
+        return &self.histogram;
+    }
+
+    /// the size the input is shrunk to before it is counted, so about `sample_size` pixels are counted
+    fn samples_size(&self, texture_size: (u32, u32)) -> (u32, u32) {
+        let aspect = texture_size.0 as f32 / texture_size.1 as f32;
+        return (
+            texture_size.0.min(((self.sample_size as f32).sqrt() * aspect).max(1.0) as u32),
+            texture_size.1.min(((self.sample_size as f32).sqrt() / aspect).max(1.0) as u32),
+        );
+    }
+}
+
+/// counts the values of the red, green and blue channels of the pixels that aren't transparent
+fn count(samples: impl Iterator<Item = [u8; 4]>) -> [[u32; 3]; 256] {
+    let mut histogram = [[0; 3]; 256];
+    for rgba in samples {
+        if rgba[3] != 0 {
+            histogram[rgba[0] as usize][0] += 1;
+            histogram[rgba[1] as usize][1] += 1;
+            histogram[rgba[2] as usize][2] += 1;
         }
     }
+    return histogram;
 }
 
 impl MyNode for HistogramNode {
@@ -75,6 +145,10 @@ impl MyNode for HistogramNode {
     fn type_(&self) -> NodeType {
         NodeType::Histogram
     }
+
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
@@ -137,6 +211,10 @@ impl MyNode for HistogramNode {
         ui.text("show a color histogram");
     }
 
+    fn computing(&self) -> bool {
+        self.job.is_some()
+    }
+
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
         return save_file(
             path.join(self.name()).join(self.id() + ".bin"),
@@ -158,6 +236,32 @@ impl MyNode for HistogramNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let texture = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let samples_size = self.samples_size(texture.dimensions());
+        // the gpu shrinks the input using its mipmaps
+        let lod = (texture.width() as f32 / samples_size.0 as f32)
+            .max(texture.height() as f32 / samples_size.1 as f32)
+            .log2();
+        let levels = mipmaps(texture);
+        let samples = CpuTexture::from_fn(samples_size.0, samples_size.1, |x, y| {
+            let uv = [
+                (x as f32 + 0.5) / samples_size.0 as f32,
+                (y as f32 + 0.5) / samples_size.1 as f32,
+            ];
+            Rgba(sample_lod(&levels, uv, lod))
+        });
+        let output_id = self.id.clone() + "downsized";
+        storage.set_texture(output_id.clone(), samples);
+        let samples = storage.get_texture(&output_id).unwrap();
+        self.histogram = count(
+            samples
+                .pixels()
+                .map(|a| a.0.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)),
+        );
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -171,21 +275,7 @@ impl MyNode for HistogramNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-
-
-
-            void main() {
-            color = texture(tex, v_tex_coords);
-            }
-            "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -197,12 +287,8 @@ impl MyNode for HistogramNode {
             .ok_or(anyhow!("failed to compile shader"))?;
         
 
-        let aspect = texture_size.0 as f32 / texture_size.1 as f32;
-
-        storage.create_and_set_texture(
-            texture_size.0.min(((self.sample_size as f32).sqrt() * aspect).max(1.0) as u32), 
-            texture_size.1.min(((self.sample_size as f32).sqrt() / aspect).max(1.0) as u32), 
-            output_id.clone());
+        let samples_size = self.samples_size(texture_size);
+        storage.create_and_set_texture(samples_size.0, samples_size.1, output_id.clone());
 
         let texture: &glium::Texture2d = match storage.get_texture(get_output) {
             Some(a) => a,
@@ -226,19 +312,40 @@ let shader = storage
                     ..Default::default()
                 },
             )?;
-            self.histogram = [[0;3];256];
-            for rgba in texture2
-            .read_to_pixel_buffer()
-            .map()
-            .iter() {
-                if rgba.3 != 0 {
-                    self.histogram[rgba.0 as usize][0] += 1;
-                    self.histogram[rgba.1 as usize][1] += 1;
-                    self.histogram[rgba.2 as usize][2] += 1;
-                }
+        // the copy that was started on the last run has had a frame to finish, so reading it doesn't stall the ui
+        let samples = if storage.block_on_jobs {
+            self.readback = None;
+            texture2.read_to_pixel_buffer().read().ok()
+        } else {
+            let previous = self.readback.replace(texture2.read_to_pixel_buffer());
+            if previous.is_none() {
+                request_run();
             }
+            previous.and_then(|a| a.read().ok())
+        };
+        if let Some(samples) = samples {
+            let hash = calculate_hash(&samples);
+            // the input is changing, so run again to read the copy that was just started
+            if !storage.block_on_jobs && self.samples_hash != Some(hash) {
+                request_run();
+            }
+            self.samples_hash = Some(hash);
+            if self.histogram_hash != Some(hash) && self.job.is_none() {
+                self.job = Some(CpuJob::spawn(hash, move || {
+                    count(samples.iter().map(|a| [a.0, a.1, a.2, a.3]))
+                }));
+            }
+        }
+
+        match poll_job(&mut self.job, storage.block_on_jobs) {
+            Some(Ok((hash, histogram))) => {
+                self.histogram = histogram;
+                self.histogram_hash = Some(hash);
+            }
+            Some(Err(e)) => return Err(e),
+            None => {}
+        }
 
-            
 
         return Ok(());
     }
@@ -8,12 +8,15 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, hsv2rgb, sample, CpuStorage, CpuTexture},
     node::{random_id, MyNode},
     storage::Storage,
 };
 
 use super::node_enum::NodeType;
 
+const FRAGMENT_SHADER: &str = include_str!("movement.glsl");
+
 #[derive(Savefile)]
 pub struct MotionNode {
     x: f32,
@@ -22,6 +25,10 @@ pub struct MotionNode {
     #[savefile_ignore]
     #[savefile_introspect_ignore]
     last_frame: Option<Texture2d>,
+    /// `last_frame` for `run_cpu`
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    cpu_last_frame: Option<CpuTexture>,
 }
 
 impl Default for MotionNode {
@@ -31,6 +38,7 @@ impl Default for MotionNode {
             y: 0.0,
             id: random_id(),
             last_frame: None,
+            cpu_last_frame: None,
         }
     }
 }
@@ -68,6 +76,10 @@ impl MyNode for MotionNode {
         NodeType::Motion
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -91,6 +103,54 @@ impl MyNode for MotionNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let texture = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        // the last frame is kept at 8 bits
+        let mut frame = texture.clone();
+        for channel in frame.iter_mut() {
+            *channel = (channel.clamp(0.0, 1.0) * 255.0).round() / 255.0;
+        }
+        let last_frame = match self.cpu_last_frame.take() {
+            Some(a) if a.dimensions() == texture.dimensions() => a,
+            _ => frame.clone(),
+        };
+
+        let resolution = [texture.width() as f32, texture.height() as f32];
+        let range = 4.0_f32;
+        let length = |a: &[f32]| a.iter().map(|a| a * a).sum::<f32>().sqrt();
+        let output = draw(texture.dimensions(), &[&last_frame], |uv, c| {
+            let last_color = c[0];
+            let mut best = [0.0, 0.0];
+            let mut score = 0.0;
+            for x in -4..=4 {
+                for y in -4..=4 {
+                    let offset = [x as f32, y as f32];
+                    if length(&offset) <= range {
+                        let next_color = sample(texture, [0, 1].map(|a| uv[a] + offset[a] / resolution[a]));
+                        let difference = [0, 1, 2, 3].map(|i| last_color[i] - next_color[i]);
+                        let s = (1.0 - length(&difference)) / 1.732 - length(&offset) / length(&[range, range]);
+                        if s > score {
+                            score = s;
+                            best = offset;
+                        }
+                    }
+                }
+            }
+            if best[0] < 0.0 {
+                score = 0.0;
+            }
+            let [r, g, b] = hsv2rgb([
+                (best[0].atan2(best[1]) + 3.14159) / (3.14159 * 2.0),
+                score,
+                length(&best) / length(&[range, range]),
+            ]);
+            [r, g, b, last_color[3]]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        self.cpu_last_frame = Some(frame);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -104,7 +164,7 @@ impl MyNode for MotionNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = include_str!("movement.glsl");
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -157,7 +217,7 @@ impl MyNode for MotionNode {
             let uniforms = uniform! {
                 tex: texture,
                 last_tex: last_frame,
-
+                u_resolution: [texture.width() as f32, texture.height() as f32],
             };
             let texture2 = storage.get_texture(&output_id).unwrap();
             texture2
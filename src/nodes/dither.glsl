@@ -1,5 +1,4 @@
-
-
+#version 140
 
 in vec2 v_tex_coords;
 out vec4 color;
@@ -160,6 +159,7 @@ float dither2x2(vec2 position,float brightness){
 uniform sampler2D tex;
 void main(){
     vec4 c =texture(tex,v_tex_coords);
+    color = vec4(0.0);
 
     float brightness = rgb2hsv(vec3(c.r,c.g,c.b)).b;
 
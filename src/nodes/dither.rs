@@ -2,6 +2,7 @@ use std::{any::Any, collections::HashMap, path::PathBuf};
 
 
 use glium::{
+    pixel_buffer::PixelBuffer,
     texture::{RawImage2d},
     uniform, DrawParameters, Rect, Surface,
 };
@@ -12,14 +13,19 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, glsl_mod, rgb2hsv, CpuStorage, CpuTexture},
     node::{random_id, MyNode},
+    project::calculate_hash,
     storage::Storage,
     widgets::link_widget,
+    worker_pool::{poll_job, request_run, CpuJob},
 };
 
 use super::node_enum::NodeType;
 
-#[derive(Savefile, Debug)]
+const FRAGMENT_SHADER: &str = include_str!("dither.glsl");
+
+#[derive(Savefile, Debug, Clone)]
 
 struct DitherPatternPos {
     x: i8,
@@ -99,6 +105,21 @@ pub struct LinearErrorDitherNode {
     pattern: Vec<DitherPatternPos>,
     devisor: i32,
     // image_vec:
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    job: Option<CpuJob<((u32, u32), Vec<f32>)>>,
+    /// the hash of the input the result was made from, the size of the result, and the dithered pixels
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    result: Option<(u64, (u32, u32), Vec<f32>)>,
+    /// the input from the last run and its size, it is copied in the background and read on the next run so the ui doesn't wait for the gpu
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    readback: Option<(PixelBuffer<(u8, u8, u8, u8)>, (u32, u32))>,
+    /// the hash of the last input that was read back
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    input_hash: Option<u64>,
 }
 
 impl Default for LinearErrorDitherNode {
@@ -110,12 +131,56 @@ impl Default for LinearErrorDitherNode {
             notation: PATTERN[7].1.to_owned(),
             pattern: vec![],
             devisor: PATTERN[7].2 as i32,
+            job: None,
+            result: None,
+            readback: None,
+            input_hash: None,
         };
         new.load_notation();
         return new;
     }
 }
 
+/// takes the brightness and alpha of each pixel and returns rgba pixels, this runs on the worker pool
+fn error_diffusion(
+    mut data: Vec<(i16, u8)>,
+    width: u32,
+    pattern: &[DitherPatternPos],
+    devisor: i32,
+) -> Vec<f32> {
+    let mut error;
+
+    for i in 0..data.len() {
+        let x = i as i32 % width as i32;
+        let black = data[i].0 < 127;
+
+        error = data[i].0 as i32 - if black { 0 } else { 255 };
+        for p in pattern {
+            if x >= p.x as i32 && x + (p.x as i32) < width as i32 {
+                let index = i as i32 + p.x as i32 + p.y as i32 * width as i32;
+                if index >= 0 && index < data.len() as i32 {
+                    data[index as usize].0 = (data[index as usize].0 as i32
+                        + (((error) * p.multiplier as i32) / devisor))
+                        .clamp(i16::MIN as i32, i16::MAX as i32)
+                        as i16;
+                }
+            }
+        }
+        data[i].0 = if black { 0 } else { 255 };
+    }
+
+    data.iter()
+        .flat_map(|(b, a)| {
+            [
+                *b as f32 / 255.0,
+                *b as f32 / 255.0,
+                *b as f32 / 255.0,
+                *a as f32 / 255.0,
+            ]
+        })
+        .collect()
+}
+
 impl LinearErrorDitherNode {
     fn load_notation(&mut self) {
         let mut offset: i8 = 0;
@@ -249,6 +314,24 @@ impl MyNode for LinearErrorDitherNode {
         });
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        // the same as reading the input back from opengl
+        let to_u8 = |a: f32| (a.clamp(0.0, 1.0) * 255.0).round() as u16;
+        let data = input
+            .pixels()
+            .map(|p| {
+                let [r, g, b, a] = p.0.map(to_u8);
+                (((r + g + b) / 3) as i16, a as u8)
+            })
+            .collect::<Vec<(i16, u8)>>();
+        let pixels = error_diffusion(data, input.width(), &self.pattern, self.devisor);
+        let output = CpuTexture::from_raw(input.width(), input.height(), pixels)
+            .ok_or(anyhow!("the dithered image is the wrong size"))?;
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -274,81 +357,78 @@ impl MyNode for LinearErrorDitherNode {
             None => return Err(anyhow!("failed to get input texture from storage")),
         };
 
-        let mut data = texture
-            .read_to_pixel_buffer()
-            .as_slice()
-            .read()
-            .unwrap_or_default()
-            .iter()
-            .map(|(r, g, b, a)| {
-                // (((*r.max(g).max(b) as u16 + *r.min(g).min(b) as u16)/2) as i16,*a)
-                (((*r as u16 + *g as u16 + *b as u16) / 3) as i16, *a)
-            })
-            .collect::<Vec<(i16, u8)>>();
+        // the copy that was started on the last run has had a frame to finish, so reading it doesn't stall the ui
+        let samples = if storage.block_on_jobs {
+            self.readback = None;
+            texture.read_to_pixel_buffer().read().ok().map(|a| (a, texture_size))
+        } else {
+            let previous = self
+                .readback
+                .replace((texture.read_to_pixel_buffer(), texture_size));
+            if previous.is_none() {
+                request_run();
+            }
+            previous.and_then(|(a, size)| a.read().ok().map(|a| (a, size)))
+        };
 
-        // let can_bit_shift = (self.devisor as u32).is_power_of_two();
-
-        // let bit_shifts = (self.devisor as f32).powf(0.5) as i32;
-
-        let mut error;
-
-        let width = texture_size.0;
-        for i in 0..data.len() {
-            let x = i as i32 % width as i32;
-            let black = data[i].0 < 127;
-
-            error = data[i].0 as i32 - if black { 0 } else { 255 };
-            // log::info!("{error}");
-            // if error == 0 {
-            //     data[i].0 = if black {0} else {255};
-            //     continue;
-            // }
-            for p in &self.pattern {
-                // i16::MAX
-                if x >= p.x as i32 && x + (p.x as i32) < width as i32 {
-                    let index = i as i32 + p.x as i32 + p.y as i32 * width as i32;
-                    if index >= 0 && index < data.len() as i32 {
-                        data[index as usize].0 = (data[index as usize].0 as i32
-                            + (((error) * p.multiplier as i32) / self.devisor))
-                            .clamp(i16::MIN as i32, i16::MAX as i32)
-                            as i16;
-                    }
-                }
+        if let Some((samples, size)) = samples {
+            let hash = calculate_hash(&(&samples, size, &self.notation, self.devisor));
+            // the input is changing, so run again to read the copy that was just started
+            if !storage.block_on_jobs && self.input_hash != Some(hash) {
+                request_run();
+            }
+            self.input_hash = Some(hash);
+
+            // only one job is run at a time, if the input changes while it is running the
+            // newest input is picked up the next time the node runs
+            let up_to_date = self.result.as_ref().map(|r| r.0) == Some(hash);
+            if !up_to_date && self.job.is_none() {
+                let pattern = self.pattern.clone();
+                let devisor = self.devisor;
+                self.job = Some(CpuJob::spawn(hash, move || {
+                    let data = samples
+                        .iter()
+                        .map(|(r, g, b, a)| {
+                            // (((*r.max(g).max(b) as u16 + *r.min(g).min(b) as u16)/2) as i16,*a)
+                            (((*r as u16 + *g as u16 + *b as u16) / 3) as i16, *a)
+                        })
+                        .collect::<Vec<(i16, u8)>>();
+                    (size, error_diffusion(data, size.0, &pattern, devisor))
+                }));
             }
-            data[i].0 = if black { 0 } else { 255 };
-            // error = 0;
         }
 
-        let texture2 = storage.get_texture(&output_id).unwrap();
-        let image2d = RawImage2d::from_raw_rgba(
-            data.iter()
-                .flat_map(|(b, a)| {
-                    [
-                        *b as f32 / 255.0,
-                        *b as f32 / 255.0,
-                        *b as f32 / 255.0,
-                        *a as f32 / 255.0,
-                    ]
-                })
-                .collect(),
-            texture.dimensions(),
-        );
-
-        // log::info!("{:?} {}", texture2.dimensions(), texture2.width());
-        texture2.write(
-            Rect {
-                left: 0,
-                bottom: 0,
-                width: texture2.width(),
-                height: texture2.height(),
+        match poll_job(&mut self.job, storage.block_on_jobs) {
+            Some(Ok((hash, (size, pixels)))) => self.result = Some((hash, size, pixels)),
+            Some(Err(e)) => return Err(e),
+            None => {}
+        }
 
-            },
-            image2d,
-        );
+        let texture2 = storage.get_texture(&output_id).unwrap();
+        match &self.result {
+            Some((_, size, pixels)) if *size == texture_size => {
+                let image2d = RawImage2d::from_raw_rgba(pixels.clone(), texture_size);
+                texture2.write(
+                    Rect {
+                        left: 0,
+                        bottom: 0,
+                        width: texture2.width(),
+                        height: texture2.height(),
+                    },
+                    image2d,
+                );
+            }
+            // still computing the first result for this size
+            _ => texture2.as_surface().clear_color(0.0, 0.0, 0.0, 0.0),
+        }
 
         return Ok(());
     }
 
+    fn computing(&self) -> bool {
+        self.job.is_some()
+    }
+
     fn description(&mut self, ui: &imgui::Ui) {
         ui.text_wrapped(
             "Applies dither effect based on implementation in this post by tanner helland:",
@@ -375,6 +455,15 @@ x                      .o..P'
 x                      `Y8P'
 */
 
+/// the limits dither.glsl uses for each size, out of `size * size`, by `x + y * size`
+const BAYER_2X2: [u8; 4] = [1, 3, 4, 2];
+const BAYER_4X4: [u8; 16] = [1, 9, 3, 11, 13, 5, 15, 7, 4, 12, 2, 10, 16, 8, 14, 6];
+const BAYER_8X8: [u8; 64] = [
+    1, 33, 9, 41, 3, 35, 11, 43, 49, 17, 57, 25, 51, 19, 59, 27, 13, 45, 5, 37, 15, 47, 7, 39, 61, 29, 53, 21, 63, 31,
+    55, 23, 4, 36, 12, 44, 2, 34, 10, 42, 52, 20, 60, 28, 50, 18, 58, 26, 16, 48, 8, 40, 14, 46, 6, 38, 64, 32, 56,
+    24, 62, 30, 54, 22,
+];
+
 #[derive(Savefile)]
 pub struct BayerDitherNode {
     x: f32,
@@ -427,6 +516,10 @@ impl MyNode for BayerDitherNode {
         NodeType::BayerDither
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
     fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
         ui.text(format!("size: {}x{}", self.size, self.size));
         ui.separator();
@@ -464,6 +557,32 @@ impl MyNode for BayerDitherNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let size = input.dimensions();
+        let n = self.size;
+        let limits: &[u8] = match n {
+            2 => &BAYER_2X2,
+            4 => &BAYER_4X4,
+            8 => &BAYER_8X8,
+            _ => &[],
+        };
+        let output = draw(size, &[input], |uv, c| {
+            if limits.is_empty() {
+                return [0.0; 4];
+            }
+            let c = c[0];
+            let brightness = rgb2hsv([c[0], c[1], c[2]])[2];
+            let x = glsl_mod(uv[0] * size.0 as f32, n as f32) as i32;
+            let y = glsl_mod(uv[1] * size.1 as f32, n as f32) as i32;
+            let limit = limits[(x + y * n) as usize] as f32 / (n * n) as f32;
+            let b = if brightness < limit { 0.0 } else { 1.0 };
+            [b, b, b, c[3]]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -477,7 +596,7 @@ impl MyNode for BayerDitherNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = include_str!("dither.glsl");
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
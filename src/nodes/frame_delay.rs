@@ -1,6 +1,7 @@
 use std::{any::Any, collections::HashMap, path::PathBuf};
 
 use glium::{texture::RawImage2d, BlitTarget, Rect, Surface, Texture2d};
+use image::imageops::{self, FilterType};
 use imgui_glium_renderer::Renderer;
 use savefile::{save_file, SavefileError};
 use crate::generic_node_info::GenericNodeInfo;
@@ -8,6 +9,7 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{CpuStorage, CpuTexture},
     node::{random_id, MyNode},
     storage::Storage,
 };
@@ -23,6 +25,10 @@ pub struct DelayNode {
     #[savefile_introspect_ignore]
     frames: Vec<Texture2d>,
     frame_delay_count: i32,
+    /// `frames` for `run_cpu`
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    cpu_frames: Vec<CpuTexture>,
 }
 
 impl Default for DelayNode {
@@ -33,6 +39,7 @@ impl Default for DelayNode {
             id: random_id(),
             frames: vec![],
             frame_delay_count: 1,
+            cpu_frames: vec![],
         }
     }
 }
@@ -91,6 +98,29 @@ impl MyNode for DelayNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        // frames are kept at 8 bits
+        let mut frame = input.clone();
+        for channel in frame.iter_mut() {
+            *channel = (channel.clamp(0.0, 1.0) * 255.0).round() / 255.0;
+        }
+
+        if self.frame_delay_count != self.cpu_frames.len() as i32 {
+            self.cpu_frames = vec![frame.clone(); self.frame_delay_count.max(0) as usize];
+        }
+
+        if let Some(delayed_frame) = self.cpu_frames.pop() {
+            let output = match delayed_frame.dimensions() == input.dimensions() {
+                true => delayed_frame,
+                false => imageops::resize(&delayed_frame, input.width(), input.height(), FilterType::Triangle),
+            };
+            storage.set_texture(self.output_id(&self.outputs()[0]), output);
+            self.cpu_frames.insert(0, frame);
+        }
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
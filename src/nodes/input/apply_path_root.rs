@@ -1,4 +1,7 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use crate::storage::Storage;
 
@@ -13,11 +16,16 @@ pub fn set_root(path: &mut PathBuf, storage: &Storage) {
 }
 
 pub fn get_with_root(path: &PathBuf, storage: &Storage) -> PathBuf {
+    return replace_root(path, &storage.project_root);
+}
+
+/// the same as `get_with_root`, for when there is no `Storage`
+pub fn replace_root(path: &PathBuf, project_root: &Path) -> PathBuf {
     if path.starts_with(PathBuf::from_str("PROJECT_ROOT").unwrap_or_default()) {
         let s = path
             .display()
             .to_string()
-            .replace("PROJECT_ROOT", &storage.project_root.display().to_string());
+            .replace("PROJECT_ROOT", &project_root.display().to_string());
         return PathBuf::from_str(&s).unwrap_or_else(|_| path.clone());
     } else {
         return path.clone();
@@ -1,11 +1,15 @@
 use crate::{
+    cpu::{CpuStorage, CpuTexture},
     node::{random_id, MyNode},
-    storage::Storage,
+    storage::{param_cache_key, Storage},
     widgets::link_widget,
 };
 use glium::{texture::RawImage2d, Texture2d};
 use image;
-use image::EncodableLayout;
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, EncodableLayout,
+};
 
 use imgui_glium_renderer::Renderer;
 use savefile::{save_file, SavefileError};
@@ -16,14 +20,39 @@ use std::{any::Any, collections::HashMap, path::PathBuf};
 
 use crate::nodes::node_enum::NodeType;
 
+const IMAGES: [&[u8]; 9] = [
+    include_bytes!("img/image-1.jpg"),
+    include_bytes!("img/image-2.jpg"),
+    include_bytes!("img/image-3.jpg"),
+    include_bytes!("img/image-4.jpg"),
+    include_bytes!("img/image-5.jpg"),
+    include_bytes!("img/image-6.jpg"),
+    include_bytes!("img/image-7.jpg"),
+    include_bytes!("img/image-8.jpg"),
+    include_bytes!("img/image-9.jpg"),
+];
+
+/// decodes one of `IMAGES`, flipped so the rows go from the bottom up
+fn load(index: usize) -> image::RgbaImage {
+    return image::load_from_memory_with_format(IMAGES[index], image::ImageFormat::Jpeg)
+        .unwrap()
+        .flipv()
+        .into_rgba8();
+}
+
 #[derive(Savefile)]
 pub struct DefaultImage {
     x: f32,
     y: f32,
     id: String,
+    /// the image that was picked, it is loaded again when the preview scale changes
     #[savefile_ignore]
     #[savefile_introspect_ignore]
-    texture_cache: Option<u64>,
+    index: Option<usize>,
+    /// the image `run_cpu` picked
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    cpu_texture: Option<CpuTexture>,
 }
 
 impl Default for DefaultImage {
@@ -32,7 +61,8 @@ impl Default for DefaultImage {
             x: 0.0,
             y: 0.0,
             id: random_id(),
-            texture_cache: None,
+            index: None,
+            cpu_texture: None,
         }
     }
 }
@@ -90,6 +120,15 @@ impl MyNode for DefaultImage {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, _map: HashMap<String, String>) -> anyhow::Result<()> {
+        if self.cpu_texture.is_none() {
+            let index = fastrand::usize(0..IMAGES.len());
+            self.cpu_texture = Some(DynamicImage::ImageRgba8(load(index)).into_rgba32f());
+        }
+        storage.set_texture(self.output_id(&self.outputs()[0]), self.cpu_texture.clone().unwrap());
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -98,28 +137,17 @@ impl MyNode for DefaultImage {
     ) -> anyhow::Result<()> {
         let output_id =self.output_id(&self.outputs()[0]);;
 
-        if self.texture_cache.is_none()
-            || !storage.cached_texture_exists(self.texture_cache.unwrap())
-        {
-            let image = image::load_from_memory_with_format(
-                &fastrand::choice([
-                    include_bytes!("img/image-1.jpg").to_vec(),
-                    include_bytes!("img/image-2.jpg").to_vec(),
-                    include_bytes!("img/image-3.jpg").to_vec(),
-                    include_bytes!("img/image-4.jpg").to_vec(),
-                    include_bytes!("img/image-5.jpg").to_vec(),
-                    include_bytes!("img/image-6.jpg").to_vec(),
-                    include_bytes!("img/image-7.jpg").to_vec(),
-                    include_bytes!("img/image-8.jpg").to_vec(),
-                    include_bytes!("img/image-9.jpg").to_vec(),
-
-                ])
-                .unwrap(),
-                image::ImageFormat::Jpeg,
-            )
-            .unwrap()
-            .flipv()
-            .into_rgba8();
+        let index = *self
+            .index
+            .get_or_insert_with(|| fastrand::usize(0..IMAGES.len()));
+        let key = param_cache_key(&("default image", index, storage.preview_scale.to_bits()));
+
+        if !storage.cached_texture_exists(key) {
+            let mut image = load(index);
+            if storage.preview_scale < 1.0 {
+                let (width, height) = storage.preview_size(image.dimensions());
+                image = imageops::resize(&image, width, height, FilterType::Triangle);
+            }
 
             let not_texture = RawImage2d::from_raw_rgba(
                 image.as_bytes().to_vec(),
@@ -127,18 +155,23 @@ impl MyNode for DefaultImage {
             );
             // let a: HashMap<Texture2d, String> = HashMap::new();
             let texture: Texture2d = Texture2d::new(&storage.display, not_texture).unwrap();
-            self.texture_cache = Some(storage.cache_texture(texture));
+            storage.cache_texture(key, texture);
         }
 
-        storage.set_id_of_cached_texture(self.texture_cache.unwrap(), output_id);
+        storage.set_id_of_cached_texture(key, output_id);
         // storage.set_texture(output_id,  texture);
 
         return Ok(());
     }
 
+    fn scales_preview(&self) -> bool {
+        true
+    }
+
     fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
         if ui.button("new image") {
-            self.texture_cache = None;
+            self.index = None;
+            self.cpu_texture = None;
         }
     }
 
@@ -8,13 +8,11 @@
 // };
 
 
-use regex::Regex;
-
 use std::{any::Any, collections::HashMap, path::PathBuf};
-use win_screenshot::prelude::*;
 
 use glium::texture::RawImage2d;
 use glium::{uniform, DrawParameters, Rect, Surface};
+use image::{DynamicImage, RgbaImage};
 use imgui_glium_renderer::Renderer;
 use savefile::{save_file, SavefileError};
 use crate::generic_node_info::GenericNodeInfo;
@@ -22,12 +20,16 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{CpuStorage, CpuTexture},
     node::{random_id, MyNode},
+    platform::{capture_display, capture_window, find_window, Screenshot},
     storage::Storage,
 };
 
 use crate::nodes::node_enum::NodeType;
 
+const FRAGMENT_SHADER: &str = include_str!("fix_desktop_capture.glsl");
+
 #[derive(Savefile)]
 pub struct CaptureWindowNode {
     x: f32,
@@ -39,6 +41,10 @@ pub struct CaptureWindowNode {
     #[savefile_ignore]
     #[savefile_introspect_ignore]
     data: Vec<u8>,
+    /// used instead of capturing when it is set
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    frame: Option<Screenshot>,
 }
 
 impl Default for CaptureWindowNode {
@@ -51,7 +57,37 @@ impl Default for CaptureWindowNode {
             app_name: "".to_owned(),
             entire_screen: false,
             hwnd: 0,
+            frame: None,
+        }
+    }
+}
+
+impl CaptureWindowNode {
+    /// uses `pixels` instead of capturing the screen, it is `width * height` rgba pixels with the rows going from the top down
+    pub fn set_frame(&mut self, width: u32, height: u32, pixels: Vec<u8>) {
+        self.frame = Some(Screenshot {
+            width,
+            height,
+            pixels,
+        });
+    }
+
+    fn capture(&self) -> anyhow::Result<Screenshot> {
+        if let Some(frame) = &self.frame {
+            return Ok(Screenshot {
+                width: frame.width,
+                height: frame.height,
+                pixels: frame.pixels.clone(),
+            });
         }
+        if self.entire_screen {
+            return capture_display();
+        }
+        // match capture_window_ex(self.hwnd, Using::BitBlt, Area::ClientOnly, None, None) {
+        //     Ok(a) => a,
+        //     Err(_) => {return false;},
+        // }
+        return capture_window(self.hwnd);
     }
 }
 impl MyNode for CaptureWindowNode {
@@ -76,6 +112,10 @@ impl MyNode for CaptureWindowNode {
         0
     }
 
+    fn scales_preview(&self) -> bool {
+        true
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -88,6 +128,10 @@ impl MyNode for CaptureWindowNode {
         NodeType::CaptureDesktop
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -118,17 +162,7 @@ impl MyNode for CaptureWindowNode {
             ui.input_text("program name", &mut self.app_name).build();
 
             if ui.is_item_edited() {
-                let re = Regex::new(&self.app_name).unwrap_or(Regex::new(r"~~~~error~~~").unwrap());
-                let hwnd = window_list()
-                    .unwrap()
-                    .iter()
-                    .find(|i| re.is_match(&i.window_name))
-                    .unwrap_or(&HwndName {
-                        hwnd: 0,
-                        window_name: "error".to_owned(),
-                    })
-                    .hwnd;
-                self.hwnd = hwnd;
+                self.hwnd = find_window(&self.app_name, false);
             }
 
             let mut hwnd = self.hwnd as i32;
@@ -142,6 +176,23 @@ impl MyNode for CaptureWindowNode {
         });
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, _map: HashMap<String, String>) -> anyhow::Result<()> {
+        let buf = self.capture()?;
+        let size = (buf.width, buf.height);
+        self.data = buf.pixels;
+
+        // the shader flips the capture, the screen's rows go from the top down
+        let texture = match RgbaImage::from_raw(size.0, size.1, self.data.clone()) {
+            Some(a) => DynamicImage::ImageRgba8(a).flipv().into_rgba32f(),
+            None => {
+                log::info!("incorrect size {:?} {}", size, self.data.len());
+                CpuTexture::new(size.0, size.1)
+            }
+        };
+        storage.set_texture(self.output_id(&self.outputs()[0]), texture);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -150,36 +201,20 @@ impl MyNode for CaptureWindowNode {
     ) -> anyhow::Result<()> {
         let output_id =self.output_id(&self.outputs()[0]);;
 
-        let fragment_shader_src = include_str!("fix_desktop_capture.glsl");
+        let fragment_shader_src = FRAGMENT_SHADER;
         storage
             .gen_frag_shader(fragment_shader_src.to_string())
             .ok_or(anyhow!("failed to compile shader"))?;
 
-        let buf = if self.entire_screen {
-            match capture_display() {
-                Ok(a) => a,
-                Err(_) => {
-                    return Err(anyhow!("failed to capture display"));
-                }
-            }
-        } else {
-            // match capture_window_ex(self.hwnd, Using::BitBlt, Area::ClientOnly, None, None) {
-            //     Ok(a) => a,
-            //     Err(_) => {return false;},
-            // }
-            match capture_window(self.hwnd) {
-                Ok(a) => a,
-                Err(_) => {
-                    return Err(anyhow!("failed to capture window", ));
-                }
-            }
-        };
+        let buf = self.capture()?;
 
         let size = (buf.width, buf.height);
 
         self.data = buf.pixels;
 
-        storage.create_and_set_texture(size.0, size.1, output_id.clone());
+        // the capture is shrunk to the preview size when it is drawn to the output
+        let output_size = storage.preview_size(size);
+        storage.create_and_set_texture(output_size.0, output_size.1, output_id.clone());
         storage.create_and_set_texture(size.0, size.1, output_id.clone() + "-temp-texture");
 
         // let mut data: Vec<u8> = Vec::new();
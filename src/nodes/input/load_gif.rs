@@ -1,6 +1,7 @@
 use crate::{
+    cpu::{CpuStorage, CpuTexture},
     node::{random_id, MyNode},
-    storage::Storage,
+    storage::{file_cache_key, param_cache_key, Storage},
 };
 use glium::{texture::RawImage2d, Texture2d};
 use crate::generic_node_info::GenericNodeInfo;
@@ -8,7 +9,8 @@ use anyhow::anyhow;
 use image::{codecs::gif::GifDecoder, EncodableLayout};
 use image::{
     self,
-    AnimationDecoder, DynamicImage, GenericImageView, ImageDecoder,
+    imageops::{self, FilterType},
+    AnimationDecoder, DynamicImage, RgbaImage,
 };
 
 use std::io::{BufRead, BufReader};
@@ -16,14 +18,24 @@ use std::io::{BufRead, BufReader};
 use imgui_glium_renderer::Renderer;
 use rfd::FileDialog;
 use savefile::{save_file, SavefileError};
-use std::{any::Any, collections::HashMap, fs, path::PathBuf};
+use std::{
+    any::Any,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use crate::nodes::node_enum::NodeType;
 
-use super::apply_path_root;
+use super::apply_path_root::{self, replace_root};
 
 const VERSION: u32 = 0;
 
+/// the cache key of frame `index` of the gif with the file key `key`
+fn gif_frame_key(key: u64, index: usize) -> u64 {
+    return param_cache_key(&(key, index));
+}
+
 #[derive(Savefile)]
 pub struct LoadGifNode {
     x: f32,
@@ -34,6 +46,45 @@ pub struct LoadGifNode {
     #[savefile_ignore]
     #[savefile_introspect_ignore]
     texture_cache: Vec<u64>,
+    /// the cache key of the file the frames in `texture_cache` were loaded from
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    source_key: Option<u64>,
+    /// the frames `run_cpu` loaded, and the file they were loaded from
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    cpu_frames: Option<(PathBuf, Vec<CpuTexture>)>,
+}
+
+/// the frames of the gif at `path`, flipped so the rows go from the bottom up, and how long it is in seconds
+fn decode(path: &Path) -> anyhow::Result<(Vec<RgbaImage>, f32)> {
+    let file = match fs::File::open(path) {
+        Ok(a) => a,
+        Err(_e) => {
+            return Err(anyhow!("file not found"));
+        }
+    };
+    let gif: GifDecoder<BufReader<fs::File>> = match GifDecoder::new(BufReader::new(file)) {
+        Ok(a) => a,
+        Err(_e) => {
+            return Err(anyhow!("unable to decode file to gif"));
+        }
+    };
+    let mut length = 0.0;
+    let mut frames = vec![];
+    for frame in gif.into_frames().flatten() {
+        let (msu, msl) = frame.delay().numer_denom_ms();
+        length += (msu as f32 / msl as f32) / 1000.0;
+        frames.push(imageops::flip_vertical(frame.buffer()));
+    }
+    return Ok((frames, length));
+}
+
+impl LoadGifNode {
+    /// the frame shown at `time`, the gif loops
+    fn frame_index(&self, frame_count: usize, time: f64) -> usize {
+        return (frame_count as f64 * (time % self.length as f64) / (self.length as f64)).floor() as usize;
+    }
 }
 
 impl Default for LoadGifNode {
@@ -43,6 +94,8 @@ impl Default for LoadGifNode {
             y: 0.0,
             id: random_id(),
             texture_cache: vec![],
+            source_key: None,
+            cpu_frames: None,
             path: None,
             length: 0.0,
         }
@@ -133,6 +186,26 @@ impl MyNode for LoadGifNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, _map: HashMap<String, String>) -> anyhow::Result<()> {
+        let path = self.path.as_ref().ok_or(anyhow!("no path set"))?;
+        let full_path = replace_root(path, &storage.project_root);
+        if self.cpu_frames.as_ref().map(|a| &a.0) != Some(&full_path) {
+            let (frames, length) = decode(&full_path)?;
+            self.length = length;
+            let frames = frames
+                .into_iter()
+                .map(|a| DynamicImage::ImageRgba8(a).into_rgba32f())
+                .collect();
+            self.cpu_frames = Some((full_path, frames));
+        }
+        let frames = &self.cpu_frames.as_ref().unwrap().1;
+        if frames.len() > 0 {
+            let texture = frames[self.frame_index(frames.len(), storage.time)].clone();
+            storage.set_texture(self.output_id(&self.outputs()[0]), texture);
+        }
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -147,45 +220,50 @@ impl MyNode for LoadGifNode {
 
         // log::info!("{:?}", self.texture_cache);
 
-        if let Some(path) = &self.path {
-            if self.texture_cache.len() == 0 {
-                self.length = 0.0;
-                let file = match fs::File::open(apply_path_root::get_with_root(path, &storage)) {
-                    Ok(a) => a,
-                    Err(_e) => {
-                        return Err(anyhow!("file not found"));
-                    }
-                };
+        let full_path = apply_path_root::get_with_root(self.path.as_ref().unwrap(), &storage);
+        // the frames are shrunk to the preview size before they are uploaded
+        let key = file_cache_key(&full_path, &("gif", storage.preview_scale.to_bits()));
+
+        // the file has changed, or one of the frames was freed to stay under the memory budget,
+        // so they all need to be reloaded
+        if key != self.source_key
+            || !self.texture_cache.iter().all(|h| storage.cached_texture_exists(*h))
+        {
+            self.texture_cache.clear();
+            self.source_key = key;
+        }
 
-                
+        // there is only no key if the file's metadata can't be read, and frames cached without one would clash with other gifs
+        let Some(key) = key else {
+            return Err(anyhow!("file not found"));
+        };
+
+        // the frames are still cached from before the project was reloaded
+        if self.texture_cache.len() == 0 && self.length > 0.0 {
+            let mut index = 0;
+            while storage.cached_texture_exists(gif_frame_key(key, index)) {
+                self.texture_cache.push(gif_frame_key(key, index));
+                index += 1;
+            }
+        }
 
-                let gif: GifDecoder<BufReader<fs::File>> = match GifDecoder::new(BufReader::new(file)) {
-                    Ok(a) => a,
-                    Err(_e) => {
-                        return Err(anyhow!("unable to decode file to gif"));
-                    }
-                };
-                
-                let mut image = DynamicImage::new_rgba8(gif.dimensions().0, gif.dimensions().1);
-
-                for a in gif.into_frames() {
-                    if let Ok(frame) = a {
-                        let (msu, msl) = frame.delay().numer_denom_ms();
-                        self.length += (msu as f32 / msl as f32) / 1000.0;
-
-                        // let image = frame.buffer();
-                        let b = frame.into_buffer();
-                        *image.as_mut_rgba8().unwrap() = b;
-
-                        let not_texture = RawImage2d::from_raw_rgba(
-                            image.flipv().as_bytes().to_vec(),
-                            (image.dimensions().0, image.dimensions().1),
-                        );
-
-                        let texture: Texture2d =
-                            Texture2d::new(&storage.display, not_texture).unwrap();
-                        self.texture_cache.push(storage.cache_texture(texture));
+        if let Some(_path) = &self.path {
+            if self.texture_cache.len() == 0 {
+                let (frames, length) = decode(&full_path)?;
+                self.length = length;
+                for mut image in frames {
+                    if storage.preview_scale < 1.0 {
+                        let (width, height) = storage.preview_size(image.dimensions());
+                        image = imageops::resize(&image, width, height, FilterType::Triangle);
                     }
+                    let not_texture = RawImage2d::from_raw_rgba(
+                        image.as_bytes().to_vec(),
+                        (image.dimensions().0, image.dimensions().1),
+                    );
+                    let texture: Texture2d =
+                        Texture2d::new(&storage.display, not_texture).unwrap();
+                    let frame_key = gif_frame_key(key, self.texture_cache.len());
+                    self.texture_cache.push(storage.cache_texture(frame_key, texture));
                 }
                 // let a: HashMap<Texture2d, String> = HashMap::new();
             } else {
@@ -194,15 +272,17 @@ impl MyNode for LoadGifNode {
         }
 
         if self.texture_cache.len() > 0 {
-            let index = (self.texture_cache.len() as f64 * (storage.time % self.length as f64)
-                / (self.length as f64))
-                .floor() as usize;
+            let index = self.frame_index(self.texture_cache.len(), storage.time);
             storage.set_id_of_cached_texture(self.texture_cache[index], output_id);
         }
 
         return Ok(());
     }
 
+    fn scales_preview(&self) -> bool {
+        true
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -1,20 +1,34 @@
 use crate::{
+    cpu::CpuStorage,
     node::{random_id, MyNode},
-    storage::{Storage},
+    storage::{file_cache_key, param_cache_key, Storage, TextureDepth},
+};
+use glium::{
+    texture::{MipmapsOption, RawImage2d},
+    Texture2d,
 };
-use glium::{texture::RawImage2d, Texture2d};
 use image::EncodableLayout;
-use image::{self};
+use image::{
+    self,
+    imageops::{crop_imm, flip_vertical, FilterType},
+    DynamicImage, RgbaImage,
+};
 
 use imgui_glium_renderer::Renderer;
 use rfd::FileDialog;
 use savefile::{save_file, SavefileError};
-use std::{any::Any, collections::HashMap, fs, path::PathBuf};
+use std::{
+    any::Any,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 use crate::generic_node_info::GenericNodeInfo;
 use anyhow::anyhow;
 use crate::nodes::node_enum::NodeType;
 
-use super::apply_path_root;
+use super::apply_path_root::{self, replace_root};
 
 #[derive(Savefile)]
 pub struct LoadImage {
@@ -25,6 +39,10 @@ pub struct LoadImage {
     #[savefile_ignore]
     #[savefile_introspect_ignore]
     texture_cache: Option<u64>,
+    /// the whole image at full resolution, only decoded for tiled renders so that tiles can be cut out of it
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    full_image: Option<(u64, Rc<RgbaImage>)>,
 }
 
 impl Default for LoadImage {
@@ -34,11 +52,48 @@ impl Default for LoadImage {
             y: 0.0,
             id: random_id(),
             texture_cache: None,
+            full_image: None,
             path: None,
         }
     }
 }
 
+fn decode(full_path: &Path) -> anyhow::Result<DynamicImage> {
+    let bytes = match fs::read(full_path) {
+        Ok(a) => a,
+        Err(_e) => {
+            return Err(anyhow!("file not found"));
+        }
+    };
+    return match image::load_from_memory(&bytes) {
+        Ok(a) => Ok(a),
+        Err(_e) => Err(anyhow!("unable to decode file as image")),
+    };
+}
+
+impl LoadImage {
+    /// the size of the image file, which may be bigger than the texture shown while editing
+    pub fn source_size(&mut self, storage: &Storage) -> anyhow::Result<(u32, u32)> {
+        return Ok(self.full_image(storage)?.dimensions());
+    }
+
+    fn full_image(&mut self, storage: &Storage) -> anyhow::Result<Rc<RgbaImage>> {
+        let path = self.path.as_ref().ok_or(anyhow!("path is none"))?;
+        let full_path = apply_path_root::get_with_root(path, storage);
+        let key = file_cache_key(&full_path, &"full image");
+        if let Some((k, image)) = &self.full_image {
+            if key == Some(*k) {
+                return Ok(image.clone());
+            }
+        }
+        let image = Rc::new(decode(&full_path)?.into_rgba8());
+        if let Some(key) = key {
+            self.full_image = Some((key, image.clone()));
+        }
+        return Ok(image);
+    }
+}
+
 impl MyNode for LoadImage {
     fn savefile_version() -> u32
     where
@@ -114,6 +169,10 @@ impl MyNode for LoadImage {
         NodeType::LoadImageType
     }
 
+    fn tile_margin(&self) -> Option<u32> {
+        Some(0)
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -137,6 +196,15 @@ impl MyNode for LoadImage {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, _map: HashMap<String, String>) -> anyhow::Result<()> {
+        let path = self.path.as_ref().ok_or(anyhow!("path is none"))?;
+        let image = decode(&replace_root(path, &storage.project_root))?
+            .flipv()
+            .into_rgba32f();
+        storage.set_texture(self.output_id(&self.outputs()[0]), image);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -151,33 +219,73 @@ impl MyNode for LoadImage {
 
         // log::info!("{:?}", self.texture_cache);
 
+        if let Some(tile) = storage.tile {
+            let image = self.full_image(storage)?;
+            let (x, y, width, height) = tile.padded(image.dimensions());
+            let tile_image = flip_vertical(&crop_imm(&*image, x, y, width, height).to_image());
+            let texture = Texture2d::new(
+                &storage.display,
+                RawImage2d::from_raw_rgba(tile_image.into_raw(), (width, height)),
+            )?;
+            storage.set_texture(output_id, texture);
+            return Ok(());
+        }
+
         if let Some(path) = &self.path {
-            if self.texture_cache.is_none()
-                || !storage.cached_texture_exists(self.texture_cache.unwrap())
-            {
-                let bytes = match fs::read(apply_path_root::get_with_root(path, &storage)) {
-                    Ok(a) => a,
-                    Err(_e) => {
-                        return Err(anyhow!("file not found"));
-                    }
-                };
-                let image = match image::load_from_memory(&bytes) {
-                    Ok(a) => a,
-                    Err(_e) => {
-                        return Err(anyhow!("unable to decode file as image"));
-                    }
-                }
-                .flipv()
-                .into_rgba8();
-                let not_texture = RawImage2d::from_raw_rgba(
-                    image.as_bytes().to_vec(),
-                    (image.width(), image.height()),
-                );
-                // let a: HashMap<Texture2d, String> = HashMap::new();
-                let texture: Texture2d = Texture2d::new(&storage.display, not_texture).unwrap();
-                self.texture_cache = Some(storage.cache_texture(texture));
+            let full_path = apply_path_root::get_with_root(path, &storage);
+            // the image is flipped when it is decoded, and shrunk to the preview size
+            let key = file_cache_key(&full_path, &("flipv", storage.preview_scale.to_bits()));
+            if key.is_none() || self.texture_cache != key {
+                self.texture_cache = None;
+            }
+            if let Some(key) = key.filter(|k| storage.cached_texture_exists(*k)) {
+                // the file hasn't changed since it was last loaded (this survives reloading the project)
+                self.texture_cache = Some(key);
             } else {
-                // return false;
+                let mut image = decode(&full_path)?.flipv();
+                let key = key.unwrap_or_else(|| {
+                    param_cache_key(&(image.as_bytes(), storage.preview_scale.to_bits()))
+                });
+                // images that are too big for one texture are shrunk for editing, "render tiled" on an
+                // output node uses the full resolution
+                let max_size = storage.max_texture_size();
+                if image.width() > max_size || image.height() > max_size {
+                    log::warn!(
+                        "{:?} is {}x{}, which is bigger than the largest texture ({max_size}), it will be shrunk until it is rendered in tiles",
+                        full_path,
+                        image.width(),
+                        image.height()
+                    );
+                    image = image.resize(max_size, max_size, FilterType::Triangle);
+                }
+                if storage.preview_scale < 1.0 {
+                    let (width, height) = storage.preview_size((image.width(), image.height()));
+                    image = image.resize_exact(width, height, FilterType::Triangle);
+                }
+                // hdr and exr images are kept as floats so values above 1 aren't lost
+                if let DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) = image {
+                    let image = image.into_rgba32f();
+                    let not_texture = RawImage2d::from_raw_rgba(
+                        image.as_raw().to_vec(),
+                        (image.width(), image.height()),
+                    );
+                    let texture = Texture2d::with_format(
+                        &storage.display,
+                        not_texture,
+                        TextureDepth::F32.format(),
+                        MipmapsOption::NoMipmap,
+                    )?;
+                    self.texture_cache =
+                        Some(storage.cache_texture_with_depth(key, texture, TextureDepth::F32));
+                } else {
+                    let image = image.into_rgba8();
+                    let not_texture = RawImage2d::from_raw_rgba(
+                        image.as_bytes().to_vec(),
+                        (image.width(), image.height()),
+                    );
+                    let texture: Texture2d = Texture2d::new(&storage.display, not_texture).unwrap();
+                    self.texture_cache = Some(storage.cache_texture(key, texture));
+                }
             }
         }
         storage.set_id_of_cached_texture(self.texture_cache.unwrap(), output_id);
@@ -185,6 +293,10 @@ impl MyNode for LoadImage {
         return Ok(());
     }
 
+    fn scales_preview(&self) -> bool {
+        true
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
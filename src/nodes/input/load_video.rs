@@ -1,9 +1,14 @@
-use std::{any::Any, collections::HashMap, path::PathBuf};
+use std::{
+    any::Any,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use ffmpeg_sidecar::{
     command::FfmpegCommand,
 };
 use glium::{texture::RawImage2d, Rect};
+use image::{imageops, DynamicImage, ImageBuffer, Rgba, RgbaImage};
 use imgui_glium_renderer::Renderer;
 use itertools::Itertools;
 use rfd::FileDialog;
@@ -13,6 +18,7 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{CpuStorage, CpuTexture},
     node::{random_id, MyNode},
     storage::Storage,
     widgets::link_widget,
@@ -29,21 +35,32 @@ pub struct LoadVideoNode {
     id: String,
     path: Option<PathBuf>,
     #[savefile_ignore]
+    #[savefile_introspect_ignore]
     frames: Vec<Vec<u8>>,
     playback_speed: f32,
     #[savefile_ignore]
+    #[savefile_introspect_ignore]
     length: f32,
     #[savefile_ignore]
+    #[savefile_introspect_ignore]
     paused: bool,
     #[savefile_ignore]
+    #[savefile_introspect_ignore]
     play_head: f64,
     autoplay: bool,
     #[savefile_ignore]
+    #[savefile_introspect_ignore]
     width: u32,
     #[savefile_ignore]
+    #[savefile_introspect_ignore]
     height: u32,
     #[savefile_ignore]
+    #[savefile_introspect_ignore]
     last_time: f64,
+    /// if `load` has been called, so `run_cpu` only tries once
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    load_attempted: bool,
     do_loop: bool,
     #[savefile_versions = "1.."]
     custom_input: bool,
@@ -55,6 +72,10 @@ pub struct LoadVideoNode {
 
     #[savefile_versions = "1.."]
     ffmpeg_args: String,
+    /// the index of the last frame that was shrunk to the preview size, and the shrunk frame
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    preview_frame: Option<(usize, RgbaImage)>,
 }
 
 impl Default for LoadVideoNode {
@@ -71,6 +92,7 @@ impl Default for LoadVideoNode {
             play_head: 0.0,
             autoplay: true,
             last_time: 0.0,
+            load_attempted: false,
             width: 1,
             height: 1,
             do_loop: true,
@@ -78,7 +100,80 @@ impl Default for LoadVideoNode {
             custom_args: false,
             custom_input_text: String::new(),
             ffmpeg_args: String::new(),
+            preview_frame: None,
+        }
+    }
+}
+
+impl LoadVideoNode {
+    /// uses `frames` instead of loading the video, each frame is `width * height` rgba pixels with the rows going from the top down
+    pub fn set_frames(&mut self, length: f32, width: u32, height: u32, frames: Vec<Vec<u8>>) {
+        self.length = length;
+        self.width = width;
+        self.height = height;
+        self.frames = frames;
+        self.preview_frame = None;
+    }
+
+    /// moves the play head to `time` and returns the index of the frame under it
+    fn current_frame(&mut self, time: f64) -> Option<usize> {
+        if self.last_time > time {
+            self.last_time = 0.0;
+            if !self.autoplay {
+                self.paused = false;
+            }
+        }
+
+        if !self.paused {
+            self.play_head += (time - self.last_time) / self.length as f64;
+            if self.play_head >= 1.0 {
+                if self.do_loop {
+                    self.play_head = 0.0;
+                } else {
+                    self.play_head = 1.0;
+                    self.paused;
+                }
+            }
+        }
+
+        self.last_time = time;
+
+        if self.frames.len() == 0 {
+            return None;
         }
+        return Some((self.play_head * self.frames.len() as f64)
+            .floor()
+            .clamp(0.0, (self.frames.len() - 1) as f64) as usize);
+    }
+
+    /// loads the frames with ffmpeg, `project_root` replaces `PROJECT_ROOT` in the path
+    fn load(&mut self, project_root: &Path) {
+        self.load_attempted = true;
+        match load_video_bytes(
+            &if self.custom_input {
+                PathBuf::new()
+            } else {
+                apply_path_root::replace_root(&self.path.clone().unwrap_or_default(), project_root)
+            },
+            if self.custom_input {
+                self.custom_input_text.clone()
+            } else {
+                String::new()
+            },
+            if self.custom_args {
+                self.ffmpeg_args.clone()
+            } else {
+                String::new()
+            },
+        ) {
+            Ok((length, width, height, frame_data)) => {
+                self.set_frames(length, width, height, frame_data);
+            }
+            // the frames from before are kept, they may have been given with `set_frames`
+            Err(e) => {
+                log::error!("{e}");
+            }
+        };
     }
 }
 
@@ -216,93 +311,83 @@ impl MyNode for LoadVideoNode {
         ui.slider("Video %", 0.0, 1.0, &mut self.play_head);
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, _map: HashMap<String, String>) -> anyhow::Result<()> {
+        if self.path.is_none() && self.frames.len() == 0 {
+            return Err(anyhow!("path is none"));
+        }
+        if self.frames.len() == 0 && !self.load_attempted {
+            self.load(&storage.project_root.clone());
+        }
+        let Some(index) = self.current_frame(storage.time) else {
+            return Err(anyhow!("no frames loaded"));
+        };
+        // a frame of the wrong size is skipped, leaving the texture as it was made
+        let image = match RgbaImage::from_raw(self.width, self.height, self.frames[index].clone()) {
+            Some(a) => DynamicImage::ImageRgba8(a).flipv().into_rgba32f(),
+            None => CpuTexture::new(self.width, self.height),
+        };
+        storage.set_texture(self.output_id(&self.outputs()[0]), image);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
         _map: HashMap<String, String>,
         _renderer: &mut Renderer,
     ) -> anyhow::Result<()> {
-        if self.path.is_none() {
+        if self.path.is_none() && self.frames.len() == 0 {
             return Err(anyhow!("path is none"));
         }
 
         let output_id =self.output_id(&self.outputs()[0]);;
 
-        storage.create_and_set_texture(self.width, self.height, output_id.clone());
-
-        if self.last_time > storage.time {
-            self.last_time = 0.0;
-            if !self.autoplay {
-                self.paused = false;
-            }
-        }
-
-        if !self.paused {
-            self.play_head += (storage.time - self.last_time) / self.length as f64;
-            if self.play_head >= 1.0 {
-                if self.do_loop {
-                    self.play_head = 0.0;
-                } else {
-                    self.play_head = 1.0;
-                    self.paused;
-                }
-            }
-        }
-
-        self.last_time = storage.time;
-
-        if self.frames.len() == 0 {
+        let Some(index) = self.current_frame(storage.time) else {
             return Err(anyhow!("no frames loaded"));
+        };
+        let size = storage.preview_size((self.width, self.height));
+        storage.create_and_set_texture(size.0, size.1, output_id.clone());
+
+        let data = &self.frames[index];
+        if data.len() as u32 != self.height * self.width * 4 {
+            return Ok(());
         }
-        let data = &self.frames[(self.play_head * self.frames.len() as f64)
-            .floor()
-            .clamp(0.0, (self.frames.len() - 1) as f64) as usize];
-        if data.len() as u32 == self.height * self.width * 4 {
-            if let Some(texture) = storage.get_texture(&output_id) {
-                texture.write(
-                    Rect {
-                        left: 0,
-                        bottom: 0,
-                        width: self.width,
-                        height: self.height,
-                    },
-                    RawImage2d::from_raw_rgba_reversed(data, (self.width, self.height)),
-                );
+        let data = if size == (self.width, self.height) {
+            data
+        } else {
+            // the frame is shrunk before it is uploaded, and kept for when the video is paused
+            let up_to_date = self
+                .preview_frame
+                .as_ref()
+                .is_some_and(|(i, frame)| *i == index && frame.dimensions() == size);
+            if !up_to_date {
+                let frame = ImageBuffer::<Rgba<u8>, &[u8]>::from_raw(self.width, self.height, data)
+                    .ok_or(anyhow!("the frame is the wrong size"))?;
+                self.preview_frame = Some((index, imageops::thumbnail(&frame, size.0, size.1)));
             }
+            self.preview_frame.as_ref().unwrap().1.as_raw()
+        };
+        if let Some(texture) = storage.get_texture(&output_id) {
+            texture.write(
+                Rect {
+                    left: 0,
+                    bottom: 0,
+                    width: size.0,
+                    height: size.1,
+                },
+                RawImage2d::from_raw_rgba_reversed(data, size),
+            );
         }
 
         return Ok(());
     }
 
     fn load_assets(&mut self, storage: &Storage) {
-        self.frames = vec![];
-            match load_video_bytes(
-                &if self.custom_input {
-                    PathBuf::new()
-                } else {
-                    apply_path_root::get_with_root(&self.path.clone().unwrap_or_default(), &storage)
-                },
-                if self.custom_input {
-                    self.custom_input_text.clone()
-                } else {
-                    String::new()
-                },
-                if self.custom_args {
-                    self.ffmpeg_args.clone()
-                } else {
-                    String::new()
-                },
-            ) {
-                Ok((length, width, height, frame_data)) => {
-                    self.frames = frame_data;
-                    self.length = length;
-                    self.width = width;
-                    self.height = height;
-                }
-                Err(e) => {
-                    log::error!("{e}");
-                }
-            };
+        self.load(&storage.project_root);
+    }
+
+    fn scales_preview(&self) -> bool {
+        true
     }
 
     fn description(&mut self, ui: &imgui::Ui) {
@@ -347,7 +432,11 @@ pub fn load_video_bytes(
         .rawvideo()
         .pix_fmt("rgba")
         .spawn();
-    let frames = command.unwrap().iter().unwrap().filter_frames();
+    // ffmpeg may not be installed
+    let frames = command
+        .map_err(|e| anyhow!("failed to start ffmpeg: {e}"))?
+        .iter()?
+        .filter_frames();
 
     let mut frame_index = 0;
     for frame in frames {
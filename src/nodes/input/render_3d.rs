@@ -1,22 +1,30 @@
 use crate::{
-    generic_node_info::GenericNodeInfo, node::{random_id, MyNode}, storage::Storage
+    cpu::CpuStorage,
+    generic_node_info::GenericNodeInfo, node::{random_id, MyNode}, project::calculate_hash,
+    render_nodes::RenderNodesParams, storage::{param_cache_key, Storage},
+    worker_pool::{poll_job, CpuJob, JobHandle},
 };
-use glium::{texture::RawImage2d, Texture2d};
-
-use image::{self};
+use anyhow::anyhow;
+use glium::{
+    texture::RawImage2d,
+    uniforms::{MagnifySamplerFilter, SamplerBehavior},
+    BlitTarget, Rect, Surface, Texture2d,
+};
+use image::{DynamicImage, RgbaImage};
+use imgui::{ProgressBar, TextureId};
 
+use glam::{DVec2, IVec2};
 use imgui_glium_renderer::Renderer;
 use lumo::tracer::*;
 use lumo::*;
-use platform_dirs::AppDirs;
 use rfd::FileDialog;
 use savefile::{save_file, SavefileError};
 use std::{
     any::Any,
     collections::HashMap,
-    env::current_exe,
-    fs::{self, remove_dir_all},
+    rc::Rc,
     path::PathBuf,
+    thread,
 };
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
@@ -60,8 +68,46 @@ pub struct Render3DNode {
     #[savefile_versions = "1.."]
     // #[savefile_default_val=""]
     render_data: Vec<u8>,
+
+    #[savefile_versions = "2.."]
+    camera: OrbitCamera,
+    #[savefile_versions = "2.."]
+    light: SceneLight,
+    #[savefile_versions = "2.."]
+    animation: Render3DAnimation,
+    /// render at the project canvas size instead of `width` and `height`
+    #[savefile_versions = "3.."]
+    use_canvas: bool,
+
+    /// `Storage::time` the last time the node was run, used by the animation
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    time: f64,
+
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    render_job: Option<CpuJob<Result<RenderResult, String>, RenderResult>>,
+    /// the hash of the settings used by the last render that was started
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    render_hash: Option<u64>,
+    /// the most recent partial render from `render_job`
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    partial_render: Option<RenderResult>,
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    preview_id: Option<TextureId>,
+    /// the size of `render_data`, the width and height can be changed while a render is running
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    render_size: Option<(u32, u32)>,
+    /// `Storage::preview_scale` from the last run, the scene is rendered this much smaller
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    preview_scale: f32,
 }
-#[derive(Savefile, EnumIter, PartialEq, Eq, Clone, Copy)]
+#[derive(Savefile, EnumIter, PartialEq, Eq, Hash, Clone, Copy)]
 enum MaterialType {
     Mirror,
     Matt,
@@ -70,6 +116,77 @@ enum MaterialType {
     Volume,
 }
 
+/// a camera that orbits around `target`, the default matches lumo's `Camera::default`
+#[derive(Savefile, Clone, Copy, PartialEq)]
+struct OrbitCamera {
+    target: [f32; 3],
+    distance: f32,
+    /// radians around the y axis
+    yaw: f32,
+    /// radians above the target
+    pitch: f32,
+    /// vertical field of view in degrees
+    fov: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        OrbitCamera {
+            target: [0.0, 0.0, -1.3],
+            distance: 1.3,
+            yaw: 0.0,
+            pitch: 0.0,
+            fov: 90.0,
+        }
+    }
+}
+
+impl OrbitCamera {
+    fn position(&self) -> [f32; 3] {
+        [
+            self.target[0] + self.distance * self.pitch.cos() * self.yaw.sin(),
+            self.target[1] + self.distance * self.pitch.sin(),
+            self.target[2] + self.distance * self.pitch.cos() * self.yaw.cos(),
+        ]
+    }
+}
+
+/// the spherical light used when the scene is not inside a room
+#[derive(Savefile, Clone, Copy, PartialEq)]
+struct SceneLight {
+    position: [f32; 3],
+    radius: f32,
+    color: [f32; 3],
+}
+
+impl Default for SceneLight {
+    fn default() -> Self {
+        SceneLight {
+            position: [0.0, 8.0, -2.5],
+            radius: 4.0,
+            color: [1.0; 3],
+        }
+    }
+}
+
+/// how fast the object and camera move over `Storage::time`, in radians per second
+#[derive(Savefile, Clone, Copy, PartialEq)]
+struct Render3DAnimation {
+    enabled: bool,
+    rotate_speed: [f32; 3],
+    orbit_speed: f32,
+}
+
+impl Default for Render3DAnimation {
+    fn default() -> Self {
+        Render3DAnimation {
+            enabled: false,
+            rotate_speed: [0.0, 1.0, 0.0],
+            orbit_speed: 0.0,
+        }
+    }
+}
+
 impl Default for MaterialType {
     fn default() -> Self {
         MaterialType::Matt
@@ -86,6 +203,17 @@ impl Default for Render3DNode {
             mtl_path: None,
             obj_path: None,
             render_data: vec![],
+            camera: OrbitCamera::default(),
+            light: SceneLight::default(),
+            animation: Render3DAnimation::default(),
+            use_canvas: false,
+            time: 0.0,
+            render_job: None,
+            render_size: None,
+            preview_scale: 1.0,
+            render_hash: None,
+            partial_render: None,
+            preview_id: None,
             room: true,
             height: 256,
             width: 256,
@@ -107,144 +235,431 @@ impl Default for Render3DNode {
     }
 }
 
+/// everything needed to render the scene, this is copied out of the node so the render can run on the worker pool
+struct RenderSettings {
+    id: String,
+    obj_path: PathBuf,
+    color: [f32; 3],
+    height: u32,
+    width: u32,
+    material_type: MaterialType,
+    v_scatter_param: f32,
+    refraction_index: f32,
+    room: bool,
+    samples: i32,
+    use_gaussen: bool,
+    gaussen_alpha: f32,
+    rotate: [f32; 3],
+    camera: OrbitCamera,
+    light: SceneLight,
+    render_data: Vec<u8>,
+}
+
 impl Render3DNode {
+    /// starts rendering the scene on the worker pool, the result is picked up in `run`
     fn render(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if self.obj_path.is_none() {
-            return Ok(());
+        let obj_path = match &self.obj_path {
+            Some(a) => a.clone(),
+            None => return Ok(()),
+        };
+
+        let (width, height) = self.scaled_size();
+        let settings = RenderSettings {
+            id: self.id(),
+            obj_path,
+            color: self.color,
+            height,
+            width,
+            material_type: self.material_type,
+            v_scatter_param: self.v_scatter_param,
+            refraction_index: self.refraction_index,
+            room: self.room,
+            samples: self.samples,
+            use_gaussen: self.use_gaussen,
+            gaussen_alpha: self.gaussen_alpha,
+            rotate: self.animated_rotation(),
+            camera: self.animated_camera(),
+            light: self.light,
+            render_data: self.render_data.clone(),
+        };
+
+        // replacing the old job cancels it
+        let hash = self.settings_hash();
+        self.render_hash = Some(hash);
+        self.render_job = Some(CpuJob::spawn_with_handle(hash, move |handle| {
+            render_scene(settings, handle)
+        }));
+
+        Ok(())
+    }
+
+    /// the size the scene is rendered at, shrunk by the preview scale while editing
+    fn scaled_size(&self) -> (u32, u32) {
+        if self.preview_scale >= 1.0 {
+            return (self.width, self.height);
+        }
+        return (
+            ((self.width as f32 * self.preview_scale).round() as u32).max(1),
+            ((self.height as f32 * self.preview_scale).round() as u32).max(1),
+        );
+    }
+
+    /// the rotation of the object at the current time
+    fn animated_rotation(&self) -> [f32; 3] {
+        if !self.animation.enabled {
+            return self.rotate;
         }
+        let t = self.time as f32;
+        [
+            self.rotate[0] + self.animation.rotate_speed[0] * t,
+            self.rotate[1] + self.animation.rotate_speed[1] * t,
+            self.rotate[2] + self.animation.rotate_speed[2] * t,
+        ]
+    }
 
-        let camera = Camera::default(self.width as i32, self.height as i32);
+    /// the camera at the current time
+    fn animated_camera(&self) -> OrbitCamera {
+        let mut camera = self.camera;
+        if self.animation.enabled {
+            camera.yaw += self.animation.orbit_speed * self.time as f32;
+        }
+        return camera;
+    }
 
-        let def_color = Color::new(242, 242, 242);
+    fn settings_hash(&self) -> u64 {
+        let camera = self.animated_camera();
+        let floats = [
+            self.color.as_slice(),
+            self.v_sigma_t.as_slice(),
+            self.animated_rotation().as_slice(),
+            camera.target.as_slice(),
+            self.light.position.as_slice(),
+            self.light.color.as_slice(),
+            &[
+                self.v_scatter_param,
+                self.refraction_index,
+                self.gaussen_alpha,
+                camera.distance,
+                camera.yaw,
+                camera.pitch,
+                camera.fov,
+                self.light.radius,
+                self.preview_scale,
+            ],
+        ]
+        .concat()
+        .iter()
+        .map(|f| f.to_bits())
+        .collect::<Vec<u32>>();
+
+        calculate_hash(&(
+            &self.obj_path,
+            &self.mtl_path,
+            self.width,
+            self.height,
+            self.material_type,
+            self.room,
+            self.samples,
+            self.use_gaussen,
+            floats,
+        ))
+    }
 
-        // let mut scene = Scene::default();
-        let mut scene = Scene::default();
+    /// starts a new render if any of the settings have changed since the last one was started
+    fn restart_if_changed(&mut self) {
+        if self.render_hash.is_some() && self.render_hash != Some(self.settings_hash()) {
+            let a = self.render();
 
-        if self.room {
-            scene = Scene::empty_box(
-                def_color,
-                Material::diffuse(Texture::Solid(Color::new(255, 0, 0))),
-                Material::diffuse(Texture::Solid(Color::new(0, 255, 0))),
-            );
-        } else {
-            scene.add_light(Sphere::new(
-                8.0 * Vec3::Y + 2.5 * Vec3::NEG_Z,
-                4.0,
-                Material::Light(Texture::Solid(Color::WHITE)),
-            ));
+            if a.is_err() {
+                log::info!("{a:?}");
+            }
         }
+    }
 
-        let mut im_buffer = vec![];
+    /// restarts the render if the size or the animation changed it and picks up the finished render,
+    /// `block` waits for the render to finish
+    fn update(
+        &mut self,
+        canvas_size: (u32, u32),
+        preview_scale: f32,
+        time: f64,
+        block: bool,
+    ) -> anyhow::Result<()> {
+        if self.use_canvas && (self.width, self.height) != canvas_size {
+            (self.width, self.height) = canvas_size;
+            self.restart_if_changed();
+        }
+        if self.preview_scale != preview_scale {
+            self.preview_scale = preview_scale;
+            self.restart_if_changed();
+        }
 
-        for i in self.render_data.chunks(3) {
-            if i.len() < 3 {
-                break;
+        if self.animation.enabled {
+            self.time = time;
+            // while previewing, the newest frame is rendered once the current one is done.
+            // batch files wait for every frame
+            if self.render_job.is_none() || block {
+                self.restart_if_changed();
             }
-            im_buffer.push(Color::new(i[0], i[1], i[2]))
         }
 
-        let color = Color {
-            rgb: Vec3 {
-                x: self.color[0] as f64,
-                y: self.color[1] as f64,
-                z: self.color[2] as f64,
-            },
+        if let Some(partial) = self.render_job.as_ref().and_then(|job| job.latest_partial()) {
+            self.partial_render = Some(partial);
+        }
+
+        match poll_job(&mut self.render_job, block) {
+            Some(Ok((_, Ok((render_data, size))))) => {
+                self.render_data = render_data;
+                self.render_size = Some(size);
+                self.texture_cache = None;
+                self.partial_render = None;
+            }
+            Some(Ok((_, Err(e)))) => return Err(anyhow!(e)),
+            Some(Err(e)) => return Err(e),
+            None => {}
+        }
+        return Ok(());
+    }
+
+    /// copies the output into the texture shown in the node
+    fn update_preview(&mut self, storage: &Storage, renderer: &mut Renderer, output_id: &String) {
+        let Some(frame) = storage.get_texture(output_id) else {
+            return;
         };
-        let sigma_color = Vec3 {
-            x: self.color[0] as f64,
-            y: self.color[1] as f64,
-            z: self.color[2] as f64,
+        if self.preview_id.is_none() {
+            self.preview_id = Some(renderer.textures().insert(imgui_glium_renderer::Texture {
+                texture: Rc::new(Texture2d::empty(&storage.display, 10, 10).unwrap()),
+                sampler: SamplerBehavior {
+                    magnify_filter: MagnifySamplerFilter::Nearest,
+                    ..Default::default()
+                },
+            }));
+        }
+        let Some(texture) = renderer.textures().get_mut(self.preview_id.unwrap()) else {
+            // the renderer lost the texture, so it is added again next time
+            self.preview_id = None;
+            return;
         };
+        if texture.texture.dimensions() != frame.dimensions() {
+            texture.texture = Rc::new(
+                Texture2d::empty(&storage.display, frame.width(), frame.height()).unwrap(),
+            );
+        }
+        frame.as_surface().blit_color(
+            &Rect {
+                left: 0,
+                bottom: 0,
+                width: frame.width(),
+                height: frame.height(),
+            },
+            &texture.texture.as_surface(),
+            &BlitTarget {
+                left: 0,
+                bottom: texture.texture.height(),
+                width: texture.texture.width() as i32,
+                height: -(texture.texture.height() as i32),
+            },
+            MagnifySamplerFilter::Nearest,
+        );
+    }
 
-        let mat = match self.material_type {
-            MaterialType::Mirror => Material::Mirror,
-            MaterialType::Matt => Material::Lambertian(Texture::Solid(color)),
-            MaterialType::Texture => Material::Lambertian(Texture::Image(Image {
-                buffer: im_buffer,
-                width: self.width,
-                height: self.height,
-            })),
-            MaterialType::Glass => Material::Glass(self.refraction_index as f64),
-            MaterialType::Volume => {
-                Material::Volumetric(self.v_scatter_param as f64, sigma_color, color)
+    fn render_progress(&mut self, ui: &imgui::Ui) {
+        if let Some(job) = &self.render_job {
+            ProgressBar::new(job.progress())
+                .overlay_text(format!(
+                    "{:.0}% ({:.1}s)",
+                    job.progress() * 100.0,
+                    job.started.elapsed().as_secs_f32()
+                ))
+                .build(ui);
+            if ui.button("cancel render") {
+                self.render_job = None;
+                // keep whatever has been rendered so far
+                if let Some((render_data, size)) = self.partial_render.take() {
+                    self.render_data = render_data;
+                    self.render_size = Some(size);
+                    self.texture_cache = None;
+                }
             }
-        };
+        }
+    }
+}
 
-        let obj = parser::mesh_from_path(
-            <Option<PathBuf> as Clone>::clone(&self.obj_path)
-                .unwrap()
-                .to_str()
-                .unwrap_or(""),
-            mat,
-        )
-        .unwrap();
-
-        // let bounds = obj.bounding_box();
-
-        scene.add(
-            obj.to_unit_size()
-                .to_origin()
-                .rotate_x(self.rotate[0] as f64)
-                .rotate_y(self.rotate[1] as f64)
-                .rotate_x(self.rotate[2] as f64)
-                .translate(0.0, 0.0, -1.3),
+/// the rendered image as rgba bytes, along with its size
+type RenderResult = (Vec<u8>, (u32, u32));
+
+/// renders the scene one sample at a time, sending the image so far back to the node after each sample
+fn render_scene(
+    settings: RenderSettings,
+    handle: &JobHandle<RenderResult>,
+) -> Result<RenderResult, String> {
+    let position = settings.camera.position();
+    let camera = Camera::perspective(
+        Vec3::new(position[0] as f64, position[1] as f64, position[2] as f64),
+        Vec3::new(
+            settings.camera.target[0] as f64,
+            settings.camera.target[1] as f64,
+            settings.camera.target[2] as f64,
+        ),
+        Vec3::Y,
+        settings.camera.fov as f64,
+        0.0,
+        0.0,
+        settings.width as i32,
+        settings.height as i32,
+    );
+
+    let def_color = Color::new(242, 242, 242);
+
+    // let mut scene = Scene::default();
+    let mut scene = Scene::default();
+
+    if settings.room {
+        scene = Scene::empty_box(
+            def_color,
+            Material::diffuse(Texture::Solid(Color::new(255, 0, 0))),
+            Material::diffuse(Texture::Solid(Color::new(0, 255, 0))),
         );
+    } else {
+        let light = settings.light;
+        scene.add_light(Sphere::new(
+            Vec3::new(
+                light.position[0] as f64,
+                light.position[1] as f64,
+                light.position[2] as f64,
+            ),
+            light.radius as f64,
+            Material::Light(Texture::Solid(Color {
+                rgb: Vec3::new(light.color[0] as f64, light.color[1] as f64, light.color[2] as f64),
+            })),
+        ));
+    }
 
-        // scene.add_light();
-
-        let mut renderer: lumo::Renderer = lumo::Renderer::new(scene, camera);
+    let mut im_buffer = vec![];
 
-        if self.use_gaussen {
-            renderer.set_filter(Filter::Gaussian(self.gaussen_alpha as f64));
-        } else {
-            renderer.set_filter(Filter::Box)
+    for i in settings.render_data.chunks(3) {
+        if i.len() < 3 {
+            break;
         }
-        renderer.set_samples(self.samples as i32);
-
-        let film: Film = renderer.render();
+        im_buffer.push(Color::new(i[0], i[1], i[2]))
+    }
 
-        let temp = match AppDirs::new(Some("ReAnimator"), false) {
-            Some(a) => {
-                fs::create_dir_all(a.config_dir.clone());
-                a.config_dir
-            }
-            None => current_exe().unwrap(),
+    let color = Color {
+        rgb: Vec3 {
+            x: settings.color[0] as f64,
+            y: settings.color[1] as f64,
+            z: settings.color[2] as f64,
+        },
+    };
+    let sigma_color = Vec3 {
+        x: settings.color[0] as f64,
+        y: settings.color[1] as f64,
+        z: settings.color[2] as f64,
+    };
+
+    let mat = match settings.material_type {
+        MaterialType::Mirror => Material::Mirror,
+        MaterialType::Matt => Material::Lambertian(Texture::Solid(color)),
+        MaterialType::Texture => Material::Lambertian(Texture::Image(Image {
+            buffer: im_buffer,
+            width: settings.width,
+            height: settings.height,
+        })),
+        MaterialType::Glass => Material::Glass(settings.refraction_index as f64),
+        MaterialType::Volume => {
+            Material::Volumetric(settings.v_scatter_param as f64, sigma_color, color)
         }
-        .join("temp");
+    };
 
-        fs::create_dir_all(&temp);
+    let obj = parser::mesh_from_path(settings.obj_path.to_str().unwrap_or(""), mat)
+        .map_err(|e| e.to_string())?;
 
-        let img_path = temp.join(self.id() + ".png");
+    // let bounds = obj.bounding_box();
 
-        film.save(img_path.to_str().unwrap());
+    scene.add(
+        obj.to_unit_size()
+            .to_origin()
+            .rotate_x(settings.rotate[0] as f64)
+            .rotate_y(settings.rotate[1] as f64)
+            .rotate_x(settings.rotate[2] as f64)
+            .translate(0.0, 0.0, -1.3),
+    );
 
-        let bytes = match fs::read(img_path) {
-            Ok(a) => a,
-            Err(e) => {
-                log::error!("{e}");
-                return Ok(());
-            }
-        };
-        let image = match image::load_from_memory(&bytes) {
-            Ok(a) => a,
-            Err(e) => {
-                log::error!("{e}");
-                return Ok(());
-            }
-        }
-        .flipv()
-        .into_rgba8();
+    // scene.add_light();
 
-        self.render_data = image.into_vec();
+    if scene.num_lights() == 0 {
+        return Err("the scene has no lights".to_owned());
+    }
 
-        log::info!("data len: {}", self.render_data.len());
+    let filter = if settings.use_gaussen {
+        Filter::Gaussian(settings.gaussen_alpha as f64)
+    } else {
+        Filter::Box
+    };
+    let integrator = Integrator::PathTrace;
+
+    // the samples go through lumo's `FilmTile`s and `Film`, like `lumo::Renderer` does, but one sample
+    // per pixel at a time so the image so far can be shown
+    let (width, height) = (settings.width.max(1) as i32, settings.height.max(1) as i32);
+    let samples = settings.samples.max(1);
+    let mut film = Film::new(width, height, samples);
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(4) as i32;
+    let rows = (height + threads - 1) / threads;
+
+    let mut result = (vec![], (0, 0));
+
+    for sample in 0..samples {
+        if handle.cancelled() {
+            return Err("render cancelled".to_owned());
+        }
 
-        remove_dir_all(temp);
+        let tiles: Vec<FilmTile> = thread::scope(|s| {
+            let workers: Vec<_> = (0..height)
+                .step_by(rows as usize)
+                .map(|y_min| {
+                    let (scene, camera, integrator) = (&scene, &camera, &integrator);
+                    s.spawn(move || {
+                        let px_min = IVec2::new(0, y_min);
+                        let px_max = IVec2::new(width, (y_min + rows).min(height));
+                        let mut tile = FilmTile::new(px_min, px_max, filter);
+                        for y in px_min.y..px_max.y {
+                            for x in 0..width {
+                                let raster_xy = DVec2::new(x as f64, y as f64)
+                                    + DVec2::new(fastrand::f64(), fastrand::f64());
+                                let ray = camera.generate_ray(raster_xy);
+                                for film_sample in integrator.integrate(scene, camera, raster_xy, ray) {
+                                    tile.add_sample(film_sample);
+                                }
+                            }
+                        }
+                        return tile;
+                    })
+                })
+                .collect();
+            return workers.into_iter().map(|w| w.join().unwrap()).collect();
+        });
+        for tile in tiles {
+            film.add_tile(tile);
+        }
 
-        self.texture_cache = None;
+        let rgb = film.rgb_image();
+        let mut data = Vec::with_capacity(rgb.len() / 3 * 4);
+        // opengl textures start from the bottom row
+        for row in rgb.chunks(width as usize * 3).rev() {
+            for pixel in row.chunks(3) {
+                data.extend([pixel[0], pixel[1], pixel[2], 255]);
+            }
+        }
+        result = (data, (width as u32, height as u32));
 
-        Ok(())
+        let count = (sample + 1) as f32;
+        handle.set_progress(count / samples as f32);
+        if sample + 1 < samples {
+            handle.send_partial(result.clone());
+        }
     }
+
+    Ok(result)
 }
 
 impl MyNode for Render3DNode {
@@ -252,7 +667,7 @@ impl MyNode for Render3DNode {
     where
         Self: Sized,
     {
-        1
+        3
     }
 
     fn set_id(&mut self, id: String) {
@@ -281,10 +696,13 @@ impl MyNode for Render3DNode {
     fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, storage: &Storage) {
         ui.columns(3, "render col", true);
 
-        let mut input_val = [self.width as i32, self.height as i32];
-        ui.input_int2("dimensions (w,h)", &mut input_val).build();
-        self.width = input_val[0].max(1) as u32;
-        self.height = input_val[1].max(1) as u32;
+        ui.checkbox("use project canvas size", &mut self.use_canvas);
+        ui.disabled(self.use_canvas, || {
+            let mut input_val = [self.width as i32, self.height as i32];
+            ui.input_int2("dimensions (w,h)", &mut input_val).build();
+            self.width = input_val[0].max(1) as u32;
+            self.height = input_val[1].max(1) as u32;
+        });
 
         ui.text(format!(
             "object path: {}",
@@ -367,6 +785,47 @@ impl MyNode for Render3DNode {
         ui.input_int("samples per pixel", &mut self.samples).build();
         self.samples = self.samples.max(1);
 
+        ui.columns(1, "render col end", false);
+
+        if ui.collapsing_header("camera", imgui::TreeNodeFlags::empty()) {
+            ui.input_float3("target", &mut self.camera.target).build();
+            ui.slider("distance", 0.01, 20.0, &mut self.camera.distance);
+            ui.slider_config("yaw", -std::f32::consts::PI, std::f32::consts::PI)
+                .display_format("%.3f rad")
+                .build(&mut self.camera.yaw);
+            ui.slider_config("pitch", -89.0_f32.to_radians(), 89.0_f32.to_radians())
+                .display_format("%.3f rad")
+                .build(&mut self.camera.pitch);
+            ui.slider("fov", 1.0, 179.0, &mut self.camera.fov);
+            if ui.button("reset camera") {
+                self.camera = OrbitCamera::default();
+            }
+        }
+
+        if ui.collapsing_header("light", imgui::TreeNodeFlags::empty()) {
+            ui.disabled(self.room, || {
+                ui.input_float3("light position", &mut self.light.position)
+                    .build();
+                ui.input_float("light radius", &mut self.light.radius).build();
+                ui.color_edit3("light color", &mut self.light.color);
+            });
+            if self.room {
+                ui.text_disabled("the room has its own light, turn off \"room\" to use this one");
+            }
+            self.light.radius = self.light.radius.max(0.01);
+        }
+
+        if ui.collapsing_header("animation", imgui::TreeNodeFlags::empty()) {
+            ui.checkbox("animate over time", &mut self.animation.enabled);
+            ui.disabled(!self.animation.enabled, || {
+                ui.input_float3("rotation speed (x,y,z)", &mut self.animation.rotate_speed)
+                    .build();
+                ui.input_float("orbit speed", &mut self.animation.orbit_speed)
+                    .build();
+            });
+            ui.text_disabled("speeds are in radians per second");
+        }
+
         if ui.button("render") {
             let a = self.render();
 
@@ -374,12 +833,50 @@ impl MyNode for Render3DNode {
                 log::info!("{a:?}");
             }
         }
+        self.render_progress(ui);
+
+        // an animated render that is still running is left to finish, see `run`
+        if !self.animation.enabled || self.render_job.is_none() {
+            self.restart_if_changed();
+        }
+    }
+
+    fn render_in_node(
+        &self,
+        ui: &imgui::Ui,
+        ui_scale: f32,
+        renderer: &mut Renderer,
+        _params: &mut RenderNodesParams,
+    ) {
+        if let Some(image_id) = self.preview_id {
+            if let Some(texture) = renderer.textures().get(image_id) {
+                let size = texture.texture.dimensions();
+                let scale = (50.0 * ui_scale) / size.0.max(size.1).max(1) as f32;
+                ui.image_button(
+                    "preview",
+                    image_id,
+                    [size.0 as f32 * scale, size.1 as f32 * scale],
+                );
+            }
+        }
+        if let Some(job) = &self.render_job {
+            ProgressBar::new(job.progress())
+                .size([50.0 * ui_scale, 0.0])
+                .build(ui);
+        }
     }
 
     fn type_(&self) -> NodeType {
         NodeType::Render3D
     }
 
+    fn unload(&mut self, renderer: &mut Renderer) {
+        self.render_job = None;
+        if let Some(id) = self.preview_id.take() {
+            renderer.textures().remove(id);
+        }
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -403,43 +900,115 @@ impl MyNode for Render3DNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, _map: HashMap<String, String>) -> anyhow::Result<()> {
+        // `load_assets` starts the first render in the editor
+        if self.render_hash.is_none() {
+            self.render().map_err(|e| anyhow!("{e}"))?;
+        }
+        self.update(storage.canvas_size, 1.0, storage.time, true)?;
+
+        let render_size = self.render_size.unwrap_or((self.width, self.height));
+        if let Some(image) = RgbaImage::from_raw(render_size.0, render_size.1, self.render_data.clone()) {
+            storage.set_texture(self.output_id(&self.outputs()[0]), DynamicImage::ImageRgba8(image).into_rgba32f());
+        }
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
         _map: HashMap<String, String>,
-        _renderer: &mut Renderer,
+        renderer: &mut Renderer,
     ) -> anyhow::Result<()> {
         let output_id =self.output_id(&self.outputs()[0]);;
 
+        self.update(
+            storage.canvas_size,
+            storage.preview_scale,
+            storage.time,
+            storage.block_on_jobs,
+        )?;
+
         // log::info!("{:?}", self.texture_cache);
 
-        if self.render_data.len() > 0 {
+        if let (Some(_), Some((render_data, size))) = (&self.render_job, &self.partial_render) {
+            // partial renders are uploaded straight to the output instead of being cached,
+            // otherwise there would be a cached texture for every sample
+            let size = *size;
+            storage.create_and_set_texture(size.0, size.1, output_id.clone());
+            storage.get_texture(&output_id).unwrap().write(
+                Rect {
+                    left: 0,
+                    bottom: 0,
+                    width: size.0,
+                    height: size.1,
+                },
+                RawImage2d::from_raw_rgba(render_data.clone(), size),
+            );
+            self.update_preview(storage, renderer, &output_id);
+            return Ok(());
+        }
+
+        // render data loaded from a save file is always the size of the node
+        let render_size = self.render_size.unwrap_or((self.width, self.height));
+
+        if self.render_data.len() == (render_size.0 * render_size.1 * 4) as usize {
             if self.texture_cache.is_none()
                 || !storage.cached_texture_exists(self.texture_cache.unwrap())
             {
-                let not_texture =
-                    RawImage2d::from_raw_rgba(self.render_data.clone(), (self.width, self.height));
-                // let a: HashMap<Texture2d, String> = HashMap::new();
-                let texture: Texture2d = Texture2d::new(&storage.display, not_texture).unwrap();
-                self.texture_cache = Some(storage.cache_texture(texture));
+                // hashing the render on the cpu is much cheaper than reading the texture back
+                let key = param_cache_key(&(&self.render_data, render_size));
+                if storage.cached_texture_exists(key) {
+                    self.texture_cache = Some(key);
+                } else {
+                    let not_texture =
+                        RawImage2d::from_raw_rgba(self.render_data.clone(), render_size);
+                    // let a: HashMap<Texture2d, String> = HashMap::new();
+                    let texture: Texture2d =
+                        Texture2d::new(&storage.display, not_texture).unwrap();
+                    self.texture_cache = Some(storage.cache_texture(key, texture));
+                }
             } else {
                 // return false;
             }
         }
         if self.texture_cache.is_some() {
-            storage.set_id_of_cached_texture(self.texture_cache.unwrap(), output_id);
+            storage.set_id_of_cached_texture(self.texture_cache.unwrap(), output_id.clone());
+            self.update_preview(storage, renderer, &output_id);
         }
         // storage.set_texture(output_id,  texture);
 
         return Ok(());
     }
 
+    fn scales_preview(&self) -> bool {
+        true
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+    fn actions(&self) -> Vec<String> {
+        vec!["render".to_owned()]
+    }
+
+    fn computing(&self) -> bool {
+        self.render_job.is_some()
+    }
+
+    fn trigger_action(&mut self, action: &str, _time: f64) {
+        if action == "render" {
+            let a = self.render();
+
+            if a.is_err() {
+                log::info!("{a:?}");
+            }
+        }
+    }
+
     fn load_assets(&mut self, _storage: &Storage) {
         let a = self.render();
 
@@ -8,6 +8,7 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, CpuStorage},
     node::{random_id, MyNode},
     nodes::node_enum,
     storage::Storage,
@@ -15,6 +16,19 @@ use crate::{
 
 use node_enum::NodeType;
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            void main() {
+            color = vec4(v_tex_coords.x,v_tex_coords.y, 0.0,1.0);
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct UvInputNode {
     x: f32,
@@ -65,6 +79,10 @@ impl MyNode for UvInputNode {
         NodeType::UvInput
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -88,6 +106,13 @@ impl MyNode for UvInputNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let output = draw(input.dimensions(), &[], |uv, _| [uv[0], uv[1], 0.0, 1.0]);
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -101,18 +126,7 @@ impl MyNode for UvInputNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            void main() {
-            color = vec4(v_tex_coords.x,v_tex_coords.y, 0.0,1.0);
-            }
-            "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -1,4 +1,5 @@
 use crate::{
+    cpu::CpuStorage,
     node::{random_id, MyNode},
     storage::Storage,
 };
@@ -6,7 +7,7 @@ use glium::{
     texture::RawImage2d, uniforms::MagnifySamplerFilter, BlitTarget, Rect, Surface, Texture2d,
 };
 
-use image::{self};
+use image::{self, DynamicImage, RgbaImage};
 
 use imgui_glium_renderer::Renderer;
 
@@ -15,8 +16,7 @@ use std::{any::Any, collections::HashMap, path::PathBuf};
 use crate::generic_node_info::GenericNodeInfo;
 use anyhow::anyhow;
 use crate::nodes::node_enum::NodeType;
-
-use escapi;
+use crate::platform::{open_webcams, Webcam};
 
 #[derive(Savefile)]
 pub struct WebcamNode {
@@ -46,7 +46,7 @@ pub struct WebcamNode {
 
     #[savefile_ignore]
     #[savefile_introspect_ignore]
-    camera: Option<escapi::Device>,
+    camera: Option<Webcam>,
 }
 
 impl Default for WebcamNode {
@@ -153,6 +153,16 @@ impl MyNode for WebcamNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, _map: HashMap<String, String>) -> anyhow::Result<()> {
+        self.capture()?;
+
+        let Some(image) = RgbaImage::from_raw(self.size.0, self.size.1, self.data.clone()) else {
+            return Err(anyhow!("webcam error"));
+        };
+        storage.set_texture(self.output_id(&self.outputs()[0]), DynamicImage::ImageRgba8(image).into_rgba32f());
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -164,18 +174,13 @@ impl MyNode for WebcamNode {
 
         // log::info!("{:?}", self.texture_cache);
 
-        if let Some(cam) = &self.camera {
-            self.size = (cam.capture_width(), cam.capture_height());
-            self.data = match cam.capture() {
-                Ok(a) => a,
-                Err(_) => return Err(anyhow!("flailed to capture webcam")),
-            }
-            .to_vec();
-        };
+        self.capture()?;
 
         log::info!("size {:?}", self.size);
 
-        storage.create_and_set_texture(self.size.0, self.size.1, output_id.clone());
+        // the frame is shrunk to the preview size when it is copied to the output
+        let size = storage.preview_size(self.size);
+        storage.create_and_set_texture(size.0, size.1, output_id.clone());
 
         // log::info!("created texture");
 
@@ -230,6 +235,10 @@ impl MyNode for WebcamNode {
         return Ok(());
     }
 
+    fn scales_preview(&self) -> bool {
+        true
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -239,13 +248,26 @@ impl MyNode for WebcamNode {
 }
 
 impl WebcamNode {
+    /// uses `data` as the frame while no camera is open, it is `width * height` rgba pixels in the order the camera gives them
+    pub fn set_frame(&mut self, width: u32, height: u32, data: Vec<u8>) {
+        self.size = (width, height);
+        self.data = data;
+    }
+
+    /// takes a new frame from the camera, if one is open
+    fn capture(&mut self) -> anyhow::Result<()> {
+        if let Some(cam) = &self.camera {
+            self.size = cam.size();
+            self.data = cam.capture()?;
+        };
+        return Ok(());
+    }
+
     fn load_webcams(&mut self) {
         return;
 
         self.available = vec![];
-        let num_of_devices = escapi::num_devices();
-        for i in 0..num_of_devices {
-            let cam = escapi::init(i, self.size.0, self.size.0, self.desired_fps);
+        for (i, cam) in open_webcams(self.size, self.desired_fps).into_iter().enumerate() {
             if let Ok(cam) = cam {
                 log::info!("cam name: {}", cam.name());
                 if self.selected_cam == 999 && cam.name() == self.main_webcam {
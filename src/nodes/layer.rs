@@ -13,12 +13,59 @@ use anyhow::anyhow;
 
 
 use crate::{
-    node::{random_id, MyNode},
+    canvas::SizePolicy,
+    cpu::{draw, mipmaps, sample_lod, CpuStorage},
+    node::{random_id, ColorSpace, MyNode},
     storage::Storage,
 };
 
 use super::node_enum::NodeType;
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+            
+            uniform sampler2D base_texture;
+            
+            uniform sampler2D layer;
+            uniform vec2 layer_pos;
+            uniform vec2 base_size;
+            uniform vec2 layer_size;
+            uniform vec2 layer_target_size;
+            uniform vec2 layer_scale;
+            uniform vec2 layer_offset;
+
+            vec4 add_layer(vec2 target_pos, vec2 size, vec2 target_size, vec4 base_color) {
+                if (v_tex_coords.x * base_size.x < target_pos.x || v_tex_coords.y * base_size.y < target_pos.y || v_tex_coords.x * base_size.x > target_pos.x + target_size.x || v_tex_coords.y * base_size.y > target_pos.y + target_size.y) {
+                    return base_color;
+                }
+
+                vec2 layer_uv = ((v_tex_coords * base_size - target_pos) / target_size) * layer_scale + layer_offset;
+                if (layer_uv.x < 0.0 || layer_uv.y < 0.0 || layer_uv.x > 1.0 || layer_uv.y > 1.0) {
+                    return base_color;
+                }
+                vec4 layer_color = texture(layer, layer_uv);
+
+                float p1 = layer_color.a;
+                float p2 = base_color.a;
+                vec3 c1 = layer_color.xyz;
+                vec3 c2 = base_color.xyz;
+
+                return vec4((p1*c1+p2*c2-p1*p2*c2)/(p1+p2-p1*p2),p1+p2-p1*p2);
+
+            }
+        
+            void main() {
+                
+                color = texture(base_texture, v_tex_coords);
+
+                color = add_layer(layer_pos, layer_size,layer_target_size,  color);
+            }
+            "#;
+
 fn convert(old: Vec<[f32; 4]>) -> Vec<([f32; 4], bool)> {
     old.iter().map(|x| (x.clone(), false)).collect()
 }
@@ -32,6 +79,9 @@ pub struct LayerNode {
     #[savefile_versions = "1.."]
     layers: Vec<([f32; 4], bool)>,
     base_texture_size: (u32, u32),
+    /// how each layer is placed in its target rectangle if their aspect ratios are different
+    #[savefile_versions = "2.."]
+    size_policy: SizePolicy,
 }
 
 impl Default for LayerNode {
@@ -42,16 +92,25 @@ impl Default for LayerNode {
             id: random_id(),
             layers: vec![],
             base_texture_size: (1, 1),
+            size_policy: SizePolicy::default(),
         }
     }
 }
+impl LayerNode {
+    /// adds an input that is drawn over the base in `rect`, which is `[x, y, width, height]` in pixels,
+    /// or as a fraction of the size of the base if `use_percent` is set
+    pub fn add_layer(&mut self, rect: [f32; 4], use_percent: bool) {
+        self.layers.push((rect, use_percent));
+    }
+}
+
 impl MyNode for LayerNode {
     fn path(&self) -> Vec<&str> {
         vec!["Image", "Combine"]
     }
 
     fn savefile_version() -> u32 {
-        1
+        2
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -64,6 +123,14 @@ impl MyNode for LayerNode {
         NodeType::Layer
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
+    fn color_space(&self) -> ColorSpace {
+        ColorSpace::Linear
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -91,6 +158,48 @@ impl MyNode for LayerNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let mut output = storage.input(&map, &self.input_id(&self.inputs()[0]))?.clone();
+        self.base_texture_size = output.dimensions();
+        let base_size = [self.base_texture_size.0 as f32, self.base_texture_size.1 as f32];
+
+        for (i, (rect, use_percent)) in self.layers.iter().enumerate() {
+            let texture = storage.input(&map, &self.input_id(&format!("Layer {}", i + 1)))?;
+            let scale = |v: f32, axis: usize| if *use_percent { v * base_size[axis] } else { v };
+            let layer_pos = [scale(rect[0], 0), scale(rect[1], 1)];
+            let layer_target_size = [scale(rect[2], 0), scale(rect[3], 1)];
+            let target_size = (layer_target_size[0].max(1.0) as u32, layer_target_size[1].max(1.0) as u32);
+            let (layer_scale, layer_offset) = self.size_policy.uv_transform(target_size, texture.dimensions());
+            // layers are usually drawn smaller than they are, which the gpu does with mipmaps
+            let texture_size = [texture.width() as f32, texture.height() as f32];
+            let lod = [0, 1]
+                .map(|a| layer_scale[a] / layer_target_size[a] * texture_size[a])
+                .into_iter()
+                .fold(0.0_f32, f32::max)
+                .log2();
+            let levels = mipmaps(texture);
+
+            output = draw(output.dimensions(), &[&output], |uv, c| {
+                let base_color = c[0];
+                let pos = [uv[0] * base_size[0], uv[1] * base_size[1]];
+                if (0..2).any(|a| pos[a] < layer_pos[a] || pos[a] > layer_pos[a] + layer_target_size[a]) {
+                    return base_color;
+                }
+                let layer_uv = [0, 1].map(|a| ((pos[a] - layer_pos[a]) / layer_target_size[a]) * layer_scale[a] + layer_offset[a]);
+                if layer_uv.iter().any(|a| *a < 0.0 || *a > 1.0) {
+                    return base_color;
+                }
+                let layer_color = sample_lod(&levels, layer_uv, lod);
+                let (p1, p2) = (layer_color[3], base_color[3]);
+                let alpha = p1 + p2 - p1 * p2;
+                let c = |i: usize| (p1 * layer_color[i] + p2 * base_color[i] - p1 * p2 * base_color[i]) / alpha;
+                [c(0), c(1), c(2), alpha]
+            });
+        }
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -117,44 +226,7 @@ impl MyNode for LayerNode {
             inputs.push(get_output);
         }
 
-        let fragment_shader_src2 = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-            
-            uniform sampler2D base_texture;
-            
-            uniform sampler2D layer;
-            uniform vec2 layer_pos;
-            uniform vec2 base_size;
-            uniform vec2 layer_size;
-            uniform vec2 layer_target_size;
-
-            vec4 add_layer(vec2 target_pos, vec2 size, vec2 target_size, vec4 base_color) {
-                if (v_tex_coords.x * base_size.x < target_pos.x || v_tex_coords.y * base_size.y < target_pos.y || v_tex_coords.x * base_size.x > target_pos.x + target_size.x || v_tex_coords.y * base_size.y > target_pos.y + target_size.y) {
-                    return base_color;
-                }
-
-                vec4 layer_color = texture(layer, ((v_tex_coords * base_size - target_pos) * layer_size / target_size)/layer_size);
-
-                float p1 = layer_color.a;
-                float p2 = base_color.a;
-                vec3 c1 = layer_color.xyz;
-                vec3 c2 = base_color.xyz;
-
-                return vec4((p1*c1+p2*c2-p1*p2*c2)/(p1+p2-p1*p2),p1+p2-p1*p2);
-
-            }
-        
-            void main() {
-                
-                color = texture(base_texture, v_tex_coords);
-
-                color = add_layer(layer_pos, layer_size,layer_target_size,  color);
-            }
-            "#;
+        let fragment_shader_src2 = FRAGMENT_SHADER;
 
         // log::info!("{}", fragment_shader_src);
 
@@ -174,6 +246,13 @@ impl MyNode for LayerNode {
             .gen_frag_shader(fragment_shader_src2.to_string())
             .unwrap();
 
+        self.base_texture_size = match storage.get_texture(base_input_key) {
+            Some(a) => a.dimensions(),
+            None => {
+                return Err(anyhow!("failed to get base texture"));
+            }
+        };
+
         storage.create_and_set_texture(
             self.base_texture_size.0,
             self.base_texture_size.1,
@@ -184,14 +263,7 @@ impl MyNode for LayerNode {
             .get_frag_shader(fragment_shader_src2.to_string())
             .unwrap();
 
-        let base_texture: &glium::Texture2d = match storage.get_texture(base_input_key) {
-            Some(a) => a,
-            None => {
-                return Err(anyhow!("failed to get base texture"));
-            }
-        };
-
-        self.base_texture_size = base_texture.dimensions();
+        let base_texture: &glium::Texture2d = storage.get_texture(base_input_key).unwrap();
         let texture2 = storage.get_texture(&output_id).unwrap();
 
         base_texture.as_surface().blit_color(
@@ -222,13 +294,22 @@ impl MyNode for LayerNode {
                 }
             };
             // log::info!("{:?} {:?} {:?}", [layer[0], layer[1]],[layer[2], layer[3]],[texture.dimensions().0 as f32, texture.dimensions().1 as f32]);
+            // positions in pixels are shrunk along with the preview
+            let pixels = storage.preview_scale;
+            let target_size = (
+                (layer.0[2] * if layer.1 {self.base_texture_size.0 as f32} else {pixels}).max(1.0) as u32,
+                (layer.0[3] * if layer.1 {self.base_texture_size.1 as f32} else {pixels}).max(1.0) as u32,
+            );
+            let (layer_scale, layer_offset) = self.size_policy.uv_transform(target_size, texture.dimensions());
             let uniforms = uniform! {
                 base_texture: texture2,
                 base_size: [self.base_texture_size.0 as f32, self.base_texture_size.1 as f32],
-                layer_pos: [layer.0[0] * if layer.1 {self.base_texture_size.0 as f32} else {1.0}, layer.0[1] * if layer.1 {self.base_texture_size.1 as f32} else {1.0}],
-                layer_target_size: [layer.0[2] * if layer.1 {self.base_texture_size.0 as f32} else {1.0}, layer.0[3] * if layer.1 {self.base_texture_size.1 as f32} else {1.0}],
+                layer_pos: [layer.0[0] * if layer.1 {self.base_texture_size.0 as f32} else {pixels}, layer.0[1] * if layer.1 {self.base_texture_size.1 as f32} else {pixels}],
+                layer_target_size: [layer.0[2] * if layer.1 {self.base_texture_size.0 as f32} else {pixels}, layer.0[3] * if layer.1 {self.base_texture_size.1 as f32} else {pixels}],
                 layer_size: [texture.dimensions().0 as f32, texture.dimensions().1 as f32],
                 layer: texture,
+                layer_scale: layer_scale,
+                layer_offset: layer_offset,
             };
 
             texture2
@@ -303,8 +384,9 @@ impl MyNode for LayerNode {
         }
 
         if ui.button("add layer") {
-            self.layers.push(([0.0, 0.0, 1.0, 1.0], true));
+            self.add_layer([0.0, 0.0, 1.0, 1.0], true);
         }
+        self.size_policy.combo(ui, "layer size");
 
         ui.next_column();
 
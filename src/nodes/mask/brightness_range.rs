@@ -8,11 +8,14 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, CpuStorage},
     node::{random_id, MyNode},
     nodes::node_enum::{NodeType},
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = include_str!("brightness_range.glsl");
+
 #[derive(Savefile)]
 pub struct BrightnessRangeMaskNode {
     x: f32,
@@ -67,6 +70,10 @@ impl MyNode for BrightnessRangeMaskNode {
         NodeType::BrightnessRangeMask
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
@@ -108,6 +115,27 @@ impl MyNode for BrightnessRangeMaskNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let (low, high) = (self.low, self.high);
+        let output = draw(input.dimensions(), &[input], |_, c| {
+            let mut u = (c[0][0] + c[0][1] + c[0][2]) / 3.0;
+            let (mut low2, mut high2) = (low, high);
+            if low > high {
+                low2 = high;
+                high2 = low;
+                u = 1.0 - u;
+            }
+            if high2 < u || low2 >= u {
+                [0.0; 4]
+            } else {
+                [1.0; 4]
+            }
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -121,7 +149,7 @@ impl MyNode for BrightnessRangeMaskNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = include_str!("brightness_range.glsl");
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -8,11 +8,42 @@ use anyhow::anyhow;
 
 
 use crate::{
+    canvas::generator_size_ui,
+    cpu::{draw, rand, CpuStorage},
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+            
+            uniform sampler2D tex;
+            uniform float time;
+            uniform float seed; 
+            
+            float random (vec2 st) {
+            return fract(sin(dot(st.xy,
+                                vec2(12.9898,78.233)))*
+                43758.5453123);
+            }
+
+
+
+            void main() {
+
+            float rnd_r = random( v_tex_coords * (time + seed) * seed);
+            float rnd_g = random( v_tex_coords * ((time+seed) * seed ) );
+            float rnd_b = random( v_tex_coords * (time * seed*2.3 ) );
+
+            color = vec4(rnd_r, rnd_g, rnd_b, 1.0);
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct ColorNoiseNode {
     x: f32,
@@ -23,6 +54,9 @@ pub struct ColorNoiseNode {
     #[savefile_versions = "1.."]
     #[savefile_default_val = "1"]
     seed: i32,
+    /// follow the project canvas size instead of `size`
+    #[savefile_versions = "2.."]
+    use_canvas: bool,
 }
 
 impl Default for ColorNoiseNode {
@@ -34,6 +68,7 @@ impl Default for ColorNoiseNode {
             input: false,
             size: (1, 1),
             seed: 1,
+            use_canvas: true,
         }
     }
 }
@@ -56,7 +91,7 @@ impl MyNode for ColorNoiseNode {
     }
 
     fn savefile_version() -> u32 {
-        1
+        2
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -69,6 +104,10 @@ impl MyNode for ColorNoiseNode {
         NodeType::ColorNoise
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -96,6 +135,29 @@ impl MyNode for ColorNoiseNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        if self.use_canvas {
+            self.size = storage.canvas_size;
+        } else if self.input && self.inputs().len() > 0 {
+            self.size = storage
+                .input(&map, &self.input_id(&self.inputs()[0]))?
+                .dimensions();
+        }
+        let time = storage.time as f32;
+        let seed = 10.0 / (self.seed % 100) as f32;
+        let output = draw(self.size, &[], |uv, _| {
+            // the shader scales red by each factor in turn and green by their product
+            [
+                rand(uv.map(|a| a * (time + seed) * seed)),
+                rand(uv.map(|a| a * ((time + seed) * seed))),
+                rand(uv.map(|a| a * (time * seed * 2.3))),
+                1.0,
+            ]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -104,36 +166,11 @@ impl MyNode for ColorNoiseNode {
     ) -> anyhow::Result<()> {
         let output_id =self.output_id(&self.outputs()[0]);;
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-            
-            uniform sampler2D tex;
-            uniform float time;
-            uniform float seed; 
-            
-            float random (vec2 st) {
-            return fract(sin(dot(st.xy,
-                                vec2(12.9898,78.233)))*
-                43758.5453123);
-            }
-
+        let fragment_shader_src = FRAGMENT_SHADER;
 
-
-            void main() {
-
-            float rnd_r = random( v_tex_coords * (time + seed) * seed);
-            float rnd_g = random( v_tex_coords * ((time+seed) * seed ) );
-            float rnd_b = random( v_tex_coords * (time * seed*2.3 ) );
-
-            color = vec4(rnd_r, rnd_g, rnd_b, 1.0);
-            }
-            "#;
-
-        if self.input && self.inputs().len() > 0 {
+        if self.use_canvas {
+            self.size = storage.canvas_size;
+        } else if self.input && self.inputs().len() > 0 {
             let input_id = self.input_id(&self.inputs()[0]);
             let get_output = match map.get(&input_id) {
                 Some(a) => a,
@@ -148,7 +185,13 @@ impl MyNode for ColorNoiseNode {
                 storage
             .gen_frag_shader(fragment_shader_src.to_string())
             .ok_or(anyhow!("failed to compile shader"))?;
-        storage.create_and_set_texture(self.size.0, self.size.1, output_id.clone());
+        // sizes that don't come from the input are shrunk while editing
+        let size = if self.input && !self.use_canvas {
+            self.size
+        } else {
+            storage.preview_size(self.size)
+        };
+        storage.create_and_set_texture(size.0, size.1, output_id.clone());
 
         let texture: &glium::Texture2d = match storage.get_texture(&output_id) {
             Some(a) => a,
@@ -188,12 +231,7 @@ let shader = storage
     }
 
     fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
-        ui.checkbox("use input texture for dimensions", &mut self.input);
-        ui.disabled(self.input, || {
-            let mut input_val = [self.size.0 as i32, self.size.1 as i32];
-            ui.input_int2("dimensions (w,h)", &mut input_val).build();
-            self.size = (input_val[0].max(1) as u32, input_val[1].max(1) as u32);
-        });
+        generator_size_ui(ui, &mut self.use_canvas, &mut self.input, &mut self.size);
         ui.input_int("seed", &mut self.seed).build();
         if ui.button("gen random seed") {
             self.seed = fastrand::i32(i32::MIN..i32::MAX);
@@ -202,4 +240,8 @@ let shader = storage
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn scales_preview(&self) -> bool {
+        true
+    }
 }
@@ -9,7 +9,12 @@ use anyhow::anyhow;
 
 use strum::IntoEnumIterator;
 
-use crate::{node::*, nodes::*, storage::Storage};
+use crate::{
+    cpu::{draw, CpuStorage},
+    node::*,
+    nodes::*,
+    storage::Storage,
+};
 
 fn default_node_type_for_mask() -> NodeType {
     NodeType::BrightnessMask
@@ -32,6 +37,7 @@ impl NodeType {
 pub struct GenericMaskNode {
     #[savefile_default_fn = "default_node_type_for_mask"]
     #[savefile_ignore]
+    #[savefile_introspect_ignore]
     #[savefile_versions = "..0"]
     type_: NodeType,
     #[savefile_versions = "1.."]
@@ -47,6 +53,15 @@ pub struct GenericMaskNode {
 }
 
 impl GenericMaskNode {
+    fn fragment_shader(&self) -> &'static str {
+        match self.type_ {
+            NodeType::BrightnessMask => include_str!("brightness.glsl"),
+            a => {
+                unreachable!("node type: {a:?} is not a generic shader type or has not has the input default value fully implemented")
+            }
+        }
+    }
+
     pub fn new(type_: NodeType) -> GenericMaskNode {
         GenericMaskNode {
             x: 0.0,
@@ -104,7 +119,7 @@ impl MyNode for GenericMaskNode {
     where
         Self: Sized,
     {
-        0
+        1
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -117,6 +132,10 @@ impl MyNode for GenericMaskNode {
         self.type_
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![self.fragment_shader().to_string()]
+    }
+
      
 
     fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
@@ -173,6 +192,24 @@ impl MyNode for GenericMaskNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        if self.type_ != NodeType::BrightnessMask {
+            return Err(anyhow!("{} can't run on the cpu yet", self.name()));
+        }
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let threshold = self.input;
+        let output = draw(input.dimensions(), &[input], |_, c| {
+            let u = (c[0][0] + c[0][1] + c[0][2]) / 3.0;
+            if threshold > u {
+                [0.0; 4]
+            } else {
+                [1.0; 4]
+            }
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -201,12 +238,7 @@ impl MyNode for GenericMaskNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = match self.type_ {
-            NodeType::BrightnessMask => include_str!("brightness.glsl"),
-            a => {
-                unreachable!("node type: {a:?} is not a generic shader type or has not has the input default value fully implemented")
-            }
-        };
+        let fragment_shader_src = self.fragment_shader();
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
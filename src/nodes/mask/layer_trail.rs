@@ -8,11 +8,14 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, CpuStorage, CpuTexture},
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = include_str!("layer_trail.glsl");
+
 #[derive(Savefile)]
 pub struct LayerTrailNode {
     x: f32,
@@ -23,6 +26,10 @@ pub struct LayerTrailNode {
     #[savefile_ignore]
     #[savefile_introspect_ignore]
     last_frame: Option<Texture2d>,
+    /// `last_frame` for `run_cpu`
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    cpu_last_frame: Option<CpuTexture>,
 }
 
 impl Default for LayerTrailNode {
@@ -33,6 +40,7 @@ impl Default for LayerTrailNode {
             id: random_id(),
             last_frame: None,
             fade: 0.9,
+            cpu_last_frame: None,
         }
     }
 }
@@ -70,6 +78,10 @@ impl MyNode for LayerTrailNode {
         NodeType::LayerTrail
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -93,6 +105,30 @@ impl MyNode for LayerTrailNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let texture = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let last_frame = match self.cpu_last_frame.take() {
+            Some(a) if a.dimensions() == texture.dimensions() => a,
+            _ => texture.clone(),
+        };
+        let fade = self.fade;
+        let mut output = draw(texture.dimensions(), &[texture, &last_frame], |_, c| {
+            let [r, g, b, a] = c[1];
+            match c[0][3] == 0.0 {
+                true => [r, g, b, a * fade],
+                false => c[0],
+            }
+        });
+        let output_id = self.output_id(&self.outputs()[0]);
+        storage.set_texture(output_id.clone(), output.clone());
+        // the last frame is kept at 8 bits
+        for channel in output.iter_mut() {
+            *channel = (channel.clamp(0.0, 1.0) * 255.0).round() / 255.0;
+        }
+        self.cpu_last_frame = Some(output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -106,7 +142,7 @@ impl MyNode for LayerTrailNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = include_str!("layer_trail.glsl");
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -11,10 +11,51 @@ use anyhow::anyhow;
 
 
 use crate::{
-    node::{random_id, MyNode}, nodes::node_enum::NodeType, storage::Storage
+    cpu::{draw, CpuStorage, CpuTexture}, fuse::PixelCode, node::{random_id, MyNode}, nodes::node_enum::NodeType, storage::Storage
 };
 
 
+const OR_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            uniform sampler2D tex2;
+            void main() {
+            color = max(texture(tex2, v_tex_coords), texture(tex, v_tex_coords));
+            }
+            "#;
+
+const AND_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            uniform sampler2D tex2;
+            void main() {
+            color = (texture(tex2, v_tex_coords) * texture(tex, v_tex_coords));
+            }
+            "#;
+
+const NOT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            void main() {
+            color = vec4(1.0) - texture(tex, v_tex_coords);
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct LogicNotNode {
     x: f32,
@@ -65,6 +106,18 @@ impl MyNode for LogicNotNode {
         NodeType::LogicNot
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![NOT_SHADER.to_string()]
+    }
+
+    fn pixel_code(&self) -> Option<PixelCode> {
+        Some(PixelCode {
+            code: "$out_0 = vec4(1.0) - $in_0;".to_string(),
+            functions: String::new(),
+            uniforms: vec![],
+        })
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -88,6 +141,13 @@ impl MyNode for LogicNotNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let output = draw(input.dimensions(), &[input], |_, c| c[0].map(|a| 1.0 - a));
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -101,18 +161,7 @@ impl MyNode for LogicNotNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            void main() {
-            color = vec4(1.0) - texture(tex, v_tex_coords);
-            }
-            "#;
+        let fragment_shader_src = NOT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -207,6 +256,18 @@ impl MyNode for LogicAndNode {
         NodeType::LogicAnd
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![AND_SHADER.to_string()]
+    }
+
+    fn pixel_code(&self) -> Option<PixelCode> {
+        Some(PixelCode {
+            code: "$out_0 = $in_1 * $in_0;".to_string(),
+            functions: String::new(),
+            uniforms: vec![],
+        })
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -230,6 +291,19 @@ impl MyNode for LogicAndNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let inputs = self
+            .inputs()
+            .iter()
+            .map(|a| storage.input(&map, &self.input_id(a)))
+            .collect::<anyhow::Result<Vec<&CpuTexture>>>()?;
+        let output = draw(inputs[0].dimensions(), &inputs, |_, c| {
+            std::array::from_fn(|i| c[1][i] * c[0][i])
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -248,19 +322,7 @@ impl MyNode for LogicAndNode {
             None => return  Err(anyhow!("missing input 2")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            uniform sampler2D tex2;
-            void main() {
-            color = (texture(tex2, v_tex_coords) * texture(tex, v_tex_coords));
-            }
-            "#;
+        let fragment_shader_src = AND_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output1) {
             Some(a) => (a.width(), a.height()),
@@ -359,6 +421,18 @@ impl MyNode for LogicOrNode {
         NodeType::LogicOr
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![OR_SHADER.to_string()]
+    }
+
+    fn pixel_code(&self) -> Option<PixelCode> {
+        Some(PixelCode {
+            code: "$out_0 = max($in_1, $in_0);".to_string(),
+            functions: String::new(),
+            uniforms: vec![],
+        })
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -382,6 +456,19 @@ impl MyNode for LogicOrNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let inputs = self
+            .inputs()
+            .iter()
+            .map(|a| storage.input(&map, &self.input_id(a)))
+            .collect::<anyhow::Result<Vec<&CpuTexture>>>()?;
+        let output = draw(inputs[0].dimensions(), &inputs, |_, c| {
+            std::array::from_fn(|i| c[1][i].max(c[0][i]))
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -400,19 +487,7 @@ impl MyNode for LogicOrNode {
             None => return  Err(anyhow!("missing input 2")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            uniform sampler2D tex2;
-            void main() {
-            color = max(texture(tex2, v_tex_coords), texture(tex, v_tex_coords));
-            }
-            "#;
+        let fragment_shader_src = OR_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output1) {
             Some(a) => (a.width(), a.height()),
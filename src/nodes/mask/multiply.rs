@@ -8,16 +8,43 @@ use anyhow::anyhow;
 
 
 use crate::{
-    node::{random_id, MyNode},
+    canvas::SizePolicy,
+    cpu::{draw, sample, CpuStorage},
+    node::{random_id, ColorSpace, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D a;
+            uniform sampler2D b;
+            uniform vec2 b_scale;
+            uniform vec2 b_offset;
+
+            void main() {
+            vec2 b_uv = v_tex_coords * b_scale + b_offset;
+            vec4 b_color = vec4(0.0);
+            if (b_uv.x >= 0.0 && b_uv.y >= 0.0 && b_uv.x <= 1.0 && b_uv.y <= 1.0) {
+                b_color = texture(b, b_uv);
+            }
+            color = texture(a, v_tex_coords) * b_color;
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct MultiplyNode {
     x: f32,
     y: f32,
     id: String,
+    /// how input 2 is placed if it isn't the same size as input 1
+    #[savefile_versions = "3.."]
+    size_policy: SizePolicy,
 }
 
 impl Default for MultiplyNode {
@@ -26,6 +53,7 @@ impl Default for MultiplyNode {
             x: 0.0,
             y: 0.0,
             id: random_id(),
+            size_policy: SizePolicy::default(),
         }
     }
 }
@@ -48,7 +76,7 @@ impl MyNode for MultiplyNode {
     }
 
     fn savefile_version() -> u32 {
-        2
+        3
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -61,6 +89,22 @@ impl MyNode for MultiplyNode {
         NodeType::Multiply
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
+    fn color_space(&self) -> ColorSpace {
+        ColorSpace::Linear
+    }
+
+    fn tile_margin(&self) -> Option<u32> {
+        // the inputs only line up in every tile if they are stretched to the same size
+        match self.size_policy {
+            SizePolicy::Stretch => Some(0),
+            _ => None,
+        }
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -88,6 +132,30 @@ impl MyNode for MultiplyNode {
         self.y = y;
     }
 
+    fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
+        self.size_policy.combo(ui, "input 2 size");
+    }
+
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let a = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let b = storage.input(&map, &self.input_id(&self.inputs()[1]))?;
+        let (b_scale, b_offset) = self.size_policy.uv_transform(a.dimensions(), b.dimensions());
+        let output = draw(a.dimensions(), &[a], |uv, c| {
+            let b_uv = [
+                uv[0] * b_scale[0] + b_offset[0],
+                uv[1] * b_scale[1] + b_offset[1],
+            ];
+            let b_color = if b_uv.iter().all(|x| (0.0..=1.0).contains(x)) {
+                sample(b, b_uv)
+            } else {
+                [0.0; 4]
+            };
+            std::array::from_fn(|i| c[0][i] * b_color[i])
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -111,22 +179,7 @@ impl MyNode for MultiplyNode {
             return Err(anyhow!("missing input"));
         }
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D a;
-            uniform sampler2D b;
-
-
-
-            void main() {
-            color = texture(a, v_tex_coords) * texture(b, v_tex_coords);
-            }
-            "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(&get_outputs[0]) {
             Some(a) => (a.width(), a.height()),
@@ -149,9 +202,12 @@ impl MyNode for MultiplyNode {
 let shader = storage
             .get_frag_shader(fragment_shader_src.to_string())
             .unwrap();
+        let (b_scale, b_offset) = self.size_policy.uv_transform(texture_size, b.dimensions());
         let uniforms = uniform! {
             a: a,
             b: b,
+            b_scale: b_scale,
+            b_offset: b_offset,
         };
         let texture2 = storage.get_texture(&output_id).unwrap();
         texture2
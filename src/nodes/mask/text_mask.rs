@@ -15,6 +15,7 @@ use anyhow::anyhow;
 // use glium_text_rusttype::TextSystem;
 
 use crate::{
+    cpu::{CpuStorage, CpuTexture},
     fonts::MyFonts,
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
@@ -34,6 +35,9 @@ pub struct TextMaskNode {
 
     text_data: Vec<u8>,
     output_size: (u32, u32),
+    /// render at the project canvas size instead of `output_size`
+    #[savefile_versions = "1.."]
+    use_canvas: bool,
 }
 
 impl Default for TextMaskNode {
@@ -47,6 +51,7 @@ impl Default for TextMaskNode {
             font_size: 35.0,
             text_data: vec![],
             output_size: (1, 1),
+            use_canvas: false,
         }
     }
 }
@@ -112,7 +117,7 @@ impl MyNode for TextMaskNode {
     }
 
     fn savefile_version() -> u32 {
-        0
+        1
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -150,6 +155,23 @@ impl MyNode for TextMaskNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        if !map.contains_key(&self.input_id(&self.inputs()[0])) {
+            return Err(anyhow!("missing input"));
+        }
+        if self.use_canvas {
+            self.output_size = storage.canvas_size;
+        }
+        // text isn't rendered yet, so this is the empty texture `run` makes
+        if self.text_data.is_empty()
+            || self.text_data.len() as u32 == self.output_size.0 * self.output_size.1 * 4
+        {
+            let texture = CpuTexture::new(self.output_size.0, self.output_size.1);
+            storage.set_texture(self.output_id(&self.outputs()[0]), texture);
+        }
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -171,7 +193,13 @@ impl MyNode for TextMaskNode {
         //     }
         // }
 
-        if self.text_data.len() as u32 == self.output_size.0 * self.output_size.1 * 4 {
+        // until text has been rendered into `text_data` the mask is the purple placeholder texture
+        if self.use_canvas {
+            self.output_size = storage.canvas_size;
+        }
+        if self.text_data.is_empty()
+            || self.text_data.len() as u32 == self.output_size.0 * self.output_size.1 * 4
+        {
             storage.create_and_set_texture(
                 self.output_size.0,
                 self.output_size.1,
@@ -189,6 +217,15 @@ impl MyNode for TextMaskNode {
         return Ok(());
     }
 
+    fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
+        ui.checkbox("use project canvas size", &mut self.use_canvas);
+        ui.disabled(self.use_canvas, || {
+            let mut input_val = [self.output_size.0 as i32, self.output_size.1 as i32];
+            ui.input_int2("dimensions (w,h)", &mut input_val).build();
+            self.output_size = (input_val[0].max(1) as u32, input_val[1].max(1) as u32);
+        });
+    }
+
     fn description(&mut self, ui: &imgui::Ui) {
         ui.text_wrapped("Render text as a mask");
     }
@@ -8,11 +8,49 @@ use anyhow::anyhow;
 
 
 use crate::{
+    canvas::generator_size_ui,
+    cpu::{draw, fract, glsl_mod, CpuStorage},
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+            
+            uniform sampler2D tex;
+            uniform float time;
+            uniform float seed;
+            uniform bool alpha;
+            
+            highp float rand(vec2 co)
+            {
+                highp float a=12.9898;
+                highp float b=78.233;
+                highp float c=43758.5453;
+                highp float dt=dot(co.xy,vec2(a,b));
+                highp float sn=mod(dt,3.14);
+                return fract(sin(sn)*c);
+            }
+
+
+
+            void main() {
+
+
+            color = vec4(rand(v_tex_coords * seed + mod(time, 3.0) + mod(time, 5.0) + mod(time, 7.0)));
+
+            if (alpha) {
+                color.a = 1.0;
+            }
+
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct WhiteNoiseNode {
     x: f32,
@@ -23,6 +61,9 @@ pub struct WhiteNoiseNode {
     #[savefile_versions = "1.."]
     #[savefile_default_val = "1"]
     seed: i32,
+    /// follow the project canvas size instead of `size`
+    #[savefile_versions = "2.."]
+    use_canvas: bool,
 }
 
 impl Default for WhiteNoiseNode {
@@ -34,6 +75,7 @@ impl Default for WhiteNoiseNode {
             input: false,
             size: (1, 1),
             seed: 1,
+            use_canvas: true,
         }
     }
 }
@@ -43,7 +85,7 @@ impl MyNode for WhiteNoiseNode {
     }
 
     fn savefile_version() -> u32 {
-        1
+        2
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -56,6 +98,10 @@ impl MyNode for WhiteNoiseNode {
         NodeType::WhiteNoise
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -96,6 +142,30 @@ impl MyNode for WhiteNoiseNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        if self.use_canvas {
+            self.size = storage.canvas_size;
+        } else if self.input && self.inputs().len() > 0 {
+            if !map.contains_key(&self.input_id(&self.inputs()[0])) {
+                return Ok(());
+            }
+            self.size = storage
+                .input(&map, &self.input_id(&self.inputs()[0]))?
+                .dimensions();
+        }
+        let time = storage.time as f32;
+        let seed = (self.seed % 100) as f32;
+        let offset = glsl_mod(time, 3.0) + glsl_mod(time, 5.0) + glsl_mod(time, 7.0);
+        let output = draw(self.size, &[], |uv, _| {
+            let co = uv.map(|a| a * seed + offset);
+            // the sine is kept small so it is the same on every gpu
+            let value = fract((glsl_mod(co[0] * 12.9898 + co[1] * 78.233, 3.14)).sin() * 43758.5453);
+            [value; 4]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -104,43 +174,11 @@ impl MyNode for WhiteNoiseNode {
     ) -> anyhow::Result<()> {
         let output_id =self.output_id(&self.outputs()[0]);;
 
-        let fragment_shader_src = r#"
-
-            #version 140
+        let fragment_shader_src = FRAGMENT_SHADER;
 
-            in vec2 v_tex_coords;
-            out vec4 color;
-            
-            uniform sampler2D tex;
-            uniform float time;
-            uniform float seed;
-            uniform bool alpha;
-            
-            highp float rand(vec2 co)
-            {
-                highp float a=12.9898;
-                highp float b=78.233;
-                highp float c=43758.5453;
-                highp float dt=dot(co.xy,vec2(a,b));
-                highp float sn=mod(dt,3.14);
-                return fract(sin(sn)*c);
-            }
-
-
-
-            void main() {
-
-
-            color = vec4(rand(v_tex_coords * seed + mod(time, 3.0) + mod(time, 5.0) + mod(time, 7.0)));
-
-            if (alpha) {
-                color.a = 1.0;
-            }
-
-            }
-            "#;
-
-        if self.input && self.inputs().len() > 0 {
+        if self.use_canvas {
+            self.size = storage.canvas_size;
+        } else if self.input && self.inputs().len() > 0 {
             let input_id = self.input_id(&self.inputs()[0]);
             let get_output = match map.get(&input_id) {
                 Some(a) => a,
@@ -155,7 +193,13 @@ impl MyNode for WhiteNoiseNode {
                 storage
             .gen_frag_shader(fragment_shader_src.to_string())
             .ok_or(anyhow!("failed to compile shader"))?;
-        storage.create_and_set_texture(self.size.0, self.size.1, output_id.clone());
+        // sizes that don't come from the input are shrunk while editing
+        let size = if self.input && !self.use_canvas {
+            self.size
+        } else {
+            storage.preview_size(self.size)
+        };
+        storage.create_and_set_texture(size.0, size.1, output_id.clone());
 
         let texture: &glium::Texture2d = match storage.get_texture(&output_id) {
             Some(a) => a,
@@ -193,12 +237,7 @@ let shader = storage
     }
 
     fn edit_menu_render(&mut self, ui: &imgui::Ui, _renderer: &mut Renderer, _storage: &Storage) {
-        ui.checkbox("use input texture for dimensions", &mut self.input);
-        ui.disabled(self.input, || {
-            let mut input_val = [self.size.0 as i32, self.size.1 as i32];
-            ui.input_int2("dimensions (w,h)", &mut input_val).build();
-            self.size = (input_val[0].max(1) as u32, input_val[1].max(1) as u32);
-        });
+        generator_size_ui(ui, &mut self.use_canvas, &mut self.input, &mut self.size);
         ui.input_int("seed", &mut self.seed).build();
         if ui.button("gen random seed") {
             self.seed = fastrand::i32(i32::MIN..i32::MAX);
@@ -207,4 +246,8 @@ let shader = storage
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn scales_preview(&self) -> bool {
+        true
+    }
 }
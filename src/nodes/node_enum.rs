@@ -1,6 +1,10 @@
 use std::path::PathBuf;
 
+use basic_shader_nodes::custom_shader::CustomShaderNode;
+use basic_shader_nodes::feedback::FeedbackNode;
 use basic_shader_nodes::invert::InvertTextureNode;
+use basic_shader_nodes::isf::IsfNode;
+use basic_shader_nodes::shadertoy::ShadertoyNode;
 use basic_shader_nodes::shader_generic::GenericShaderNode;
 use basic_shader_nodes::solid_color::ColorNode;
 use basic_shader_nodes::*;
@@ -36,7 +40,7 @@ use node_error::ErrorNode;
 use output::cover_window::CoverWindowNode;
 use output::image_io::OutputNode;
 use pick_random::RandomInputNode;
-use regex::Regex;
+use crate::platform::find_window;
 use rgb_hsl::combine_hsv::*;
 use rgb_hsl::combine_rgba::CombineRgbaNode;
 use rgb_hsl::split_hsv::SplitHsvNode;
@@ -51,12 +55,12 @@ use text::text_input::TextInputNode;
 use transform::sample_uv::SampleUvNode;
 use transform::scale::ScaleNode;
 use watercolor::watercolor::WaterColorNode;
-use win_screenshot::utils::{window_list, HwndName};
 
 use crate::nodes::*;
 
 use super::data::histogram::HistogramNode;
 
+/// every kind of node, the variants are saved in projects so they can only be added to the end
 #[derive(Savefile, EnumIter, PartialEq, Eq, Copy, Clone, Debug, Hash, Serialize, ToJsonString)]
 pub enum NodeType {
     Debug,
@@ -111,6 +115,10 @@ pub enum NodeType {
     LogicOr,
     HueShift,
     Histogram,
+    CustomShader,
+    Shadertoy,
+    Isf,
+    Feedback,
 }
 
 impl NodeType {
@@ -168,6 +176,10 @@ impl NodeType {
             NodeType::LogicNot => "Not",
             NodeType::HueShift => "Hue Shift",
             NodeType::Histogram => "Color Histogram",
+            NodeType::CustomShader => "Custom Shader",
+            NodeType::Shadertoy => "Shadertoy",
+            NodeType::Isf => "ISF Shader",
+            NodeType::Feedback => "Feedback Shader",
 
         }
         .to_owned();
@@ -202,6 +214,50 @@ impl NodeType {
                     }
                 }
             }
+            NodeType::CustomShader => {
+                let a: Result<CustomShaderNode, SavefileError> =
+                    savefile::load_file(project_file, CustomShaderNode::savefile_version());
+                match a {
+                    Ok(b) => Some(Box::new(b)),
+                    Err(e) => {
+                        log::error!("{e}");
+                        None
+                    }
+                }
+            }
+            NodeType::Shadertoy => {
+                let a: Result<ShadertoyNode, SavefileError> =
+                    savefile::load_file(project_file, ShadertoyNode::savefile_version());
+                match a {
+                    Ok(b) => Some(Box::new(b)),
+                    Err(e) => {
+                        log::error!("{e}");
+                        None
+                    }
+                }
+            }
+            NodeType::Isf => {
+                let a: Result<IsfNode, SavefileError> =
+                    savefile::load_file(project_file, IsfNode::savefile_version());
+                match a {
+                    Ok(b) => Some(Box::new(b)),
+                    Err(e) => {
+                        log::error!("{e}");
+                        None
+                    }
+                }
+            }
+            NodeType::Feedback => {
+                let a: Result<FeedbackNode, SavefileError> =
+                    savefile::load_file(project_file, FeedbackNode::savefile_version());
+                match a {
+                    Ok(b) => Some(Box::new(b)),
+                    Err(e) => {
+                        log::error!("{e}");
+                        None
+                    }
+                }
+            }
             NodeType::LogicNot => {
                 let a: Result<LogicNotNode, SavefileError> =
                     savefile::load_file(project_file, LogicNotNode::savefile_version());
@@ -302,8 +358,8 @@ impl NodeType {
                 }
             }
             NodeType::WaterColor => {
-                let a: Result<WhiteNoiseNode, SavefileError> =
-                    savefile::load_file(project_file, WhiteNoiseNode::savefile_version());
+                let a: Result<WaterColorNode, SavefileError> =
+                    savefile::load_file(project_file, WaterColorNode::savefile_version());
                 match a {
                     Ok(b) => Some(Box::new(b)),
                     Err(e) => {
@@ -351,18 +407,7 @@ impl NodeType {
 
                 match a {
                     Ok(mut b) => {
-                        let re =
-                            Regex::new(&b.app_name).unwrap_or(Regex::new(r"~~~~error~~~").unwrap());
-                        let hwnd = window_list()
-                            .unwrap()
-                            .iter()
-                            .find(|i| re.is_match(&i.window_name))
-                            .unwrap_or(&HwndName {
-                                hwnd: 0,
-                                window_name: "error".to_owned(),
-                            })
-                            .hwnd;
-                        b.hwnd = hwnd;
+                        b.hwnd = find_window(&b.app_name, false);
                         Some(Box::new(b))
                     }
                     Err(e) => {
@@ -376,18 +421,7 @@ impl NodeType {
                     savefile::load_file(project_file, CoverWindowNode::savefile_version());
                 match a {
                     Ok(mut b) => {
-                        let re =
-                            Regex::new(&b.app_name).unwrap_or(Regex::new(r"~~~~error~~~").unwrap());
-                        let hwnd = window_list()
-                            .unwrap()
-                            .iter()
-                            .find(|i| re.is_match(&i.window_name))
-                            .unwrap_or(&HwndName {
-                                hwnd: 0,
-                                window_name: "error".to_owned(),
-                            })
-                            .hwnd;
-                        b.hwnd = hwnd;
+                        b.hwnd = find_window(&b.app_name, false);
                         Some(Box::new(b))
                     }
                     Err(e) => {
@@ -541,7 +575,7 @@ impl NodeType {
             }
             NodeType::SolidColor => {
                 let a: Result<ColorNode, SavefileError> =
-                    savefile::load_file(project_file, CombineRgbaNode::savefile_version());
+                    savefile::load_file(project_file, ColorNode::savefile_version());
                 match a {
                     Ok(b) => Some(Box::new(b)),
                     Err(_) => None,
@@ -683,6 +717,10 @@ impl NodeType {
             NodeType::DefaultImageOut => Box::new(DefaultImage::default()),
             NodeType::InvertTexture => Box::new(InvertTextureNode::default()),
             NodeType::Histogram => Box::new(HistogramNode::default()),
+            NodeType::CustomShader => Box::new(CustomShaderNode::default()),
+            NodeType::Shadertoy => Box::new(ShadertoyNode::default()),
+            NodeType::Isf => Box::new(IsfNode::default()),
+            NodeType::Feedback => Box::new(FeedbackNode::default()),
             NodeType::VHS
             | NodeType::ChromaticAberration
             | NodeType::Blur
@@ -733,6 +771,9 @@ impl NodeType {
     }
 
     pub fn disabled(&self) -> bool {
+        if self.windows_only() && !cfg!(target_os = "windows") {
+            return true;
+        }
         matches!(self, |NodeType::Debug| NodeType::Webcam
             | NodeType::BlurSp
             | NodeType::TextInput
@@ -755,10 +796,13 @@ impl NodeType {
             | NodeType::BrightnessMask
         )
     }
+    /// nodes that need windows apis, they are disabled everywhere else
     pub fn windows_only(&self) -> bool {
         matches!(
             self,
             | NodeType::CoverWindow
+            | NodeType::CaptureDesktop
+            | NodeType::Webcam
         )
     }
 }
@@ -1,6 +1,6 @@
 use std::{any::Any, collections::HashMap, path::PathBuf};
 
-use glium::{texture::RawImage2d, uniform, DrawParameters, Surface};
+use glium::{uniform, DrawParameters, Surface};
 use imgui_glium_renderer::Renderer;
 use savefile::{save_file, SavefileError};
 use crate::generic_node_info::GenericNodeInfo;
@@ -8,12 +8,26 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::CpuStorage,
     node::{random_id, MyNode},
     storage::Storage,
 };
 
 use super::node_enum::NodeType;
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            void main() {
+            color = texture(tex, v_tex_coords);
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct ErrorNode {
     x: f32,
@@ -64,6 +78,10 @@ impl MyNode for ErrorNode {
         NodeType::Error
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -87,6 +105,12 @@ impl MyNode for ErrorNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let texture = storage.input(&map, &self.input_id(&self.inputs()[0]))?.clone();
+        storage.set_texture(self.output_id(&self.outputs()[0]), texture);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -100,18 +124,7 @@ impl MyNode for ErrorNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            void main() {
-            color = texture(tex, v_tex_coords);
-            }
-            "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -148,19 +161,6 @@ let shader = storage
             )
             .unwrap();
 
-        let mut a = vec![1, 2, 3];
-        a.extend_from_slice(&[1, 3, 4]);
-
-        texture2.write(
-            glium::Rect {
-                left: 0,
-                bottom: 0,
-                width: 10,
-                height: 1,
-            },
-            RawImage2d::<u8>::from_raw_rgb(vec![], (10, 1)),
-        );
-
         return Ok(());
     }
 
@@ -13,21 +13,14 @@ use savefile::{save_file, SavefileError};
 use crate::generic_node_info::GenericNodeInfo;
 use anyhow::anyhow;
 
-use windows::Win32::{
-    Foundation::{HWND, POINT, RECT},
-    Graphics::Gdi::ClientToScreen,
-    UI::WindowsAndMessaging::{GetClientRect, GetForegroundWindow},
-};
-// use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
 use crate::{
+    cpu::CpuStorage,
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
+    platform::{find_window, is_foreground_window, window_client_rect},
     storage::Storage,
 };
 
-use regex::Regex;
-use win_screenshot::prelude::*;
-
 #[derive(Savefile)]
 pub struct CoverWindowNode {
     x: f32,
@@ -76,32 +69,19 @@ impl CoverWindowNode {
         }
 
         if self.render {
-            let mut rect = RECT::default();
-            unsafe {
-                let a = GetClientRect(HWND(self.hwnd as *mut _), &mut rect);
-                if a.is_err() {
-                    self.render = false;
-                    // log::info!("{a:?}");
-                    return Err(anyhow!("invalid window id"));
-                }
-                let mut point = POINT {
-                    x: rect.left,
-                    y: rect.top,
-                };
-                let a = ClientToScreen(HWND(self.hwnd as *mut _), &mut point);
-                if !a.as_bool() {
+            let (position, size) = match window_client_rect(self.hwnd) {
+                Ok(a) => a,
+                Err(e) => {
                     self.render = false;
-                    return Err(anyhow!("failed 'ClientToScreen' conversion, likely because of an invalid window id"));
+                    return Err(e);
                 }
-                rect.left = point.x;
-                rect.top = point.y;
-            }
+            };
 
-            let steam_focused = unsafe { GetForegroundWindow().0 == self.hwnd as *mut _ };
+            let steam_focused = is_foreground_window(self.hwnd);
 
             if steam_focused {
                 let _ = window.request_inner_size(Size::Physical(
-                    (rect.right as f64, rect.bottom as f64).into(),
+                    (size.0 as f64, size.1 as f64).into(),
                 ));
                 let _ = window.set_cursor_hittest(false);
                 window.set_decorations(false);
@@ -110,7 +90,7 @@ impl CoverWindowNode {
 
                 window
                     .set_window_level(imgui_winit_support::winit::window::WindowLevel::AlwaysOnTop);
-                window.set_outer_position(Position::Physical((rect.left, rect.top).into()));
+                window.set_outer_position(Position::Physical(position.into()));
 
                 if self.texture_id.is_none() {
                     self.texture_id = Some(renderer.textures().insert(Texture {
@@ -210,6 +190,12 @@ impl MyNode for CoverWindowNode {
         NodeType::CoverWindow
     }
 
+    fn unload(&mut self, renderer: &mut Renderer) {
+        if let Some(id) = self.texture_id.take() {
+            renderer.textures().remove(id);
+        }
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -243,16 +229,7 @@ impl MyNode for CoverWindowNode {
         ui.input_text("program name", &mut self.app_name).build();
 
         if ui.is_item_edited() {
-            let re = Regex::new(&self.app_name).unwrap_or(Regex::new(r"~~~~error~~~").unwrap());
-            let hwnd = window_list()
-                .unwrap()
-                .iter()
-                .find(|i| re.is_match(&i.window_name) && !i.window_name.contains("ReAnimator"))
-                .unwrap_or(&HwndName {
-                    hwnd: 0,
-                    window_name: "error".to_owned(),
-                })
-                .hwnd;
+            let hwnd = find_window(&self.app_name, true);
             if self.app_name.len() >= 2 {
                 self.hwnd = hwnd;
             } else {
@@ -270,6 +247,12 @@ impl MyNode for CoverWindowNode {
         self.hwnd = hwnd as isize;
     }
 
+    /// the window can only be covered with opengl, this only checks the input
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -4,6 +4,7 @@ use std::fs::{File};
 use std::path::PathBuf;
 use std::rc::Rc;
 
+use crate::cpu::CpuStorage;
 use crate::node::{random_id, MyNode};
 use crate::nodes::node_enum::*;
 use crate::render_nodes::RenderNodesParams;
@@ -87,6 +88,10 @@ pub struct OutputNode {
     #[savefile_ignore]
     #[savefile_introspect_ignore]
     pub texture_id: Option<TextureId>,
+    /// set by the "render tiled" button, the project renders the graph in tiles and resets this
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    pub tiled_render_requested: bool,
 }
 
 impl Default for OutputNode {
@@ -102,11 +107,32 @@ impl Default for OutputNode {
             },
             run_with_time: vec![],
             texture_id: None,
+            tiled_render_requested: false,
+        }
+    }
+}
+
+impl OutputNode {
+    /// true if the node renders to a file rather than showing a live preview
+    pub fn is_export(&self) -> bool {
+        !matches!(self.output, OutputType::LiveDisplay { .. })
+    }
+
+    /// flips the "live render" checkbox, does nothing if the node is not set to live output
+    pub fn toggle_live_render(&mut self) {
+        if let OutputType::LiveDisplay { ref mut run, .. } = self.output {
+            *run = !*run;
         }
     }
 }
 
 impl MyNode for OutputNode {
+    /// the output only shows and saves its input, so on the cpu it just checks that there is one
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -204,6 +230,16 @@ impl MyNode for OutputNode {
         NodeType::Output
     }
 
+    fn unload(&mut self, renderer: &mut Renderer) {
+        if let Some(id) = self.texture_id.take() {
+            renderer.textures().remove(id);
+        }
+    }
+
+    fn tile_margin(&self) -> Option<u32> {
+        Some(0)
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -259,6 +295,13 @@ impl MyNode for OutputNode {
                 if ui.button("render") {
                     self.run_with_time.push(ui.time());
                 };
+                ui.same_line();
+                if ui.button("render tiled") {
+                    self.tiled_render_requested = true;
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("renders images that are too big for the gpu one piece at a time and saves the result.\nonly works with load image and nodes that don't change the size of the image");
+                }
                 if let Some(image_id) = &self.texture_id {
                     if ui.button("download") {
                         let user_dirs = UserDirs::new();
@@ -436,6 +479,39 @@ impl MyNode for OutputNode {
         self
     }
 
+    fn actions(&self) -> Vec<String> {
+        match self.output {
+            OutputType::LiveDisplay { .. } => vec![
+                "render single frame".to_owned(),
+                "toggle live render".to_owned(),
+            ],
+            OutputType::RenderImage => vec!["render".to_owned()],
+            OutputType::RenderGif { record: false, .. } => vec!["record gif".to_owned()],
+            OutputType::RenderGif { .. } => vec![],
+        }
+    }
+
+    fn trigger_action(&mut self, action: &str, time: f64) {
+        match action {
+            "render single frame" | "render" => self.run_with_time.push(time),
+            "toggle live render" => self.toggle_live_render(),
+            "record gif" => {
+                if let OutputType::RenderGif {
+                    ref mut record,
+                    ref mut frames,
+                    start_time,
+                    ..
+                } = self.output
+                {
+                    *record = true;
+                    *frames = vec![];
+                    self.run_with_time.push(start_time as f64);
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn render_in_node(&self, ui: &Ui, ui_scale: f32,  renderer: &mut Renderer, params: &mut RenderNodesParams) {
             params
                 .time_list
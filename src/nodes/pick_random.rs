@@ -8,12 +8,26 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::CpuStorage,
     node::{random_id, MyNode},
     storage::Storage,
 };
 
 use super::node_enum::NodeType;
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            void main() {
+            color = texture(tex, v_tex_coords);
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct RandomInputNode {
     x: f32,
@@ -33,6 +47,26 @@ impl Default for RandomInputNode {
     }
 }
 
+impl RandomInputNode {
+    /// the index of a random input, inputs with bigger weights are picked more often
+    fn pick(&self) -> anyhow::Result<usize> {
+        if self.weights.len() < 1 {
+            return Err(anyhow!("not enough weights"));
+        }
+        let total1 = self.weights.iter().sum::<f32>() * fastrand::f32();
+        let mut total2 = 0.0;
+        let mut index = 0;
+        for (i, v) in self.weights.iter().enumerate() {
+            total2 += v;
+            if total2 > total1 {
+                index = i;
+                break;
+            }
+        }
+        return Ok(index);
+    }
+}
+
 impl MyNode for RandomInputNode {
     fn path(&self) -> Vec<&str> {
         vec!["Image", "msc"]
@@ -65,6 +99,10 @@ impl MyNode for RandomInputNode {
         NodeType::RandomInput
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -113,27 +151,20 @@ impl MyNode for RandomInputNode {
         }
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let index = self.pick()?;
+        let texture = storage.input(&map, &self.input_id(&self.inputs()[index]))?.clone();
+        storage.set_texture(self.output_id(&self.outputs()[0]), texture);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
         map: HashMap<String, String>,
         _renderer: &mut Renderer,
     ) -> anyhow::Result<()> {
-        if self.weights.len() < 1 {
-            return Err(anyhow!("not enough weights"));
-        }
-        // let num: f32 = fastrand::f32();
-        let total1 = self.weights.iter().sum::<f32>() * fastrand::f32();
-        let mut total2 = 0.0;
-        let mut index = 0;
-        for (i, v) in self.weights.iter().enumerate() {
-            total2 += v;
-            if total2 > total1 {
-                index = i;
-                break;
-            }
-        }
-
+        let index = self.pick()?;
         let input_id = self.input_id(&self.inputs()[index]);
         let output_id =self.output_id(&self.outputs()[0]);
         let get_output = match map.get(&input_id) {
@@ -141,18 +172,7 @@ impl MyNode for RandomInputNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            void main() {
-            color = texture(tex, v_tex_coords);
-            }
-            "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
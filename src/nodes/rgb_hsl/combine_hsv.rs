@@ -8,11 +8,14 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, hsv2rgb, rgb2hsv, CpuStorage, CpuTexture},
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = include_str!("combine_hsv.glsl");
+
 #[derive(Savefile)]
 pub struct CombineHsvNode {
     x: f32,
@@ -64,6 +67,10 @@ impl MyNode for CombineHsvNode {
         NodeType::CombineHsv
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn as_any_mut(&mut self) -> &mut dyn Any {
@@ -104,6 +111,26 @@ impl MyNode for CombineHsvNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let inputs = self
+            .inputs()
+            .iter()
+            .map(|a| storage.input(&map, &self.input_id(a)))
+            .collect::<anyhow::Result<Vec<&CpuTexture>>>()?;
+        let raw = self.raw;
+        let output = draw(inputs[0].dimensions(), &inputs, |_, c| {
+            let rgb = |i: usize| [c[i][0], c[i][1], c[i][2]];
+            let color = if raw {
+                hsv2rgb([c[0][0], c[1][1], c[2][2]])
+            } else {
+                hsv2rgb([rgb2hsv(rgb(0))[0], rgb2hsv(rgb(1))[1], rgb2hsv(rgb(2))[2]])
+            };
+            [color[0], color[1], color[2], c[3][3]]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -127,7 +154,7 @@ impl MyNode for CombineHsvNode {
             return Err(anyhow!("missing input"));
         }
 
-        let fragment_shader_src = include_str!("combine_hsv.glsl");
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(&get_outputs[0]) {
             Some(a) => (a.width(), a.height()),
@@ -8,11 +8,36 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, CpuStorage, CpuTexture},
+    fuse::PixelCode,
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex_red;
+            uniform sampler2D tex_green;
+            uniform sampler2D tex_blue;
+            uniform sampler2D tex_alpha;
+
+
+            void main() {
+            color = vec4(
+            texture(tex_red, v_tex_coords).r,
+            texture(tex_green, v_tex_coords).g,
+            texture(tex_blue, v_tex_coords).b,
+            texture(tex_alpha, v_tex_coords).a
+            );
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct CombineRgbaNode {
     x: f32,
@@ -61,6 +86,18 @@ impl MyNode for CombineRgbaNode {
         NodeType::CombineRgba
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
+    fn pixel_code(&self) -> Option<PixelCode> {
+        Some(PixelCode {
+            code: "$out_0 = vec4($in_0.r, $in_1.g, $in_2.b, $in_3.a);".to_string(),
+            functions: String::new(),
+            uniforms: vec![],
+        })
+    }
+
      
 
     fn as_any_mut(&mut self) -> &mut dyn Any {
@@ -93,6 +130,19 @@ impl MyNode for CombineRgbaNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let inputs = self
+            .inputs()
+            .iter()
+            .map(|a| storage.input(&map, &self.input_id(a)))
+            .collect::<anyhow::Result<Vec<&CpuTexture>>>()?;
+        let output = draw(inputs[0].dimensions(), &inputs, |_, c| {
+            [c[0][0], c[1][1], c[2][2], c[3][3]]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -116,28 +166,7 @@ impl MyNode for CombineRgbaNode {
             return Err(anyhow!("missing input"));
         }
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex_red;
-            uniform sampler2D tex_green;
-            uniform sampler2D tex_blue;
-            uniform sampler2D tex_alpha;
-
-
-            void main() {
-            color = vec4(
-            texture(tex_red, v_tex_coords).r,
-            texture(tex_green, v_tex_coords).g,
-            texture(tex_blue, v_tex_coords).b,
-            texture(tex_alpha, v_tex_coords).a
-            );
-            }
-            "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(&get_outputs[0]) {
             Some(a) => (a.width(), a.height()),
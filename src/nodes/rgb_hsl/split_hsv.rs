@@ -8,11 +8,14 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, hsv2rgb, rgb2hsv, CpuStorage, CpuTexture},
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = include_str!("split_hsv.glsl");
+
 #[derive(Savefile)]
 pub struct SplitHsvNode {
     x: f32,
@@ -64,6 +67,10 @@ impl MyNode for SplitHsvNode {
         NodeType::SplitHsv
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -102,6 +109,42 @@ impl MyNode for SplitHsvNode {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let raw = self.raw;
+        let textures = (0..4)
+            .map(|i| {
+                draw(input.dimensions(), &[input], |_, c| {
+                    let px = c[0];
+                    let mut hsv = rgb2hsv([px[0], px[1], px[2]]);
+                    if i == 3 {
+                        return [px[3]; 4];
+                    }
+                    if raw {
+                        return [hsv[i], hsv[i], hsv[i], px[3]];
+                    }
+                    match i {
+                        0 => {
+                            hsv[1] = 1.0;
+                            hsv[2] = 1.0;
+                        }
+                        1 => hsv[2] = 1.0,
+                        _ => {
+                            hsv[0] = 0.0;
+                            hsv[1] = 0.0;
+                        }
+                    }
+                    let rgb = hsv2rgb(hsv);
+                    [rgb[0], rgb[1], rgb[2], px[3]]
+                })
+            })
+            .collect::<Vec<CpuTexture>>();
+        for (output, texture) in self.outputs().iter().zip(textures) {
+            storage.set_texture(self.output_id(output), texture);
+        }
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -119,7 +162,7 @@ impl MyNode for SplitHsvNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = include_str!("split_hsv.glsl");
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
@@ -8,11 +8,41 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, CpuStorage, CpuTexture},
+    fuse::PixelCode,
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            uniform int rgba_index;
+
+            void main() {
+                
+            float channel = 0.0;
+            vec4 px = texture(tex, v_tex_coords);
+            if (rgba_index == 0) {
+                channel = px.r;
+                }else if (rgba_index == 1) {
+                channel = px.g;
+                }else if (rgba_index == 2) {
+                channel = px.b;
+                }else if (rgba_index == 3) {
+                channel = px.a;
+            }
+
+            color = vec4(channel);
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct SplitRgbaNode {
     x: f32,
@@ -61,6 +91,24 @@ impl MyNode for SplitRgbaNode {
         NodeType::SplitRgba
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
+    fn pixel_code(&self) -> Option<PixelCode> {
+        Some(PixelCode {
+            code: r#"
+                $out_0 = vec4($in_0.r);
+                $out_1 = vec4($in_0.g);
+                $out_2 = vec4($in_0.b);
+                $out_3 = vec4($in_0.a);
+            "#
+            .to_string(),
+            functions: String::new(),
+            uniforms: vec![],
+        })
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -91,6 +139,17 @@ impl MyNode for SplitRgbaNode {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let textures = (0..4)
+            .map(|i| draw(input.dimensions(), &[input], |_, c| [c[0][i]; 4]))
+            .collect::<Vec<CpuTexture>>();
+        for (output, texture) in self.outputs().iter().zip(textures) {
+            storage.set_texture(self.output_id(output), texture);
+        }
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -108,33 +167,7 @@ impl MyNode for SplitRgbaNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            uniform int rgba_index;
-
-            void main() {
-                
-            float channel = 0.0;
-            vec4 px = texture(tex, v_tex_coords);
-            if (rgba_index == 0) {
-                channel = px.r;
-                }else if (rgba_index == 1) {
-                channel = px.g;
-                }else if (rgba_index == 2) {
-                channel = px.b;
-                }else if (rgba_index == 3) {
-                channel = px.a;
-            }
-
-            color = vec4(channel);
-            }
-            "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
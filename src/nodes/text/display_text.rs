@@ -7,11 +7,25 @@ use anyhow::anyhow;
 use std::{any::Any, collections::HashMap, path::PathBuf};
 
 use crate::{
+    cpu::CpuStorage,
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = r#"
+
+            #version 140
+
+            in vec2 v_tex_coords;
+            out vec4 color;
+
+            uniform sampler2D tex;
+            void main() {
+            color = texture(tex, v_tex_coords);
+            }
+            "#;
+
 #[derive(Savefile)]
 pub struct DisplayTextNode {
     x: f32,
@@ -64,6 +78,10 @@ impl MyNode for DisplayTextNode {
         NodeType::DisplayText
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -87,6 +105,12 @@ impl MyNode for DisplayTextNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let texture = storage.input(&map, &self.input_id(&self.inputs()[0]))?.clone();
+        storage.set_texture(self.output_id(&self.outputs()[0]), texture);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -100,18 +124,7 @@ impl MyNode for DisplayTextNode {
             None => return  Err(anyhow!("missing input")),
         };
 
-        let fragment_shader_src = r#"
-
-            #version 140
-
-            in vec2 v_tex_coords;
-            out vec4 color;
-
-            uniform sampler2D tex;
-            void main() {
-            color = texture(tex, v_tex_coords);
-            }
-            "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
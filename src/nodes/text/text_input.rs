@@ -7,6 +7,7 @@ use crate::generic_node_info::GenericNodeInfo;
 
 
 use crate::{
+    cpu::CpuStorage,
     node::{random_id, MyNode},
     nodes::node_enum,
     storage::Storage,
@@ -95,6 +96,11 @@ impl MyNode for TextInputNode {
             .build();
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, _map: HashMap<String, String>) -> anyhow::Result<()> {
+        storage.set_text(self.output_id(&self.outputs()[0]), self.text.clone());
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
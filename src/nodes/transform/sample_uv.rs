@@ -13,11 +13,27 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, sample, CpuStorage},
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
 };
 
+const FRAGMENT_SHADER: &str = r#"
+
+        #version 140
+
+        in vec2 v_tex_coords;
+        out vec4 color;
+
+        uniform sampler2D tex;
+        uniform sampler2D uvc;
+        void main() {
+        vec4 uv = texture(uvc, v_tex_coords);
+        color = textureLod(tex, vec2(uv.r, uv.g), 0.0);
+        }
+        "#;
+
 #[derive(Savefile)]
 pub struct SampleUvNode {
     x: f32,
@@ -68,6 +84,10 @@ impl MyNode for SampleUvNode {
         NodeType::SampleUV
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -91,6 +111,17 @@ impl MyNode for SampleUvNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        if !map.contains_key(&self.input_id(&self.inputs()[1])) {
+            return Ok(());
+        }
+        let texture = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let uv_texture = storage.input(&map, &self.input_id(&self.inputs()[1]))?;
+        let output = draw(texture.dimensions(), &[uv_texture], |_, c| sample(texture, [c[0][0], c[0][1]]));
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -114,20 +145,7 @@ impl MyNode for SampleUvNode {
             None => return Err(anyhow!("cannot find input texture")),
         };
 
-        let fragment_shader_src = r#"
-
-        #version 140
-
-        in vec2 v_tex_coords;
-        out vec4 color;
-
-        uniform sampler2D tex;
-        uniform sampler2D uvc;
-        void main() {
-        vec4 uv = texture(uvc, v_tex_coords);
-        color = texture2DLod(tex, vec2(uv.r, uv.g), 0.0);
-        }
-        "#;
+        let fragment_shader_src = FRAGMENT_SHADER;
                 storage
             .gen_frag_shader(fragment_shader_src.to_string())
             .ok_or(anyhow!("failed to compile shader"))?;
@@ -3,6 +3,7 @@ use std::{any::Any, collections::HashMap, path::PathBuf};
 use glium::{
     uniforms::MagnifySamplerFilter, BlitTarget, Rect, Surface,
 };
+use image::Rgba;
 use imgui_glium_renderer::Renderer;
 use savefile::{save_file, SavefileError};
 use crate::generic_node_info::GenericNodeInfo;
@@ -12,6 +13,7 @@ use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use crate::{
+    cpu::{sample, CpuStorage, CpuTexture},
     node::{random_id, MyNode},
     nodes::node_enum::NodeType,
     storage::Storage,
@@ -66,6 +68,30 @@ impl Default for ScaleNode {
         }
     }
 }
+impl ScaleNode {
+    /// the size the input is scaled to, `preview_scale` shrinks sizes given in pixels
+    fn output_size(&mut self, input_size: (u32, u32), preview_scale: f32) -> (u32, u32) {
+        self.og_size = input_size;
+        if self.use_percent {
+            // round evan to annoy anatol
+            self.target_size = (
+                (self.og_size.0 as f32 * self.target_percent / 100.0)
+                    .round_ties_even()
+                    .max(1.0) as u32,
+                (self.og_size.1 as f32 * self.target_percent / 100.0)
+                    .round_ties_even()
+                    .max(1.0) as u32,
+            );
+            return self.target_size;
+        }
+        // a size in pixels is shrunk along with the preview
+        return (
+            ((self.target_size.0 as f32 * preview_scale).round() as u32).max(1),
+            ((self.target_size.1 as f32 * preview_scale).round() as u32).max(1),
+        );
+    }
+}
+
 impl MyNode for ScaleNode {
     fn path(&self) -> Vec<&str> {
         vec!["Image", "Transform"]
@@ -161,24 +187,38 @@ impl MyNode for ScaleNode {
         });
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let texture = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let (width, height) = texture.dimensions();
+        let output_size = self.output_size((width, height), 1.0);
+        // blitting clamps to the edge instead of repeating. with nearest filtering, pixels whose centre
+        // is exactly on the edge between two texels can get either one from the gpu
+        let clamp = |v: f32, size: u32| v.clamp(0.5 / size as f32, 1.0 - 0.5 / size as f32);
+        let output = CpuTexture::from_fn(output_size.0, output_size.1, |x, y| {
+            let uv = [
+                (x as f32 + 0.5) / output_size.0 as f32,
+                (y as f32 + 0.5) / output_size.1 as f32,
+            ];
+            Rgba(match self.filter {
+                MyFilterType::Linear => sample(texture, [clamp(uv[0], width), clamp(uv[1], height)]),
+                MyFilterType::Nearest => texture
+                    .get_pixel(
+                        ((uv[0] * width as f32) as u32).min(width - 1),
+                        ((uv[1] * height as f32) as u32).min(height - 1),
+                    )
+                    .0,
+            })
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
         map: HashMap<String, String>,
         _renderer: &mut Renderer,
     ) -> anyhow::Result<()> {
-        if self.use_percent {
-            // round evan to annoy anatol
-            self.target_size = (
-                (self.og_size.0 as f32 * self.target_percent / 100.0)
-                    .round_ties_even()
-                    .max(1.0) as u32,
-                (self.og_size.1 as f32 * self.target_percent / 100.0)
-                    .round_ties_even()
-                    .max(1.0) as u32,
-            );
-        }
-
         let input_id = self.input_id(&self.inputs()[0]);
         let output_id =self.output_id(&self.outputs()[0]);;
         let get_output = match map.get(&input_id) {
@@ -191,9 +231,8 @@ impl MyNode for ScaleNode {
             None => return Err(anyhow!("cannot find input texture")),
         };
 
-        self.og_size = texture_size;
-
-        storage.create_and_set_texture(self.target_size.0, self.target_size.1, output_id.clone());
+        let output_size = self.output_size(texture_size, storage.preview_scale);
+        storage.create_and_set_texture(output_size.0, output_size.1, output_id.clone());
 
         let texture: &glium::Texture2d = match storage.get_texture(get_output) {
             Some(a) => a,
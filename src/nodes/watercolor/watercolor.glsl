@@ -44,7 +44,7 @@ vec4 diff_g()
     vec2 pos=v_tex_coords;
     float xs=(u_resolution.x);
     float ys=(u_resolution.y);
-    float x,y,xx,yy,rr=r*r,dx,dy,w,w0;
+    float x=0.0,y=0.0,xx,yy,rr=r*r,dx,dy,w,w0;
     w0=.3780/pow(r,1.975);
     float weight_total=0.;
     vec2 p;
@@ -64,7 +64,7 @@ vec4 diff_g()
             {
                 w=w0*exp((-xx-yy)/(2.*rr));
                 weight_total+=w;
-                col+=texture2D(tex,p)*w;
+                col+=texture(tex,p)*w;
             }
         }
     }
@@ -95,7 +95,7 @@ vec4 diff_g()
             {
                 w=w0*exp((-xx-yy)/(2.*rr));
                 weight_total+=w;
-                col+=texture2D(tex,p)*w;
+                col+=texture(tex,p)*w;
             }
         }
     }
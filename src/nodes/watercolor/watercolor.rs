@@ -9,11 +9,14 @@ use anyhow::anyhow;
 
 
 use crate::{
+    cpu::{draw, hsv2rgb, rgb2hsv, CpuStorage, CpuTexture},
     node::{random_id, MyNode},
-    nodes::node_enum::NodeType,
+    nodes::{basic_shader_nodes::difference_of_gaussians::difference_of_gaussians, node_enum::NodeType},
     storage::Storage,
 };
-use image::EncodableLayout;
+use image::{imageops::FilterType, DynamicImage, EncodableLayout};
+
+const FRAGMENT_SHADER: &str = include_str!("watercolor.glsl");
 
 #[derive(Savefile)]
 pub struct WaterColorNode {
@@ -25,6 +28,21 @@ pub struct WaterColorNode {
     #[savefile_ignore]
     #[savefile_introspect_ignore]
     paper_texture: Option<Texture2d>,
+    /// the paper for `run_cpu`, scaled to the size of the last input
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    cpu_paper: Option<CpuTexture>,
+}
+
+/// the paper texture with its rows from the bottom up
+fn paper_image() -> image::RgbaImage {
+    return image::load_from_memory_with_format(
+        include_bytes!("textured-paper-background-tile-with-bluish-tint.jpg"),
+        image::ImageFormat::Jpeg,
+    )
+    .unwrap()
+    .flipv()
+    .into_rgba8();
 }
 
 impl Default for WaterColorNode {
@@ -36,6 +54,7 @@ impl Default for WaterColorNode {
             scale: 1.0,
             size: (1, 1),
             paper_texture: None,
+            cpu_paper: None,
         }
     }
 }
@@ -73,6 +92,10 @@ impl MyNode for WaterColorNode {
         NodeType::WaterColor
     }
 
+    fn fragment_shaders(&self) -> Vec<String> {
+        vec![FRAGMENT_SHADER.to_string()]
+    }
+
      
 
     fn save(&self, path: PathBuf) -> Result<(), SavefileError> {
@@ -96,6 +119,31 @@ impl MyNode for WaterColorNode {
         self.y = y;
     }
 
+    fn run_cpu(&mut self, storage: &mut CpuStorage, map: HashMap<String, String>) -> anyhow::Result<()> {
+        let input = storage.input(&map, &self.input_id(&self.inputs()[0]))?;
+        let size = input.dimensions();
+        // opengl reads the paper from its mipmaps, which is close to averaging it down to the size of the output
+        if self.cpu_paper.as_ref().map(|a| a.dimensions()) != Some(size) {
+            let paper = image::imageops::resize(&paper_image(), size.0, size.1, FilterType::Triangle);
+            self.cpu_paper = Some(DynamicImage::ImageRgba8(paper).into_rgba32f());
+        }
+        let paper = self.cpu_paper.as_ref().unwrap();
+        let output = draw(size, &[input, paper], |uv, c| {
+            let (px, px2) = (c[0], c[1]);
+            let gauss = 1.0 - difference_of_gaussians(input, uv, [1.4, 30.0], 0.5, Some((0.1, 10.0)), true)[0];
+            let hsv = rgb2hsv([px[0], px[1], px[2]]);
+            let hsv_paper = rgb2hsv([px2[0], px2[1], px2[2]]);
+            let col = hsv2rgb([
+                hsv[0],
+                hsv[1].clamp(hsv[2] * 0.1, hsv[2] * 1.3),
+                (hsv[2] * 0.5 + hsv_paper[2] * 0.3 + gauss * 0.2).clamp(0.0, 1.0),
+            ]);
+            [col[0], col[1], col[2], px[3]]
+        });
+        storage.set_texture(self.output_id(&self.outputs()[0]), output);
+        return Ok(());
+    }
+
     fn run(
         &mut self,
         storage: &mut Storage,
@@ -110,13 +158,7 @@ impl MyNode for WaterColorNode {
         };
 
         if self.paper_texture.is_none() {
-            let image = image::load_from_memory_with_format(
-                include_bytes!("textured-paper-background-tile-with-bluish-tint.jpg"),
-                image::ImageFormat::Jpeg,
-            )
-            .unwrap()
-            .flipv()
-            .into_rgba8();
+            let image = paper_image();
 
             let not_texture = RawImage2d::from_raw_rgba(
                 image.as_bytes().to_vec(),
@@ -127,7 +169,7 @@ impl MyNode for WaterColorNode {
             self.paper_texture = Some(texture);
         }
 
-        let fragment_shader_src = include_str!("watercolor.glsl");
+        let fragment_shader_src = FRAGMENT_SHADER;
 
         let texture_size: (u32, u32) = match storage.get_texture(get_output) {
             Some(a) => (a.width(), a.height()),
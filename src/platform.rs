@@ -0,0 +1,389 @@
+//! code that only works on some operating systems
+
+use std::{env::current_exe, process::exit};
+
+use log::info;
+
+/// closes reanimator and opens it again with `args`, as admin if `admin` is true.
+/// TODO: make this cross platform, `admin` is ignored outside of windows
+#[cfg(target_os = "windows")]
+pub fn relaunch_program(admin: bool, args: impl Into<String>) {
+    use std::os::windows::process::CommandExt;
+    use winapi::um::winbase::CREATE_NO_WINDOW;
+
+    let command = if admin {
+        format!(
+            "Start-Process \"{}\" -ArgumentList \"{}\" -Verb RunAs",
+            current_exe().unwrap().as_os_str().to_str().unwrap(),
+            args.into()
+        )
+    } else {
+        format!(
+            "Start-Process \"{}\" -ArgumentList \"{}\"",
+            current_exe().unwrap().as_os_str().to_str().unwrap(),
+            args.into()
+        )
+    }.replace("-ArgumentList \"\"", "");
+    info!("{command}");
+
+    // win_msgbox::show::<Okay>(&format!("command: {}", command));
+
+    std::process::Command::new("powershell")
+        .arg(command)
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()
+        .expect("cannot spawn command");
+    exit(0);
+}
+
+/// closes reanimator and opens it again with `args`, as admin if `admin` is true.
+/// TODO: make this cross platform, `admin` is ignored outside of windows
+#[cfg(not(target_os = "windows"))]
+pub fn relaunch_program(_admin: bool, args: impl Into<String>) {
+    let args: String = args.into();
+    info!("relaunching with {args}");
+    std::process::Command::new(current_exe().unwrap())
+        .args(args.split_whitespace())
+        .spawn()
+        .expect("cannot spawn command");
+    exit(0);
+}
+
+/// asks windows to run reanimator on the high performance gpu
+#[cfg(target_os = "windows")]
+pub fn set_gpu_pref() -> anyhow::Result<()> {
+    use std::path::Path;
+    use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let path = Path::new("Software")
+        .join("Microsoft")
+        .join("DirectX")
+        .join("UserGpuPreferences")
+        ;
+    let (key, disp) = hkcu.create_subkey(&path)?;
+        match disp {
+            winreg::enums::RegDisposition::REG_CREATED_NEW_KEY => info!("A new key has been created"),
+            winreg::enums::RegDisposition::REG_OPENED_EXISTING_KEY => info!("An existing key has been opened"),
+        }
+
+        key.set_value(current_exe()?.display().to_string(), &"GpuPreference=2;")?;
+        // let value = key.get_value::<String, _>(current_exe()?.display());
+        // println!("{:?} {:?}", current_exe()?.display().to_string(), value);
+    return Ok(());
+}
+
+/// asks windows to run reanimator on the high performance gpu, other systems don't have a setting for this
+#[cfg(not(target_os = "windows"))]
+pub fn set_gpu_pref() -> anyhow::Result<()> {
+    return Ok(());
+}
+
+/// makes reanimator the program that opens .repj files, windows asks for admin to run the generated script
+#[cfg(target_os = "windows")]
+pub fn set_as_default_for_filetype() {
+    use std::{os::windows::process::CommandExt, process::Command};
+    use winapi::um::winbase::CREATE_NO_WINDOW;
+
+    let command = format!(
+        "ftype \"ReAnimator Project\"=\"{}\"  \"%1\" && assoc .repj=\"ReAnimator Project\"",
+        current_exe().unwrap().as_os_str().to_str().unwrap()
+    );
+    let path = current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .join("ReAnimator Setup Util.bat");
+    let _ = std::fs::write(&path, command);
+    let command = format!(
+        "Start-Process -FilePath \"{}\" -Verb RunAs",
+        path.display()
+    );
+    info!("{command}");
+    let _res = Command::new("powershell")
+        .arg(command)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+}
+
+/// makes reanimator the program that opens .repj files, without asking for admin
+#[cfg(target_os = "windows")]
+pub fn set_as_default_for_filetype2() {
+    use std::{os::windows::process::CommandExt, process::Command};
+    use winapi::um::winbase::CREATE_NO_WINDOW;
+
+    let command = format!(
+        "ftype \"ReAnimator Project\"=\"{}\"  \"%1\" && assoc .repj=\"ReAnimator Project\"",
+        current_exe().unwrap().as_os_str().to_str().unwrap()
+    );
+    info!("{command}");
+    let _res = Command::new("cmd")
+        .raw_arg("/C ".to_owned() + &command)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output();
+}
+
+/// TODO: add support for linux, file associations are only set on windows for now
+#[cfg(not(target_os = "windows"))]
+pub fn set_as_default_for_filetype() {
+    info!("setting the default program for .repj files is only supported on windows");
+}
+
+/// TODO: add support for linux, file associations are only set on windows for now
+#[cfg(not(target_os = "windows"))]
+pub fn set_as_default_for_filetype2() {
+    set_as_default_for_filetype();
+}
+
+/// pixels from the screen or a window, 4 bytes per pixel
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// the handle of the first window with a title matching `pattern`, or 0 if there isn't one.
+/// when `skip_self` is set reanimator's own windows are ignored
+#[cfg(target_os = "windows")]
+pub fn find_window(pattern: &str, skip_self: bool) -> isize {
+    let Ok(re) = regex::Regex::new(pattern) else {
+        return 0;
+    };
+    let Ok(windows) = win_screenshot::utils::window_list() else {
+        return 0;
+    };
+    return windows
+        .iter()
+        .find(|i| re.is_match(&i.window_name) && !(skip_self && i.window_name.contains("ReAnimator")))
+        .map(|i| i.hwnd)
+        .unwrap_or(0);
+}
+
+/// there is no way to list windows outside of windows yet, so this is always 0
+#[cfg(not(target_os = "windows"))]
+pub fn find_window(_pattern: &str, _skip_self: bool) -> isize {
+    return 0;
+}
+
+#[cfg(target_os = "windows")]
+pub fn capture_display() -> anyhow::Result<Screenshot> {
+    let buf = win_screenshot::prelude::capture_display()
+        .map_err(|e| anyhow::anyhow!("failed to capture display: {e:?}"))?;
+    return Ok(Screenshot {
+        width: buf.width,
+        height: buf.height,
+        pixels: buf.pixels,
+    });
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn capture_display() -> anyhow::Result<Screenshot> {
+    return Err(anyhow::anyhow!("capturing the display is only supported on windows"));
+}
+
+#[cfg(target_os = "windows")]
+pub fn capture_window(hwnd: isize) -> anyhow::Result<Screenshot> {
+    let buf = win_screenshot::prelude::capture_window(hwnd)
+        .map_err(|e| anyhow::anyhow!("failed to capture window: {e}"))?;
+    return Ok(Screenshot {
+        width: buf.width,
+        height: buf.height,
+        pixels: buf.pixels,
+    });
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn capture_window(_hwnd: isize) -> anyhow::Result<Screenshot> {
+    return Err(anyhow::anyhow!("capturing a window is only supported on windows"));
+}
+
+/// where the mouse is on the screen
+#[cfg(target_os = "windows")]
+pub fn cursor_pos() -> Option<(i32, i32)> {
+    use windows::Win32::{Foundation::POINT, UI::WindowsAndMessaging::GetCursorPos};
+
+    let mut point = POINT::default();
+    unsafe { GetCursorPos(&mut point) }.ok()?;
+    return Some((point.x, point.y));
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn cursor_pos() -> Option<(i32, i32)> {
+    return None;
+}
+
+/// the area inside a window's border, as (left, top) on the screen and (width, height)
+#[cfg(target_os = "windows")]
+pub fn window_client_rect(hwnd: isize) -> anyhow::Result<((i32, i32), (i32, i32))> {
+    use windows::Win32::{
+        Foundation::{HWND, POINT, RECT},
+        Graphics::Gdi::ClientToScreen,
+        UI::WindowsAndMessaging::GetClientRect,
+    };
+
+    let mut rect = RECT::default();
+    unsafe {
+        if GetClientRect(HWND(hwnd as *mut _), &mut rect).is_err() {
+            return Err(anyhow::anyhow!("invalid window id"));
+        }
+        let mut point = POINT {
+            x: rect.left,
+            y: rect.top,
+        };
+        if !ClientToScreen(HWND(hwnd as *mut _), &mut point).as_bool() {
+            return Err(anyhow::anyhow!("failed 'ClientToScreen' conversion, likely because of an invalid window id"));
+        }
+        return Ok(((point.x, point.y), (rect.right, rect.bottom)));
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn window_client_rect(_hwnd: isize) -> anyhow::Result<((i32, i32), (i32, i32))> {
+    return Err(anyhow::anyhow!("covering a window is only supported on windows"));
+}
+
+/// if the window is the one the user is using
+#[cfg(target_os = "windows")]
+pub fn is_foreground_window(hwnd: isize) -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
+
+    return unsafe { GetForegroundWindow().0 == hwnd as *mut _ };
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn is_foreground_window(_hwnd: isize) -> bool {
+    return false;
+}
+
+/// a camera opened with `open_webcams`
+pub struct Webcam {
+    #[cfg(target_os = "windows")]
+    device: escapi::Device,
+}
+
+#[cfg(target_os = "windows")]
+impl Webcam {
+    pub fn name(&self) -> String {
+        return self.device.name();
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        return (self.device.capture_width(), self.device.capture_height());
+    }
+
+    /// the next frame, 4 bytes per pixel
+    pub fn capture(&self) -> anyhow::Result<Vec<u8>> {
+        return match self.device.capture() {
+            Ok(a) => Ok(a.to_vec()),
+            Err(e) => Err(anyhow::anyhow!("failed to capture webcam: {e}")),
+        };
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+impl Webcam {
+    pub fn name(&self) -> String {
+        return String::new();
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        return (0, 0);
+    }
+
+    pub fn capture(&self) -> anyhow::Result<Vec<u8>> {
+        return Err(anyhow::anyhow!("webcams are only supported on windows"));
+    }
+}
+
+/// opens every camera, the ones that fail to open are errors so they keep their place in the list
+#[cfg(target_os = "windows")]
+pub fn open_webcams(size: (u32, u32), fps: u64) -> Vec<anyhow::Result<Webcam>> {
+    return (0..escapi::num_devices())
+        .map(|i| {
+            escapi::init(i, size.0, size.1, fps)
+                .map(|device| Webcam { device })
+                .map_err(|e| anyhow::anyhow!("{e}"))
+        })
+        .collect();
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn open_webcams(_size: (u32, u32), _fps: u64) -> Vec<anyhow::Result<Webcam>> {
+    return vec![];
+}
+
+/// an opengl context that isn't attached to a window, made with egl, for running graphs without the editor
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+pub fn headless_context() -> anyhow::Result<std::rc::Rc<glium::backend::Context>> {
+    use std::ffi::{c_void, CString};
+
+    use glium::glutin::{
+        api::egl::{context::PossiblyCurrentContext, device::Device, display::Display},
+        config::{ConfigSurfaceTypes, ConfigTemplateBuilder},
+        context::ContextAttributesBuilder,
+        prelude::*,
+    };
+
+    struct Headless {
+        context: PossiblyCurrentContext,
+        display: Display,
+    }
+
+    unsafe impl glium::backend::Backend for Headless {
+        fn swap_buffers(&self) -> Result<(), glium::SwapBuffersError> {
+            return Ok(());
+        }
+
+        unsafe fn get_proc_address(&self, symbol: &str) -> *const c_void {
+            let Ok(symbol) = CString::new(symbol) else {
+                return std::ptr::null();
+            };
+            return self.display.get_proc_address(&symbol);
+        }
+
+        /// there is no default framebuffer, nodes only draw into textures
+        fn get_framebuffer_dimensions(&self) -> (u32, u32) {
+            return (1, 1);
+        }
+
+        fn resize(&self, _new_size: (u32, u32)) {}
+
+        fn is_current(&self) -> bool {
+            return self.context.is_current();
+        }
+
+        unsafe fn make_current(&self) {
+            if let Err(e) = self.context.make_current_surfaceless() {
+                log::error!("failed to make the headless context current: {e}");
+            }
+        }
+    }
+
+    let device = Device::query_devices()
+        .map_err(|e| anyhow::anyhow!("failed to list egl devices: {e}"))?
+        .next()
+        .ok_or(anyhow::anyhow!("there are no egl devices"))?;
+    let display = unsafe { Display::with_device(&device, None) }
+        .map_err(|e| anyhow::anyhow!("failed to open egl device: {e}"))?;
+    let template = ConfigTemplateBuilder::new()
+        .with_surface_type(ConfigSurfaceTypes::empty())
+        .build();
+    let config = unsafe { display.find_configs(template) }?
+        .next()
+        .ok_or(anyhow::anyhow!("the egl device has no configs without a surface"))?;
+    let context = unsafe { display.create_context(&config, &ContextAttributesBuilder::new().build(None)) }?
+        .make_current_surfaceless()?;
+    let context = unsafe { glium::backend::Context::new(Headless { context, display }, true, Default::default()) }
+        .map_err(|e| anyhow::anyhow!("failed to create the opengl context: {e:?}"))?;
+    info!(
+        "headless context: {} {}",
+        context.get_opengl_renderer_string(),
+        context.get_opengl_version_string()
+    );
+    return Ok(context);
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+pub fn headless_context() -> anyhow::Result<std::rc::Rc<glium::backend::Context>> {
+    return Err(anyhow::anyhow!("running without a window needs egl, which isn't available on apple systems"));
+}
@@ -1,5 +1,5 @@
 use std::{
-    env::{self, current_exe}, fs, os::windows::process::CommandExt, process::{exit, Command}, thread::{self}
+    env::{self, current_exe}, process::exit, thread::{self}
 };
 
 use enum_to_string::ToJsonString;
@@ -14,10 +14,8 @@ use serde::Serialize;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-use winapi::um::winbase::CREATE_NO_WINDOW;
-
 use crate::{
-    relaunch_program, set_gpu_pref, support::{create_context, init_with_startup}, user_info::{UserSettings, USER_SETTINGS_SAVEFILE_VERSION}, widgets::{path_input}
+    platform::{relaunch_program, set_as_default_for_filetype2, set_gpu_pref}, support::{create_context, init_with_startup}, user_info::{UserSettings, USER_SETTINGS_SAVEFILE_VERSION}, widgets::{path_input}
 };
 
 #[derive(EnumIter, ToJsonString, Serialize)]
@@ -317,39 +315,6 @@ pub fn setup_popup(settings: &UserSettings) {
     exit(0);
 }
 
-/// TODO add support for linux
-pub fn set_as_default_for_filetype() {
-    let command = format!(
-        "ftype \"ReAnimator Project\"=\"{}\"  \"%1\" && assoc .repj=\"ReAnimator Project\"",
-        current_exe().unwrap().as_os_str().to_str().unwrap()
-    );
-    let path = current_exe()
-        .unwrap()
-        .parent()
-        .unwrap()
-        .join("ReAnimator Setup Util.bat");
-    let _ = fs::write(
-        current_exe()
-            .unwrap()
-            .parent()
-            .unwrap()
-            .join("ReAnimator Setup Util.bat"),
-        command,
-    );
-    // command = command.replace("\"", "\\`\"");
-    let _res = Command::new("powershell")
-        .arg(format!(
-            "Start-Process -FilePath \"{}\" -Verb RunAs",
-            path.display().to_string()
-        ))
-        .creation_flags(CREATE_NO_WINDOW)
-        .output();
-    println!(
-        "Start-Process -FilePath \"{}\" -Verb RunAs",
-        path.display().to_string()
-    );
-}
-
 pub fn spinner(ui: &Ui) -> String {
     let s = "⡀⡁⡂⡃⡄⡅⡆⡇⡈⡉⡊⡋⡌⡍⡎⡏⡐⡑⡒⡓⡔⡕⡖⡗⡘⡙⡚⡛⡜⡝⡞⡟⡠⡡⡢⡣⡤⡥⡦⡧⡨⡩⡪⡫⡬⡭⡮⡯⡰⡱⡲⡳⡴⡵⡶⡷⡸⡹⡺⡻⡼⡽⡾⡿⢀⢁⢂⢃⢄⢅⢆⢇⢈⢉⢊⢋⢌⢍⢎⢏⢐⢑⢒⢓⢔⢕⢖⢗⢘⢙⢚⢛⢜⢝⢞⢟⢠⢡⢢⢣⢤⢥⢦⢧⢨⢩⢪⢫⢬⢭⢮⢯⢰⢱⢲⢳⢴⢵⢶⢷⢸⢹⢺⢻⢼⢽⢾⢿⣀⣁⣂⣃⣄⣅⣆⣇⣈⣉⣊⣋⣌⣍⣎⣏⣐⣑⣒⣓⣔⣕⣖⣗⣘⣙⣚⣛⣜⣝⣞⣟⣠⣡⣢⣣⣤⣥⣦⣧⣨⣩⣪⣫⣬⣭⣮⣯⣰⣱⣲⣳⣴⣵⣶⣷⣸⣹⣺⣻⣼⣽⣾⣿";
     let index = ((ui.time() * 3.0) % s.chars().count() as f64).floor() as usize;
@@ -363,19 +328,6 @@ pub fn spinner(ui: &Ui) -> String {
         .to_owned();
 }
 
-pub fn set_as_default_for_filetype2() {
-    let command = format!(
-        "ftype \"ReAnimator Project\"=\"{}\"  \"%1\" && assoc .repj=\"ReAnimator Project\"",
-        current_exe().unwrap().as_os_str().to_str().unwrap()
-    );
-    info!("{command}");
-    let _res = Command::new("cmd")
-        .raw_arg("/C ".to_owned() + &command)
-        .creation_flags(CREATE_NO_WINDOW)
-        .output();
-}
-
-
 pub fn update() -> Result<(), Box<dyn (::std::error::Error)>> {
     info!("updating");
 
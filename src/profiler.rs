@@ -0,0 +1,252 @@
+use std::{collections::HashMap, time::Duration};
+
+use std::rc::Rc;
+
+use glium::{backend::Context, debug::TimestampQuery};
+use imgui::{TableColumnSetup, TableFlags, TableSortDirection, Ui};
+
+/// how many runs are kept for the history graphs
+const HISTORY_LENGTH: usize = 120;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NodeProfile {
+    pub cpu_time: Duration,
+    /// `None` if gpu timing is turned off or the driver doesn't support timestamp queries
+    pub gpu_time: Option<Duration>,
+    /// bytes of the textures `Storage::create_and_set_texture` allocated for the node, not counting ones reused from the pool
+    pub texture_bytes: u64,
+}
+
+pub struct NodeProfileHistory {
+    pub name: String,
+    pub samples: Vec<NodeProfile>,
+}
+
+impl NodeProfileHistory {
+    pub fn last(&self) -> NodeProfile {
+        self.samples.last().copied().unwrap_or_default()
+    }
+
+    pub fn average_cpu_time(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().map(|s| s.cpu_time).sum::<Duration>() / self.samples.len() as u32
+    }
+}
+
+/// a node that is currently being timed, the timestamps are read once the whole graph has run
+/// so that the cpu isn't stalled waiting on the gpu after every node
+pub struct NodeTimer {
+    id: String,
+    name: String,
+    cpu_start: std::time::Instant,
+    texture_bytes_start: u64,
+    gpu_start: Option<TimestampQuery>,
+}
+
+pub struct Profiler {
+    pub history: HashMap<String, NodeProfileHistory>,
+    /// timestamp queries can be slow on some drivers so they are only used when asked for
+    pub gpu_timing: bool,
+    pub paused: bool,
+    sort_column: usize,
+    sort_ascending: bool,
+    pending: Vec<(NodeTimer, Duration, u64, Option<TimestampQuery>)>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler {
+            history: HashMap::new(),
+            gpu_timing: false,
+            paused: false,
+            sort_column: 1,
+            sort_ascending: false,
+            pending: vec![],
+        }
+    }
+}
+
+impl Profiler {
+    pub fn start(
+        &self,
+        id: &str,
+        name: String,
+        texture_bytes: u64,
+        display: &Rc<Context>,
+    ) -> NodeTimer {
+        NodeTimer {
+            id: id.to_owned(),
+            name,
+            cpu_start: std::time::Instant::now(),
+            texture_bytes_start: texture_bytes,
+            gpu_start: if self.gpu_timing {
+                TimestampQuery::new(display)
+            } else {
+                None
+            },
+        }
+    }
+
+    /// returns the cpu time the node took
+    pub fn stop(
+        &mut self,
+        timer: NodeTimer,
+        texture_bytes: u64,
+        display: &Rc<Context>,
+    ) -> Duration {
+        let cpu_time = timer.cpu_start.elapsed();
+        let gpu_end = if timer.gpu_start.is_some() {
+            TimestampQuery::new(display)
+        } else {
+            None
+        };
+        let bytes = texture_bytes.saturating_sub(timer.texture_bytes_start);
+        self.pending.push((timer, cpu_time, bytes, gpu_end));
+        return cpu_time;
+    }
+
+    /// forgets a deleted node's history
+    pub fn remove_node(&mut self, id: &str) {
+        self.history.remove(id);
+        self.pending.retain(|(timer, ..)| timer.id != id);
+    }
+
+    /// reads the gpu timestamps of every node that ran and adds them to the history
+    pub fn finish_run(&mut self) {
+        for (timer, cpu_time, texture_bytes, gpu_end) in self.pending.drain(..) {
+            if self.paused {
+                continue;
+            }
+            let gpu_time = match (timer.gpu_start, gpu_end) {
+                (Some(start), Some(end)) => {
+                    Some(Duration::from_nanos(end.get().saturating_sub(start.get())))
+                }
+                _ => None,
+            };
+            let history = self
+                .history
+                .entry(timer.id)
+                .or_insert_with(|| NodeProfileHistory {
+                    name: timer.name.clone(),
+                    samples: vec![],
+                });
+            history.name = timer.name;
+            history.samples.push(NodeProfile {
+                cpu_time,
+                gpu_time,
+                texture_bytes,
+            });
+            if history.samples.len() > HISTORY_LENGTH {
+                history.samples.remove(0);
+            }
+        }
+    }
+
+    pub fn render(&mut self, ui: &Ui) {
+        ui.checkbox("gpu timing", &mut self.gpu_timing);
+        if ui.is_item_hovered() {
+            ui.tooltip_text("uses timestamp queries, this can slow down rendering");
+        }
+        ui.same_line();
+        ui.checkbox("pause", &mut self.paused);
+        ui.same_line();
+        if ui.button("clear") {
+            self.history.clear();
+        }
+
+        let Some(_table) = ui.begin_table_with_flags(
+            "profiler",
+            5,
+            TableFlags::SORTABLE
+                | TableFlags::RESIZABLE
+                | TableFlags::ROW_BG
+                | TableFlags::BORDERS_INNER_V,
+        ) else {
+            return;
+        };
+
+        for name in ["node", "cpu (ms)", "gpu (ms)", "textures", "history"] {
+            ui.table_setup_column_with(TableColumnSetup::new(name));
+        }
+        ui.table_headers_row();
+
+        if let Some(mut specs) = ui.table_sort_specs_mut() {
+            specs.conditional_sort(|specs| {
+                if let Some(spec) = specs.iter().next() {
+                    self.sort_column = spec.column_idx();
+                    self.sort_ascending =
+                        spec.sort_direction() == Some(TableSortDirection::Ascending);
+                }
+            });
+        }
+
+        let mut rows = self.history.iter().collect::<Vec<_>>();
+        rows.sort_by(|(_, a), (_, b)| {
+            let (a_last, b_last) = (a.last(), b.last());
+            let ordering = match self.sort_column {
+                0 => a.name.cmp(&b.name),
+                2 => a_last.gpu_time.cmp(&b_last.gpu_time),
+                3 => a_last.texture_bytes.cmp(&b_last.texture_bytes),
+                _ => a.average_cpu_time().cmp(&b.average_cpu_time()),
+            };
+            if self.sort_ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+
+        for (id, history) in rows {
+            let last = history.last();
+            ui.table_next_row();
+
+            ui.table_next_column();
+            ui.text(&history.name);
+            if ui.is_item_hovered() {
+                ui.tooltip_text(id);
+            }
+
+            ui.table_next_column();
+            ui.text(format!(
+                "{:.3} (avg {:.3})",
+                last.cpu_time.as_secs_f64() * 1000.0,
+                history.average_cpu_time().as_secs_f64() * 1000.0
+            ));
+
+            ui.table_next_column();
+            match last.gpu_time {
+                Some(t) => ui.text(format!("{:.3}", t.as_secs_f64() * 1000.0)),
+                None => ui.text_disabled("-"),
+            }
+
+            ui.table_next_column();
+            ui.text(format_bytes(last.texture_bytes));
+
+            ui.table_next_column();
+            let values = history
+                .samples
+                .iter()
+                .map(|s| match (self.gpu_timing, s.gpu_time) {
+                    (true, Some(t)) => t.as_secs_f32() * 1000.0,
+                    _ => s.cpu_time.as_secs_f32() * 1000.0,
+                })
+                .collect::<Vec<f32>>();
+            ui.plot_lines(format!("##history {id}"), &values)
+                .graph_size([ui.content_region_avail()[0], 30.0])
+                .scale_min(0.0)
+                .build();
+        }
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{bytes} B")
+    }
+}
@@ -2,10 +2,11 @@
 
 use glium::debug::TimestampQuery;
 use glium::texture::RawImage2d;
+use glium::uniforms::MagnifySamplerFilter;
 use glium::{program, BlitTarget, Display, Program, Rect, Surface};
 use imgui::drag_drop::PayloadIsWrongType;
 use imgui::{sys::ImVec2, ImColor32, TreeNodeToken, Ui};
-use imgui::{Style, WindowFlags, WindowHoveredFlags};
+use imgui::{Style, TabItemFlags, WindowFlags, WindowHoveredFlags};
 use imgui_glium_renderer::Renderer;
 use imgui_winit_support::winit::dpi::{Position, Size};
 use itertools::Itertools;
@@ -24,20 +25,25 @@ use std::{
 };
 use textdistance::{Algorithm, Cosine, Hamming, Levenshtein};
 
-use glium::{backend::Facade, glutin::surface::WindowSurface, Texture2d};
+use glium::{backend::{Context, Facade}, Texture2d};
 use savefile::SavefileError;
 use std::{
     collections::HashMap,
     fs,
     path::PathBuf,
+    rc::Rc,
     time::{Duration, Instant},
 };
 use strum::IntoEnumIterator;
 
+use crate::canvas::PreviewQuality;
+use crate::command_palette::CommandPalette;
+use crate::fuse::FusedGroup;
 use crate::generic_io::EditTab;
 use crate::generic_node_info::GenericNodeInfo;
+use crate::graph;
 use anyhow::anyhow;
-use crate::node::random_id;
+use crate::node::{random_id, ColorSpace, NodeFallback, NodeMode};
 use crate::nodes::debug;
 
 use crate::nodes::input::load_gif::LoadGifNode;
@@ -46,11 +52,12 @@ use crate::nodes::output::cover_window::CoverWindowNode;
 use crate::project_settings::{ProjectSettings, PROJECT_SETTINGS_VERSION};
 use crate::render_nodes::RenderNodesParams;
 use crate::sidebar::SidebarParams;
+use crate::worker_pool;
 use crate::{
     advanced_color_picker::AdvancedColorPicker, history_tracker::Snapshot, node,
     nodes::node_enum::*,
 };
-use crate::{node::MyNode, storage::Storage, user_info::UserSettings};
+use crate::{node::MyNode, storage::{Storage, TextureDepth}, user_info::UserSettings};
 use crate::{project, project_settings};
 use rfd::FileDialog;
 
@@ -62,6 +69,9 @@ pub fn calculate_hash<T: Hash>(t: &T) -> u64 {
 
 const MAX_LOADING: i32 = 6;
 
+/// the size of the frame produced by a muted node with no image input
+pub(crate) const MUTED_FRAME_SIZE: (u32, u32) = (256, 256);
+
 // #[savefile_derive]
 pub struct Project {
     pub storage: Storage,
@@ -92,12 +102,24 @@ pub struct Project {
     pub total_gpu_frame_time: f32,
     pub loading: i32,
     pub render_ticker_timer: Instant,
+    /// how long since nodes were last asked if the files they use have changed
+    pub file_watch_timer: Instant,
     pub open_settings: bool,
     pub project_settings: ProjectSettings,
     pub edit_tab: EditTab,
+    /// set this to change the selected tab on the next frame
+    pub switch_tab: Option<EditTab>,
     pub node_search_string: String,
     pub backup_data: Vec<GenericNodeInfo>,
-    pub node_error_value: HashMap<String, anyhow::Result<()>>
+    pub node_error_value: HashMap<String, anyhow::Result<()>>,
+    pub command_palette: CommandPalette,
+    /// when this is set the main loop will close this project and open the one at the given path
+    pub open_project: Option<PathBuf>,
+    /// the nodes that were drawn together by generated shaders on the last run,
+    /// along with the hash of the connections and node settings they were found for
+    pub fused_groups: (u64, Vec<FusedGroup>),
+    /// the nodes that failed without a fallback on the last run, the connections after them are drawn red
+    pub failed_nodes: HashSet<String>,
 }
 
 impl Project {
@@ -107,7 +129,7 @@ impl Project {
     //     // }
     // }
 
-    pub fn new<P: AsRef<Path>>(path: P, display: Display<WindowSurface>) -> Project {
+    pub fn new<P: AsRef<Path>>(path: P, display: Rc<Context>) -> Project {
         // log::info!("{:?}", fs::create_dir_all(path.join("nodes")));
         let path = path.as_ref();
         let new = Project {
@@ -139,15 +161,58 @@ impl Project {
             loading: 0,
             project_settings: ProjectSettings::default(),
             render_ticker_timer: Instant::now(),
+            file_watch_timer: Instant::now(),
             open_settings: false,
             edit_tab: EditTab::BatchFileEdit,
+            switch_tab: None,
             backup_data: vec![],
             node_search_string: String::new(),
             node_error_value: HashMap::new(),
+            command_palette: CommandPalette::default(),
+            open_project: None,
+            fused_groups: (0, vec![]),
+            failed_nodes: HashSet::new(),
         };
         return new;
     }
 
+    /// opens the project at `path` straight away, without the loading screen or the editor.
+    /// run it with `run_nodes_on_io_arrays`
+    pub fn load<P: AsRef<Path>>(path: P, display: Rc<Context>) -> Project {
+        let mut project = Project::new(path, display);
+        project.storage.project_name = project.name();
+        project.load_graph();
+        for node in &mut project.nodes {
+            node.load_assets(&project.storage);
+        }
+        project.loading = MAX_LOADING + 2;
+        return project;
+    }
+
+    /// reads the settings, connections and nodes of the project from `self.path`.
+    /// the nodes are left alone if there is no project there
+    fn load_graph(&mut self) {
+        if let Some(project_settings) = graph::load_settings(&self.path) {
+            self.project_settings = project_settings;
+        }
+
+        if self.project_settings.batch_files.save_path == PathBuf::new() {
+            self.project_settings.batch_files.save_path = UserDirs::new()
+                .unwrap()
+                .download_dir
+                .join(format!("{}", self.name()));
+        }
+
+        if let Some(connections) = graph::load_connections(&self.path) {
+            self.connections = connections;
+            self.nodes = graph::load_nodes(&self.path);
+        } else {
+            log::info!("project not found");
+        }
+
+        self.storage.project_root = self.path.join("root");
+    }
+
     pub fn name(&self) -> String {
         match self.path.as_path().file_name() {
             Some(a) => a.to_owned().into_string().unwrap(),
@@ -155,7 +220,7 @@ impl Project {
         }
     }
 
-    /// wont save if the project is not loaded yet
+    /// saves the project to `self.path`. wont save if the project is not loaded yet
     pub fn save(&mut self) -> Result<(), SavefileError> {
         let mut input_ok = false;
         let mut output_ok = false;
@@ -180,11 +245,7 @@ impl Project {
             &self.project_settings,
         )?;
 
-        // remove any node files that may be left over from the last save
-        let _ = fs::remove_dir_all(self.path.join("nodes"));
-
-        // create the nodes directory cos we just deleted it
-        fs::create_dir_all(self.path.join("nodes"))?;
+        graph::save_nodes(&self.path, &self.nodes)?;
 
         // iter over nodes
         for node in &self.nodes {
@@ -194,9 +255,7 @@ impl Project {
             if Some(node.id()) == self.project_settings.generic_io.output_id {
                 output_ok = true;
             }
-            fs::create_dir_all(self.path.join("nodes").join(node.name()))?;
             self.backup_data.push(node.generic_info());
-            node.save(self.path.join("nodes"))?;
         }
 
         // save the fact that the input id/ output id are valid
@@ -208,6 +267,18 @@ impl Project {
             self.project_settings.generic_io.output_id = None;
         }
 
+        // forget about the bypass/mute state of nodes that have been deleted
+        let node_ids = self.nodes.iter().map(|n| n.id()).collect::<HashSet<String>>();
+        self.project_settings
+            .node_modes
+            .retain(|id, _| node_ids.contains(id));
+        self.project_settings
+            .node_fallbacks
+            .retain(|id, _| node_ids.contains(id));
+        self.project_settings
+            .node_texture_depths
+            .retain(|id, _| node_ids.contains(id));
+
         
 
         return Ok(());
@@ -296,7 +367,7 @@ impl Project {
                             ui.text("Saving");
                         }
                         4 => {
-                            ui.text("Verifying save integrity");
+                            ui.text("Compiling shaders");
                         }
                         5 => {
                             ui.text("Updateing ffmpeg");
@@ -353,75 +424,24 @@ impl Project {
                             });
                         }
                         1 => {
-                            if let Ok(project_settings) =
-                                savefile::load_file::<ProjectSettings, PathBuf>(
-                                    self.path.join("project_settings.bin"),
-                                    0,
-                                )
-                            {
-                                self.project_settings = project_settings;
-                            }
-                            
-                            if let Ok(project_settings) =
-                                savefile::load_file::<ProjectSettings, PathBuf>(
-                                    self.path.join("project_settings.bin"),
-                                    0,
-                                )
-                            {
-                                self.project_settings = project_settings;
-                            }
-
-                            if self.project_settings.batch_files.save_path == PathBuf::new() {
-                                self.project_settings.batch_files.save_path = UserDirs::new()
-                                    .unwrap()
-                                    .download_dir
-                                    .join(format!("{}", self.name()));
-                            }
-
-                            if let Ok(connections) =
-                                savefile::load_file::<HashMap<String, String>, PathBuf>(
-                                    self.path.join("connections.bin"),
-                                    0,
-                                )
-                            {
-                                self.connections = connections;
-
-                                self.nodes = vec![];
-
-                                for node_type in NodeType::iter() {
-                                    if let Ok(node_paths) =
-                                        fs::read_dir(self.path.join("nodes").join(node_type.name()))
-                                    {
-                                        for node in node_paths {
-                                            if let Ok(node) = node {
-                                                if let Some(new_node) =
-                                                    node_type.load_node(node.path())
-                                                {
-                                                    self.nodes.push(new_node);
-                                                    log::info!("loaded node");
-                                                };
-                                            }
-                                        }
-                                    }
-                                }
-                            } else {
-                                log::info!("project not found");
-                            }
-
-                            self.storage.project_root = self.path.join("root");
+                            self.load_graph();
                         }
                         2 => {
                             self.recenter_nodes(ui);
 
                             self.run_nodes(renderer);
+                            let before_run_nodes = Instant::now();
                             self.run_nodes(renderer);
-                            for (i, (_id, speed)) in self.node_speeds.iter().enumerate() {
-                                let type_: NodeType = self.nodes[i].type_();
+                            self.total_frame_time = before_run_nodes.elapsed().as_secs_f32();
+                            for (id, speed) in self.node_speeds.iter() {
+                                let Some(node) = self.nodes.iter().find(|n| &n.id() == id) else {
+                                    continue;
+                                };
                                 let speed = speed.as_secs_f32()
                                     / (self.total_frame_time.max(self.total_gpu_frame_time)
                                         / self.node_speeds.len() as f32);
                                 if let Some(array) =
-                                    user_settings.node_speed.get_mut(&self.nodes[i].name())
+                                    user_settings.node_speed.get_mut(&node.name())
                                 {
                                     array.push(speed);
                                     if array.len() > 40 {
@@ -430,7 +450,7 @@ impl Project {
                                 } else {
                                     user_settings
                                         .node_speed
-                                        .insert(self.nodes[i].name(), vec![speed]);
+                                        .insert(node.name(), vec![speed]);
                                 }
                             }
                             #[cfg(debug_assertion)]
@@ -458,8 +478,15 @@ impl Project {
                             self.project_settings.local_files.reload(&self.storage)
                         }
                         4 => {
-                            // this step is kinda useless now, remend me to do something with it later
-                            
+                            // every node type is compiled, not just the ones in the project,
+                            // so adding a node doesn't stall the first frame it is used
+                            let mut shaders = vec![];
+                            for node in self.new_node_types.iter().chain(self.nodes.iter()) {
+                                shaders.extend(node.fragment_shaders());
+                            }
+                            shaders.sort();
+                            shaders.dedup();
+                            self.storage.precompile_frag_shaders(shaders);
                         }
                         5 => {
                             if user_settings.install_ffmpeg {
@@ -540,6 +567,7 @@ impl Project {
         };
 
         // start by rendering the menu bar.
+        let mut settings_changed = false;
         ui.main_menu_bar(|| {
             
             ui.menu("project", || {
@@ -561,6 +589,66 @@ impl Project {
             if ui.menu_item("user settings") {
                 self.open_settings = true;
             }
+            if let Some(_menu) = ui.begin_menu("canvas size") {
+                let size = &mut self.project_settings.canvas_size;
+                let mut input_val = [size.0 as i32, size.1 as i32];
+                if ui.input_int2("(w,h)", &mut input_val).build() {
+                    *size = (input_val[0].clamp(1, 16384) as u32, input_val[1].clamp(1, 16384) as u32);
+                    settings_changed = true;
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("the size generator nodes output unless they are given their own size");
+                }
+            }
+            if let Some(_menu) = ui.begin_menu("preview quality") {
+                for quality in PreviewQuality::iter() {
+                    if ui
+                        .menu_item_config(quality.name())
+                        .selected(user_settings.preview_quality == quality)
+                        .build()
+                    {
+                        user_settings.preview_quality = quality;
+                        settings_changed = true;
+                    }
+                }
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("downsamples images, videos and other sources while editing.\nrendering from an output node and batch files always use full resolution");
+            }
+            if let Some(_menu) = ui.begin_menu("texture depth") {
+                for depth in TextureDepth::iter() {
+                    if ui
+                        .menu_item_config(depth.name())
+                        .selected(self.project_settings.texture_depth == depth)
+                        .build()
+                    {
+                        self.project_settings.texture_depth = depth;
+                        settings_changed = true;
+                    }
+                }
+            }
+            if ui
+                .menu_item_config("linear light")
+                .selected(self.project_settings.linear_light)
+                .build()
+            {
+                self.project_settings.linear_light = !self.project_settings.linear_light;
+                settings_changed = true;
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("blurs and blends are done in linear light instead of on the srgb values.\nthis works best with 16 bit float textures");
+            }
+            if ui
+                .menu_item_config("fuse per-pixel nodes")
+                .selected(self.project_settings.fuse_nodes)
+                .build()
+            {
+                self.project_settings.fuse_nodes = !self.project_settings.fuse_nodes;
+                settings_changed = true;
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text("chains of nodes like invert, greyscale and the logic masks are drawn by one generated shader.\nright click a fused node to export the shader");
+            }
             });
 
             ui.menu("debug", || {
@@ -571,26 +659,21 @@ impl Project {
                     self.storage.max_lines_of_text = 1;
                     self.storage.show_debug_window = !self.storage.show_debug_window;
                 }
-            });
-            ui.menu("feedback", || {
-                if ui.menu_item("star on github") {
-                    open::that("https://github.com/ollielynas/reanimator");
-                }
-                if ui.menu_item("bug report") {
-                    let bug_body = "Try to include info like:%0A- how to replicate the issue%0A- what the issue was%0A-screenshots%0A- error messages%0A- an exported version of the project%A0%0Adon't forget a title".replace(" ", "%20");
-                    open::that(format!("https://github.com/ollielynas/reanimator/issues/new?labels=bug&title=[bug]%20v{}-&body=\"{}\"", cargo_crate_version!(), bug_body));
-                }
-                if ui.menu_item("request feature") {
-                    open::that("https://github.com/ollielynas/reanimator/issues/new?title=[feature%20request]&body=don't%20forget%20a%20title");
-                }
-                if ui.menu_item("spelling mistake") {
-                    open::that("https://github.com/ollielynas/reanimator/issues/new?title=[important]&body=please%20be%20thorough%20in%20your%20description%20of%20the%20error");
-                }
-                if ui.menu_item("google form") {
-                    open::that("https://docs.google.com/forms/d/e/1FAIpQLSfBSZZc8oqVrxUUfmNsEjvmKtE3RKdPIok7WvWQWk5S3mW4XQ/viewform?usp=sf_link");
+                if ui.menu_item("compare with cpu") {
+                    self.compare_with_cpu();
                 }
                 if ui.is_item_hovered() {
-                    ui.tooltip_text("if you don't have/don't want to create a github account you can use this google form");
+                    ui.tooltip_text("runs the graph again on the cpu and logs how different each node's output is");
+                }
+            });
+            ui.menu("feedback", || {
+                for (name, link) in feedback_links() {
+                    if ui.menu_item(name) {
+                        open::that(link);
+                    }
+                    if name == "google form" && ui.is_item_hovered() {
+                        ui.tooltip_text("if you don't have/don't want to create a github account you can use this google form");
+                    }
                 }
             });
         });
@@ -612,14 +695,35 @@ impl Project {
             node_pos_map: HashMap::new(),
             time_list: vec![],
             delete_node: None,
+            full_resolution: false,
+            export_fused_shader: None,
         };
 
+        if settings_changed {
+            params.time_list.push(ui.time());
+        }
+
+        for action in self.pressed_actions(ui, user_settings) {
+            self.run_action(action, ui, user_settings, &mut params, &mut sidebar_params);
+        }
+        self.render_command_palette(ui, user_settings, &mut params, &mut sidebar_params);
+
         let window_params = vec![
             ui.push_style_var(imgui::StyleVar::WindowBorderSize(0.0)),
             ui.push_style_var(imgui::StyleVar::WindowRounding(0.0)),
             // ui.push_style_var(imgui::StyleVar::),
         ];
 
+        let [nodes_flags, batch_flags, res_flags] =
+            [EditTab::Nodes, EditTab::BatchFileEdit, EditTab::ProjectRes].map(|tab| {
+                if self.switch_tab == Some(tab) {
+                    TabItemFlags::SET_SELECTED
+                } else {
+                    TabItemFlags::empty()
+                }
+            });
+        self.switch_tab = None;
+
         ui.window("tabs")
             // .draw_background(false)
             .bring_to_front_on_focus(false)
@@ -634,13 +738,15 @@ impl Project {
             .no_decoration()
             .build(|| {
                 if let Some(_tab_bar) = ui.tab_bar("top tab bar") {
-                    if let Some(_item) = ui.tab_item("nodes") {
+                    if let Some(_item) = ui.tab_item_with_flags("nodes", None, nodes_flags) {
                         self.edit_tab = EditTab::Nodes;
                     }
-                    if let Some(_item) = ui.tab_item("batch edit") {
+                    if let Some(_item) = ui.tab_item_with_flags("batch edit", None, batch_flags) {
                         self.edit_tab = EditTab::BatchFileEdit;
                     }
-                    if let Some(_item) = ui.tab_item("project resources") {
+                    if let Some(_item) =
+                        ui.tab_item_with_flags("project resources", None, res_flags)
+                    {
                         self.edit_tab = EditTab::ProjectRes;
                     }
                 }
@@ -747,7 +853,15 @@ impl Project {
         }
 
         if let Some(kill) = params.delete_node {
-            self.nodes.remove(kill);
+            let mut node = self.nodes.remove(kill);
+            self.storage.profiler.remove_node(&node.id());
+            node.unload(renderer);
+        }
+
+        if let Some(id) = &params.export_fused_shader {
+            if let Err(e) = self.export_fused_shader(id) {
+                log::error!("failed to export fused shader: {e}");
+            }
         }
 
         self.recenter = false;
@@ -814,7 +928,13 @@ impl Project {
             if let Some(pos2) = params.node_pos_map.get(a) {
                 if let Some(pos) = params.node_pos_map.get(b) {
                     let texture_input = self.storage.get_texture(b).is_some();
+                    // outputs inside a fused shader aren't drawn into textures, they get their own colour
+                    let fused = self.is_fused_connection(a, b);
                     let text_input = self.storage.get_text(b).is_some();
+                    // the output comes from a node that failed, so everything after it is on the failing path
+                    let failed = self
+                        .failed_nodes
+                        .contains(b.split("-").collect::<Vec<&str>>()[1]);
                     let dif = (pos.x - pos2.x).abs();
                     draw_list
                         .add_bezier_curve(
@@ -822,16 +942,20 @@ impl Project {
                             [(pos.x + dif * 0.3), pos.y],
                             [(pos2.x - dif * 0.3), pos2.y],
                             [pos2.x, pos2.y],
-                            if text_input {
+                            if failed {
+                                [0.8, 0.1, 0.1, 1.0]
+                            } else if text_input {
                                 [0.0, 0.5, 0.0, 1.0]
                             } else if texture_input {
                                 [0.0, 0.0, 0.5, 1.0]
+                            } else if fused {
+                                [0.4, 0.0, 0.5, 1.0]
                             } else {
                                 [0.0, 0.0, 0.0, 1.0]
                             },
                         )
                         .thickness(
-                            if texture_input || text_input {
+                            if texture_input || text_input || failed || fused {
                                 3.0
                             } else {
                                 2.0
@@ -852,6 +976,36 @@ impl Project {
             params.time_list.push(ui.time());
         }
 
+        if self.file_watch_timer.elapsed().as_secs_f32() > 0.5 {
+            self.file_watch_timer = Instant::now();
+            let mut changed = false;
+            for node in &mut self.nodes {
+                changed |= node.watch_files(&self.storage);
+            }
+            if changed {
+                params.time_list.push(ui.time());
+            }
+        }
+
+        self.storage.memory_budget = user_settings.texture_memory_budget.max(0) as u64 * 1024 * 1024;
+
+        self.storage.exporting = params.full_resolution;
+        self.storage.preview_scale = if params.full_resolution {
+            1.0
+        } else {
+            user_settings.preview_quality.scale()
+        };
+
+        // the last tile is left in storage, so the preview needs to be run again
+        if self.run_requested_tiled_renders(renderer) {
+            params.time_list.push(ui.time());
+        }
+
+        // a node finished some work on the worker pool so the result needs to be uploaded
+        if worker_pool::take_finished() {
+            params.time_list.push(ui.time());
+        }
+
         let mut before = glium::debug::TimestampQuery::new(&self.storage.display);
         let mut after = glium::debug::TimestampQuery::new(&self.storage.display);
         let mut first = true;
@@ -976,6 +1130,8 @@ impl Project {
             &mut RawImage2d::from_raw_rgb(vec![], (0, 0)),
         );
     }
+    /// runs every node once. if `input` isn't empty it is given to the generic input node,
+    /// and the texture going into the generic output node is read back into `output`
     pub fn run_nodes_on_io_arrays(
         &mut self,
         renderer: &mut Renderer,
@@ -983,6 +1139,11 @@ impl Project {
         output: &mut RawImage2d<u8>,
     ) {
         self.storage.reset();
+        if input.data.len() > 0 {
+            // batch files are always full resolution
+            self.storage.preview_scale = 1.0;
+        }
+        self.storage.block_on_jobs = input.data.len() > 0 || self.storage.exporting;
         self.node_speeds.clear();
         self.node_error_value.clear();
 
@@ -1038,12 +1199,7 @@ impl Project {
 
         // log::info!("run");
 
-        let connection_hash = self.nodes.len() as u64
-            + calculate_hash(
-                &<HashMap<String, String> as Clone>::clone(&self.connections)
-                    .into_iter()
-                    .collect::<Vec<(String, String)>>(),
-            );
+        self.update_run_order();
 
         let mut node_indices: HashMap<String, usize> = HashMap::new();
 
@@ -1051,6 +1207,59 @@ impl Project {
             node_indices.insert(n.id(), i);
         }
 
+        // nodes that failed without a fallback, along with the message that is passed on to their dependents
+        let mut failed_nodes: HashMap<String, String> = HashMap::new();
+
+        self.storage.canvas_size = self.project_settings.canvas_size;
+
+        self.update_fused_groups();
+        let fused_groups = std::mem::take(&mut self.fused_groups.1);
+
+        let run_order = self.node_run_order.1.clone();
+        for id in &run_order {
+            // fused nodes are all drawn at once when the last of them would have run
+            if let Some(group) = fused_groups.iter().find(|a| a.contains(id)) {
+                if group.members.last() == Some(id) {
+                    self.run_fused_group(group, &mut failed_nodes);
+                }
+                continue;
+            }
+            if let Some(index) = node_indices.get(id) {
+                if self.nodes.len() > *index {
+                    let skip = do_io && self.nodes[*index].id() == input_node_id;
+                    self.run_node_at(*index, skip, &mut failed_nodes, renderer);
+                }
+            }
+        }
+
+        self.storage.profiler.finish_run();
+        self.fused_groups.1 = fused_groups;
+        self.failed_nodes = failed_nodes.into_keys().collect();
+
+        if let Some(texture) = self.storage.get_texture(&output_texture_id) {
+            *output = RawImage2d::from_raw_rgba(
+                texture
+                    .read_to_pixel_buffer()
+                    .read()
+                    .unwrap()
+                    .iter()
+                    .flat_map(|(r, g, b, a)| [r, g, b, a])
+                    .copied()
+                    .collect_vec(),
+                texture.dimensions(),
+            );
+        }
+    }
+
+    /// works out the order the nodes run in again if the connections have changed since it was last worked out
+    pub(crate) fn update_run_order(&mut self) {
+        let connection_hash = self.nodes.len() as u64
+            + calculate_hash(
+                &<HashMap<String, String> as Clone>::clone(&self.connections)
+                    .into_iter()
+                    .collect::<Vec<(String, String)>>(),
+            );
+
         if connection_hash != self.node_run_order.0 {
             let mut node_graph: HashMap<String, Vec<String>> = HashMap::new();
 
@@ -1115,44 +1324,185 @@ impl Project {
             }
             self.node_run_order = (connection_hash, order);
         }
+    }
 
-        for id in &self.node_run_order.1 {
-            if let Some(index) = node_indices.get(id) {
-                if self.nodes.len() > *index {
-                    let now = Instant::now();
+    /// runs the node at `index` with the mode, fallback and texture depth the project settings give it.
+    /// nodes downstream of one in `failed_nodes` fail as well, and if this one fails without a fallback it is added.
+    /// `skip` leaves the node's outputs as they are, for the generic input node when a batch file gives the input
+    pub(crate) fn run_node_at(
+        &mut self,
+        index: usize,
+        skip: bool,
+        failed_nodes: &mut HashMap<String, String>,
+        renderer: &mut Renderer,
+    ) {
+        let id = &self.nodes[index].id();
+        self.storage.texture_depth = self.project_settings.node_texture_depth(id);
+        let timer = self.storage.profiler.start(
+            id,
+            self.nodes[index].name(),
+            self.storage.texture_bytes,
+            &self.storage.display,
+        );
 
-                    let worked = if !do_io || self.nodes[*index].id() != input_node_id {
-                        self.nodes[*index].run(
-                            &mut self.storage,
-                            self.connections.clone(),
-                            renderer,
-                        )
-                    } else {
-                        Ok(())
-                    };
-                    // if worked.is_ok() {
-                    //     self.node_speeds.insert(id.to_string(), now.elapsed());
-                    // } else {
-                    //     self.node_speeds.remove(id);
-                    // }
-                    self.node_error_value.insert(id.to_owned(), worked);
+        // muted nodes ignore their inputs, so they can't be broken by them
+        let upstream_error = match self.project_settings.node_mode(id) {
+            NodeMode::Mute => None,
+            _ => self.upstream_error(index, failed_nodes),
+        };
+
+        let worked = if let Some(e) = &upstream_error {
+            Err(anyhow!(e.to_owned()))
+        } else if !skip {
+            match self.project_settings.node_mode(id) {
+                NodeMode::Normal if self.project_settings.linear_light
+                    && self.nodes[index].color_space() == ColorSpace::Linear =>
+                {
+                    self.run_node_in_linear_light(index, renderer)
+                }
+                NodeMode::Normal => self.nodes[index].run(
+                    &mut self.storage,
+                    self.connections.clone(),
+                    renderer,
+                ),
+                NodeMode::Bypass => self.bypass_node(index),
+                NodeMode::Mute => self.mute_node(index),
+            }
+        } else {
+            Ok(())
+        };
+        // sources are shrunk while editing so everything after them has less to do,
+        // most make their output at the preview size and the rest are shrunk here
+        if self.storage.preview_scale < 1.0
+            && worked.is_ok()
+            && self.nodes[index].inputs().is_empty()
+            && !self.nodes[index].scales_preview()
+        {
+            let node = &self.nodes[index];
+            for output in node.outputs() {
+                self.storage
+                    .downsample_texture(&node.output_id(&output), self.storage.preview_scale);
+            }
+        }
+        let cpu_time = self.storage.profiler.stop(
+            timer,
+            self.storage.texture_bytes,
+            &self.storage.display,
+        );
+        if worked.is_ok() {
+            self.node_speeds.insert(id.to_string(), cpu_time);
+        } else {
+            self.node_speeds.remove(id);
+        }
+        if let Err(e) = &worked {
+            let message = match upstream_error {
+                Some(a) => a,
+                None => format!(
+                    "upstream node {} ({}) failed: {}",
+                    self.nodes[index].name(),
+                    id,
+                    e
+                ),
+            };
+            let recovered = match self.project_settings.node_fallback(id) {
+                NodeFallback::None => false,
+                NodeFallback::PassThrough => self.bypass_node(index).is_ok(),
+                NodeFallback::ErrorTexture => self.error_texture_node(index).is_ok(),
+            };
+            if !recovered {
+                failed_nodes.insert(id.to_owned(), message);
+            }
+        }
+        self.node_error_value.insert(id.to_owned(), worked);
+    }
+
+    /// decodes the inputs of a node to linear light, runs it, then encodes its outputs back to srgb
+    fn run_node_in_linear_light(&mut self, index: usize, renderer: &mut Renderer) -> anyhow::Result<()> {
+        let node = &self.nodes[index];
+        let input_keys = node
+            .inputs()
+            .iter()
+            .filter_map(|a| self.connections.get(&node.input_id(a)).cloned())
+            .collect::<Vec<String>>();
+        for key in &input_keys {
+            self.storage.decode_to_linear(key);
+        }
+
+        // the node's own outputs are linear as well, so they need the extra precision
+        let depth = self.storage.texture_depth;
+        self.storage.texture_depth = depth.max(TextureDepth::F16);
+        let worked = self.nodes[index].run(&mut self.storage, self.connections.clone(), renderer);
+        self.storage.texture_depth = depth;
+        self.storage.clear_linear_inputs();
+
+        if worked.is_ok() {
+            let node = &self.nodes[index];
+            for output in node.outputs() {
+                self.storage.encode_from_linear(&node.output_id(&output));
+            }
+        }
+        return worked;
+    }
+
+    /// returns the message of the first node connected to the inputs of this node that failed
+    pub(crate) fn upstream_error(&self, index: usize, failed_nodes: &HashMap<String, String>) -> Option<String> {
+        let node = &self.nodes[index];
+        for input in node.inputs() {
+            if let Some(output_id) = self.connections.get(&node.input_id(&input)) {
+                let parent_id = output_id.split("-").collect::<Vec<&str>>()[1];
+                if let Some(message) = failed_nodes.get(parent_id) {
+                    return Some(message.to_owned());
                 }
             }
         }
+        return None;
+    }
 
-        if let Some(texture) = self.storage.get_texture(&output_texture_id) {
-            *output = RawImage2d::from_raw_rgba(
-                texture
-                    .read_to_pixel_buffer()
-                    .read()
-                    .unwrap()
-                    .iter()
-                    .flat_map(|(r, g, b, a)| [r, g, b, a])
-                    .copied()
-                    .collect_vec(),
-                texture.dimensions(),
-            );
+    /// sets every output of a node to a copy of the error texture
+    fn error_texture_node(&mut self, index: usize) -> anyhow::Result<()> {
+        let node = &self.nodes[index];
+        for output in node.outputs() {
+            self.storage.set_error_texture(node.output_id(&output));
         }
+        return Ok(());
+    }
+
+    /// passes the first input of a node straight through to its first output
+    fn bypass_node(&mut self, index: usize) -> anyhow::Result<()> {
+        let node = &self.nodes[index];
+        let output_id = match node.outputs().first() {
+            Some(a) => node.output_id(a),
+            None => return Ok(()),
+        };
+        let input_key = match node.inputs().first() {
+            Some(a) => match self.connections.get(&node.input_id(a)) {
+                Some(b) => b.to_owned(),
+                None => return Err(anyhow!("missing input")),
+            },
+            None => return Err(anyhow!("this node has no input to pass through")),
+        };
+
+        return self.storage.copy(&input_key, output_id);
+    }
+
+    /// sets every output of a node to a transparent frame the size of its first input
+    fn mute_node(&mut self, index: usize) -> anyhow::Result<()> {
+        let node = &self.nodes[index];
+        let size = match node
+            .inputs()
+            .first()
+            .and_then(|a| self.connections.get(&node.input_id(a)))
+            .and_then(|a| self.storage.get_texture(a))
+        {
+            Some(a) => a.dimensions(),
+            None => MUTED_FRAME_SIZE,
+        };
+
+        for output in node.outputs() {
+            self.storage.clear_texture(node.output_id(&output), size);
+        }
+
+        return Ok(());
     }
 
     pub fn new_node_menu(&mut self, ui: &Ui, user_settings: &UserSettings) -> bool {
@@ -1252,13 +1602,8 @@ impl Project {
 
                 if let Some(new_node) = self.new_node_types.get(self.selected_node_to_add) {
                     if ui.button("add") {
-                        let mut new_node2 = self.new_node_types[self.selected_node_to_add]
-                            .type_()
-                            .new_node();
-                        // let center = screen_to_graph_pos(ui.cursor_screen_pos(), self.graph_offset, self.scale);
-                        let center = [size_array[0] * 0.5, size_array[1] * 0.3];
-                        new_node2.set_xy(center[0], center[1]);
-                        self.nodes.push(new_node2);
+                        let node_type = new_node.type_();
+                        self.add_node(node_type, ui);
 
                         ui.close_current_popup();
                     }
@@ -1298,6 +1643,15 @@ impl Project {
         return open;
     }
 
+    pub fn add_node(&mut self, node_type: NodeType, ui: &Ui) {
+        let size_array = ui.io().display_size;
+        let mut new_node = node_type.new_node();
+        // let center = screen_to_graph_pos(ui.cursor_screen_pos(), self.graph_offset, self.scale);
+        let center = [size_array[0] * 0.5, size_array[1] * 0.3];
+        new_node.set_xy(center[0], center[1]);
+        self.nodes.push(new_node);
+    }
+
     pub fn drop_file(&mut self, path: PathBuf, ui: &Ui) {
         let binding = OsString::new();
         let ext = path.extension().unwrap_or(&binding).to_str().unwrap_or("");
@@ -1333,6 +1687,18 @@ impl Project {
     }
 }
 
+/// the links shown in the feedback menu as (name, url)
+pub fn feedback_links() -> Vec<(&'static str, String)> {
+    let bug_body = "Try to include info like:%0A- how to replicate the issue%0A- what the issue was%0A-screenshots%0A- error messages%0A- an exported version of the project%A0%0Adon't forget a title".replace(" ", "%20");
+    vec![
+        ("star on github", "https://github.com/ollielynas/reanimator".to_owned()),
+        ("bug report", format!("https://github.com/ollielynas/reanimator/issues/new?labels=bug&title=[bug]%20v{}-&body=\"{}\"", cargo_crate_version!(), bug_body)),
+        ("request feature", "https://github.com/ollielynas/reanimator/issues/new?title=[feature%20request]&body=don't%20forget%20a%20title".to_owned()),
+        ("spelling mistake", "https://github.com/ollielynas/reanimator/issues/new?title=[important]&body=please%20be%20thorough%20in%20your%20description%20of%20the%20error".to_owned()),
+        ("google form", "https://docs.google.com/forms/d/e/1FAIpQLSfBSZZc8oqVrxUUfmNsEjvmKtE3RKdPIok7WvWQWk5S3mW4XQ/viewform?usp=sf_link".to_owned()),
+    ]
+}
+
 pub fn graph_to_screen_pos(mut pos: [f32; 2], graph_offset: [f32; 2], scale: f32) -> [f32; 2] {
     for i in [0, 1] {
         pos[i] -= graph_offset[i];
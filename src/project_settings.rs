@@ -1,8 +1,8 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
-use crate::{batch_edit::RunBatch, generic_io::GenericIO, project_files::LocalFiles};
+use crate::{batch_edit::RunBatch, canvas::DEFAULT_CANVAS_SIZE, generic_io::GenericIO, node::{NodeFallback, NodeMode}, project_files::LocalFiles, storage::TextureDepth};
 
-pub const PROJECT_SETTINGS_VERSION: u32 = 0;
+pub const PROJECT_SETTINGS_VERSION: u32 = 6;
 
 #[derive(Savefile)]
 pub struct ProjectSettings {
@@ -16,6 +16,34 @@ pub struct ProjectSettings {
     pub batch_files: RunBatch,
     #[savefile_ignore]
     pub local_files: LocalFiles,
+    /// nodes that are bypassed or muted, keyed by node id. nodes that are not in this map run normally
+    #[savefile_versions = "1.."]
+    pub node_modes: HashMap<String, NodeMode>,
+    /// what nodes should output when they fail, nodes that are not in this map have no fallback
+    #[savefile_versions = "2.."]
+    pub node_fallbacks: HashMap<String, NodeFallback>,
+    /// the format of the textures nodes output, float formats keep values outside of 0-1 between nodes
+    #[savefile_versions = "3.."]
+    pub texture_depth: TextureDepth,
+    /// nodes that override `texture_depth`
+    #[savefile_versions = "3.."]
+    pub node_texture_depths: HashMap<String, TextureDepth>,
+    /// nodes that work in linear light get their inputs decoded from srgb and their outputs encoded back
+    #[savefile_versions = "4.."]
+    pub linear_light: bool,
+    /// the size generator nodes output unless they are given their own size
+    #[savefile_versions = "5.."]
+    #[savefile_default_fn = "default_canvas_size"]
+    pub canvas_size: (u32, u32),
+    /// chains of per-pixel nodes are drawn by one generated shader instead of one draw per node.
+    /// projects saved before this existed keep running every node on its own
+    #[savefile_versions = "6.."]
+    #[savefile_default_val = "false"]
+    pub fuse_nodes: bool,
+}
+
+fn default_canvas_size() -> (u32, u32) {
+    DEFAULT_CANVAS_SIZE
 }
 
 impl Default for ProjectSettings {
@@ -33,6 +61,48 @@ impl Default for ProjectSettings {
                 index: 0,
             },
             local_files: LocalFiles::default(),
+            node_modes: HashMap::new(),
+            node_fallbacks: HashMap::new(),
+            texture_depth: TextureDepth::default(),
+            node_texture_depths: HashMap::new(),
+            linear_light: false,
+            canvas_size: DEFAULT_CANVAS_SIZE,
+            fuse_nodes: true,
+        }
+    }
+}
+
+impl ProjectSettings {
+    pub fn node_mode(&self, id: &str) -> NodeMode {
+        self.node_modes.get(id).copied().unwrap_or_default()
+    }
+
+    pub fn node_fallback(&self, id: &str) -> NodeFallback {
+        self.node_fallbacks.get(id).copied().unwrap_or_default()
+    }
+
+    pub fn set_node_fallback(&mut self, id: String, fallback: NodeFallback) {
+        if fallback == NodeFallback::None {
+            self.node_fallbacks.remove(&id);
+        } else {
+            self.node_fallbacks.insert(id, fallback);
+        }
+    }
+
+    /// the depth the node's output textures are made with
+    pub fn node_texture_depth(&self, id: &str) -> TextureDepth {
+        self.node_texture_depths
+            .get(id)
+            .copied()
+            .unwrap_or(self.texture_depth)
+    }
+
+    /// puts a node into `mode`, or back to normal if it is already in that mode
+    pub fn toggle_node_mode(&mut self, id: String, mode: NodeMode) {
+        if self.node_mode(&id) == mode {
+            self.node_modes.remove(&id);
+        } else {
+            self.node_modes.insert(id, mode);
         }
     }
 }
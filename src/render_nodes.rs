@@ -12,7 +12,6 @@ use imgui_glium_renderer::Renderer;
 
 use std::{
     collections::HashMap,
-    fs,
 };
 
 
@@ -23,8 +22,10 @@ use crate::{
     nodes::node_enum::*,
 };
 use crate::{
-    node::MyNode,
+    node::{duplicate_node, MyNode, NodeFallback, NodeMode},
+    storage::TextureDepth,
 };
+use strum::IntoEnumIterator;
 
 
 pub struct RenderNodesParams {
@@ -37,6 +38,10 @@ pub struct RenderNodesParams {
     pub node_pos_map: HashMap<String, ImVec2>,
     pub time_list: Vec<f64>,
     pub delete_node: Option<usize>,
+    /// set when an output node is rendering or recording, so the preview quality is ignored
+    pub full_resolution: bool,
+    /// the id of a node that is part of a fused shader, the shader is saved after the nodes are drawn
+    pub export_fused_shader: Option<String>,
 }
 
 impl Project {
@@ -76,6 +81,19 @@ impl Project {
             let mut node_window_size = [0.0, 0.0];
             let mut node_window_pos = [0.0, 0.0];
 
+            let node_mode = self.project_settings.node_mode(&node.id());
+            let title_color = match node_mode {
+                NodeMode::Normal => None,
+                NodeMode::Bypass => Some([0.85, 0.55, 0.1, 1.0]),
+                NodeMode::Mute => Some([0.35, 0.35, 0.35, 1.0]),
+            };
+            let _title_colors = title_color.map(|color| {
+                [
+                    ui.push_style_color(imgui::StyleColor::TitleBg, color),
+                    ui.push_style_color(imgui::StyleColor::TitleBgActive, color),
+                ]
+            });
+
             ui.window(format!(
                 "{}{}##({})",
                 node.name(),
@@ -87,7 +105,7 @@ impl Project {
             .opened(&mut del_window_not)
             .scroll_bar(false)
             // .focused()
-            .bg_alpha(0.9)
+            .bg_alpha(if node_mode == NodeMode::Mute { 0.5 } else { 0.9 })
             .bring_to_front_on_focus(false)
             .collapsible(false)
             .movable(true)
@@ -125,10 +143,27 @@ impl Project {
                     if ui.is_item_hovered() {
                         ui.tooltip(|| {
                             ui.text(format!("{:?}", e));
+                            let fallback = self.project_settings.node_fallback(&node.id());
+                            if fallback != NodeFallback::None {
+                                ui.text_disabled(format!("using fallback: {}", fallback.name()));
+                            }
                         });
                     }
                 }
 
+                if node.computing() {
+                    ui.text_disabled(format!(
+                        "computing{}",
+                        ".".repeat((ui.time() * 3.0) as usize % 4)
+                    ));
+                }
+
+                match node_mode {
+                    NodeMode::Normal => {}
+                    NodeMode::Bypass => ui.text_colored([0.85, 0.55, 0.1, 1.0], "bypassed"),
+                    NodeMode::Mute => ui.text_disabled("muted"),
+                }
+
                 if self.project_settings.generic_io.input_id == Some(node.id()) {
                     ui.text("generic input")
                 }
@@ -202,6 +237,9 @@ impl Project {
                 if node.type_() == NodeType::Output {
                     let a: Option<&OutputNode> = (*node).as_any().downcast_ref::<OutputNode>();
                     if let Some(output_node) = a {
+                        if output_node.is_export() && !output_node.run_with_time.is_empty() {
+                            params.full_resolution = true;
+                        }
                         params
                             .time_list
                             .append(&mut output_node.run_with_time.clone());
@@ -221,26 +259,77 @@ impl Project {
                     }
 
                     if ui.menu_item("duplicate") {
-                        let a = fs::create_dir_all(self.path.join("temp").join(node.name()));
-                        if let Err(e) = a {
-                            log::info!("{e:?}")
-                        }
-                        node.save(self.path.join("temp"));
-                        let node_clone = node.type_().load_node(
-                            self.path
-                                .join("temp")
-                                .join(node.name())
-                                .join(node.id() + ".bin"),
-                        );
+                        let node_clone = duplicate_node(&**node, self.path.join("temp"));
                         if let Some(n) = node_clone {
                             // n.run(&mut Storage::new(r), self.connections, renderer);
                             params.duplicate_node = Some(n);
                         }
-                        fs::remove_dir_all(self.path.join("temp"));
                     }
                     if ui.menu_item("pop editor window") {
                         self.pop_out_edit_window.insert(node.id(), true);
                     }
+                    if self.fused_groups.1.iter().any(|a| a.contains(&node.id()))
+                        && ui.menu_item("export fused shader")
+                    {
+                        params.export_fused_shader = Some(node.id());
+                    }
+                    if let Some(_menu) = ui.begin_menu("on error") {
+                        let current = self.project_settings.node_fallback(&node.id());
+                        for fallback in [
+                            NodeFallback::None,
+                            NodeFallback::PassThrough,
+                            NodeFallback::ErrorTexture,
+                        ] {
+                            if ui
+                                .menu_item_config(fallback.name())
+                                .selected(current == fallback)
+                                .build()
+                            {
+                                self.project_settings
+                                    .set_node_fallback(node.id(), fallback);
+                            }
+                        }
+                    }
+                    if let Some(_menu) = ui.begin_menu("texture depth") {
+                        let overridden = self.project_settings.node_texture_depths.get(&node.id()).copied();
+                        if ui
+                            .menu_item_config("project default")
+                            .selected(overridden.is_none())
+                            .build()
+                        {
+                            self.project_settings.node_texture_depths.remove(&node.id());
+                            params.time_list.push(ui.time());
+                        }
+                        for depth in TextureDepth::iter() {
+                            if ui
+                                .menu_item_config(depth.name())
+                                .selected(overridden == Some(depth))
+                                .build()
+                            {
+                                self.project_settings
+                                    .node_texture_depths
+                                    .insert(node.id(), depth);
+                                params.time_list.push(ui.time());
+                            }
+                        }
+                    }
+                    ui.separator();
+                    if ui
+                        .menu_item_config("bypass")
+                        .selected(node_mode == NodeMode::Bypass)
+                        .build()
+                    {
+                        self.project_settings
+                            .toggle_node_mode(node.id(), NodeMode::Bypass);
+                    }
+                    if ui
+                        .menu_item_config("mute")
+                        .selected(node_mode == NodeMode::Mute)
+                        .build()
+                    {
+                        self.project_settings
+                            .toggle_node_mode(node.id(), NodeMode::Mute);
+                    }
                 }
 
                 node_window_size = ui.window_size();
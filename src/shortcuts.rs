@@ -0,0 +1,370 @@
+use imgui::{Key, Ui};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::{
+    generic_io::EditTab,
+    node::{duplicate_node, NodeMode},
+    nodes::output::image_io::OutputNode,
+    project::Project,
+    render_nodes::RenderNodesParams,
+    sidebar::SidebarParams,
+    user_info::UserSettings,
+};
+
+/// Every editor action that can be bound to a keyboard shortcut.
+/// new actions should be added to the end so that old save files still load
+#[derive(Savefile, EnumIter, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Action {
+    AddNode,
+    DeleteNode,
+    DuplicateNode,
+    PopOutEditor,
+    Save,
+    ReloadProject,
+    Recenter,
+    RenderFrame,
+    ToggleLiveRender,
+    Export,
+    NodesTab,
+    BatchEditTab,
+    ProjectResourcesTab,
+    ColorPicker,
+    Timeline,
+    RunBatch,
+    UserSettings,
+    OpenProjectFolder,
+    OpenResourceRoot,
+    DebugImgui,
+    DebugMemory,
+    ReturnHome,
+    CommandPalette,
+    BypassNode,
+    MuteNode,
+}
+
+impl Action {
+    pub fn name(&self) -> String {
+        match self {
+            Action::AddNode => "Add Node",
+            Action::DeleteNode => "Delete Selected Node",
+            Action::DuplicateNode => "Duplicate Selected Node",
+            Action::PopOutEditor => "Pop Out Editor Window",
+            Action::Save => "Save",
+            Action::ReloadProject => "Reload Project",
+            Action::Recenter => "Recenter",
+            Action::RenderFrame => "Render Single Frame",
+            Action::ToggleLiveRender => "Toggle Live Render",
+            Action::Export => "Export Project",
+            Action::NodesTab => "Switch To Nodes Tab",
+            Action::BatchEditTab => "Switch To Batch Edit Tab",
+            Action::ProjectResourcesTab => "Switch To Project Resources Tab",
+            Action::ColorPicker => "Toggle Color Picker",
+            Action::Timeline => "Toggle Timeline",
+            Action::RunBatch => "Run Batch",
+            Action::UserSettings => "User Settings",
+            Action::OpenProjectFolder => "Open Project Folder",
+            Action::OpenResourceRoot => "Open Resource Root",
+            Action::DebugImgui => "Debug Imgui",
+            Action::DebugMemory => "Debug Memory",
+            Action::ReturnHome => "Return Home",
+            Action::CommandPalette => "Command Palette",
+            Action::BypassNode => "Toggle Bypass On Selected Node",
+            Action::MuteNode => "Toggle Mute On Selected Node",
+        }
+        .to_owned()
+    }
+
+    pub fn default_shortcut(&self) -> Option<Shortcut> {
+        match self {
+            Action::AddNode => Some(Shortcut::new(Key::A, false, true, false)),
+            Action::DeleteNode => Some(Shortcut::new(Key::Delete, false, false, false)),
+            Action::DuplicateNode => Some(Shortcut::new(Key::D, true, false, false)),
+            Action::PopOutEditor => Some(Shortcut::new(Key::E, false, true, false)),
+            Action::Save => Some(Shortcut::new(Key::S, true, false, false)),
+            Action::Recenter => Some(Shortcut::new(Key::Home, false, false, false)),
+            Action::RenderFrame => Some(Shortcut::new(Key::Enter, true, false, false)),
+            Action::ToggleLiveRender => Some(Shortcut::new(Key::Space, false, false, false)),
+            Action::Export => Some(Shortcut::new(Key::E, true, false, false)),
+            Action::NodesTab => Some(Shortcut::new(Key::Alpha1, true, false, false)),
+            Action::BatchEditTab => Some(Shortcut::new(Key::Alpha2, true, false, false)),
+            Action::ProjectResourcesTab => Some(Shortcut::new(Key::Alpha3, true, false, false)),
+            Action::ColorPicker => Some(Shortcut::new(Key::C, true, true, false)),
+            Action::UserSettings => Some(Shortcut::new(Key::Comma, true, false, false)),
+            Action::CommandPalette => Some(Shortcut::new(Key::P, true, true, false)),
+            Action::BypassNode => Some(Shortcut::new(Key::B, true, false, false)),
+            Action::MuteNode => Some(Shortcut::new(Key::M, true, false, false)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Savefile, PartialEq, Eq, Clone, Debug)]
+pub struct Shortcut {
+    /// the debug name of the `imgui::Key`, this is stored as a string so the
+    /// save file doesn't depend on the order of the imgui key enum
+    pub key: String,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Shortcut {
+    pub fn new(key: Key, ctrl: bool, shift: bool, alt: bool) -> Shortcut {
+        Shortcut {
+            key: format!("{key:?}"),
+            ctrl,
+            shift,
+            alt,
+        }
+    }
+
+    pub fn key(&self) -> Option<Key> {
+        Key::VARIANTS
+            .iter()
+            .find(|k| format!("{k:?}") == self.key)
+            .copied()
+    }
+
+    pub fn pressed(&self, ui: &Ui) -> bool {
+        let io = ui.io();
+        match self.key() {
+            Some(key) => {
+                io.key_ctrl == self.ctrl
+                    && io.key_shift == self.shift
+                    && io.key_alt == self.alt
+                    && ui.is_key_pressed_no_repeat(key)
+            }
+            None => false,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        if self.ctrl {
+            name += "Ctrl+";
+        }
+        if self.shift {
+            name += "Shift+";
+        }
+        if self.alt {
+            name += "Alt+";
+        }
+        name + self.key.trim_start_matches("Alpha")
+    }
+}
+
+/// modifier keys (and the mouse) can't be bound on their own
+fn bindable(key: Key) -> bool {
+    let name = format!("{key:?}");
+    !(name.contains("Ctrl")
+        || name.contains("Shift")
+        || name.contains("Alt")
+        || name.contains("Super")
+        || name.starts_with("Mouse")
+        || name.starts_with("Gamepad"))
+}
+
+impl UserSettings {
+    /// returns the users binding for an action, or the default binding if it has not been changed
+    pub fn shortcut(&self, action: Action) -> Option<Shortcut> {
+        match self.shortcuts.get(&action) {
+            Some(a) => a.clone(),
+            None => action.default_shortcut(),
+        }
+    }
+
+    pub fn shortcut_editor(&mut self, ui: &Ui) {
+        ui.text_wrapped(
+            "Click on a shortcut and then press the new key combination. Press escape to cancel",
+        );
+        if ui.button("reset all to default") {
+            self.shortcuts.clear();
+            self.rebinding_shortcut = None;
+        }
+        ui.spacing();
+
+        ui.columns(3, "shortcut col", false);
+        for action in Action::iter() {
+            ui.text(action.name());
+            ui.next_column();
+
+            let label = if self.rebinding_shortcut == Some(action) {
+                "press keys...".to_owned()
+            } else {
+                match self.shortcut(action) {
+                    Some(shortcut) => shortcut.name(),
+                    None => "unbound".to_owned(),
+                }
+            };
+            if ui.button(format!("{label}##{action:?}")) {
+                self.rebinding_shortcut = Some(action);
+            }
+            ui.next_column();
+
+            if ui.button(format!("clear##{action:?}")) {
+                self.shortcuts.insert(action, None);
+            }
+            ui.same_line();
+            if ui.button(format!("default##{action:?}")) {
+                self.shortcuts.remove(&action);
+            }
+            ui.next_column();
+        }
+        ui.columns(1, "shortcut col end", false);
+
+        if let Some(action) = self.rebinding_shortcut {
+            if ui.is_key_pressed(Key::Escape) {
+                self.rebinding_shortcut = None;
+            } else if let Some(key) = Key::VARIANTS
+                .iter()
+                .find(|k| bindable(**k) && ui.is_key_pressed_no_repeat(**k))
+            {
+                let io = ui.io();
+                let shortcut = Shortcut::new(*key, io.key_ctrl, io.key_shift, io.key_alt);
+
+                // a key combination can only be bound to one action at a time
+                for other in Action::iter() {
+                    if other != action && self.shortcut(other) == Some(shortcut.clone()) {
+                        self.shortcuts.insert(other, None);
+                    }
+                }
+
+                self.shortcuts.insert(action, Some(shortcut));
+                self.rebinding_shortcut = None;
+            }
+        }
+    }
+}
+
+impl Project {
+    /// returns every action whose shortcut was pressed this frame
+    pub fn pressed_actions(&self, ui: &Ui, user_settings: &UserSettings) -> Vec<Action> {
+        if ui.io().want_text_input || user_settings.rebinding_shortcut.is_some() {
+            return vec![];
+        }
+        Action::iter()
+            .filter(|action| matches!(user_settings.shortcut(*action), Some(s) if s.pressed(ui)))
+            .collect()
+    }
+
+    pub fn run_action(
+        &mut self,
+        action: Action,
+        ui: &Ui,
+        user_settings: &mut UserSettings,
+        params: &mut RenderNodesParams,
+        sidebar_params: &mut SidebarParams,
+    ) {
+        log::info!("action: {}", action.name());
+        match action {
+            Action::AddNode => {
+                sidebar_params.new_node_popup = self.edit_tab == EditTab::Nodes;
+            }
+            Action::DeleteNode => {
+                if let Some(index) = self.node_edit {
+                    if self.edit_tab == EditTab::Nodes && index < self.nodes.len() {
+                        params.delete_node = Some(index);
+                        self.node_edit = None;
+                    }
+                }
+            }
+            Action::DuplicateNode => {
+                if let Some(index) = self.node_edit {
+                    if self.edit_tab == EditTab::Nodes && index < self.nodes.len() {
+                        params.duplicate_node =
+                            duplicate_node(&*self.nodes[index], self.path.join("temp"));
+                    }
+                }
+            }
+            Action::PopOutEditor => {
+                if let Some(index) = self.node_edit {
+                    if index < self.nodes.len() {
+                        self.pop_out_edit_window.insert(self.nodes[index].id(), true);
+                    }
+                }
+            }
+            Action::Save => {
+                let _ = self.save();
+            }
+            Action::ReloadProject => {
+                self.loading = 0;
+            }
+            Action::Recenter => {
+                self.recenter_nodes(ui);
+                self.recenter = true;
+            }
+            Action::RenderFrame => {
+                params.time_list.push(ui.time());
+            }
+            Action::ToggleLiveRender => {
+                for node in &mut self.nodes {
+                    if let Some(output_node) = node.as_any_mut().downcast_mut::<OutputNode>() {
+                        output_node.toggle_live_render();
+                    }
+                }
+            }
+            Action::Export => {
+                let _ = self.export();
+            }
+            Action::NodesTab => {
+                self.switch_tab = Some(EditTab::Nodes);
+            }
+            Action::BatchEditTab => {
+                self.switch_tab = Some(EditTab::BatchFileEdit);
+            }
+            Action::ProjectResourcesTab => {
+                self.switch_tab = Some(EditTab::ProjectRes);
+            }
+            Action::ColorPicker => {
+                self.advanced_color_picker.open = !self.advanced_color_picker.open;
+            }
+            Action::Timeline => {
+                if user_settings.history {
+                    self.display_history = !self.display_history;
+                }
+            }
+            Action::RunBatch => {
+                self.project_settings.batch_files.run =
+                    self.project_settings.batch_files.files.len() > 0;
+            }
+            Action::UserSettings => {
+                self.open_settings = true;
+            }
+            Action::OpenProjectFolder => {
+                let _ = open::that_detached(&self.path);
+            }
+            Action::OpenResourceRoot => {
+                let _ = open::that_detached(&self.path.join("root"));
+            }
+            Action::DebugImgui => {
+                self.metrics = !self.metrics;
+            }
+            Action::DebugMemory => {
+                self.storage.max_lines_of_text = 1;
+                self.storage.show_debug_window = !self.storage.show_debug_window;
+            }
+            Action::ReturnHome => {
+                let _ = self.save();
+                user_settings.update_projects();
+                self.return_to_home_menu = true;
+            }
+            Action::CommandPalette => {
+                self.command_palette.show();
+            }
+            Action::BypassNode | Action::MuteNode => {
+                let mode = if action == Action::BypassNode {
+                    NodeMode::Bypass
+                } else {
+                    NodeMode::Mute
+                };
+                if let Some(index) = self.node_edit {
+                    if index < self.nodes.len() {
+                        let id = self.nodes[index].id();
+                        self.project_settings.toggle_node_mode(id, mode);
+                    }
+                }
+            }
+        }
+    }
+}
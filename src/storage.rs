@@ -1,25 +1,148 @@
 use core::{panic};
 use std::collections::HashMap;
 use std::hash::{DefaultHasher, Hasher};
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use anyhow::anyhow;
+use blake2::{Blake2s256, Digest};
 
 use fast_smaz::Smaz;
 
 use glium::index::NoIndices;
 
-use glium::texture::{self, RawImage2d};
+use glium::texture::{self, RawImage2d, UncompressedFloatFormat};
 use glium::vertex::VertexBufferAny;
-use glium::{glutin::surface::WindowSurface, Display, Texture2d};
-use glium::{implement_vertex, Program, Surface};
+use glium::backend::Context;
+use glium::Texture2d;
+use glium::uniforms::MagnifySamplerFilter;
+use glium::program::{ProgramCreationError, ShaderType};
+use glium::{implement_vertex, uniform, BlitTarget, Program, Surface};
 use image::EncodableLayout;
 use image::{DynamicImage, ImageBuffer, Rgba};
-use imgui::{TreeNodeFlags, Ui};
+use imgui::{ProgressBar, TreeNodeFlags, Ui};
+use lazy_static::lazy_static;
+use platform_dirs::AppDirs;
+use regex::Regex;
 use std::hash::Hash;
+use strum_macros::EnumIter;
 
+use crate::canvas::DEFAULT_CANVAS_SIZE;
+use crate::tiles::Tile;
 use crate::fonts::MyFonts;
+use crate::profiler::{format_bytes, Profiler};
 use crate::render_nodes::RenderNodesParams;
 use crate::widgets::link_widget;
-use crate::{relaunch_program, LOG_TEXT};
+use crate::{platform::relaunch_program, LOG_TEXT};
+
+/// the format of the textures made by `create_and_set_texture`
+#[derive(Savefile, EnumIter, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Debug)]
+pub enum TextureDepth {
+    /// 8 bits per channel, values are clamped between 0 and 1
+    U8,
+    /// 16 bit float per channel, values above 1 are kept
+    F16,
+    F32,
+}
+
+impl Default for TextureDepth {
+    fn default() -> Self {
+        TextureDepth::U8
+    }
+}
+
+impl TextureDepth {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextureDepth::U8 => "8 bit",
+            TextureDepth::F16 => "16 bit float",
+            TextureDepth::F32 => "32 bit float",
+        }
+    }
+
+    pub fn format(&self) -> UncompressedFloatFormat {
+        match self {
+            TextureDepth::U8 => UncompressedFloatFormat::U8U8U8U8,
+            TextureDepth::F16 => UncompressedFloatFormat::F16F16F16F16,
+            TextureDepth::F32 => UncompressedFloatFormat::F32F32F32F32,
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> u64 {
+        match self {
+            TextureDepth::U8 => 4,
+            TextureDepth::F16 => 8,
+            TextureDepth::F32 => 16,
+        }
+    }
+}
+
+/// a cache key for a texture loaded from a file. it changes when the file is modified,
+/// `options` should include anything else that changes how the file is decoded.
+/// returns `None` if the file can't be read
+pub fn file_cache_key<T: Hash>(path: &Path, options: &T) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(param_cache_key(&(path, modified, options)))
+}
+
+/// a cache key for a texture made from some parameters (e.g. the settings of a node)
+pub fn param_cache_key<T: Hash>(params: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    params.hash(&mut hasher);
+    hasher.finish()
+}
+
+lazy_static! {
+    /// drivers write line numbers as `0(12)`, `0:12(5)` or `ERROR: 0:12:`
+    static ref SHADER_ERROR_LINE: Regex = Regex::new(r"^\s*(?:ERROR:\s*)?\d+[:(](\d+)").unwrap();
+}
+
+/// the most shader errors `Storage` keeps. each edit of a broken custom shader adds one, so they are forgotten past this
+const MAX_SHADER_ERRORS: usize = 64;
+
+/// adds the line of source that each line of a driver's compile log points to.
+/// the first `header_lines` lines of `source` were added by reanimator, line numbers are counted from the line after them
+pub fn annotate_shader_error(log: &str, source: &str, header_lines: usize) -> String {
+    let lines = source.lines().collect::<Vec<&str>>();
+    let mut out = String::new();
+    for error in log.lines().filter(|l| !l.trim().is_empty()) {
+        let mut error = error.trim().to_owned();
+        let number = SHADER_ERROR_LINE.captures(&error).and_then(|c| c.get(1)).and_then(|m| {
+            let n = m.as_str().parse::<usize>().ok()?;
+            let text = lines.get(n.checked_sub(1)?)?;
+            let n = n.checked_sub(header_lines).filter(|a| *a > 0)?;
+            Some((m.range(), n, text.trim().to_owned()))
+        });
+        if let Some((range, n, text)) = number {
+            error.replace_range(range, &n.to_string());
+            out += &format!("{error}\n    {n} | {text}\n");
+        } else {
+            out += &error;
+            out += "\n";
+        }
+    }
+    return out;
+}
+
+/// a compiled program saved to disk, see `Storage::shader_cache_path`
+#[derive(Savefile)]
+struct ShaderBinary {
+    format: u32,
+    content: Vec<u8>,
+}
+
+const SHADER_BINARY_VERSION: u32 = 0;
+
+/// the most the shader cache can take up on disk. custom shaders add a program every time they are edited,
+/// so the programs that were used the longest time ago are deleted past this
+const MAX_SHADER_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// an estimate of the gpu memory used by a texture
+pub fn texture_bytes(texture: &Texture2d, depth: TextureDepth) -> u64 {
+    texture.width() as u64 * texture.height() as u64 * depth.bytes_per_pixel()
+}
 
 const VERTEX_SHADER: &'static str = r#"
         #version 140
@@ -34,18 +157,75 @@ const VERTEX_SHADER: &'static str = r#"
         }
     "#;
 
+/// converts between srgb and linear light, `to_linear` picks the direction
+const COLOR_SPACE_SHADER: &'static str = r#"
+        #version 140
+
+        in vec2 v_tex_coords;
+        out vec4 color;
+
+        uniform sampler2D tex;
+        uniform bool to_linear;
+
+        vec3 srgb_to_linear(vec3 c) {
+            return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+        }
+
+        vec3 linear_to_srgb(vec3 c) {
+            c = max(c, vec3(0.0));
+            return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+        }
+
+        void main() {
+            vec4 c = texture(tex, v_tex_coords);
+            color = vec4(to_linear ? srgb_to_linear(c.rgb) : linear_to_srgb(c.rgb), c.a);
+        }
+    "#;
+
+/// what nodes are given when they run: the textures and text they output by id, and the compiled shaders
 pub struct Storage {
     textures: HashMap<String, Texture2d>,
     text: HashMap<String, String>,
-    pub display: Display<WindowSurface>,
-    unused_textures: HashMap<(u32, u32), Vec<Texture2d>>,
+    /// the editor's window, or a context without one from `platform::headless_context`
+    pub display: Rc<Context>,
+    /// textures that can be reused by `create_and_set_texture`, along with the tick they were last used on
+    unused_textures: HashMap<(u32, u32, TextureDepth), Vec<(Texture2d, u64)>>,
+    /// the depth of each texture made by `create_and_set_texture`, so it can go back into the right pool
+    texture_depths: HashMap<String, TextureDepth>,
+    /// the depth used by `create_and_set_texture`, this is set before each node is run
+    pub texture_depth: TextureDepth,
     shaders: HashMap<String, Program>,
+    /// the errors of shaders that failed to compile, with the same keys as `shaders`. at most `MAX_SHADER_ERRORS` are kept
+    shader_errors: HashMap<String, String>,
+    /// where compiled programs are saved between sessions, `None` if there is no cache directory
+    shader_cache_dir: Option<PathBuf>,
+    /// the vendor, renderer and version of the driver. programs compiled by a different driver can't be loaded
+    driver: String,
     pub time: f64,
     pub indices: NoIndices,
     pub vertex_buffer: VertexBufferAny,
     cached_textures: HashMap<u64, Texture2d>,
     pub hasher: Box<dyn Hasher>,
     redirect_id_to_cache: HashMap<String, u64>,
+    /// textures that have been decoded to linear light for the node that is running, keyed by the id they replace
+    linear_inputs: HashMap<String, String>,
+    /// the tick each cached texture was last given an id on
+    cache_last_used: HashMap<u64, u64>,
+    /// cached textures that are not 8 bit
+    cached_depths: HashMap<u64, TextureDepth>,
+    /// incremented every time the nodes are run
+    tick: u64,
+    /// the maximum number of bytes textures can take up, including the ones the nodes are using
+    pub memory_budget: u64,
+    /// set once it has been logged that the textures the nodes use don't fit in `memory_budget`
+    over_budget_warned: bool,
+    /// the size generators output by default, from `ProjectSettings::canvas_size`
+    pub canvas_size: (u32, u32),
+    /// how much sources are downsampled by, 1.0 unless a preview quality is set.
+    /// nodes with parameters measured in pixels should multiply them by this
+    pub preview_scale: f32,
+    /// the part of the image being rendered when the graph is run in tiles, sources should only load this area
+    pub tile: Option<Tile>,
     pub project_name: String,
     pub show_debug_window: bool,
     pub error_texture: Texture2d,
@@ -54,10 +234,19 @@ pub struct Storage {
     lock_output_pos: bool,
     pub max_lines_of_text: usize,
     full_messages: bool,
+    pub profiler: Profiler,
+    /// the total size of every texture allocated by `create_and_set_texture`, used by the profiler.
+    /// textures reused from the pool aren't counted again
+    pub texture_bytes: u64,
+    /// if true nodes that use the worker pool should wait for their result instead of
+    /// showing the last one, this is set when running batch files
+    pub block_on_jobs: bool,
+    /// set while an output node is rendering to a file, so jobs are waited for instead of using results from an older frame
+    pub exporting: bool,
 }
 
 impl Storage {
-    pub fn new(display: Display<WindowSurface>) -> Storage {
+    pub fn new(display: Rc<Context>) -> Storage {
         let error_image = image::load_from_memory(include_bytes!("img/th.jpg"))
             .unwrap_or_else(|_x| DynamicImage::new_rgb8(20, 20))
             .flipv()
@@ -107,18 +296,44 @@ impl Storage {
         let vertex_buffer: glium::VertexBuffer<Vertex> =
             glium::VertexBuffer::new(&display, &shape).unwrap();
 
+        let driver = format!(
+            "{} {} {}",
+            display.get_opengl_vendor_string(),
+            display.get_opengl_renderer_string(),
+            display.get_opengl_version_string()
+        );
+        let shader_cache_dir = AppDirs::new(Some("ReAnimator"), false).and_then(|a| {
+            let dir = a.cache_dir.join("shaders");
+            fs::create_dir_all(&dir).ok()?;
+            Some(dir)
+        });
+
         let s = Storage {
             textures: HashMap::new(),
             text: HashMap::new(),
             display,
             unused_textures: HashMap::new(),
+            texture_depths: HashMap::new(),
+            texture_depth: TextureDepth::default(),
             shaders: HashMap::new(),
+            shader_errors: HashMap::new(),
+            shader_cache_dir,
+            driver,
             time: 0.0,
             indices,
             vertex_buffer: vertex_buffer.into(),
             cached_textures: HashMap::new(),
             hasher: Box::new(DefaultHasher::new()),
             redirect_id_to_cache: HashMap::new(),
+            linear_inputs: HashMap::new(),
+            cache_last_used: HashMap::new(),
+            cached_depths: HashMap::new(),
+            tick: 0,
+            memory_budget: 1024 * 1024 * 1024,
+            over_budget_warned: false,
+            canvas_size: DEFAULT_CANVAS_SIZE,
+            preview_scale: 1.0,
+            tile: None,
             project_name: String::new(),
             show_debug_window: false,
             error_texture,
@@ -127,6 +342,10 @@ impl Storage {
             max_lines_of_text: 1,
             lock_output_pos: true,
             full_messages: false,
+            profiler: Profiler::default(),
+            texture_bytes: 0,
+            block_on_jobs: false,
+            exporting: false,
         };
         return s;
     }
@@ -136,21 +355,36 @@ impl Storage {
         self.hasher.finish()
     }
 
-    /// returns the id of the cached texture (it's hash)
+    /// caches a texture under `key` and returns the key.
+    /// the key should come from whatever the texture was made from (see `file_cache_key` and `param_cache_key`)
+    /// so that the texture can be found again without having to read it back from the gpu.
     /// to use this cached texture it needs to have an id assigned to it.
     /// this is done through the function: `set_id_of_cached_texture`
     /// calls to the assigned id will be redirected to the cached texture.
     /// the cache is not deleted each frame but the id redirect table is.
-    pub fn cache_texture(&mut self, texture: Texture2d) -> u64 {
-        let pixel_buffer = &texture.read_to_pixel_buffer().read().unwrap().to_vec();
-        let cache = self.calculate_hash(pixel_buffer);
-        self.cached_textures.insert(cache, texture);
-        return cache;
+    pub fn cache_texture(&mut self, key: u64, texture: Texture2d) -> u64 {
+        return self.cache_texture_with_depth(key, texture, TextureDepth::U8);
+    }
+
+    pub fn cache_texture_with_depth(&mut self, key: u64, texture: Texture2d, depth: TextureDepth) -> u64 {
+        self.cached_textures.insert(key, texture);
+        self.cache_last_used.insert(key, self.tick);
+        if depth == TextureDepth::U8 {
+            self.cached_depths.remove(&key);
+        } else {
+            self.cached_depths.insert(key, depth);
+        }
+        return key;
+    }
+
+    fn cached_depth_of(&self, key: &u64) -> TextureDepth {
+        self.cached_depths.get(key).copied().unwrap_or_default()
     }
 
     /// this allows a cached texture to be refracted with a texture id string.
     /// this will be reset every frame.
     pub fn set_id_of_cached_texture(&mut self, cached_texture_hash: u64, id: String) {
+        self.cache_last_used.insert(cached_texture_hash, self.tick);
         self.redirect_id_to_cache.insert(id, cached_texture_hash);
     }
 
@@ -159,6 +393,15 @@ impl Storage {
     }
 
     pub fn set_texture(&mut self, k: String, v: Texture2d) {
+        self.set_texture_with_depth(k, v, TextureDepth::U8);
+    }
+    /// `depth` should match the format the texture was made with so its memory use and pooling are right
+    pub fn set_texture_with_depth(&mut self, k: String, v: Texture2d, depth: TextureDepth) {
+        if depth == TextureDepth::U8 {
+            self.texture_depths.remove(&k);
+        } else {
+            self.texture_depths.insert(k.clone(), depth);
+        }
         self.textures.insert(k, v);
     }
     pub fn set_text(&mut self, k: String, v: String) {
@@ -169,14 +412,175 @@ impl Storage {
         self.text.get(k)
     }
     pub fn get_texture(&self, k: &String) -> Option<&Texture2d> {
+        if let Some(k) = self.linear_inputs.get(k) {
+            return self.textures.get(k);
+        }
         if let Some(k) = self.redirect_id_to_cache.get(k) {
             return self.cached_textures.get(k);
         } else {
             return self.textures.get(k);
         }
     }
+    /// makes `get_texture(id)` return a linear light copy of the texture until `clear_linear_inputs` is called.
+    /// the copy is at least 16 bit so dark colours don't band
+    pub fn decode_to_linear(&mut self, id: &String) {
+        if self.linear_inputs.contains_key(id) {
+            return;
+        }
+        let linear_id = format!("linear-{id}");
+        let depth = self.texture_depth;
+        self.texture_depth = depth.max(TextureDepth::F16);
+        if self.convert_color_space(id, linear_id.clone(), true) {
+            self.linear_inputs.insert(id.to_owned(), linear_id);
+        }
+        self.texture_depth = depth;
+    }
+
+    pub fn clear_linear_inputs(&mut self) {
+        for (_, linear_id) in std::mem::take(&mut self.linear_inputs) {
+            self.drop_texture(&linear_id);
+        }
+    }
+
+    /// replaces the texture `id` (which holds linear light) with an srgb copy made at the current `texture_depth`
+    pub fn encode_from_linear(&mut self, id: &String) {
+        let srgb_id = format!("srgb-{id}");
+        if !self.convert_color_space(id, srgb_id.clone(), false) {
+            return;
+        }
+        self.replace_texture(id, &srgb_id);
+    }
+
+    /// copies the texture or text `from` to `to`
+    pub fn copy(&mut self, from: &String, to: String) -> anyhow::Result<()> {
+        if let Some(text) = self.get_text(from) {
+            let text = text.to_owned();
+            self.set_text(to, text);
+            return Ok(());
+        }
+
+        let size = match self.get_texture(from) {
+            Some(a) => a.dimensions(),
+            None => return Err(anyhow!("cannot find input texture")),
+        };
+        self.create_and_set_texture(size.0, size.1, to.clone());
+
+        let input = self.get_texture(from).unwrap();
+        let output = self.get_texture(&to).unwrap();
+        input.as_surface().blit_whole_color_to(
+            &output.as_surface(),
+            &BlitTarget {
+                left: 0,
+                bottom: 0,
+                width: size.0 as i32,
+                height: size.1 as i32,
+            },
+            MagnifySamplerFilter::Nearest,
+        );
+        return Ok(());
+    }
+
+    /// sets `k` to a transparent texture of `size`
+    pub fn clear_texture(&mut self, k: String, size: (u32, u32)) {
+        self.create_and_set_texture(size.0, size.1, k.clone());
+        self.get_texture(&k)
+            .unwrap()
+            .as_surface()
+            .clear_color(0.0, 0.0, 0.0, 0.0);
+    }
+
+    /// sets `k` to a copy of `error_texture`
+    pub fn set_error_texture(&mut self, k: String) {
+        let size = self.error_texture.dimensions();
+        self.create_and_set_texture(size.0, size.1, k.clone());
+        self.error_texture.as_surface().blit_whole_color_to(
+            &self.get_texture(&k).unwrap().as_surface(),
+            &BlitTarget {
+                left: 0,
+                bottom: 0,
+                width: size.0 as i32,
+                height: size.1 as i32,
+            },
+            MagnifySamplerFilter::Nearest,
+        );
+    }
+
+    /// shrinks the texture `id` by `scale`, used to make proxies of sources while editing
+    pub fn downsample_texture(&mut self, id: &String, scale: f32) {
+        let size = match self.get_texture(id) {
+            Some(a) => a.dimensions(),
+            None => return,
+        };
+        let target = (
+            ((size.0 as f32 * scale).round() as u32).max(1),
+            ((size.1 as f32 * scale).round() as u32).max(1),
+        );
+        if target.0 >= size.0 && target.1 >= size.1 {
+            return;
+        }
+        let proxy_id = format!("proxy-{id}");
+        self.create_and_set_texture(target.0, target.1, proxy_id.clone());
+        self.get_texture(id).unwrap().as_surface().blit_whole_color_to(
+            &self.get_texture(&proxy_id).unwrap().as_surface(),
+            &BlitTarget {
+                left: 0,
+                bottom: 0,
+                width: target.0 as i32,
+                height: target.1 as i32,
+            },
+            MagnifySamplerFilter::Linear,
+        );
+        self.replace_texture(id, &proxy_id);
+    }
+
+    /// moves the texture `from` to `id`, freeing whatever `id` was (including a cached texture it pointed to)
+    fn replace_texture(&mut self, id: &String, from: &String) {
+        let depth = self.texture_depth_of(from);
+        let texture = match self.textures.remove(from) {
+            Some(a) => a,
+            None => return,
+        };
+        self.texture_depths.remove(from);
+        self.drop_texture(id);
+        self.redirect_id_to_cache.remove(id);
+        self.set_texture_with_depth(id.to_owned(), texture, depth);
+    }
+
+    /// draws `from` into a new texture called `to`, returns false if `from` doesn't exist
+    fn convert_color_space(&mut self, from: &String, to: String, to_linear: bool) -> bool {
+        let size = match self.get_texture(from) {
+            Some(a) => a.dimensions(),
+            None => return false,
+        };
+        if self.gen_frag_shader(COLOR_SPACE_SHADER.to_string()).is_none() {
+            return false;
+        }
+        self.create_and_set_texture(size.0, size.1, to.clone());
+
+        let uniforms = uniform! {
+            tex: self.get_texture(from).unwrap(),
+            to_linear: to_linear,
+        };
+        let shader = self
+            .get_frag_shader(COLOR_SPACE_SHADER.to_string())
+            .unwrap();
+        return self
+            .get_texture(&to)
+            .unwrap()
+            .as_surface()
+            .draw(
+                &self.vertex_buffer,
+                &self.indices,
+                shader,
+                &uniforms,
+                &Default::default(),
+            )
+            .is_ok();
+    }
+
     /// does not reset time, resets memory
     pub fn reset(&mut self) {
+        let (active, _, _) = self.texture_memory();
         let mut keys: Vec<String> = vec![];
         for (string, _) in self.textures.iter() {
             keys.push(string.clone())
@@ -184,25 +588,143 @@ impl Storage {
         for key in keys {
             self.drop_texture(&key);
         }
+        self.tick += 1;
+        self.enforce_memory_budget(active);
     }
 
     pub fn drop_texture(&mut self, id: &str) {
         let texture = self.textures.remove(id);
+        let depth = self.texture_depths.remove(id).unwrap_or_default();
         if let Some(texture) = texture {
-            if let Some(ref mut a) = self
-                .unused_textures
-                .get_mut(&(texture.width(), texture.height()))
-            {
+            let tick = self.tick;
+            let key = (texture.width(), texture.height(), depth);
+            if let Some(ref mut a) = self.unused_textures.get_mut(&key) {
                 if a.len() < 20 {
-                    a.push(texture);
+                    a.push((texture, tick));
                 }
             } else {
-                self.unused_textures
-                    .insert((texture.width(), texture.height()), vec![texture]);
+                self.unused_textures.insert(key, vec![(texture, tick)]);
             }
         }
     }
 
+    /// an estimate of the largest texture that can be made and drawn to
+    pub fn max_texture_size(&self) -> u32 {
+        let (width, height) = self.display.get_max_viewport_dimensions();
+        return width.min(height);
+    }
+
+    /// `size` shrunk by `preview_scale`, sources should make their output this size while editing
+    pub fn preview_size(&self, size: (u32, u32)) -> (u32, u32) {
+        if self.preview_scale >= 1.0 {
+            return size;
+        }
+        return (
+            ((size.0 as f32 * self.preview_scale).round() as u32).max(1),
+            ((size.1 as f32 * self.preview_scale).round() as u32).max(1),
+        );
+    }
+
+    /// the format `id` was created with by `create_and_set_texture`
+    pub fn texture_depth_of(&self, id: &str) -> TextureDepth {
+        self.texture_depths.get(id).copied().unwrap_or_default()
+    }
+
+    /// returns the bytes used by (active textures, unused textures, cached textures)
+    pub fn texture_memory(&self) -> (u64, u64, u64) {
+        (
+            self.textures
+                .iter()
+                .map(|(k, t)| texture_bytes(t, self.texture_depth_of(k)))
+                .sum(),
+            self.unused_textures
+                .iter()
+                .map(|(k, v)| v.iter().map(|(t, _)| texture_bytes(t, k.2)).sum::<u64>())
+                .sum(),
+            self.cached_textures
+                .iter()
+                .map(|(k, t)| texture_bytes(t, self.cached_depth_of(k)))
+                .sum(),
+        )
+    }
+
+    /// frees the least recently used unused and cached textures until every texture fits in `memory_budget`.
+    /// `active` is the size of the textures the last run used, they have just been moved into the unused pool and are kept
+    /// along with the cached textures used on the last tick, so that they are not made again every frame
+    fn enforce_memory_budget(&mut self, active: u64) {
+        if active > self.memory_budget {
+            if !self.over_budget_warned {
+                log::warn!(
+                    "the textures the nodes use take up {}, which is more than the texture memory budget of {}",
+                    format_bytes(active),
+                    format_bytes(self.memory_budget)
+                );
+            }
+            self.over_budget_warned = true;
+        } else {
+            self.over_budget_warned = false;
+        }
+
+        let (_, unused, cached) = self.texture_memory();
+        let mut total = unused + cached;
+        if total <= self.memory_budget {
+            return;
+        }
+
+        enum Candidate {
+            Unused((u32, u32, TextureDepth), u64),
+            Cached(u64),
+        }
+
+        let mut candidates: Vec<(u64, Candidate)> = vec![];
+        for (size, textures) in &self.unused_textures {
+            for (_, last_used) in textures {
+                if last_used + 1 >= self.tick {
+                    continue;
+                }
+                candidates.push((*last_used, Candidate::Unused(*size, *last_used)));
+            }
+        }
+        for hash in self.cached_textures.keys() {
+            let last_used = self.cache_last_used.get(hash).copied().unwrap_or(0);
+            if last_used + 1 < self.tick {
+                candidates.push((last_used, Candidate::Cached(*hash)));
+            }
+        }
+        candidates.sort_by_key(|(last_used, _)| *last_used);
+
+        let mut freed = 0;
+        for (_, candidate) in candidates {
+            if total <= self.memory_budget {
+                break;
+            }
+            let removed = match candidate {
+                Candidate::Unused(size, last_used) => {
+                    let textures = self.unused_textures.get_mut(&size).unwrap();
+                    let index = textures.iter().position(|(_, t)| *t == last_used).unwrap();
+                    Some((textures.remove(index).0, size.2))
+                }
+                Candidate::Cached(hash) => {
+                    self.cache_last_used.remove(&hash);
+                    let depth = self.cached_depth_of(&hash);
+                    self.cached_depths.remove(&hash);
+                    self.cached_textures.remove(&hash).map(|t| (t, depth))
+                }
+            };
+            if let Some((texture, depth)) = removed {
+                total -= texture_bytes(&texture, depth);
+                freed += texture_bytes(&texture, depth);
+            }
+        }
+        self.unused_textures.retain(|_, v| v.len() > 0);
+        self.redirect_id_to_cache
+            .retain(|_, hash| self.cached_textures.contains_key(hash));
+
+        if freed > 0 {
+            log::info!("freed {} of texture memory", format_bytes(freed));
+        }
+    }
+
     pub fn debug_window(&mut self, ui: &Ui, params: &mut RenderNodesParams) {
         if !self.show_debug_window {
             return;
@@ -223,12 +745,40 @@ impl Storage {
         ui.text_wrapped(format!("shaders: {}", self.shaders.len()));
         ui.text_wrapped(format!("time: {}", self.time));
 
+        let (active_bytes, unused_bytes, cached_bytes) = self.texture_memory();
+        ui.text_wrapped(format!(
+            "texture memory: {} ({} active)",
+            format_bytes(active_bytes + unused_bytes + cached_bytes),
+            format_bytes(active_bytes),
+        ));
+        let total_bytes = active_bytes + unused_bytes + cached_bytes;
+        ProgressBar::new(total_bytes as f32 / self.memory_budget.max(1) as f32)
+            .overlay_text(format!(
+                "{}/{}",
+                format_bytes(total_bytes),
+                format_bytes(self.memory_budget)
+            ))
+            .build(ui);
+        if active_bytes > self.memory_budget {
+            ui.text_colored(
+                [1.00, 0.404, 0.0, 1.0],
+                "the active textures alone are over the budget",
+            );
+        }
+
         if ui.collapsing_header(
-            format!("textures: {}", self.textures.len()),
+            format!("textures: {} ({})", self.textures.len(), format_bytes(active_bytes)),
             TreeNodeFlags::empty(),
         ) {
             for (k, v) in &self.textures {
-                ui.text_wrapped(format!("{:?}, {:?}", k, v.dimensions()));
+                let depth = self.texture_depth_of(k);
+                ui.text_wrapped(format!(
+                    "{:?}, {:?}, {}, {}",
+                    k,
+                    v.dimensions(),
+                    depth.name(),
+                    format_bytes(texture_bytes(v, depth))
+                ));
             }
         }
 
@@ -238,24 +788,52 @@ impl Storage {
         }
 
         if ui.collapsing_header(
-            format!("unused textures: {}/{}", self.unused_textures.len(), total),
+            format!(
+                "unused textures: {}/{} ({})",
+                self.unused_textures.len(),
+                total,
+                format_bytes(unused_bytes)
+            ),
             TreeNodeFlags::empty(),
         ) {
             for (k, v) in &self.unused_textures {
-                ui.text_wrapped(format!("{:?}, {}", k, v.len()))
+                ui.text_wrapped(format!(
+                    "{:?}, {}, {}",
+                    k,
+                    v.len(),
+                    format_bytes(v.iter().map(|(t, _)| texture_bytes(t, k.2)).sum())
+                ))
             }
         }
 
-        ui.text_wrapped(format!("cached_textures: {}", self.cached_textures.len()));
+        if ui.collapsing_header(
+            format!(
+                "cached textures: {} ({})",
+                self.cached_textures.len(),
+                format_bytes(cached_bytes)
+            ),
+            TreeNodeFlags::empty(),
+        ) {
+            for (k, v) in &self.cached_textures {
+                let last_used = self.cache_last_used.get(k).copied().unwrap_or(0);
+                ui.text_wrapped(format!(
+                    "{k}, {:?}, {}, last used {} runs ago",
+                    v.dimensions(),
+                    format_bytes(texture_bytes(v, self.cached_depth_of(k))),
+                    self.tick.saturating_sub(last_used)
+                ));
+            }
+        }
+
+        if ui.collapsing_header("profiler", TreeNodeFlags::empty()) {
+            self.profiler.render(ui);
+        }
 
         if ui.button("crash (panic)") {
             panic!("debug crash {}", line!());
         }
         if ui.button("relaunch (admin)") {
-            #[cfg(target_os = "windows")]
-            {
-                relaunch_program(true, "");
-            }
+            relaunch_program(true, "");
         }
 
         ui.next_column();
@@ -323,6 +901,160 @@ impl Storage {
         self.gen_shader(VERTEX_SHADER.to_string(), frag)
     }
 
+    /// the same as `gen_frag_shader` but returns the compile error so it can be shown to the user
+    pub fn compile_frag_shader(&mut self, frag: String) -> Result<(), String> {
+        return self.compile_shader(VERTEX_SHADER.to_string(), frag, 0);
+    }
+
+    /// the same as `compile_frag_shader` for shaders that start with `header_lines` lines of generated code,
+    /// so the line numbers in the error are the ones the user wrote
+    pub fn compile_frag_shader_with_header(&mut self, frag: String, header_lines: usize) -> Result<(), String> {
+        return self.compile_shader(VERTEX_SHADER.to_string(), frag, header_lines);
+    }
+
+    /// compiles and caches a program. failures are cached too so that a broken shader isn't
+    /// recompiled every frame, the error has the lines of the source it points to added to it
+    fn compile_shader(&mut self, vert: String, frag: String, header_lines: usize) -> Result<(), String> {
+        let key = vert.clone() + &frag;
+        if self.shaders.contains_key(&key) {
+            return Ok(());
+        }
+        if let Some(e) = self.shader_errors.get(&key) {
+            return Err(e.clone());
+        }
+        if let Some(program) = self.load_cached_program(&vert, &frag) {
+            self.shaders.insert(key, program);
+            return Ok(());
+        }
+        match glium::Program::from_source(&self.display, &vert, &frag, None) {
+            Ok(program) => {
+                self.save_cached_program(&vert, &frag, &program);
+                self.shaders.insert(key, program);
+                return Ok(());
+            }
+            Err(e) => {
+                let error = match &e {
+                    ProgramCreationError::CompilationError(log, ShaderType::Fragment) => {
+                        annotate_shader_error(log, &frag, header_lines)
+                    }
+                    ProgramCreationError::CompilationError(log, ShaderType::Vertex) => {
+                        annotate_shader_error(log, &vert, 0)
+                    }
+                    ProgramCreationError::CompilationError(log, _) => log.to_owned(),
+                    ProgramCreationError::LinkingError(log) => log.to_owned(),
+                    _ => e.to_string(),
+                };
+                log::error!("shader compile error:\n{error}");
+                if self.shader_errors.len() >= MAX_SHADER_ERRORS {
+                    self.shader_errors.clear();
+                }
+                self.shader_errors.insert(key, error.clone());
+                return Err(error);
+            }
+        }
+    }
+
+    /// where the binary of a program is saved. the key includes the driver because
+    /// binaries can't be shared between drivers or driver versions.
+    /// it has to be the same in every build, so it uses blake2 rather than `param_cache_key`
+    fn shader_cache_path(&self, vert: &str, frag: &str) -> Option<PathBuf> {
+        let mut hash = Blake2s256::new();
+        for part in [self.driver.as_str(), vert, frag] {
+            hash.update((part.len() as u64).to_le_bytes());
+            hash.update(part.as_bytes());
+        }
+        let key = format!("{:x}", hash.finalize());
+        return Some(self.shader_cache_dir.as_ref()?.join(format!("{key}.bin")));
+    }
+
+    /// deletes the programs that were used the longest time ago until the cache fits in `MAX_SHADER_CACHE_BYTES`
+    fn trim_shader_cache(&self) {
+        let Some(dir) = &self.shader_cache_dir else {
+            return;
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut files = entries
+            .flatten()
+            .filter_map(|a| {
+                let metadata = a.metadata().ok()?;
+                Some((metadata.modified().ok()?, metadata.len(), a.path()))
+            })
+            .collect::<Vec<(SystemTime, u64, PathBuf)>>();
+        let mut total: u64 = files.iter().map(|a| a.1).sum();
+        files.sort_by_key(|a| a.0);
+        for (_, size, path) in files {
+            if total <= MAX_SHADER_CACHE_BYTES {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= size;
+            }
+        }
+    }
+
+    fn load_cached_program(&self, vert: &str, frag: &str) -> Option<Program> {
+        let path = self.shader_cache_path(vert, frag)?;
+        if !path.exists() {
+            return None;
+        }
+        let binary: ShaderBinary = savefile::load_file(&path, SHADER_BINARY_VERSION).ok()?;
+        match Program::new(
+            &self.display,
+            glium::program::Binary {
+                format: binary.format,
+                content: binary.content,
+            },
+        ) {
+            Ok(a) => {
+                // the modified time is when the program was last used, see `trim_shader_cache`
+                let _ = fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .and_then(|f| f.set_modified(SystemTime::now()));
+                return Some(a);
+            }
+            Err(e) => {
+                // drivers can refuse binaries they made (e.g. after an update), so it is compiled again
+                log::warn!("failed to load cached shader {path:?}: {e}");
+                let _ = fs::remove_file(path);
+                return None;
+            }
+        }
+    }
+
+    fn save_cached_program(&self, vert: &str, frag: &str, program: &Program) {
+        let Some(path) = self.shader_cache_path(vert, frag) else {
+            return;
+        };
+        let binary = match program.get_binary() {
+            Ok(a) => ShaderBinary {
+                format: a.format,
+                content: a.content,
+            },
+            Err(e) => {
+                log::warn!("cannot get shader binary: {e:?}");
+                return;
+            }
+        };
+        if let Err(e) = savefile::save_file(&path, SHADER_BINARY_VERSION, &binary) {
+            log::warn!("failed to save shader cache: {e}");
+        }
+        self.trim_shader_cache();
+    }
+
+    /// compiles shaders ahead of time so the first frame a node is used doesn't stall,
+    /// programs that were compiled in an earlier session are loaded from the disk cache
+    pub fn precompile_frag_shaders(&mut self, mut shaders: Vec<String>) {
+        shaders.push(COLOR_SPACE_SHADER.to_string());
+        for frag in shaders {
+            if let Err(e) = self.compile_frag_shader(frag) {
+                log::error!("a built in shader failed to compile: {e}");
+            }
+        }
+    }
+
     pub fn get_frag_shader(&self, frag: String) -> Option<&Program> {
         self.get_shader(VERTEX_SHADER.to_string(), frag)
     }
@@ -332,38 +1064,42 @@ impl Storage {
     }
 
     pub fn gen_shader(&mut self, vert: String, frag: String) -> Option<&Program> {
-        let value = &(vert.clone()+ &frag);
-        if  self.shaders.contains_key(value) {
-            return self.shaders.get(value);
-        }else {
-            let program = match glium::Program::from_source(
-                &self.display, 
-                vert.as_str(), 
-                frag.as_str(), None) {
-                Ok(a) => a,
-                Err(a) => {
-                    log::info!(
-                        "shader_comp_error:--------------- \n\n\n {a:#?}\n\n\n --------------"
-                    );
-                    return None;
-                }
-            };
-            self.shaders.insert(vert.clone() + &frag, program);
-
-            return self.shaders.get(&(vert+ &frag));
+        if self.compile_shader(vert.clone(), frag.clone(), 0).is_err() {
+            return None;
         }
-
-        
+        return self.get_shader(vert, frag);
     }
+
     /// I would like to make this function less expensive
     pub fn create_and_set_texture(&mut self, width: u32, height: u32, k: String) {
-        match self.unused_textures.get_mut(&(width, height)) {
+        let depth = self.texture_depth;
+        let bytes = width as u64 * height as u64 * depth.bytes_per_pixel();
+        if depth != TextureDepth::U8 {
+            let texture = match self.unused_textures.get_mut(&(width, height, depth)) {
+                Some(a) if a.len() > 0 => a.pop().unwrap().0,
+                _ => {
+                    self.texture_bytes += bytes;
+                    Texture2d::empty_with_format(
+                        &self.display,
+                        depth.format(),
+                        texture::MipmapsOption::NoMipmap,
+                        width,
+                        height,
+                    )
+                    .unwrap()
+                }
+            };
+            texture.as_surface().clear_color(0.0, 0.0, 0.0, 0.0);
+            self.set_texture_with_depth(k, texture, depth);
+            return;
+        }
+        match self.unused_textures.get_mut(&(width, height, depth)) {
             Some(a) if a.len() > 0 => {
-                let texture = a.pop().unwrap();
+                let (texture, _) = a.pop().unwrap();
                 texture
                     .as_surface()
                     .clear_color(148.0 / 255.0, 0.0, 211.0 / 255.0, 1.0);
-                self.set_texture(k, texture);
+                self.set_texture_with_depth(k, texture, depth);
             }
             Some(a) => {
                 let image: image::ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_raw(
@@ -373,6 +1109,7 @@ impl Storage {
                 )
                 .unwrap();
                 log::info!("{}", a.len());
+                self.texture_bytes += bytes;
                 let not_texture: RawImage2d<u8> = RawImage2d::from_raw_rgba(
                     image.as_bytes().to_vec(),
                     (image.width(), image.height()),
@@ -384,7 +1121,7 @@ impl Storage {
                 )
                 .unwrap();
 
-                self.set_texture(k, texture);
+                self.set_texture_with_depth(k, texture, depth);
             }
             _ => {
                 let image: image::ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_raw(
@@ -394,6 +1131,7 @@ impl Storage {
                 )
                 .unwrap();
                 log::info!("allocated texture: {}x{}", image.width(), image.height());
+                self.texture_bytes += bytes;
                 let not_texture: RawImage2d<u8> = RawImage2d::from_raw_rgba(
                     image.as_bytes().to_vec(),
                     (image.width(), image.height()),
@@ -405,7 +1143,7 @@ impl Storage {
                     texture::MipmapsOption::NoMipmap,
                 )
                 .unwrap();
-                self.set_texture(k, texture);
+                self.set_texture_with_depth(k, texture, depth);
             }
         }
     }
@@ -0,0 +1,233 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use glium::texture::RawImage2d;
+use image::{
+    imageops::{crop_imm, flip_vertical},
+    GenericImage, RgbaImage,
+};
+use imgui_glium_renderer::Renderer;
+use platform_dirs::UserDirs;
+use rfd::FileDialog;
+
+use crate::{
+    nodes::{input::load_image::LoadImage, output::image_io::OutputNode},
+    project::Project,
+};
+
+/// the largest tile that is rendered at once, including the margin
+const MAX_TILE_SIZE: u32 = 4096;
+
+/// a part of an image that is too big to fit into one texture.
+/// positions are in pixels from the top left of the full image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// how many extra pixels are loaded around the tile so that kernels like blurs have something to read
+    pub margin: u32,
+}
+
+impl Tile {
+    /// the area sources should load, (x, y, width, height). the margin is cut off at the edges of the image
+    pub fn padded(&self, full_size: (u32, u32)) -> (u32, u32, u32, u32) {
+        let x = self.x.saturating_sub(self.margin);
+        let y = self.y.saturating_sub(self.margin);
+        let right = (self.x + self.width + self.margin).min(full_size.0);
+        let bottom = (self.y + self.height + self.margin).min(full_size.1);
+        return (x, y, right - x, bottom - y);
+    }
+}
+
+/// splits an image into tiles that are at most `tile_size` wide and tall (not counting the margin)
+pub fn tiles(full_size: (u32, u32), tile_size: u32, margin: u32) -> Vec<Tile> {
+    let mut tiles = vec![];
+    for y in (0..full_size.1).step_by(tile_size as usize) {
+        for x in (0..full_size.0).step_by(tile_size as usize) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: tile_size.min(full_size.0 - x),
+                height: tile_size.min(full_size.1 - y),
+                margin,
+            });
+        }
+    }
+    return tiles;
+}
+
+impl Project {
+    /// the indices of every node that `index` depends on, including itself
+    pub(crate) fn upstream_nodes(&self, index: usize) -> Vec<usize> {
+        let mut found = HashSet::new();
+        let mut stack = vec![index];
+        while let Some(i) = stack.pop() {
+            if !found.insert(i) {
+                continue;
+            }
+            let node = &self.nodes[i];
+            for input in node.inputs() {
+                if let Some(output_id) = self.connections.get(&node.input_id(&input)) {
+                    let parent_id = output_id.split("-").collect::<Vec<&str>>()[1];
+                    if let Some(parent) = self.nodes.iter().position(|n| n.id() == parent_id) {
+                        stack.push(parent);
+                    }
+                }
+            }
+        }
+        return found.into_iter().collect();
+    }
+
+    /// runs the graph once per tile and stitches together what is connected to the output node at `output_index`.
+    /// this lets images that are bigger than the largest texture the gpu supports go through the graph
+    pub fn render_tiled(
+        &mut self,
+        output_index: usize,
+        renderer: &mut Renderer,
+    ) -> anyhow::Result<RgbaImage> {
+        let output_node = &self.nodes[output_index];
+        let output_key = output_node
+            .inputs()
+            .first()
+            .and_then(|a| self.connections.get(&output_node.input_id(a)))
+            .cloned()
+            .ok_or(anyhow!("the output node has no input"))?;
+
+        let upstream = self.upstream_nodes(output_index);
+        let mut margin = 0;
+        let mut full_size: Option<(u32, u32)> = None;
+        for index in &upstream {
+            let node = &self.nodes[*index];
+            match node.tile_margin() {
+                Some(m) => margin += m,
+                None => return Err(anyhow!("{} can't be rendered in tiles", node.name())),
+            }
+            if let Some(load_image) = self.nodes[*index].as_any_mut().downcast_mut::<LoadImage>() {
+                let size = load_image.source_size(&self.storage)?;
+                full_size = Some(match full_size {
+                    Some(a) => (a.0.max(size.0), a.1.max(size.1)),
+                    None => size,
+                });
+            }
+        }
+        let full_size = full_size.ok_or(anyhow!("tiled renders need a load image node"))?;
+
+        let tile_size = self
+            .storage
+            .max_texture_size()
+            .min(MAX_TILE_SIZE)
+            .saturating_sub(margin * 2);
+        if tile_size < 64 {
+            return Err(anyhow!("the nodes need a margin of {margin} pixels, which is too big to tile"));
+        }
+
+        let preview_scale = self.storage.preview_scale;
+        let exporting = self.storage.exporting;
+        self.storage.preview_scale = 1.0;
+        self.storage.exporting = true;
+        let mut image = RgbaImage::new(full_size.0, full_size.1);
+        let mut result = Ok(());
+        for tile in tiles(full_size, tile_size, margin) {
+            self.storage.tile = Some(tile);
+            result = self.render_tile(tile, full_size, &output_key, &upstream, &mut image, renderer);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.storage.tile = None;
+        self.storage.preview_scale = preview_scale;
+        self.storage.exporting = exporting;
+
+        result?;
+        return Ok(image);
+    }
+
+    fn render_tile(
+        &mut self,
+        tile: Tile,
+        full_size: (u32, u32),
+        output_key: &String,
+        upstream: &[usize],
+        image: &mut RgbaImage,
+        renderer: &mut Renderer,
+    ) -> anyhow::Result<()> {
+        // only the nodes the output depends on are run, in the order the graph runs them
+        self.storage.reset();
+        self.storage.block_on_jobs = true;
+        self.storage.canvas_size = self.project_settings.canvas_size;
+        self.update_run_order();
+        let mut failed_nodes = HashMap::new();
+        for id in self.node_run_order.1.clone() {
+            if let Some(index) = upstream.iter().copied().find(|i| self.nodes[*i].id() == id) {
+                self.run_node_at(index, false, &mut failed_nodes, renderer);
+            }
+        }
+        self.storage.profiler.finish_run();
+        for index in upstream {
+            if let Some(Err(e)) = self.node_error_value.get(&self.nodes[*index].id()) {
+                return Err(anyhow!("{} failed: {e}", self.nodes[*index].name()));
+            }
+        }
+
+        let texture = self
+            .storage
+            .get_texture(output_key)
+            .ok_or(anyhow!("the output node's input has no texture"))?;
+        let padded = tile.padded(full_size);
+        if texture.dimensions() != (padded.2, padded.3) {
+            return Err(anyhow!("nodes that change the size of the image can't be rendered in tiles"));
+        }
+        let pixels: RawImage2d<u8> = texture.read();
+        let tile_image = RgbaImage::from_raw(padded.2, padded.3, pixels.data.into_owned())
+            .ok_or(anyhow!("failed to read tile"))?;
+        // textures are stored bottom row first
+        let tile_image = flip_vertical(&tile_image);
+        let cropped = crop_imm(
+            &tile_image,
+            tile.x - padded.0,
+            tile.y - padded.1,
+            tile.width,
+            tile.height,
+        )
+        .to_image();
+        image.copy_from(&cropped, tile.x, tile.y)?;
+        return Ok(());
+    }
+
+    /// starts a tiled render for any output node that asked for one and asks where to save it.
+    /// returns true if a render was run
+    pub fn run_requested_tiled_renders(&mut self, renderer: &mut Renderer) -> bool {
+        let requested = self.nodes.iter_mut().position(|n| {
+            n.as_any_mut()
+                .downcast_mut::<OutputNode>()
+                .is_some_and(|o| std::mem::take(&mut o.tiled_render_requested))
+        });
+        let Some(index) = requested else {
+            return false;
+        };
+
+        let image = match self.render_tiled(index, renderer) {
+            Ok(a) => a,
+            Err(e) => {
+                log::error!("tiled render failed: {e}");
+                self.node_error_value.insert(self.nodes[index].id(), Err(e));
+                return true;
+            }
+        };
+        if let Some(path) = FileDialog::new()
+            .set_can_create_directories(true)
+            .set_title("Save Image")
+            .set_directory(UserDirs::new().map(|a| a.download_dir).unwrap_or_default())
+            .set_file_name("out.png")
+            .add_filter("image", &["png", "tiff", "tif"])
+            .save_file()
+        {
+            if let Err(e) = image.save(path) {
+                log::error!("failed to save tiled render: {e}");
+            }
+        }
+        return true;
+    }
+}
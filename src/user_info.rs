@@ -14,20 +14,21 @@ use rfd::FileDialog;
 use strum::IntoEnumIterator;
 use strum_macros::{EnumIter, EnumString};
 
+use glium::backend::Facade;
 use glium::Display;
 
 
 
 use glium::glutin::surface::WindowSurface;
 
-use win_msgbox::Okay;
+use system_extensions::dialogues::messagebox::{IconType, MessageBox, WindowType};
 
 use crate::{
-    fonts::MyFonts, popups::set_as_default_for_filetype, project::Project, relaunch_program,
-    support::FONT_SIZE,
+    canvas::PreviewQuality, fonts::MyFonts, platform::{relaunch_program, set_as_default_for_filetype}, project::Project,
+    shortcuts::{Action, Shortcut}, support::FONT_SIZE,
 };
 
-pub const USER_SETTINGS_SAVEFILE_VERSION: u32 = 6;
+pub const USER_SETTINGS_SAVEFILE_VERSION: u32 = 9;
 
 #[derive(Savefile, EnumIter, EnumString, PartialEq, Eq, Debug, Clone)]
 pub enum UiTheme {
@@ -68,7 +69,8 @@ pub struct UserSettings {
     pub font: String,
     #[savefile_versions = "5.."]
 
-    /// doesn't currently work, and should not be used
+    /// how long each type of node takes to run relative to the rest of the project it is in,
+    /// measured when a project is loaded. only shown in debug builds
     pub node_speed: HashMap<String, Vec<f32>>,
     #[savefile_versions = "5.."]
     #[savefile_default_val = "true"]
@@ -87,6 +89,20 @@ pub struct UserSettings {
     pub finished_setup: bool,
     #[savefile_versions = "6.."]
     pub install_ffmpeg: bool,
+    /// actions that are missing from this map use their default shortcut, `None` means the user has unbound it
+    #[savefile_versions = "7.."]
+    pub shortcuts: HashMap<Action, Option<Shortcut>>,
+    /// the action currently waiting for a new key combination in the shortcut editor
+    #[savefile_ignore]
+    #[savefile_introspect_ignore]
+    pub rebinding_shortcut: Option<Action>,
+    /// how much gpu memory (in MiB) textures can take up before the unused and cached ones that were used least recently are freed
+    #[savefile_versions = "8.."]
+    #[savefile_default_val = "1024"]
+    pub texture_memory_budget: i32,
+    /// how much sources are downsampled by while editing
+    #[savefile_versions = "9.."]
+    pub preview_quality: PreviewQuality,
 }
 
 impl Default for UserSettings {
@@ -123,6 +139,10 @@ impl Default for UserSettings {
             )
             .unwrap_or_default(),
             selected_project: None,
+            shortcuts: HashMap::new(),
+            rebinding_shortcut: None,
+            texture_memory_budget: 1024,
+            preview_quality: PreviewQuality::default(),
         };
 
         return new;
@@ -255,7 +275,10 @@ impl UserSettings {
                                 self.font = "Default".to_owned();
                                 self.save();
                                 // #[cfg(all(target_os="windows", not(debug_assertions)))]{
-                                win_msgbox::show::<Okay>(&format!("Font Loading Error"));
+                                let _ = MessageBox::new("ReAnimator", "Font Loading Error")
+                                    .set_icon_type(IconType::ICON_ERROR)
+                                    .set_window_type(WindowType::OK)
+                                    .show();
                                 // }
                                 let id: imgui::FontId =
                                     ctx.fonts().add_font(&[FontSource::TtfData {
@@ -320,6 +343,7 @@ impl UserSettings {
             ui.spacing();
             // ui.same_line();
             if ui.button("save and close") {
+                self.rebinding_shortcut = None;
                 self.save();
                 *window_open = false;
             }
@@ -432,6 +456,13 @@ impl UserSettings {
                     }
 
                 }
+                if let Some(_shortcut_settings) = ui.tab_item("shortcuts") {
+                    ui.spacing();
+                    ui.spacing();
+                    self.shortcut_editor(ui);
+                } else {
+                    self.rebinding_shortcut = None;
+                }
                 if let Some(_general_settings) = ui.tab_item("advanced") {
                     ui.spacing();
                     ui.spacing();
@@ -441,6 +472,12 @@ impl UserSettings {
                     if ui.is_item_hovered() {
                         ui.tooltip_text("Application must be run as admin");
                     }
+                    ui.input_int("texture memory budget (MiB)", &mut self.texture_memory_budget)
+                        .build();
+                    self.texture_memory_budget = self.texture_memory_budget.max(64);
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("When textures take up more than this, the unused ones that were used least recently are freed. The textures the nodes are using are never freed");
+                    }
                     if ui.button("redo setup") {
                         self.finished_setup = false;
                         self.save();
@@ -487,7 +524,7 @@ impl Project {
                         &user_settings
                             .project_folder_path
                             .join(user_settings.new_project_name.clone()),
-                        display.clone(),
+                        display.get_context().clone(),
                     ));
                 }
                 ui.next_column();
@@ -522,7 +559,7 @@ impl Project {
                         for project in &user_settings.projects {
                             if ui.button(project.file_name().unwrap().to_str().unwrap()) {
                                 let mut new_project_1 =
-                                    Project::new(project, display.clone());
+                                    Project::new(project, display.get_context().clone());
                                 // let _ = new_project_1.save();
 
                                 // I have no idea what this code does.
@@ -610,7 +647,7 @@ impl Project {
                     log::info!("exporting project");
 
                     let mut new_project_1 =
-                        Project::new(project_path.to_path_buf(), display.clone());
+                        Project::new(project_path.to_path_buf(), display.get_context().clone());
 
                     let export_path = new_project_1.export();
 
@@ -0,0 +1,227 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use lazy_static::lazy_static;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+lazy_static! {
+    static ref WORKER_POOL: WorkerPool = WorkerPool::new(
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4)
+            .saturating_sub(1)
+            .max(1)
+    );
+}
+
+/// the number of jobs that have finished (or sent a partial result) since the last call to `take_finished`
+static FINISHED_JOBS: AtomicUsize = AtomicUsize::new(0);
+
+/// a fixed number of threads that cpu heavy nodes can send work to so they don't block the ui thread.
+/// nodes should read back their inputs on the main thread (the gpu can only be used from there),
+/// do the expensive part in a `CpuJob`, and upload the result in a later call to `run`
+struct WorkerPool {
+    sender: Mutex<Sender<Job>>,
+}
+
+impl WorkerPool {
+    fn new(threads: usize) -> WorkerPool {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for i in 0..threads {
+            let receiver = receiver.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("worker {i}"))
+                .spawn(move || loop {
+                    let job = match receiver.lock() {
+                        Ok(a) => a.recv(),
+                        Err(_) => return,
+                    };
+                    match job {
+                        Ok(job) => {
+                            if catch_unwind(AssertUnwindSafe(job)).is_err() {
+                                log::error!("a job on the worker pool panicked");
+                            }
+                            FINISHED_JOBS.fetch_add(1, Ordering::SeqCst);
+                        }
+                        Err(_) => return,
+                    }
+                });
+            if let Err(e) = spawned {
+                log::error!("failed to start worker thread: {e}");
+            }
+        }
+        log::info!("started worker pool with {threads} threads");
+
+        WorkerPool {
+            sender: Mutex::new(sender),
+        }
+    }
+}
+
+/// returns true if any job has finished since this was last called, meaning
+/// there are results waiting to be uploaded and the nodes should be run again
+pub fn take_finished() -> bool {
+    FINISHED_JOBS.swap(0, Ordering::SeqCst) > 0
+}
+
+/// makes `take_finished` return true, for nodes that are waiting on something other than a job (e.g. a copy from the gpu)
+pub fn request_run() {
+    FINISHED_JOBS.fetch_add(1, Ordering::SeqCst);
+}
+
+pub enum JobStatus<T> {
+    Running,
+    Done(T),
+    /// the job panicked or was dropped before it finished
+    Failed,
+}
+
+/// given to jobs that report their progress or send partial results while they run
+pub struct JobHandle<P> {
+    cancelled: Arc<AtomicBool>,
+    progress: Arc<Mutex<f32>>,
+    partial: Sender<P>,
+}
+
+impl<P> JobHandle<P> {
+    /// long running jobs should check this and stop early if it is true
+    pub fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// `progress` should be between 0 and 1
+    pub fn set_progress(&self, progress: f32) {
+        if let Ok(mut a) = self.progress.lock() {
+            *a = progress;
+        }
+    }
+
+    /// sends an unfinished result back to the node, e.g. a render with fewer samples
+    pub fn send_partial(&self, partial: P) {
+        if self.partial.send(partial).is_ok() {
+            FINISHED_JOBS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// the result of some work that has been sent to the worker pool.
+/// dropping a job cancels it (if the job checks `JobHandle::cancelled`)
+pub struct CpuJob<T, P = ()> {
+    receiver: Receiver<T>,
+    partial: Receiver<P>,
+    cancelled: Arc<AtomicBool>,
+    progress: Arc<Mutex<f32>>,
+    pub started: Instant,
+    /// a hash of whatever the job was started with, so that nodes can tell if it is out of date
+    pub hash: u64,
+}
+
+impl<T: Send + 'static> CpuJob<T> {
+    pub fn spawn(hash: u64, f: impl FnOnce() -> T + Send + 'static) -> CpuJob<T> {
+        CpuJob::spawn_with_handle(hash, move |_| f())
+    }
+}
+
+impl<T: Send + 'static, P: Send + 'static> CpuJob<T, P> {
+    pub fn spawn_with_handle(
+        hash: u64,
+        f: impl FnOnce(&JobHandle<P>) -> T + Send + 'static,
+    ) -> CpuJob<T, P> {
+        let (sender, receiver) = mpsc::channel();
+        let (partial_sender, partial) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let progress = Arc::new(Mutex::new(0.0));
+
+        let handle = JobHandle {
+            cancelled: cancelled.clone(),
+            progress: progress.clone(),
+            partial: partial_sender,
+        };
+        let job: Job = Box::new(move || {
+            if handle.cancelled() {
+                return;
+            }
+            let _ = sender.send(f(&handle));
+        });
+        match WORKER_POOL.sender.lock() {
+            Ok(a) => {
+                let _ = a.send(job);
+            }
+            Err(e) => log::error!("{e}"),
+        }
+        CpuJob {
+            receiver,
+            partial,
+            cancelled,
+            progress,
+            started: Instant::now(),
+            hash,
+        }
+    }
+
+    /// if `block` is true this waits for the job to finish, this should only be done
+    /// when the output is needed straight away (e.g. batch files)
+    pub fn poll(&self, block: bool) -> JobStatus<T> {
+        if block {
+            return match self.receiver.recv() {
+                Ok(a) => JobStatus::Done(a),
+                Err(_) => JobStatus::Failed,
+            };
+        }
+        match self.receiver.try_recv() {
+            Ok(a) => JobStatus::Done(a),
+            Err(TryRecvError::Empty) => JobStatus::Running,
+            Err(TryRecvError::Disconnected) => JobStatus::Failed,
+        }
+    }
+
+    /// the most recent partial result that hasn't been taken yet
+    pub fn latest_partial(&self) -> Option<P> {
+        self.partial.try_iter().last()
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress.lock().map(|a| *a).unwrap_or(0.0)
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+impl<T, P> Drop for CpuJob<T, P> {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+/// checks on the job in `job` and takes it out once it has finished.
+/// returns `None` if there is no job or it is still running, otherwise the hash the job was started with and its result
+pub fn poll_job<T: Send + 'static, P: Send + 'static>(
+    job: &mut Option<CpuJob<T, P>>,
+    block: bool,
+) -> Option<anyhow::Result<(u64, T)>> {
+    let status = job.as_ref()?.poll(block);
+    match status {
+        JobStatus::Running => None,
+        JobStatus::Done(a) => {
+            let hash = job.take()?.hash;
+            Some(Ok((hash, a)))
+        }
+        JobStatus::Failed => {
+            *job = None;
+            Some(Err(anyhow::anyhow!("background job failed")))
+        }
+    }
+}
@@ -0,0 +1,750 @@
+//! golden image tests for the built-in nodes.
+//! every node type is put in a small graph built with the public api (test patterns -> node), run on the cpu and
+//! with opengl, and each of its outputs is compared with a reference in `tests/golden`.
+//! after a change that is meant to change what a node outputs, run the tests with `UPDATE_GOLDEN=1` to write
+//! new references, then check them before committing them. `GOLDEN_NODES=Blur,Layer` only tests those node types.
+//! the tests fail if opengl can't be used, `GOLDEN_CPU_ONLY=1` only checks the cpu, e.g. on a machine without a gpu
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use image::{
+    codecs::gif::GifEncoder,
+    imageops::{self, FilterType},
+    Delay, Frame, Rgba, RgbaImage,
+};
+use reanimator::{
+    cpu::CpuTexture,
+    nodes::{
+        basic_shader_nodes::isf::IsfNode,
+        data::histogram::HistogramNode,
+        input::{
+            desktop_capture::CaptureWindowNode, load_gif::LoadGifNode, load_image::LoadImage,
+            load_video::LoadVideoNode, render_3d::Render3DNode, webcam::WebcamNode,
+        },
+        layer::LayerNode,
+    },
+    Backend, Graph, MyNode, NodeType,
+};
+use savefile::{new_schema_deserializer, Schema, SchemaPrimitive};
+use strum::IntoEnumIterator;
+
+/// the size of the test patterns, it isn't square so that rows and columns can't be mixed up
+const SIZE: (u32, u32) = (16, 12);
+
+/// noise is compared by its averages, which are steadier with more pixels
+const NOISE_SIZE: (u32, u32) = (64, 48);
+
+/// outputs bigger than this are scaled down before they are compared
+const MAX_SIZE: u32 = 64;
+
+/// how far a channel can be from the reference, out of 255
+const TOLERANCE: u8 = 3;
+
+/// how far the average of a channel can be from the reference, out of 255
+const AVERAGE_TOLERANCE: f32 = 8.0;
+
+/// the nodes that remember earlier frames are run for this many frames, this far apart in seconds
+const FRAMES: usize = 3;
+const FRAME_TIME: f64 = 0.3;
+
+/// nodes that run glsl written by the user, they only run with opengl so their references come from it
+const GL_ONLY: [NodeType; 4] = [
+    NodeType::CustomShader,
+    NodeType::Shadertoy,
+    NodeType::Isf,
+    NodeType::Feedback,
+];
+
+const ISF_SHADER: &str = r#"/*{
+    "INPUTS": [
+        { "NAME": "inputImage", "TYPE": "image" },
+        { "NAME": "amount", "TYPE": "float", "DEFAULT": 0.25, "MIN": 0.0, "MAX": 1.0 }
+    ]
+}*/
+void main() {
+    vec4 c = IMG_NORM_PIXEL(inputImage, isf_FragNormCoord);
+    gl_FragColor = vec4(c.gb, isf_FragNormCoord.x * amount, c.a);
+}
+"#;
+
+const TETRAHEDRON: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 3 2\nf 1 2 4\nf 1 4 3\nf 2 3 4\n";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Compare {
+    /// every channel of every pixel
+    Pixels,
+    /// the average of each channel. the noise nodes hash floats, which gpus round differently to the cpu,
+    /// and the 3d render traces random rays, so only the image as a whole can be compared
+    Averages,
+}
+
+/// how a node type is tested
+struct Case {
+    type_: NodeType,
+    canvas_size: (u32, u32),
+    frames: usize,
+    compare: Compare,
+}
+
+impl Case {
+    fn new(type_: NodeType) -> Case {
+        let mut case = Case {
+            type_,
+            canvas_size: SIZE,
+            frames: 1,
+            compare: Compare::Pixels,
+        };
+        match type_ {
+            NodeType::WhiteNoise
+            | NodeType::ColorNoise
+            | NodeType::Crystal
+            | NodeType::Dot
+            | NodeType::VHS => {
+                case.canvas_size = NOISE_SIZE;
+                case.compare = Compare::Averages;
+            }
+            NodeType::Render3D => case.compare = Compare::Averages,
+            NodeType::Delay | NodeType::Motion | NodeType::LayerTrail => case.frames = FRAMES,
+            _ => {}
+        }
+        return case;
+    }
+
+    /// the name of the reference for `output`
+    fn reference_name(&self, output: &str) -> String {
+        return format!("{}-{}", snake_case(&format!("{:?}", self.type_)), snake_case(output));
+    }
+}
+
+/// what a node output after the last frame
+enum Output {
+    Texture(RgbaImage),
+    Text(String),
+}
+
+/// `SampleUV` -> `sample_uv`, `Selected Image` -> `selected_image`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut last_lowercase = false;
+    for c in name.chars() {
+        if c == ' ' {
+            snake.push('_');
+        } else {
+            if c.is_uppercase() && last_lowercase {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        }
+        last_lowercase = c.is_lowercase();
+    }
+    return snake;
+}
+
+fn golden_dir() -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir()
+        .join("reanimator-tests")
+        .join(format!("{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    return dir;
+}
+
+/// a pattern with gradients, hard edges and different alphas. `seed` changes which channel has which part
+fn pattern(seed: u32, size: (u32, u32)) -> RgbaImage {
+    return RgbaImage::from_fn(size.0, size.1, |x, y| {
+        let channels = [
+            (x * 255 / (size.0 - 1)) as u8,
+            (y * 255 / (size.1 - 1)) as u8,
+            if (x / 4 + y / 3) % 2 == 0 { 230 } else { 40 },
+            255 - (((x + y) % 4) * 60) as u8,
+        ];
+        let alpha = if seed == 0 { channels[3] } else { 255 - channels[3] / 2 };
+        Rgba([0, 1, 2, 3].map(|i| match i {
+            3 => alpha,
+            _ => channels[(i + seed as usize) % 3],
+        }))
+    });
+}
+
+/// turns a texture, which goes from the bottom up, back into an image file. big textures are scaled down
+fn capture(texture: &CpuTexture) -> RgbaImage {
+    let image = RgbaImage::from_fn(texture.width(), texture.height(), |x, y| {
+        Rgba(
+            texture
+                .get_pixel(x, y)
+                .0
+                .map(|a| (a.clamp(0.0, 1.0) * 255.0).round() as u8),
+        )
+    });
+    let image = imageops::flip_vertical(&image);
+    let (width, height) = image.dimensions();
+    if width.max(height) <= MAX_SIZE {
+        return image;
+    }
+    let scale = MAX_SIZE as f32 / width.max(height) as f32;
+    return imageops::resize(
+        &image,
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+        FilterType::Triangle,
+    );
+}
+
+/// the histogram as an image, with the counts of each value in the red, green and blue of its column
+fn histogram_image(node: &dyn MyNode) -> RgbaImage {
+    let histogram = node.as_any().downcast_ref::<HistogramNode>().unwrap().histogram();
+    return RgbaImage::from_fn(256, 1, |x, _| {
+        let counts = histogram[x as usize].map(|a| a.min(255) as u8);
+        Rgba([counts[0], counts[1], counts[2], 255])
+    });
+}
+
+/// adds a load image node for one of the test patterns
+fn add_pattern(graph: &mut Graph, seed: u32, dir: &Path) -> String {
+    let path = dir.join(format!("pattern-{seed}.png"));
+    pattern(seed, graph.settings.canvas_size).save(&path).unwrap();
+    let mut source = LoadImage::default();
+    source.path = Some(path);
+    return graph.add_node(Box::new(source));
+}
+
+/// adds a video that switches between the test patterns, for the nodes that remember earlier frames
+fn add_video(graph: &mut Graph) -> String {
+    let size = graph.settings.canvas_size;
+    let frames = (0..2).map(|seed| pattern(seed, size).into_raw()).collect();
+    let mut video = LoadVideoNode::default();
+    video.set_frames(FRAMES as f32 * FRAME_TIME as f32, size.0, size.1, frames);
+    return graph.add_node(Box::new(video));
+}
+
+/// the node being tested, set up so that it doesn't need a camera, a window or files outside of `dir`
+fn new_node(case: &Case, dir: &Path) -> Box<dyn MyNode> {
+    let size = case.canvas_size;
+    match case.type_ {
+        NodeType::LoadGif => {
+            let path = dir.join("patterns.gif");
+            let mut encoder = GifEncoder::new(fs::File::create(&path).unwrap());
+            for seed in [0, 1, 0] {
+                let frame = Frame::from_parts(pattern(seed, size), 0, 0, Delay::from_numer_denom_ms(100, 1));
+                encoder.encode_frame(frame).unwrap();
+            }
+            let mut node = LoadGifNode::default();
+            node.path = Some(path);
+            return Box::new(node);
+        }
+        NodeType::LoadVideo => {
+            let frames = (0..2).map(|seed| pattern(seed, size).into_raw()).collect();
+            let mut node = LoadVideoNode::default();
+            node.set_frames(1.0, size.0, size.1, frames);
+            return Box::new(node);
+        }
+        NodeType::Webcam => {
+            let mut node = WebcamNode::default();
+            node.set_frame(size.0, size.1, pattern(0, size).into_raw());
+            return Box::new(node);
+        }
+        NodeType::CaptureDesktop => {
+            let mut node = CaptureWindowNode::default();
+            node.set_frame(size.0, size.1, pattern(1, size).into_raw());
+            return Box::new(node);
+        }
+        NodeType::Render3D => {
+            let path = dir.join("tetrahedron.obj");
+            fs::write(&path, TETRAHEDRON).unwrap();
+            let mut node = Render3DNode::default();
+            node.obj_path = Some(path);
+            return Box::new(node);
+        }
+        NodeType::Isf => {
+            let mut node = IsfNode::default();
+            node.set_source(ISF_SHADER.to_owned());
+            return Box::new(node);
+        }
+        NodeType::Layer => {
+            let mut node = LayerNode::default();
+            node.add_layer([3.0, 2.0, 9.0, 7.5], false);
+            node.add_layer([0.5, 0.25, 0.5, 0.7], true);
+            return Box::new(node);
+        }
+        type_ => return type_.new_node(),
+    }
+}
+
+/// builds the graph for `case`, the inputs of the node take turns between the two test patterns.
+/// returns the graph and the id of the node being tested
+fn build(case: &Case, dir: &Path) -> (Graph, String) {
+    // node ids and some nodes are random
+    fastrand::seed(7);
+    let mut graph = Graph::default();
+    graph.settings.canvas_size = case.canvas_size;
+    if case.type_ == NodeType::LoadImageType {
+        let node = add_pattern(&mut graph, 0, dir);
+        return (graph, node);
+    }
+
+    let node = graph.add_node(new_node(case, dir));
+    let inputs = graph.node(&node).unwrap().inputs();
+    let sources = if inputs.is_empty() {
+        vec![]
+    } else if case.frames > 1 {
+        vec![add_video(&mut graph)]
+    } else {
+        vec![add_pattern(&mut graph, 0, dir), add_pattern(&mut graph, 1, dir)]
+    };
+    for (i, input) in inputs.iter().enumerate() {
+        let source = &sources[i % sources.len()];
+        let output = graph.node(source).unwrap().outputs()[0].clone();
+        graph.connect(source, &output, &node, input).unwrap();
+    }
+    return (graph, node);
+}
+
+/// runs the graph for every frame of `case`, returning the outputs of `node` after the last one
+fn run(case: &Case, graph: &mut Graph, node: &str, backend: &mut dyn Backend) -> Result<Vec<(String, Output)>, String> {
+    for frame in 0..case.frames {
+        backend.reset();
+        backend.set_time(frame as f64 * FRAME_TIME);
+        fastrand::seed(frame as u64 + 7);
+        for (id, result) in graph.run(backend) {
+            if let Err(e) = result {
+                return Err(format!("{id} failed: {e}"));
+            }
+        }
+    }
+
+    let node = graph.node(node).unwrap();
+    if case.type_ == NodeType::Histogram {
+        return Ok(vec![("Histogram".to_owned(), Output::Texture(histogram_image(node)))]);
+    }
+    let mut outputs = vec![];
+    for output in node.outputs() {
+        let id = node.output_id(&output);
+        if let Some(texture) = backend.read_texture(&id) {
+            outputs.push((output, Output::Texture(capture(&texture))));
+        } else if let Some(text) = backend.read_text(&id) {
+            outputs.push((output, Output::Text(text)));
+        } else {
+            return Err(format!("{output} was not set"));
+        }
+    }
+    return Ok(outputs);
+}
+
+/// the average of each channel, out of 255
+fn averages(image: &RgbaImage) -> [f32; 4] {
+    let mut sums = [0.0; 4];
+    for pixel in image.pixels() {
+        for c in 0..4 {
+            sums[c] += pixel.0[c] as f32;
+        }
+    }
+    return sums.map(|a| a / (image.width() * image.height()) as f32);
+}
+
+/// where outputs that don't match are written, so they can be looked at
+fn failed_path(name: &str, backend: &str, extension: &str) -> PathBuf {
+    return temp_dir("failed").join(format!("{name}-{backend}.{extension}"));
+}
+
+/// compares `output` with the reference called `name`. `UPDATE_GOLDEN` writes `output` as the reference instead,
+/// if `update` is set
+fn check(name: &str, backend: &str, output: &Output, compare: Compare, update: bool) -> Result<(), String> {
+    let (extension, bytes) = match output {
+        Output::Texture(_) => ("png", None),
+        Output::Text(text) => ("txt", Some(text.as_bytes())),
+    };
+    let path = golden_dir().join(format!("{name}.{extension}"));
+    if update && env::var("UPDATE_GOLDEN").is_ok() {
+        fs::create_dir_all(golden_dir()).unwrap();
+        match output {
+            Output::Texture(image) => image.save(&path).unwrap(),
+            Output::Text(text) => fs::write(&path, text).unwrap(),
+        }
+        return Ok(());
+    }
+
+    let save_failed = || {
+        let failed = failed_path(name, backend, extension);
+        match output {
+            Output::Texture(image) => image.save(&failed).unwrap(),
+            Output::Text(text) => fs::write(&failed, text).unwrap(),
+        }
+        return failed;
+    };
+    if !path.exists() {
+        return Err(format!(
+            "{name} has no reference, the {backend} output was saved to {:?}. \
+            run the tests with UPDATE_GOLDEN=1 to write it, check it and commit it",
+            save_failed()
+        ));
+    }
+
+    let (image, reference) = match output {
+        Output::Text(_) => {
+            if fs::read(&path).unwrap() != bytes.unwrap() {
+                return Err(format!("{name} on {backend} is different to the reference, it was saved to {:?}", save_failed()));
+            }
+            return Ok(());
+        }
+        Output::Texture(image) => (image, image::open(&path).unwrap().into_rgba8()),
+    };
+    if reference.dimensions() != image.dimensions() {
+        return Err(format!(
+            "{name} on {backend} is {:?}, the reference is {:?}. it was saved to {:?}",
+            image.dimensions(),
+            reference.dimensions(),
+            save_failed()
+        ));
+    }
+    match compare {
+        Compare::Pixels => {
+            let wrong = reference
+                .pixels()
+                .zip(image.pixels())
+                .filter(|(a, b)| a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > TOLERANCE))
+                .count();
+            if wrong > 0 {
+                return Err(format!(
+                    "{wrong} pixels of {name} on {backend} are different to the reference, it was saved to {:?}",
+                    save_failed()
+                ));
+            }
+        }
+        Compare::Averages => {
+            let (a, b) = (averages(image), averages(&reference));
+            if a.iter().zip(b).any(|(a, b)| (a - b).abs() > AVERAGE_TOLERANCE) {
+                return Err(format!(
+                    "the averages of {name} on {backend} are {a:?}, the reference's are {b:?}. it was saved to {:?}",
+                    save_failed()
+                ));
+            }
+        }
+    }
+    return Ok(());
+}
+
+/// runs `case` on the cpu and with opengl, returning what went wrong
+fn test_case(case: &Case, gl: bool) -> Vec<String> {
+    let name = format!("{:?}", case.type_);
+    let dir = temp_dir(&snake_case(&name));
+    let gl_only = GL_ONLY.contains(&case.type_);
+    let mut failed = vec![];
+
+    let (mut graph, node) = build(case, &dir);
+    let mut storage = graph.cpu_storage();
+    match run(case, &mut graph, &node, &mut storage) {
+        Ok(outputs) if !gl_only => {
+            for (output, result) in outputs {
+                let name = case.reference_name(&output);
+                if let Err(e) = check(&name, "cpu", &result, case.compare, true) {
+                    failed.push(e);
+                }
+            }
+        }
+        Ok(_) => failed.push(format!("{name} runs on the cpu now, take it out of GL_ONLY")),
+        Err(e) if gl_only && e.contains("only runs with opengl") => {}
+        Err(e) => failed.push(format!("{name} on the cpu: {e}")),
+    }
+
+    if gl {
+        let (mut graph, node) = build(case, &dir);
+        let mut backend = graph.gl_backend().unwrap();
+        match run(case, &mut graph, &node, &mut backend) {
+            Ok(outputs) => {
+                for (output, result) in outputs {
+                    let name = case.reference_name(&output);
+                    if let Err(e) = check(&name, "opengl", &result, case.compare, gl_only) {
+                        failed.push(e);
+                    }
+                }
+            }
+            Err(e) => failed.push(format!("{name} with opengl: {e}")),
+        }
+    }
+
+    let _ = fs::remove_dir_all(dir);
+    return failed;
+}
+
+#[test]
+fn golden() {
+    let only: Option<Vec<String>> = env::var("GOLDEN_NODES")
+        .ok()
+        .map(|a| a.split(',').map(|a| a.trim().to_owned()).collect());
+
+    // a machine without opengl fails instead of quietly skipping half of the checks
+    let gl = env::var("GOLDEN_CPU_ONLY").is_err();
+    if let (true, Err(e)) = (gl, Graph::default().gl_backend()) {
+        panic!("opengl isn't available ({e}), set GOLDEN_CPU_ONLY=1 to only check the nodes on the cpu");
+    }
+
+    let mut failed = vec![];
+    for type_ in NodeType::iter() {
+        if only.as_ref().is_some_and(|a| !a.contains(&format!("{type_:?}"))) {
+            continue;
+        }
+        failed.extend(test_case(&Case::new(type_), gl));
+    }
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
+
+/// saved fields that are kept as they are by `change_value`. they pick which node a generic node is and the slider
+/// that comes with it, or are looked up again when the node loads (the window handle is found from the window's name)
+const NOT_PARAMS: [&str; 5] = ["type_index", "input_name", "input_min", "input_max", "hwnd"];
+
+/// takes the next `len` bytes off the front of `data`
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], String> {
+    if data.len() < len {
+        return Err("the file ended early".to_owned());
+    }
+    let (a, rest) = data.split_at(len);
+    *data = rest;
+    return Ok(a);
+}
+
+fn take_len(data: &mut &[u8]) -> Result<usize, String> {
+    return Ok(u64::from_le_bytes(take(data, 8)?.try_into().unwrap()) as usize);
+}
+
+/// writes a value of `schema` that isn't the zero or empty one
+fn new_value(schema: &Schema, out: &mut Vec<u8>) -> Result<(), String> {
+    match schema {
+        Schema::Primitive(a) => match a {
+            SchemaPrimitive::schema_bool | SchemaPrimitive::schema_i8 | SchemaPrimitive::schema_u8 => out.push(1),
+            SchemaPrimitive::schema_i16 | SchemaPrimitive::schema_u16 => out.extend(1u16.to_le_bytes()),
+            SchemaPrimitive::schema_i32 | SchemaPrimitive::schema_u32 => out.extend(1u32.to_le_bytes()),
+            SchemaPrimitive::schema_i64 | SchemaPrimitive::schema_u64 => out.extend(1u64.to_le_bytes()),
+            SchemaPrimitive::schema_i128 | SchemaPrimitive::schema_u128 => out.extend(1u128.to_le_bytes()),
+            SchemaPrimitive::schema_f32 => out.extend(0.5f32.to_le_bytes()),
+            SchemaPrimitive::schema_f64 => out.extend(0.5f64.to_le_bytes()),
+            SchemaPrimitive::schema_string(_) => {
+                out.extend(1u64.to_le_bytes());
+                out.push(b'x');
+            }
+            a => return Err(format!("can't make a {a:?}")),
+        },
+        Schema::Struct(a) => {
+            for field in &a.fields {
+                new_value(&field.value, out)?;
+            }
+        }
+        Schema::Enum(a) => {
+            let variant = a.variants.first().ok_or("an enum without variants")?;
+            out.extend(&(variant.discriminant as u32).to_le_bytes()[..a.discriminant_size as usize]);
+            for field in &variant.fields {
+                new_value(&field.value, out)?;
+            }
+        }
+        Schema::Vector(item, _) => {
+            out.extend(1u64.to_le_bytes());
+            new_value(item, out)?;
+        }
+        Schema::Array(a) => {
+            for _ in 0..a.count {
+                new_value(&a.item_type, out)?;
+            }
+        }
+        Schema::SchemaOption(item) => {
+            out.push(1);
+            new_value(item, out)?;
+        }
+        Schema::Boxed(item) => new_value(item, out)?,
+        Schema::ZeroSize => {}
+        a => return Err(format!("can't make a {a:?}")),
+    }
+    return Ok(());
+}
+
+/// reads a value of `schema` from `data` and writes a different one to `out`. numbers go up, bools flip, strings,
+/// empty lists and `None`s get something in them and enums move to the next variant
+fn change_value(schema: &Schema, data: &mut &[u8], out: &mut Vec<u8>) -> Result<(), String> {
+    match schema {
+        Schema::Primitive(a) => match a {
+            SchemaPrimitive::schema_bool => out.push(1 - take(data, 1)?[0]),
+            SchemaPrimitive::schema_i8 | SchemaPrimitive::schema_u8 => out.push(take(data, 1)?[0].wrapping_add(1)),
+            SchemaPrimitive::schema_i16 | SchemaPrimitive::schema_u16 => {
+                let a = u16::from_le_bytes(take(data, 2)?.try_into().unwrap());
+                out.extend(a.wrapping_add(1).to_le_bytes());
+            }
+            SchemaPrimitive::schema_i32 | SchemaPrimitive::schema_u32 => {
+                let a = u32::from_le_bytes(take(data, 4)?.try_into().unwrap());
+                out.extend(a.wrapping_add(1).to_le_bytes());
+            }
+            SchemaPrimitive::schema_i64 | SchemaPrimitive::schema_u64 => {
+                let a = u64::from_le_bytes(take(data, 8)?.try_into().unwrap());
+                out.extend(a.wrapping_add(1).to_le_bytes());
+            }
+            SchemaPrimitive::schema_i128 | SchemaPrimitive::schema_u128 => {
+                let a = u128::from_le_bytes(take(data, 16)?.try_into().unwrap());
+                out.extend(a.wrapping_add(1).to_le_bytes());
+            }
+            SchemaPrimitive::schema_f32 => {
+                let a = f32::from_le_bytes(take(data, 4)?.try_into().unwrap());
+                out.extend((a + 0.5).to_le_bytes());
+            }
+            SchemaPrimitive::schema_f64 => {
+                let a = f64::from_le_bytes(take(data, 8)?.try_into().unwrap());
+                out.extend((a + 0.5).to_le_bytes());
+            }
+            SchemaPrimitive::schema_string(_) => {
+                let len = take_len(data)?;
+                out.extend((len as u64 + 1).to_le_bytes());
+                out.extend(take(data, len)?);
+                out.push(b'x');
+            }
+            a => return Err(format!("can't change a {a:?}")),
+        },
+        Schema::Struct(a) => {
+            for field in &a.fields {
+                if NOT_PARAMS.contains(&field.name.as_str()) {
+                    let before = *data;
+                    change_value(&field.value, data, &mut vec![])?;
+                    out.extend(&before[..before.len() - data.len()]);
+                } else {
+                    change_value(&field.value, data, out)?;
+                }
+            }
+        }
+        Schema::Enum(a) => {
+            let size = a.discriminant_size as usize;
+            let mut discriminant = [0; 4];
+            discriminant[..size].copy_from_slice(take(data, size)?);
+            let discriminant = u32::from_le_bytes(discriminant);
+            let index = a
+                .variants
+                .iter()
+                .position(|a| a.discriminant as u32 == discriminant)
+                .ok_or(format!("{} has no variant {discriminant}", a.dbg_name))?;
+            if a.variants.len() == 1 {
+                out.extend(&discriminant.to_le_bytes()[..size]);
+                for field in &a.variants[index].fields {
+                    change_value(&field.value, data, out)?;
+                }
+            } else {
+                // the old variant's fields are skipped
+                for field in &a.variants[index].fields {
+                    change_value(&field.value, data, &mut vec![])?;
+                }
+                let next = &a.variants[(index + 1) % a.variants.len()];
+                out.extend(&(next.discriminant as u32).to_le_bytes()[..size]);
+                for field in &next.fields {
+                    new_value(&field.value, out)?;
+                }
+            }
+        }
+        Schema::Vector(item, _) => {
+            let len = take_len(data)?;
+            if len == 0 {
+                new_value(schema, out)?;
+            } else {
+                out.extend((len as u64).to_le_bytes());
+                for _ in 0..len {
+                    change_value(item, data, out)?;
+                }
+            }
+        }
+        Schema::Array(a) => {
+            for _ in 0..a.count {
+                change_value(&a.item_type, data, out)?;
+            }
+        }
+        Schema::SchemaOption(item) => {
+            out.push(1);
+            if take(data, 1)?[0] == 0 {
+                new_value(item, out)?;
+            } else {
+                change_value(item, data, out)?;
+            }
+        }
+        Schema::Boxed(item) => change_value(item, data, out)?,
+        Schema::ZeroSize => {}
+        a => return Err(format!("can't change a {a:?}")),
+    }
+    return Ok(());
+}
+
+/// changes every param in a node saved with `save_file`, using the schema that is saved before it. the names of the
+/// saved fields are returned with the changed file
+fn change_params(file: &[u8]) -> Result<(Vec<u8>, Vec<String>), String> {
+    // "savefile\0", the savefile format, the node's version and whether it is compressed
+    let header = file.get(..16).ok_or("the file ended early")?;
+    if header[15] != 0 {
+        return Err("the file is compressed".to_owned());
+    }
+    let format = u16::from_le_bytes([header[9], header[10]]);
+    let mut data = &file[16..];
+    let schema = <Schema as savefile::Deserialize>::deserialize(&mut new_schema_deserializer(&mut data, format))
+        .map_err(|e| format!("{e:?}"))?;
+
+    let mut changed = file[..file.len() - data.len()].to_vec();
+    change_value(&schema, &mut data, &mut changed)?;
+    if !data.is_empty() {
+        return Err(format!("{} bytes were left after the node", data.len()));
+    }
+    let Schema::Struct(node) = schema else {
+        return Err("the node isn't a struct".to_owned());
+    };
+    return Ok((changed, node.fields.into_iter().map(|a| a.name).collect()));
+}
+
+/// every node is saved with all of its saved params changed from the defaults, loaded back with `load_node` and
+/// saved again. both saves have to be the same, so a param that doesn't load back comes out different. every field
+/// that isn't a runtime field has to be saved
+#[test]
+fn savefile_round_trip() {
+    let dir = temp_dir("savefile");
+    let file = |root: &str, node: &dyn MyNode| dir.join(root).join(node.name()).join(node.id() + ".bin");
+    let mut failed = vec![];
+    for type_ in NodeType::iter() {
+        let node = type_.new_node();
+        for a in ["defaults", "changed", "loaded"] {
+            fs::create_dir_all(dir.join(a).join(node.name())).unwrap();
+        }
+
+        if let Err(e) = node.save(dir.join("defaults")) {
+            failed.push(format!("{type_:?} failed to save: {e:?}"));
+            continue;
+        }
+        let (changed, saved) = match change_params(&fs::read(file("defaults", &*node)).unwrap()) {
+            Ok(a) => a,
+            Err(e) => {
+                failed.push(format!("{type_:?} couldn't have its params changed: {e}"));
+                continue;
+            }
+        };
+        fs::write(file("changed", &*node), &changed).unwrap();
+        // a field that isn't saved, e.g. because its `savefile_versions` don't include the node's version, can't be
+        // changed, so it would load back as its default without making the saves different
+        for i in 0..node.introspect_len() {
+            let field = node.introspect_child(i).unwrap();
+            if !saved.iter().any(|a| a == field.key()) {
+                failed.push(format!("{type_:?} doesn't save {}", field.key()));
+            }
+        }
+
+        let Some(loaded) = type_.load_node(file("changed", &*node)) else {
+            failed.push(format!("{type_:?} failed to load"));
+            continue;
+        };
+        // the id is a saved string, so it has an x on the end now
+        if loaded.type_() != type_ || loaded.id() != node.id() + "x" {
+            failed.push(format!("{type_:?} loaded as {:?} ({})", loaded.type_(), loaded.id()));
+            continue;
+        }
+        if let Err(e) = loaded.save(dir.join("loaded")) {
+            failed.push(format!("{type_:?} failed to save after loading: {e:?}"));
+            continue;
+        }
+        if fs::read(file("loaded", &*loaded)).unwrap() != changed {
+            failed.push(format!("{type_:?} didn't keep its params when it was saved and loaded"));
+        }
+    }
+    let _ = fs::remove_dir_all(dir);
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
@@ -0,0 +1,114 @@
+//! builds graphs with nothing but the public api, the way other tools use the engine
+
+use reanimator::{
+    cpu::{linear_to_srgb, srgb_to_linear},
+    node::{NodeFallback, NodeMode},
+    storage::TextureDepth,
+    Graph, NodeType,
+};
+
+/// a solid colour going through an invert node
+fn invert_graph() -> (Graph, String) {
+    let mut graph = Graph::default();
+    graph.settings.canvas_size = (4, 3);
+    let color = graph.add(NodeType::SolidColor);
+    let invert = graph.add(NodeType::InvertTexture);
+    graph.connect(&color, "Out", &invert, "In").unwrap();
+    return (graph, invert);
+}
+
+fn run_and_read_output(graph: &mut Graph, node: &str) -> Vec<[f32; 4]> {
+    let mut storage = graph.cpu_storage();
+    for (id, result) in graph.run_cpu(&mut storage) {
+        assert!(result.is_ok(), "{id} failed: {result:?}");
+    }
+    let output_id = graph.node(node).unwrap().output_id("Out");
+    let texture = storage.get_texture(&output_id).expect("no output texture");
+    assert_eq!(texture.dimensions(), (4, 3));
+    return texture.pixels().map(|p| p.0).collect();
+}
+
+#[test]
+fn runs_a_graph_built_in_code() {
+    let (mut graph, invert) = invert_graph();
+    // the solid colour node is white by default
+    for pixel in run_and_read_output(&mut graph, &invert) {
+        assert_eq!(pixel, [0.0, 0.0, 0.0, 1.0]);
+    }
+}
+
+#[test]
+fn connect_checks_names() {
+    let (mut graph, invert) = invert_graph();
+    let color = graph.add(NodeType::SolidColor);
+    assert!(graph.connect(&color, "Out", &invert, "Nope").is_err());
+    assert!(graph.connect(&color, "Nope", &invert, "In").is_err());
+    assert!(graph.connect("missing", "Out", &invert, "In").is_err());
+}
+
+#[test]
+fn saved_graphs_load_and_run() {
+    let (graph, invert) = invert_graph();
+    let path = std::env::temp_dir().join(format!("reanimator-graph-test-{}", std::process::id()));
+    graph.save(&path).unwrap();
+
+    let mut loaded = Graph::load(&path).unwrap();
+    let _ = std::fs::remove_dir_all(&path);
+    assert_eq!(loaded.nodes.len(), 2);
+    assert_eq!(loaded.connections, graph.connections);
+    assert_eq!(loaded.settings.canvas_size, (4, 3));
+    for pixel in run_and_read_output(&mut loaded, &invert) {
+        assert_eq!(pixel, [0.0, 0.0, 0.0, 1.0]);
+    }
+}
+
+#[test]
+fn runs_nodes_with_their_modes_and_fallbacks() {
+    let (mut graph, invert) = invert_graph();
+    // a bypassed node passes its input through, a muted one is transparent
+    graph.settings.node_modes.insert(invert.clone(), NodeMode::Bypass);
+    for pixel in run_and_read_output(&mut graph, &invert) {
+        assert_eq!(pixel, [1.0; 4]);
+    }
+    graph.settings.node_modes.insert(invert.clone(), NodeMode::Mute);
+    for pixel in run_and_read_output(&mut graph, &invert) {
+        assert_eq!(pixel, [0.0; 4]);
+    }
+
+    // an invert with nothing connected fails, with an error texture the node after it still runs
+    let broken = graph.add(NodeType::InvertTexture);
+    let after = graph.add(NodeType::InvertTexture);
+    graph.connect(&broken, "Out", &after, "In").unwrap();
+    let mut storage = graph.cpu_storage();
+    assert!(graph.run_cpu(&mut storage)[&after].is_err());
+    graph.settings.node_fallbacks.insert(broken.clone(), NodeFallback::ErrorTexture);
+    let results = graph.run_cpu(&mut storage);
+    assert!(results[&broken].is_err());
+    assert!(results[&after].is_ok(), "{:?}", results[&after]);
+}
+
+#[test]
+fn runs_linear_nodes_in_linear_light() {
+    let mut graph = Graph::default();
+    graph.settings.canvas_size = (4, 3);
+    graph.settings.texture_depth = TextureDepth::F32;
+    graph.settings.linear_light = true;
+    let color = graph.add(NodeType::SolidColor);
+    let uv = graph.add(NodeType::UvInput);
+    let multiply = graph.add(NodeType::Multiply);
+    graph.connect(&color, "Out", &uv, "In").unwrap();
+    graph.connect(&uv, "Out", &multiply, "Input 1").unwrap();
+    graph.connect(&uv, "Out", &multiply, "Input 2").unwrap();
+
+    let mut storage = graph.cpu_storage();
+    for (id, result) in graph.run_cpu(&mut storage) {
+        assert!(result.is_ok(), "{id} failed: {result:?}");
+    }
+    let uv = storage.get_texture(&graph.node(&uv).unwrap().output_id("Out")).unwrap();
+    let multiplied = storage.get_texture(&graph.node(&multiply).unwrap().output_id("Output")).unwrap();
+    // the uvs are squared in linear light, not as they are
+    for (a, b) in uv.pixels().zip(multiplied.pixels()) {
+        let expected = linear_to_srgb(srgb_to_linear(a[0]).powi(2));
+        assert!((b[0] - expected).abs() < 1e-5, "{} squared is {}, not {expected}", a[0], b[0]);
+    }
+}